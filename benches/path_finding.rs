@@ -0,0 +1,33 @@
+use std::hint::black_box;
+
+use gmtk23::{bench::{maze_field, measure, obstacle_field, path_config, walled_off_field, wall_build_actions}, world::{path_finding::a_star, towers::TowerField}};
+
+const SEED: u64 = 2115;
+
+fn bench_a_star(name: &str, field: &TowerField) {
+    let found = a_star(field, field.get_start(), field.get_end()).is_some();
+    measure(&format!("a_star {name}{}", if found { "" } else { " (no path)" }), 50, || {
+        black_box(a_star(black_box(field), field.get_start(), field.get_end()));
+    });
+}
+
+fn main() {
+    for size in [16, 32, 64] {
+        for density in [0.0, 0.15, 0.3] {
+            bench_a_star(&format!("{size}x{size} density {density:.2}"), &obstacle_field(size, size, density, SEED));
+        }
+    }
+    for size in [16, 32] {
+        bench_a_star(&format!("{size}x{size} maze"), &maze_field(size, size));
+        bench_a_star(&format!("{size}x{size} walled off"), &walled_off_field(size, size));
+    }
+    for size in [16, 32] {
+        // The maze gives the planner the longest path it will realistically see
+        let field = maze_field(size, size);
+        let config = path_config(&field);
+        let name = format!("get_wall_build_actions {size}x{size} maze, path of {}", config.paths[0].get_size());
+        measure(&name, 20, || {
+            black_box(wall_build_actions(black_box(&field), &config));
+        });
+    }
+}
@@ -0,0 +1,24 @@
+use std::hint::black_box;
+
+use gmtk23::{bench::{measure, obstacle_field, scattered_attackers, scattered_towers}, world::towers::select_targets};
+
+const SEED: u64 = 2115;
+const ATTACK_RANGE: f32 = 200.;
+
+fn main() {
+    let field = obstacle_field(16, 16, 0., SEED);
+    for towers in [10, 50] {
+        for enemies in [50, 200, 1000] {
+            let towers: Vec<_> = scattered_towers(towers, &field, SEED)
+                .iter()
+                .map(|transform| (transform.translation.truncate(), 0., ATTACK_RANGE))
+                .collect();
+            let attackers = scattered_attackers(enemies, &field, SEED);
+            // A spatial grid variant goes next to this one once targeting has a grid to compare against
+            let name = format!("find_targets brute force {} towers, {enemies} attackers", towers.len());
+            measure(&name, 200, || {
+                black_box(select_targets(black_box(&towers), black_box(&attackers)));
+            });
+        }
+    }
+}
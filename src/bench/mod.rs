@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::{Entity, Transform, Vec2};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::world::{
+    attackers::{Attacker, ORC_WARRIOR_STATS},
+    defender_controller::{get_wall_build_actions, update_path, DefenderConfiguration},
+    path_finding::Node,
+    towers::{Footprint, TowerField, SLOT_SIZE},
+};
+
+/* Everything in here builds plain data, no App or renderer is needed to run the benches */
+
+/* Field with start in the bottom left and end in the top right corner, blocking roughly `density` of the remaining slots */
+pub fn obstacle_field(width: usize, height: usize, density: f32, seed: u64) -> TowerField {
+    let start = Node::new(0, 0);
+    let end = Node::new(width as i32 - 1, height as i32 - 1);
+    let mut field = TowerField::new(width, height, Vec2::ZERO, start, end);
+    let mut rng = StdRng::seed_from_u64(seed);
+    for y in 0..height {
+        for x in 0..width {
+            let node = Node::new(x as i32, y as i32);
+            if node == start || node == end {
+                continue;
+            }
+            if rng.gen::<f32>() < density {
+                field.add_structure(Entity::PLACEHOLDER, true, slot_position(x, y), Footprint::SINGLE);
+            }
+        }
+    }
+    return field;
+}
+
+/* Walls every other row, leaving a gap at alternating ends, so the path snakes through the whole field */
+pub fn maze_field(width: usize, height: usize) -> TowerField {
+    let mut field = TowerField::new(width, height, Vec2::ZERO, Node::new(0, 0), Node::new(width as i32 - 1, height as i32 - 1));
+    for (row, y) in (1..height - 1).step_by(2).enumerate() {
+        let gap = if row % 2 == 0 { width - 1 } else { 0 };
+        for x in (0..width).filter(|x| *x != gap) {
+            field.add_structure(Entity::PLACEHOLDER, true, slot_position(x, y), Footprint::SINGLE);
+        }
+    }
+    return field;
+}
+
+/* A full row of walls through the middle, the end can't be reached from the start */
+pub fn walled_off_field(width: usize, height: usize) -> TowerField {
+    let mut field = TowerField::new(width, height, Vec2::ZERO, Node::new(0, 0), Node::new(width as i32 - 1, height as i32 - 1));
+    for x in 0..width {
+        field.add_structure(Entity::PLACEHOLDER, true, slot_position(x, height / 2), Footprint::SINGLE);
+    }
+    return field;
+}
+
+/* Defender configuration following the current path of `field`, what the wall planner reads */
+pub fn path_config(field: &TowerField) -> DefenderConfiguration {
+    let mut config = DefenderConfiguration::default();
+    update_path(field, &mut config);
+    return config;
+}
+
+/* Runs the wall planner with the limits the defender uses, returning how many candidates it found */
+pub fn wall_build_actions(field: &TowerField, config: &DefenderConfiguration) -> usize {
+    return get_wall_build_actions::<5, 10>(field, config, Footprint::SINGLE).len();
+}
+
+/* Attackers scattered over the field, entities are made up and only used as identifiers */
+pub fn scattered_attackers(count: usize, field: &TowerField, seed: u64) -> Vec<(Entity, Attacker, Transform)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let size = field_size(field);
+    let mut attackers = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut attacker = ORC_WARRIOR_STATS.clone();
+        attacker.health = rng.gen_range(1.0..attacker.max_health);
+        let position = Vec2::new(rng.gen_range(0.0..size.x), rng.gen_range(0.0..size.y));
+        attackers.push((Entity::from_raw(i as u32), attacker, Transform::from_translation(position.extend(0.))));
+    }
+    return attackers;
+}
+
+/* Tower positions snapped to slot centers */
+pub fn scattered_towers(count: usize, field: &TowerField, seed: u64) -> Vec<Transform> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut towers = Vec::with_capacity(count);
+    for _ in 0..count {
+        let x = rng.gen_range(0..field.get_width());
+        let y = rng.gen_range(0..field.get_height());
+        towers.push(Transform::from_translation(slot_position(x, y).extend(0.)));
+    }
+    return towers;
+}
+
+/* Runs `f` a few times to warm up, then reports the mean duration over `iterations` runs */
+pub fn measure<F: FnMut()>(name: &str, iterations: u32, mut f: F) -> Duration {
+    for _ in 0..iterations.min(10) {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let mean = start.elapsed() / iterations.max(1);
+    println!("{name:<40} {:>12.3?} / iter ({iterations} iterations)", mean);
+    return mean;
+}
+
+fn slot_position(x: usize, y: usize) -> Vec2 {
+    return Vec2::new((x * SLOT_SIZE) as f32, (y * SLOT_SIZE) as f32);
+}
+
+fn field_size(field: &TowerField) -> Vec2 {
+    return Vec2::new((field.get_width() * SLOT_SIZE) as f32, (field.get_height() * SLOT_SIZE) as f32);
+}
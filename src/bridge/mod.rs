@@ -0,0 +1,159 @@
+use std::{cell::RefCell, collections::VecDeque, sync::{Mutex, atomic::{AtomicBool, Ordering}}};
+
+use bevy::{prelude::{Plugin, App, EventReader, Res, ResMut}, time::Time};
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use crate::world::{events::{RoundStartEvent, RoundOverEvent, KillEvent, GameOverEvent}, defender_controller::RoundStats};
+
+/* Set once `run()` has built the app, every host facing function is a no-op before that */
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static KILL_EVENTS: AtomicBool = AtomicBool::new(false);
+static HOST_COMMANDS: Mutex<VecDeque<HostCommand>> = Mutex::new(VecDeque::new());
+
+thread_local! {
+    /* js_sys::Function is not Send, wasm is single threaded so a thread local is enough */
+    static CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HostCommand {
+    Pause,
+    Resume,
+    SetSpeed(f32)
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+pub enum GameEventPayload {
+    RoundStart,
+    RoundOver {
+        damage_dealt: f32,
+        duration_secs: f32,
+        num_reached_end: i32,
+        num_killed: i32
+    },
+    GameOver {
+        winner: &'static str
+    },
+    Kill {
+        bounty: i32,
+        x: f32,
+        y: f32
+    }
+}
+
+pub fn mark_initialized() {
+    INITIALIZED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_initialized() -> bool {
+    return INITIALIZED.load(Ordering::SeqCst);
+}
+
+pub fn set_callback(callback: js_sys::Function) {
+    if !is_initialized() {
+        return;
+    }
+    CALLBACK.with(|cell| *cell.borrow_mut() = Some(callback));
+}
+
+pub fn set_kill_events(enabled: bool) {
+    if !is_initialized() {
+        return;
+    }
+    KILL_EVENTS.store(enabled, Ordering::SeqCst);
+}
+
+pub fn push_command(command: HostCommand) {
+    if !is_initialized() {
+        return;
+    }
+    if let Ok(mut queue) = HOST_COMMANDS.lock() {
+        queue.push_back(command);
+    }
+}
+
+fn emit(payload: &GameEventPayload) {
+    CALLBACK.with(|cell| {
+        if let Some(callback) = cell.borrow().as_ref() {
+            if let Ok(json) = serde_json::to_string(payload) {
+                let _ = callback.call1(&JsValue::NULL, &JsValue::from_str(&json));
+            }
+        }
+    });
+}
+
+pub struct BridgePlugin;
+
+impl Plugin for BridgePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(apply_host_commands)
+            .add_system(forward_round_events)
+            .add_system(forward_kill_events)
+            .add_system(forward_game_over);
+    }
+}
+
+fn apply_host_commands(
+    mut time: ResMut<Time>
+) {
+    let commands: Vec<HostCommand> = match HOST_COMMANDS.lock() {
+        Ok(mut queue) => queue.drain(..).collect(),
+        Err(_) => return
+    };
+    for command in commands {
+        match command {
+            HostCommand::Pause => time.pause(),
+            HostCommand::Resume => time.unpause(),
+            HostCommand::SetSpeed(speed) => {
+                if speed > 0. {
+                    time.set_relative_speed(speed);
+                }
+            }
+        }
+    }
+}
+
+fn forward_round_events(
+    mut round_start: EventReader<RoundStartEvent>,
+    mut round_end: EventReader<RoundOverEvent>,
+    stats: Res<RoundStats>
+) {
+    for _ in round_start.iter() {
+        emit(&GameEventPayload::RoundStart);
+    }
+    for _ in round_end.iter() {
+        emit(&GameEventPayload::RoundOver {
+            damage_dealt: stats.damage_dealt,
+            duration_secs: stats.round_duration.as_secs_f32(),
+            num_reached_end: stats.num_reached_end,
+            num_killed: stats.num_killed
+        });
+    }
+}
+
+fn forward_kill_events(
+    mut deaths: EventReader<KillEvent>
+) {
+    if !KILL_EVENTS.load(Ordering::SeqCst) {
+        deaths.clear();
+        return;
+    }
+    for ev in deaths.iter() {
+        emit(&GameEventPayload::Kill {
+            bounty: ev.bounty,
+            x: ev.death_position.x,
+            y: ev.death_position.y
+        });
+    }
+}
+
+fn forward_game_over(
+    mut game_overs: EventReader<GameOverEvent>
+) {
+    for ev in game_overs.iter() {
+        emit(&GameEventPayload::GameOver { winner: if ev.attacker_won { "attacker" } else { "defender" } });
+    }
+}
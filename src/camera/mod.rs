@@ -1,22 +1,126 @@
-use bevy::{prelude::{Plugin, App, Camera2d, Camera, KeyCode, Res, Input, Query, Transform, EventReader}, input::{keyboard::KeyboardInput, mouse::MouseWheel}, time::Time};
+use bevy::{prelude::{Plugin, App, Camera, Component, KeyCode, Res, ResMut, Resource, With, Input, Query, Transform, EventReader, Vec2, Vec3, Quat, IntoSystemConfig}, input::mouse::MouseWheel, time::{Time, Timer, TimerMode}};
 
+use rand::Rng;
 
+use crate::world::{events::{RoundOverEvent, RoundStartEvent, SplashImpactEvent, EntityReachedEnd}, towers::{TowerField, SLOT_SIZE}};
+
+/* The player's view of the field, other cameras like the minimap render into their own viewport */
+#[derive(Component)]
+pub struct MainCamera;
+
+const ROUND_START_SCALE: f32 = 1.25;
+const ANIMATION_SECONDS: f32 = 1.5;
+/* Zoom limits of the mouse wheel */
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 1.25;
+pub const FRAME_KEY: KeyCode = KeyCode::Home;
+/* Room left around the field when framing it */
+const FRAME_MARGIN: f32 = 1.1;
+const MOVE_KEYS: [KeyCode; 4] = [KeyCode::W, KeyCode::A, KeyCode::S, KeyCode::D];
+
+/* Moves the camera on its own for a moment, the player has no control while active */
+#[derive(Resource)]
+pub struct CameraAnimation {
+    pub target_translation: Vec3,
+    pub target_scale: Vec3,
+    pub duration: Timer,
+    pub active: bool,
+    /* Where the player had the camera when the round started, the camera returns there after the round */
+    pub restore_translation: Vec3,
+    pub restore_scale: Vec3,
+    /* Started by the player, moving the camera by hand takes over again */
+    pub cancellable: bool,
+    start_translation: Vec3,
+    start_scale: Vec3,
+}
+
+impl Default for CameraAnimation {
+    fn default() -> Self {
+        Self {
+            target_translation: Vec3::ZERO,
+            target_scale: Vec3::ONE,
+            duration: Timer::from_seconds(ANIMATION_SECONDS, TimerMode::Once),
+            active: false,
+            restore_translation: Vec3::ZERO,
+            restore_scale: Vec3::ONE,
+            cancellable: false,
+            start_translation: Vec3::ZERO,
+            start_scale: Vec3::ONE,
+        }
+    }
+}
+
+impl CameraAnimation {
+    fn start(&mut self, from: &Transform, target_translation: Vec3, target_scale: Vec3) {
+        self.start_translation = from.translation;
+        self.start_scale = from.scale;
+        self.target_translation = target_translation;
+        self.target_scale = target_scale;
+        self.duration = Timer::from_seconds(ANIMATION_SECONDS, TimerMode::Once);
+        self.active = true;
+        self.cancellable = false;
+    }
+}
+
+const SPLASH_TRAUMA: f32 = 0.15;
+const LEAK_TRAUMA: f32 = 0.4;
+const TRAUMA_DECAY_PER_SECOND: f32 = 1.5;
+const MAX_SHAKE_OFFSET: f32 = 12.;
+const MAX_SHAKE_ANGLE: f32 = 0.03;
+
+/* Trauma in [0, 1] decays over time, the shake grows with its square so small hits stay subtle */
+#[derive(Resource)]
+pub struct CameraShake {
+    pub enabled: bool,
+    pub intensity: f32,
+    pub trauma: f32,
+    /* What was added to the camera last frame, taken off again before anything else moves the camera */
+    applied_offset: Vec3,
+    applied_angle: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self { enabled: true, intensity: 1., trauma: 0., applied_offset: Vec3::ZERO, applied_angle: 0. }
+    }
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.);
+    }
+}
 
 pub struct CameraController;
 
 impl Plugin for CameraController {
     fn build(&self, app: &mut App) {
-        app.add_system(move_camera);
+        app.init_resource::<CameraAnimation>()
+            .init_resource::<CameraShake>()
+            .add_system(remove_camera_shake.before(move_camera).before(start_camera_animation))
+            .add_system(move_camera)
+            .add_system(frame_field.after(move_camera))
+            .add_system(start_camera_animation.after(frame_field))
+            .add_system(update_camera_animation.after(start_camera_animation).after(move_camera))
+            .add_system(apply_camera_shake.after(update_camera_animation));
     }
 }
 
 
-fn move_camera(
-    mut camera_q: Query<(&Camera, &mut Transform)>,
+pub(crate) fn move_camera(
+    mut camera_q: Query<(&Camera, &mut Transform), With<MainCamera>>,
     input: Res<Input<KeyCode>>,
     mut mouse_wheel: EventReader<MouseWheel>,
+    mut animation: ResMut<CameraAnimation>,
     time: Res<Time>
 ) {
+    if animation.active && animation.cancellable && input.any_pressed(MOVE_KEYS) {
+        animation.active = false;
+    }
+    if animation.active {
+        mouse_wheel.clear();
+        return;
+    }
     match camera_q.get_single_mut() {
         Ok((camera, mut transform)) => {
             let factor = if input.pressed(KeyCode::LShift) { 2. } else { 1. };
@@ -37,8 +141,8 @@ fn move_camera(
                 match ev.unit {
                     bevy::input::mouse::MouseScrollUnit::Line => {
                         let factor = ev.y / 10.;
-                        transform.scale.x = f32::clamp(transform.scale.x - factor, 0.5, 1.25);
-                        transform.scale.y = f32::clamp(transform.scale.y - factor, 0.5, 1.25);
+                        transform.scale.x = f32::clamp(transform.scale.x - factor, MIN_SCALE, MAX_SCALE);
+                        transform.scale.y = f32::clamp(transform.scale.y - factor, MIN_SCALE, MAX_SCALE);
                     },
                     bevy::input::mouse::MouseScrollUnit::Pixel => {
                         let factor = ev.y;
@@ -48,4 +152,121 @@ fn move_camera(
         },
         Err(_) => {}
     }
-}
\ No newline at end of file
+}
+
+/* Scale at which the whole field fits the viewport, cameras without a viewport yet get the round start zoom */
+fn get_frame_scale(field: &TowerField, viewport: Option<Vec2>) -> f32 {
+    let Some(viewport) = viewport.filter(|size| size.x > 0. && size.y > 0.) else {
+        return ROUND_START_SCALE;
+    };
+    let width = (field.get_width() * SLOT_SIZE) as f32 * FRAME_MARGIN;
+    let height = (field.get_height() * SLOT_SIZE) as f32 * FRAME_MARGIN;
+    return (width / viewport.x).max(height / viewport.y).clamp(MIN_SCALE, MAX_SCALE);
+}
+
+/* Brings the whole field back into view after scrolling around */
+pub(crate) fn frame_field(
+    camera_q: Query<(&Camera, &Transform), With<MainCamera>>,
+    input: Res<Input<KeyCode>>,
+    mut animation: ResMut<CameraAnimation>,
+    field: Res<TowerField>
+) {
+    // Round start and end animations aren't interrupted
+    if !input.just_pressed(FRAME_KEY) || (animation.active && !animation.cancellable) {
+        return;
+    }
+    let Ok((camera, transform)) = camera_q.get_single() else {
+        return;
+    };
+    let center = field.get_center().extend(transform.translation.z);
+    let scale = get_frame_scale(&field, camera.logical_viewport_size());
+    animation.start(transform, center, Vec3::new(scale, scale, transform.scale.z));
+    animation.cancellable = true;
+}
+
+fn start_camera_animation(
+    camera_q: Query<(&Camera, &Transform), With<MainCamera>>,
+    mut animation: ResMut<CameraAnimation>,
+    mut round_start: EventReader<RoundStartEvent>,
+    mut round_end: EventReader<RoundOverEvent>,
+    field: Res<TowerField>
+) {
+    let Ok((_, transform)) = camera_q.get_single() else {
+        return;
+    };
+    if !round_start.is_empty() {
+        round_start.clear();
+        // Remember the manual position, not a half finished animation
+        if !animation.active {
+            animation.restore_translation = transform.translation;
+            animation.restore_scale = transform.scale;
+        }
+        let center = field.get_center().extend(transform.translation.z);
+        animation.start(transform, center, Vec3::new(ROUND_START_SCALE, ROUND_START_SCALE, transform.scale.z));
+    }
+    if !round_end.is_empty() {
+        round_end.clear();
+        let (translation, scale) = (animation.restore_translation, animation.restore_scale);
+        animation.start(transform, translation, scale);
+    }
+}
+
+pub(crate) fn update_camera_animation(
+    mut camera_q: Query<(&Camera, &mut Transform), With<MainCamera>>,
+    mut animation: ResMut<CameraAnimation>,
+    time: Res<Time>
+) {
+    if !animation.active {
+        return;
+    }
+    let Ok((_, mut transform)) = camera_q.get_single_mut() else {
+        return;
+    };
+    animation.duration.tick(time.delta());
+    let t = animation.duration.percent();
+    transform.translation = animation.start_translation.lerp(animation.target_translation, t);
+    transform.scale = animation.start_scale.lerp(animation.target_scale, t);
+    if animation.duration.finished() {
+        animation.active = false;
+    }
+}
+
+fn remove_camera_shake(
+    mut camera_q: Query<(&Camera, &mut Transform), With<MainCamera>>,
+    mut shake: ResMut<CameraShake>
+) {
+    let Ok((_, mut transform)) = camera_q.get_single_mut() else {
+        return;
+    };
+    transform.translation -= shake.applied_offset;
+    transform.rotation *= Quat::from_rotation_z(-shake.applied_angle);
+    shake.applied_offset = Vec3::ZERO;
+    shake.applied_angle = 0.;
+}
+
+fn apply_camera_shake(
+    mut camera_q: Query<(&Camera, &mut Transform), With<MainCamera>>,
+    mut shake: ResMut<CameraShake>,
+    mut splash_impacts: EventReader<SplashImpactEvent>,
+    mut reached_end: EventReader<EntityReachedEnd>,
+    time: Res<Time>
+) {
+    let impacts = splash_impacts.iter().count() as f32;
+    let leaks = reached_end.iter().count() as f32;
+    shake.add_trauma(impacts * SPLASH_TRAUMA + leaks * LEAK_TRAUMA);
+    shake.trauma = (shake.trauma - TRAUMA_DECAY_PER_SECOND * time.delta_seconds()).max(0.);
+    if !shake.enabled || shake.trauma <= 0. {
+        return;
+    }
+    let Ok((_, mut transform)) = camera_q.get_single_mut() else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+    let strength = shake.trauma * shake.trauma * shake.intensity;
+    let offset = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.) * MAX_SHAKE_OFFSET * strength * transform.scale.x;
+    let angle = rng.gen_range(-1.0..1.0) * MAX_SHAKE_ANGLE * strength;
+    transform.translation += offset;
+    transform.rotation *= Quat::from_rotation_z(angle);
+    shake.applied_offset = offset;
+    shake.applied_angle = angle;
+}
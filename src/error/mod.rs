@@ -0,0 +1,71 @@
+use bevy::prelude::{Plugin, App, Resource, Res, SystemSet, IntoSystemSetConfig, CoreSchedule};
+#[cfg(not(feature = "headless_simulation"))]
+use bevy_egui::{egui, EguiContexts};
+
+/* Gameplay systems live in this set so they can all be halted once something failed to load */
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct GameplaySet;
+
+#[derive(Resource, Default)]
+pub struct FatalError {
+    messages: Vec<String>
+}
+
+impl FatalError {
+    pub fn report(&mut self, message: impl Into<String>) {
+        self.messages.push(message.into());
+    }
+
+    pub fn is_set(&self) -> bool {
+        return !self.messages.is_empty();
+    }
+
+    pub fn get_details(&self) -> String {
+        return self.messages.join("\n");
+    }
+}
+
+pub fn no_fatal_error(fatal_error: Res<FatalError>) -> bool {
+    return !fatal_error.is_set();
+}
+
+pub struct ErrorPlugin;
+
+impl Plugin for ErrorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FatalError>()
+            .configure_set(GameplaySet.run_if(no_fatal_error))
+            // Sets are configured per schedule, movement runs in the fixed step
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_set(GameplaySet.run_if(no_fatal_error));
+            });
+        // Headless runs have no egui, the headless controller reports errors itself
+        #[cfg(not(feature = "headless_simulation"))]
+        app.add_system(fatal_error_overlay);
+    }
+}
+
+#[cfg(not(feature = "headless_simulation"))]
+fn fatal_error_overlay(
+    mut contexts: EguiContexts,
+    fatal_error: Res<FatalError>
+) {
+    if !fatal_error.is_set() {
+        return;
+    }
+    let details = fatal_error.get_details();
+    egui::Window::new("Something went wrong")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .show(contexts.ctx_mut(), |window| {
+            window.label("The game could not be started:");
+            egui::ScrollArea::vertical().max_height(240.).show(window, |scroll| {
+                scroll.monospace(&details);
+            });
+            if window.button("Copy details").clicked() {
+                window.output_mut(|o| o.copied_text = details.clone());
+            }
+        });
+}
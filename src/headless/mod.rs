@@ -0,0 +1,220 @@
+use std::{env, fs, time::Duration};
+
+use bevy::{
+    app::{AppExit, PluginGroupBuilder, ScheduleRunnerPlugin},
+    prelude::{App, DefaultPlugins, EventReader, EventWriter, IntoSystemConfig, Local, Plugin, PluginGroup, Res, ResMut, Resource},
+    render::{settings::WgpuSettings, RenderPlugin},
+    time::TimeUpdateStrategy,
+    window::{ExitCondition, WindowPlugin},
+    winit::WinitPlugin,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::FatalError,
+    world::{
+        attacker_controller::{record_round_result, RoundHistory, RoundSummary},
+        attackers::AttackerType,
+        defender_strategy::{find_strategy, ActiveStrategy},
+        defender_controller::ResourceStore,
+        events::{RequestRoundStart, RoundOverEvent},
+        rounds::RoundResource,
+    },
+};
+
+const SCRIPT_PATH: &str = "assets/benchmark_script.json";
+const DEFAULT_RUNS: u32 = 10;
+const DEFAULT_REPORT_PATH: &str = "headless_report.json";
+/* Every frame simulates this much time, no matter how long it took to compute */
+const FRAME_TIME: Duration = Duration::from_nanos(16_666_667);
+
+/* Units queued for each round, rounds past the end of the list start over from the first one */
+#[derive(Resource, Deserialize, Serialize, Debug, Default)]
+pub struct BenchmarkScript {
+    pub rounds: Vec<Vec<AttackerType>>,
+}
+
+impl BenchmarkScript {
+    pub fn load() -> Result<Self, String> {
+        let contents = fs::read_to_string(SCRIPT_PATH).map_err(|err| format!("Failed to read {}: {}", SCRIPT_PATH, err))?;
+        let script: Self = serde_json::from_str(&contents).map_err(|err| format!("Failed to parse {}: {}", SCRIPT_PATH, err))?;
+        if script.rounds.is_empty() {
+            return Err(format!("{}: needs at least one round", SCRIPT_PATH));
+        }
+        return Ok(script);
+    }
+
+    /* round is counted from 0 */
+    pub fn get_round(&self, round: usize) -> &[AttackerType] {
+        if self.rounds.is_empty() {
+            return &[];
+        }
+        return &self.rounds[round % self.rounds.len()];
+    }
+}
+
+/* How long the simulation runs, where the report goes and which defender strategy plays,
+   taken from HEADLESS_RUNS, HEADLESS_REPORT and HEADLESS_STRATEGY */
+#[derive(Resource, Debug)]
+pub struct HeadlessSettings {
+    pub runs: u32,
+    pub report_path: String,
+    pub strategy: Option<String>,
+}
+
+impl HeadlessSettings {
+    pub fn from_env() -> Self {
+        let runs = env::var("HEADLESS_RUNS").ok().and_then(|runs| runs.parse().ok()).unwrap_or(DEFAULT_RUNS);
+        let report_path = env::var("HEADLESS_REPORT").unwrap_or(DEFAULT_REPORT_PATH.to_string());
+        let strategy = env::var("HEADLESS_STRATEGY").ok();
+        return Self { runs, report_path, strategy };
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeadlessRound {
+    #[serde(flatten)]
+    pub summary: RoundSummary,
+    pub defender_lives: i32,
+    pub defender_gold: i32,
+}
+
+#[derive(Resource, Serialize, Debug, Default)]
+pub struct HeadlessReport {
+    pub strategy: &'static str,
+    pub rounds: Vec<HeadlessRound>,
+    /* Set when the simulation couldn't run at all */
+    pub error: Option<String>,
+}
+
+/* DefaultPlugins without a window or a GPU, everything else keeps working so the game's own plugins don't notice */
+pub fn headless_plugins() -> PluginGroupBuilder {
+    return DefaultPlugins
+        .set(WindowPlugin {
+            // Still registered, bevy_ui and the input plugins read its events
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        })
+        .set(RenderPlugin { wgpu_settings: WgpuSettings { backends: None, ..Default::default() } })
+        .disable::<WinitPlugin>();
+}
+
+/* Plays scripted rounds against the defender AI as fast as possible and writes a report when done */
+pub struct HeadlessController;
+
+impl Plugin for HeadlessController {
+    fn build(&self, app: &mut App) {
+        let mut report = HeadlessReport::default();
+        let script = match BenchmarkScript::load() {
+            Ok(script) => script,
+            Err(err) => {
+                report.error = Some(err);
+                BenchmarkScript::default()
+            }
+        };
+        let settings = HeadlessSettings::from_env();
+        let strategy = match settings.strategy.as_deref().map(|name| (name, find_strategy(name))) {
+            Some((_, Some(strategy))) => ActiveStrategy(strategy),
+            Some((name, None)) => {
+                report.error.get_or_insert_with(|| format!("HEADLESS_STRATEGY: no defender strategy named {}", name));
+                ActiveStrategy::default()
+            }
+            None => ActiveStrategy::default(),
+        };
+        report.strategy = strategy.0.get_name();
+        app.insert_resource(script)
+            .insert_resource(report)
+            .insert_resource(strategy)
+            .insert_resource(settings)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .add_plugin(ScheduleRunnerPlugin)
+            .add_system(start_rounds.after(record_round_result))
+            .add_system(stop_on_fatal_error)
+            .add_system(write_report.after(start_rounds).after(stop_on_fatal_error));
+    }
+}
+
+fn start_rounds(
+    mut round_over: EventReader<RoundOverEvent>,
+    mut round_start: EventWriter<RequestRoundStart>,
+    mut exit: EventWriter<AppExit>,
+    mut round: ResMut<RoundResource>,
+    mut report: ResMut<HeadlessReport>,
+    mut started: Local<bool>,
+    script: Res<BenchmarkScript>,
+    settings: Res<HeadlessSettings>,
+    history: Res<RoundHistory>,
+    store: Res<ResourceStore>,
+) {
+    if report.error.is_some() {
+        return;
+    }
+    let mut finished = !*started;
+    for _ in round_over.iter() {
+        if let Some(summary) = history.rounds.last() {
+            report.rounds.push(HeadlessRound { summary: summary.clone(), defender_lives: store.lives, defender_gold: store.gold });
+        }
+        finished = true;
+    }
+    if !finished {
+        return;
+    }
+    *started = true;
+    if report.rounds.len() as u32 >= settings.runs || store.lives <= 0 {
+        exit.send(AppExit);
+        return;
+    }
+    for attacker_type in script.get_round(report.rounds.len()) {
+        round.queue(attacker_type);
+    }
+    round_start.send(RequestRoundStart);
+}
+
+/* Nobody is there to read the error overlay, so it ends up in the report instead */
+fn stop_on_fatal_error(
+    fatal_error: Res<FatalError>,
+    mut report: ResMut<HeadlessReport>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if report.error.is_some() {
+        exit.send(AppExit);
+    } else if fatal_error.is_set() {
+        report.error = Some(fatal_error.get_details());
+        exit.send(AppExit);
+    }
+}
+
+fn write_report(
+    mut exit: EventReader<AppExit>,
+    report: Res<HeadlessReport>,
+    settings: Res<HeadlessSettings>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+    exit.clear();
+    match serde_json::to_string_pretty(&*report) {
+        Ok(json) => match fs::write(&settings.report_path, json) {
+            Ok(()) => println!("Wrote {} rounds to {}", report.rounds.len(), settings.report_path),
+            Err(err) => eprintln!("Failed to write {}: {}", settings.report_path, err),
+        },
+        Err(err) => eprintln!("Failed to serialize the headless report: {}", err),
+    }
+    if let Some(error) = &report.error {
+        eprintln!("{}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_rounds_start_over_after_the_last() {
+        let script: BenchmarkScript = serde_json::from_str(r#"{ "rounds": [["OrcWarrior", "OrcWarrior"], ["Golem"]] }"#).unwrap();
+        assert_eq!(script.get_round(0), &[AttackerType::OrcWarrior, AttackerType::OrcWarrior]);
+        assert_eq!(script.get_round(1), &[AttackerType::Golem]);
+        assert_eq!(script.get_round(2), script.get_round(0));
+    }
+}
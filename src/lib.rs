@@ -1,12 +1,25 @@
 use wasm_bindgen::prelude::*;
 
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::prelude::*;
+#[cfg(not(feature = "headless_simulation"))]
+use bevy::window::PrimaryWindow;
+#[cfg(not(feature = "headless_simulation"))]
 use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiSettings};
+use bridge::{BridgePlugin, HostCommand};
 use camera::CameraController;
+#[cfg(not(feature = "headless_simulation"))]
+use camera::MainCamera;
+use error::{ErrorPlugin, FatalError};
+#[cfg(not(feature = "headless_simulation"))]
 use particle::ParticlePlugin;
 use textures::TexturePlugin;
+#[cfg(not(feature = "headless_simulation"))]
 use ui::UiPlugin;
-use world::{TowerFieldPlugin, building_configuration::BuildingResource, attacker_controller::AttackerController, defender_controller::DefenderController};
+#[cfg(not(feature = "headless_simulation"))]
+use profile::ProfilePlugin;
+#[cfg(not(feature = "headless_simulation"))]
+use localization::LocalizationPlugin;
+use world::{TowerFieldPlugin, building_configuration::BuildingResource, building_registry::BuildingRegistry, damage_matrix::DamageMatrix, game_config::GameConfig, attacker_controller::AttackerController, attacker_ai::AttackerAi, defender_controller::DefenderController};
 
 pub mod world;
 pub mod textures;
@@ -14,37 +27,129 @@ pub mod util;
 pub mod camera;
 pub mod ui;
 pub mod particle;
+pub mod bridge;
+pub mod error;
+pub mod profile;
+pub mod localization;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "headless_simulation")]
+pub mod headless;
+#[cfg(test)]
+mod tests;
 
 #[wasm_bindgen]
 pub fn run() {
+    #[cfg(target_arch = "wasm32")]
+    console_error_panic_hook::set_once();
+
     let mut app = App::new();
 
+    let mut fatal_error = FatalError::default();
+    let registry = BuildingRegistry::default();
+    let mut buildings = match BuildingResource::load() {
+        Ok(buildings) => buildings,
+        Err(err) => {
+            fatal_error.report(err);
+            BuildingResource::empty()
+        }
+    };
+    registry.apply_default_sprites(&mut buildings);
+    let game_config = match GameConfig::load() {
+        Ok(config) => config,
+        Err(err) => {
+            fatal_error.report(err);
+            GameConfig::default()
+        }
+    };
+    let damage_matrix = match DamageMatrix::load() {
+        Ok(matrix) => matrix,
+        Err(err) => {
+            fatal_error.report(err);
+            DamageMatrix::default()
+        }
+    };
+
     app
         .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
-        .insert_resource(BuildingResource::new())
+        .insert_resource(buildings)
+        .insert_resource(registry)
+        .insert_resource(damage_matrix)
+        .insert_resource(game_config)
+        .insert_resource(fatal_error)
+        .init_resource::<util::GameRng>();
+
+    #[cfg(not(feature = "headless_simulation"))]
+    app
         .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
-        .add_plugin(EguiPlugin)
+        .add_plugin(EguiPlugin);
+    #[cfg(feature = "headless_simulation")]
+    app
+        .add_plugins(headless::headless_plugins())
+        // Nothing is drawn, so no particle is ever spawned
+        .insert_resource(particle::ParticleSettings { density: 0., ..Default::default() })
+        .add_plugin(headless::HeadlessController);
+
+    app
+        .add_plugin(ErrorPlugin)
         .add_plugin(TexturePlugin)
         .add_plugin(TowerFieldPlugin)
         .add_plugin(CameraController)
         .add_plugin(AttackerController)
+        .add_plugin(AttackerAi)
         .add_plugin(DefenderController)
+        .add_plugin(BridgePlugin);
+
+    #[cfg(not(feature = "headless_simulation"))]
+    app
         .add_plugin(UiPlugin)
         .add_plugin(ParticlePlugin)
+        // Simulated matches don't count towards the player's profile
+        .add_plugin(ProfilePlugin)
+        .add_plugin(LocalizationPlugin)
         // Systems that create Egui widgets should be run during the `CoreSet::Update` set,
         // or after the `EguiSet::BeginFrame` system (which belongs to the `CoreSet::PreUpdate` set).
         .add_startup_system(setup_graphics)
-        .add_system(update_ui_scale_factor)
-    .run();
+        .add_system(update_ui_scale_factor);
+
+    bridge::mark_initialized();
+    app.run();
+}
+
+#[wasm_bindgen]
+pub fn on_game_event(callback: js_sys::Function) {
+    bridge::set_callback(callback);
+}
+
+#[wasm_bindgen]
+pub fn set_kill_events(enabled: bool) {
+    bridge::set_kill_events(enabled);
+}
+
+#[wasm_bindgen]
+pub fn pause() {
+    bridge::push_command(HostCommand::Pause);
+}
+
+#[wasm_bindgen]
+pub fn resume() {
+    bridge::push_command(HostCommand::Resume);
+}
+
+#[wasm_bindgen]
+pub fn set_speed(speed: f32) {
+    bridge::push_command(HostCommand::SetSpeed(speed));
 }
 
 
+#[cfg(not(feature = "headless_simulation"))]
 fn setup_graphics(mut commands: Commands) {
     // Add a camera so we can see the debug-render.
     let mut camera = Camera2dBundle {..Default::default()};
-    commands.spawn(camera);
+    commands.spawn((camera, MainCamera));
 }
 
+#[cfg(not(feature = "headless_simulation"))]
 fn update_ui_scale_factor(mut egui_settings: ResMut<EguiSettings>, windows: Query<&Window, With<PrimaryWindow>>) {
     if let Ok(window) = windows.get_single() {
         egui_settings.scale_factor = 1.2 / window.scale_factor();
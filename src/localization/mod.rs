@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt::Display, fs};
+
+use bevy::{ecs::change_detection::DetectChanges, log::warn, prelude::{Plugin, App, Resource, Res}};
+
+use crate::util::storage;
+
+const LANGUAGE_FILE: &str = "language.txt";
+
+/* Code and the name listed in the language selector. Keys are the English texts, so English needs no table */
+pub const LANGUAGES: [(&str, &str); 2] = [("en", "English"), ("de", "Deutsch")];
+const KEY_LANGUAGE: &str = "en";
+
+type Table = HashMap<String, String>;
+
+/* Texts of the language picked in the menu, systems showing text take it as a resource.
+   The get_name functions hand out keys, they are looked up here like any other text */
+#[derive(Resource)]
+pub struct Localization {
+    language: &'static str,
+    table: Table,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        return Self { language: KEY_LANGUAGE, table: Table::new() };
+    }
+}
+
+impl Localization {
+    /* Reads assets/lang/<language>.json */
+    pub fn load(language: &'static str) -> Result<Self, String> {
+        if language == KEY_LANGUAGE {
+            return Ok(Self::default());
+        }
+        let path = format!("assets/lang/{}.json", language);
+        let contents = fs::read_to_string(&path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+        return Ok(Self { language, table: parse_table(&path, &contents)? });
+    }
+
+    pub fn from_json(language: &'static str, contents: &str) -> Result<Self, String> {
+        return Ok(Self { language, table: parse_table(language, contents)? });
+    }
+
+    pub fn get_language(&self) -> &'static str {
+        return self.language;
+    }
+
+    /* Missing translations show the key, i.e. the English text */
+    pub fn t<'a>(&'a self, key: &'a str) -> &'a str {
+        return self.table.get(key).map_or(key, |text| text.as_str());
+    }
+
+    /* t() for texts with values in them, every {} of the translation takes the next argument */
+    pub fn t_args(&self, key: &str, args: &[&dyn Display]) -> String {
+        return fill(self.t(key), args);
+    }
+}
+
+fn parse_table(source: &str, contents: &str) -> Result<Table, String> {
+    return serde_json::from_str(contents).map_err(|err| format!("Failed to parse {}: {}", source, err));
+}
+
+fn fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut parts = text.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    return filled;
+}
+
+fn saved_language() -> &'static str {
+    let saved = storage::load(LANGUAGE_FILE).unwrap_or_default();
+    return LANGUAGES.iter().map(|(code, _)| *code).find(|code| *code == saved.trim()).unwrap_or(KEY_LANGUAGE);
+}
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        let localization = Localization::load(saved_language()).unwrap_or_else(|err| {
+            warn!("{}", err);
+            Localization::default()
+        });
+        app
+            .insert_resource(localization)
+            .add_system(save_language);
+    }
+}
+
+/* The language loaded on startup came from the saved choice already */
+fn save_language(localization: Res<Localization>) {
+    if !localization.is_changed() || localization.is_added() {
+        return;
+    }
+    if let Err(err) = storage::save(LANGUAGE_FILE, localization.language) {
+        warn!("Failed to save the language: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_language_changes_known_keys() {
+        let english = Localization::load("en").unwrap();
+        let german = Localization::load("de").unwrap();
+        assert_eq!(english.t("Start Round"), "Start Round");
+        assert_eq!(german.t("Start Round"), "Runde starten");
+        assert_eq!(german.get_language(), "de");
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_the_key() {
+        let partial = Localization::from_json("de", r#"{ "Gold": "Gold" }"#).unwrap();
+        assert_eq!(partial.t("Not translated at all"), "Not translated at all");
+        assert_eq!(partial.t_args("Score: {}", &[&7]), "Score: 7");
+        assert!(Localization::from_json("de", "not json").is_err());
+        // Missing arguments leave the rest of the text alone
+        assert_eq!(fill("Round {}: {} units", &[&3]), "Round 3:  units");
+    }
+}
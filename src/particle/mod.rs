@@ -1,8 +1,9 @@
 use std::time::Duration;
 
-use bevy::{prelude::{Plugin, App, Bundle, Component, Commands, Vec2, Transform, Query, Entity, Res}, sprite::{SpriteSheetBundle, TextureAtlasSprite}, time::{Timer, Time}};
+use bevy::{prelude::{Plugin, App, Bundle, Component, Commands, Vec2, Vec3, Transform, Query, Entity, Res, Resource, IntoSystemConfig, Color, Camera, GlobalTransform, With, Without, Quat, EventReader}, sprite::{SpriteSheetBundle, TextureAtlasSprite, Sprite, SpriteBundle}, time::{Timer, Time}};
+use bevy_egui::{egui, EguiContexts, EguiSettings};
 
-use crate::{world::attackers::{AnimationIndices, AnimationTimer}, textures::TextureResource};
+use crate::{camera::MainCamera, world::{events::{BlinkEvent, DamageEvent, GoldStolenEvent}, towers::{DamageConsumerSet, DamageType}}, world::attackers::{AnimationIndices, AnimationTimer, Attacker, AnimationMode, AnimationPlayback, AnimationStep, advance_animation}, textures::{AnimationTiming, TextureResource}, error::GameplaySet, ui::util::world_to_egui};
 use rand::Rng;
 
 pub struct ParticlePreset {
@@ -24,7 +25,7 @@ pub enum ParticleBehaviour {
 pub struct Particle {
     timer: Timer,
     velocity: Vec2,
-    behavior: ParticleBehaviour
+    timing: AnimationTiming
 }
 
 #[derive(Bundle)]
@@ -32,19 +33,98 @@ pub struct ParticleBundle {
     particle: Particle,
     animation: AnimationIndices,
     animation_timer: AnimationTimer,
+    playback: AnimationPlayback,
     #[bundle]
     sprite: SpriteSheetBundle,
 }
 
+const FOOTSTEP_INTERVAL: f32 = 0.4;
+
+/* Emits dust behind a walking attacker, faster attackers step more often */
+#[derive(Component)]
+pub struct Footstep {
+    emit_interval: Timer
+}
+
+impl Footstep {
+    pub fn new(movement_speed: f32) -> Self {
+        let interval = FOOTSTEP_INTERVAL / (movement_speed / 40.).max(0.1);
+        return Self { emit_interval: Timer::from_seconds(interval, bevy::time::TimerMode::Repeating) };
+    }
+}
+
+#[derive(Resource)]
+pub struct ParticleSettings {
+    /* Off by default, a screen full of attackers spawns a lot of particles */
+    pub show_footsteps: bool,
+    /* Floating numbers for every hit, critical hits still get a "CRIT!" when this is off */
+    pub show_damage_numbers: bool,
+    /* Chance for each requested particle to actually spawn */
+    pub density: f32,
+    /* Oldest particles are removed once more than this are alive */
+    pub max_particles: usize,
+    pub quality: ParticleQuality
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        let mut settings = Self { show_footsteps: false, show_damage_numbers: true, density: 1., max_particles: 0, quality: ParticleQuality::High };
+        settings.apply_quality(ParticleQuality::High);
+        return settings;
+    }
+}
+
+impl ParticleSettings {
+    pub fn apply_quality(&mut self, quality: ParticleQuality) {
+        (self.density, self.max_particles) = match quality {
+            ParticleQuality::Low => (0.25, 100),
+            ParticleQuality::Medium => (0.6, 250),
+            ParticleQuality::High => (1., 600),
+        };
+        self.quality = quality;
+    }
+
+    /* Particles are purely cosmetic, so this doesn't go through GameRng and never changes the outcome of a round */
+    pub fn should_spawn(&self, rng: &mut impl Rng) -> bool {
+        return self.density >= 1. || rng.gen::<f32>() < self.density;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleQuality {
+    Low,
+    Medium,
+    High
+}
+
+impl ParticleQuality {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            ParticleQuality::Low => "Low",
+            ParticleQuality::Medium => "Medium",
+            ParticleQuality::High => "High",
+        };
+    }
+}
+
 pub struct ParticlePlugin;
 
 impl Plugin for ParticlePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_particles);
+        app.init_resource::<ParticleSettings>()
+            .add_system(update_particles.in_set(GameplaySet))
+            .add_system(enforce_particle_budget.in_set(GameplaySet).after(update_particles))
+            .add_system(spawn_footsteps.in_set(GameplaySet))
+            .add_system(update_floating_texts.in_set(GameplaySet))
+            .add_system(spawn_damage_texts.in_set(GameplaySet).in_set(DamageConsumerSet))
+            .add_system(spawn_stolen_gold_effects.in_set(GameplaySet))
+            .add_system(spawn_blink_effects.in_set(GameplaySet))
+            .add_system(update_lightning.in_set(GameplaySet))
+            .add_system(draw_floating_texts);
     }
 }
 
-pub fn spawn_large_explosion(commands: &mut Commands, transform: &Transform, textures: &TextureResource) {
+pub fn spawn_large_explosion(commands: &mut Commands, transform: &Transform, textures: &TextureResource, settings: &ParticleSettings) {
     spawn_particle(commands, &ParticlePreset {
         sprite_name: "large_explosion".to_string(),
         animation_name: "primary".to_string(),
@@ -52,10 +132,10 @@ pub fn spawn_large_explosion(commands: &mut Commands, transform: &Transform, tex
         frame_time: Duration::from_secs_f32(0.2),
         time_to_live: Duration::from_secs_f32(1.5),
         velocity: Vec2::ZERO
-    }, transform, textures)
+    }, transform, textures, settings)
 }
 
-pub fn spawn_blood_splatter(commands: &mut Commands, transform: &Transform, textures: &TextureResource) {
+pub fn spawn_blood_splatter(commands: &mut Commands, transform: &Transform, textures: &TextureResource, settings: &ParticleSettings) {
     spawn_particle(commands, &ParticlePreset {
         sprite_name: "blood_splatter".to_string(),
         animation_name: "primary".to_string(),
@@ -63,45 +143,307 @@ pub fn spawn_blood_splatter(commands: &mut Commands, transform: &Transform, text
         frame_time: Duration::from_secs_f32(0.4),
         time_to_live: Duration::from_secs_f32(1.5),
         velocity: Vec2::new(rand::thread_rng().gen_range(-1.0..1.), rand::thread_rng().gen_range(-1.0..1.))
-    }, transform, textures)
+    }, transform, textures, settings)
+}
+
+pub fn spawn_dust(commands: &mut Commands, transform: &Transform, textures: &TextureResource, settings: &ParticleSettings) {
+    spawn_particle(commands, &ParticlePreset {
+        sprite_name: "dust".to_string(),
+        animation_name: "primary".to_string(),
+        behavior: ParticleBehaviour::DespawnOnTTL,
+        frame_time: Duration::from_secs_f32(0.1),
+        time_to_live: Duration::from_secs_f32(0.3),
+        velocity: Vec2::new(rand::thread_rng().gen_range(-2.0..2.), rand::thread_rng().gen_range(0.0..3.))
+    }, transform, textures, settings)
 }
 
-pub fn spawn_coin(commands: &mut Commands, transform: &Transform, textures: &TextureResource) {
+/* Floats upwards, drifting sideways by `drift` */
+pub fn spawn_coin(commands: &mut Commands, transform: &Transform, drift: f32, textures: &TextureResource, settings: &ParticleSettings) {
     spawn_particle(commands, &ParticlePreset {
         sprite_name: "coin".to_string(),
         animation_name: "primary".to_string(),
         behavior: ParticleBehaviour::DespawnOnTTL,
         frame_time: Duration::from_secs_f32(1.2),
         time_to_live: Duration::from_secs_f32(1.5),
-        velocity: Vec2::new(0., 10. + rand::thread_rng().gen_range(0.0..5.))
-    }, transform, textures)
+        velocity: Vec2::new(drift, 10. + rand::thread_rng().gen_range(0.0..5.))
+    }, transform, textures, settings)
+}
+
+const MAX_DEATH_COINS: i32 = 8;
+/* Size of the Orc Warrior, its splatter is shown at the original size */
+const DEATH_SPLATTER_REFERENCE_SIZE: f32 = 44.;
+
+/* A coin per 10 bounty, so a Golem visibly pays more than a Spider */
+pub fn death_coin_count(bounty: i32) -> usize {
+    return (bounty / 10).clamp(1, MAX_DEATH_COINS) as usize;
+}
+
+pub fn death_splatter_scale(size: Vec2) -> f32 {
+    return (size.length() / DEATH_SPLATTER_REFERENCE_SIZE).clamp(0.5, 2.);
+}
+
+/* Cosmetic only, the bounty itself is paid out elsewhere */
+pub fn spawn_death_effects(commands: &mut Commands, transform: &Transform, bounty: i32, size: Vec2, with_coins: bool, textures: &TextureResource, settings: &ParticleSettings) {
+    spawn_blood_splatter(commands, &transform.with_scale(Vec3::splat(death_splatter_scale(size))), textures, settings);
+    if !with_coins {
+        return;
+    }
+    let coins = death_coin_count(bounty);
+    for i in 0..coins {
+        // Fanned out around the death position so the coins don't stack
+        let drift = (i as f32 - (coins - 1) as f32 / 2.) * 8.;
+        spawn_coin(commands, transform, drift, textures, settings);
+    }
 }
 
-pub fn spawn_particle(commands: &mut Commands, preset: &ParticlePreset, transform: &Transform, textures: &TextureResource) {
+const COIN_BURST_SIZE: usize = 6;
+
+/* Coins flying off in every direction, spawn_coin only floats a single one upwards */
+pub fn spawn_coin_burst(commands: &mut Commands, transform: &Transform, textures: &TextureResource, settings: &ParticleSettings) {
+    let mut rng = rand::thread_rng();
+    for i in 0..COIN_BURST_SIZE {
+        let angle = std::f32::consts::TAU * (i as f32 + rng.gen_range(0.0..0.5)) / COIN_BURST_SIZE as f32;
+        spawn_particle(commands, &ParticlePreset {
+            sprite_name: "coin".to_string(),
+            animation_name: "primary".to_string(),
+            behavior: ParticleBehaviour::DespawnOnTTL,
+            frame_time: Duration::from_secs_f32(0.3),
+            time_to_live: Duration::from_secs_f32(0.8),
+            velocity: Vec2::from_angle(angle) * rng.gen_range(20.0..35.)
+        }, transform, textures, settings)
+    }
+}
+
+fn spawn_stolen_gold_effects(
+    mut commands: Commands,
+    mut stolen: EventReader<GoldStolenEvent>,
+    textures: Res<TextureResource>,
+    settings: Res<ParticleSettings>
+) {
+    for ev in stolen.iter() {
+        let transform = Transform::from_translation(ev.position.extend(20.));
+        spawn_coin_burst(&mut commands, &transform, &textures, &settings);
+        spawn_floating_text(&mut commands, &transform, format!("-{}g", ev.amount), Color::GOLD);
+    }
+}
+
+fn spawn_blink_effects(
+    mut commands: Commands,
+    mut blinks: EventReader<BlinkEvent>,
+    textures: Res<TextureResource>,
+    settings: Res<ParticleSettings>
+) {
+    for ev in blinks.iter() {
+        for position in [ev.from, ev.to] {
+            spawn_dust(&mut commands, &Transform::from_translation(position.extend(20.)), &textures, &settings);
+        }
+    }
+}
+
+/* Text has no sprite atlas, so it lives at a world position and is painted by egui */
+#[derive(Component)]
+pub struct FloatingText {
+    text: String,
+    color: Color,
+    timer: Timer,
+    velocity: Vec2,
+    size: f32
+}
+
+const FLOATING_TEXT_SECONDS: f32 = 0.8;
+const FLOATING_TEXT_SIZE: f32 = 14.;
+const CRITICAL_TEXT_SIZE: f32 = 20.;
+/* Hits on the same target within this window add up in one number instead of stacking new ones */
+const DAMAGE_MERGE_SECONDS: f32 = 0.25;
+
+/* Marks a floating text that shows the damage taken by `target` */
+#[derive(Component)]
+pub struct DamageText {
+    target: Entity,
+    total: f32,
+    critical: bool,
+    merge_window: Timer
+}
+
+impl DamageText {
+    fn label(&self) -> String {
+        return if self.critical { format!("{:.0}!", self.total) } else { format!("{:.0}", self.total) };
+    }
+
+    fn size(&self) -> f32 {
+        return if self.critical { CRITICAL_TEXT_SIZE } else { FLOATING_TEXT_SIZE };
+    }
+}
+
+pub fn damage_color(damage_type: DamageType) -> Color {
+    return match damage_type {
+        DamageType::Magic => Color::rgb(0.75, 0.5, 1.),
+        DamageType::Piercing => Color::rgb(1., 1., 0.85),
+        DamageType::Crushing => Color::rgb(0.8, 0.8, 0.8),
+        DamageType::Explosive => Color::ORANGE,
+        DamageType::Fire => Color::rgb(1., 0.4, 0.1),
+        DamageType::Ice => Color::rgb(0.6, 0.9, 1.),
+        DamageType::Lightning => Color::rgb(1., 0.95, 0.4),
+        DamageType::Earth => Color::rgb(0.65, 0.5, 0.3),
+    };
+}
+
+/* Damage numbers of hits that only chipped away at a shield */
+const SHIELD_TEXT_COLOR: Color = Color::rgb(0.55, 0.85, 1.);
+
+const LIGHTNING_SECONDS: f32 = 0.15;
+const LIGHTNING_WIDTH: f32 = 3.;
+
+/* A plain sprite stretched between two points, fades out over its lifetime */
+#[derive(Component)]
+pub struct Lightning {
+    timer: Timer
+}
+
+pub fn spawn_lightning(commands: &mut Commands, from: Vec2, to: Vec2, settings: &ParticleSettings) {
+    if !settings.should_spawn(&mut rand::thread_rng()) {
+        return;
+    }
+    let offset = to - from;
+    commands.spawn((
+        Lightning { timer: Timer::from_seconds(LIGHTNING_SECONDS, bevy::time::TimerMode::Once) },
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.7, 0.85, 1.),
+                custom_size: Some(Vec2::new(offset.length(), LIGHTNING_WIDTH)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(((from + to) / 2.).extend(40.))
+                .with_rotation(Quat::from_rotation_z(offset.y.atan2(offset.x))),
+            ..Default::default()
+        }
+    ));
+}
+
+pub(crate) fn update_lightning(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Lightning, &mut Sprite)>,
+    time: Res<Time>
+) {
+    for (entity, mut lightning, mut sprite) in query.iter_mut() {
+        lightning.timer.tick(time.delta());
+        if lightning.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(lightning.timer.percent_left());
+        }
+    }
+}
+
+pub fn spawn_floating_text(commands: &mut Commands, transform: &Transform, text: impl Into<String>, color: Color) {
+    commands.spawn((
+        FloatingText::new(text.into(), color, FLOATING_TEXT_SIZE),
+        Transform::from_translation(transform.translation)
+    ));
+}
+
+impl FloatingText {
+    fn new(text: String, color: Color, size: f32) -> Self {
+        return Self {
+            text,
+            color,
+            timer: Timer::from_seconds(FLOATING_TEXT_SECONDS, bevy::time::TimerMode::Once),
+            velocity: Vec2::new(0., 30.),
+            size
+        };
+    }
+}
+
+fn spawn_damage_texts(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut texts: Query<(&mut DamageText, &mut FloatingText)>,
+    targets: Query<&Transform, Without<FloatingText>>,
+    settings: Res<ParticleSettings>,
+    time: Res<Time>
+) {
+    for (mut damage_text, _) in texts.iter_mut() {
+        damage_text.merge_window.tick(time.delta());
+    }
+    // Texts spawned this frame only show up in the query next frame, so hits in the same frame are merged here
+    let mut spawned: Vec<(DamageText, Color)> = Vec::new();
+    for ev in damage_events.iter() {
+        if !settings.show_damage_numbers {
+            if let (true, Ok(transform)) = (ev.critical, targets.get(ev.target)) {
+                spawn_floating_text(&mut commands, transform, "CRIT!", Color::ORANGE_RED);
+            }
+            continue;
+        }
+        if let Some((mut damage_text, mut floating_text)) = texts
+            .iter_mut()
+            .find(|(damage_text, _)| damage_text.target == ev.target && !damage_text.merge_window.finished())
+        {
+            damage_text.total += ev.amount;
+            damage_text.critical |= ev.critical;
+            damage_text.merge_window.reset();
+            floating_text.text = damage_text.label();
+            floating_text.size = damage_text.size();
+            floating_text.timer.reset();
+            continue;
+        }
+        if let Some((damage_text, _)) = spawned.iter_mut().find(|(damage_text, _)| damage_text.target == ev.target) {
+            damage_text.total += ev.amount;
+            damage_text.critical |= ev.critical;
+            continue;
+        }
+        spawned.push((DamageText {
+            target: ev.target,
+            total: ev.amount,
+            critical: ev.critical,
+            merge_window: Timer::from_seconds(DAMAGE_MERGE_SECONDS, bevy::time::TimerMode::Once)
+        }, if ev.absorbed >= ev.amount { SHIELD_TEXT_COLOR } else { damage_color(ev.damage_type) }));
+    }
+    for (damage_text, color) in spawned {
+        let Ok(transform) = targets.get(damage_text.target) else {
+            continue;
+        };
+        commands.spawn((
+            FloatingText::new(damage_text.label(), color, damage_text.size()),
+            // Slightly above and in front of the target so the number doesn't hide behind it
+            Transform::from_translation(transform.translation + Vec3::new(0., 8., 1.)),
+            damage_text
+        ));
+    }
+}
+
+pub fn spawn_particle(commands: &mut Commands, preset: &ParticlePreset, transform: &Transform, textures: &TextureResource, settings: &ParticleSettings) {
+    if !settings.should_spawn(&mut rand::thread_rng()) {
+        return;
+    }
     let animation = textures.get_animation(&preset.sprite_name, &preset.animation_name);
+    let timing = textures.get_animation_timing(&preset.sprite_name, &preset.animation_name, preset.frame_time.as_secs_f32());
     commands.spawn(ParticleBundle {
+        animation_timer: AnimationTimer(Timer::new(timing.get_frame_time(0), bevy::time::TimerMode::Repeating)),
         particle: Particle {
             timer: Timer::from_seconds(preset.time_to_live.as_secs_f32(), bevy::time::TimerMode::Once),
             velocity: preset.velocity,
-            behavior: preset.behavior
+            timing
         },
-        animation_timer: AnimationTimer(Timer::new(preset.frame_time, bevy::time::TimerMode::Repeating)),
+        playback: AnimationPlayback::default(),
         sprite: SpriteSheetBundle { 
             sprite: TextureAtlasSprite::new(animation.1.start), 
             texture_atlas: animation.0.clone_weak(), 
             transform: *transform, 
             ..Default::default()
         },
-        animation: AnimationIndices { start: animation.1.start, end: animation.1.end }
+        animation: match preset.behavior {
+            ParticleBehaviour::DespawnLastFrame => animation.1.with_mode(AnimationMode::OnceThenDespawn),
+            ParticleBehaviour::DespawnOnTTL => *animation.1
+        }
     });
 }
 
 pub fn update_particles(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &mut Particle, &mut TextureAtlasSprite, &mut AnimationTimer, &AnimationIndices)>,
+    mut query: Query<(Entity, &mut Transform, &mut Particle, &mut TextureAtlasSprite, &mut AnimationTimer, &mut AnimationPlayback, &AnimationIndices)>,
     time: Res<Time>
 ) {
-    for (entity, mut transform, mut particle, mut sprite, mut animation_timer, animation_index) in query.iter_mut() {
+    for (entity, mut transform, mut particle, mut sprite, mut animation_timer, mut playback, animation_index) in query.iter_mut() {
         particle.timer.tick(time.delta());
         animation_timer.0.tick(time.delta());
         if particle.timer.finished() {
@@ -109,25 +451,202 @@ pub fn update_particles(
         } else {
             transform.translation += particle.velocity.extend(0.) * time.delta_seconds();
             if animation_timer.0.just_finished() {
-                let index = sprite.index;
-                if animation_index.start == animation_index.end && particle.behavior == ParticleBehaviour::DespawnOnTTL {
-                    sprite.index = animation_index.start;
-                } else if animation_index.start == animation_index.end && particle.behavior == ParticleBehaviour::DespawnLastFrame {
-                    commands.entity(entity).despawn();
-                } else {
-                    if index > animation_index.end || index < animation_index.start {
-                        sprite.index = animation_index.start;
-                    } else {
-                        sprite.index = if sprite.index >= animation_index.end {
-                            animation_index.start
-                        } else {
-                            sprite.index + 1
-                        }
+                match advance_animation(animation_index, sprite.index, &mut playback) {
+                    AnimationStep::Frame(index) => {
+                        sprite.index = index;
+                        animation_timer.0.set_duration(particle.timing.get_frame_time(sprite.index - animation_index.start));
+                    }
+                    AnimationStep::Despawn => {
+                        commands.entity(entity).despawn();
                     }
                 }
-                
             }
         }
+    }
+}
+
+/* Particles that lived the longest go first, their timers have the most elapsed time */
+fn enforce_particle_budget(
+    mut commands: Commands,
+    query: Query<(Entity, &Particle)>,
+    settings: Res<ParticleSettings>
+) {
+    let alive = query.iter().count();
+    if alive <= settings.max_particles {
+        return;
+    }
+    let mut particles: Vec<(Entity, Duration)> = query.iter().map(|(entity, particle)| (entity, particle.timer.elapsed())).collect();
+    particles.sort_by_key(|(_, age)| std::cmp::Reverse(*age));
+    for (entity, _) in particles.iter().take(alive - settings.max_particles) {
+        commands.entity(*entity).despawn();
+    }
+}
+
+fn spawn_footsteps(
+    mut commands: Commands,
+    mut query: Query<(&Attacker, &Transform, &mut Footstep)>,
+    settings: Res<ParticleSettings>,
+    textures: Res<TextureResource>,
+    time: Res<Time>
+) {
+    // The dust atlas is optional, without it there is nothing to show
+    if !settings.show_footsteps || !textures.has_animation("dust", "primary") {
+        return;
+    }
+    for (attacker, transform, mut footstep) in query.iter_mut() {
+        footstep.emit_interval.tick(time.delta());
+        if footstep.emit_interval.just_finished() && attacker.velocity.length() > 1.0 {
+            let behind = transform.translation.truncate() - attacker.velocity.normalize_or_zero() * attacker.size.y / 4.;
+            // Just below the attacker so the dust doesn't cover it
+            let dust_transform = Transform::from_translation(behind.extend(transform.translation.z - 0.1));
+            spawn_dust(&mut commands, &dust_transform, &textures, &settings);
+        }
+    }
+}
+
+pub(crate) fn update_floating_texts(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut FloatingText)>,
+    time: Res<Time>
+) {
+    for (entity, mut transform, mut floating_text) in query.iter_mut() {
+        floating_text.timer.tick(time.delta());
+        if floating_text.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            transform.translation += floating_text.velocity.extend(0.) * time.delta_seconds();
+        }
+    }
+}
 
+fn draw_floating_texts(
+    mut contexts: EguiContexts,
+    query: Query<(&Transform, &FloatingText)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    egui_settings: Res<EguiSettings>
+) {
+    let Ok((camera, camera_transform)) = camera_q.get_single() else {
+        return;
+    };
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("floating_texts")));
+    for (transform, floating_text) in query.iter() {
+        if let Some(position) = world_to_egui(camera, camera_transform, transform.translation, egui_settings.scale_factor) {
+            let alpha = 1. - floating_text.timer.percent();
+            let [r, g, b, _] = floating_text.color.as_rgba_f32();
+            painter.text(
+                position,
+                egui::Align2::CENTER_CENTER,
+                &floating_text.text,
+                egui::FontId::proportional(floating_text.size),
+                egui::Color32::from_rgba_unmultiplied((r * 255.) as u8, (g * 255.) as u8, (b * 255.) as u8, (alpha * 255.) as u8)
+            );
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::{event::Events, system::CommandQueue}, prelude::{Schedule, World}};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn zero_density_never_spawns() {
+        let settings = ParticleSettings { density: 0., ..Default::default() };
+        let mut rng = StdRng::seed_from_u64(2116);
+        assert!((0..1000).all(|_| !settings.should_spawn(&mut rng)));
+    }
+
+    #[test]
+    fn particle_budget_removes_the_oldest() {
+        let mut world = World::new();
+        world.insert_resource(ParticleSettings { max_particles: 10, ..Default::default() });
+        for i in 0..25 {
+            let mut timer = Timer::from_seconds(10., bevy::time::TimerMode::Once);
+            timer.tick(Duration::from_millis(i * 100));
+            world.spawn(Particle { timer, velocity: Vec2::ZERO, timing: AnimationTiming::uniform(0.1) });
+        }
+        let mut schedule = Schedule::new();
+        schedule.add_system(enforce_particle_budget);
+        schedule.run(&mut world);
+
+        let mut ages: Vec<Duration> = world.query::<&Particle>().iter(&world).map(|particle| particle.timer.elapsed()).collect();
+        ages.sort();
+        assert_eq!(ages.len(), 10);
+        assert_eq!(*ages.last().unwrap(), Duration::from_millis(900));
+    }
+
+    #[test]
+    fn damage_event_spawns_number_at_target() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        world.insert_resource(ParticleSettings::default());
+        world.insert_resource(Time::default());
+        let target = world.spawn(Transform::from_xyz(100., 50., 10.)).id();
+        world.resource_mut::<Events<DamageEvent>>().send(DamageEvent { amount: 37., absorbed: 0., target, damage_type: DamageType::Piercing, critical: false });
+        let mut schedule = Schedule::new();
+        schedule.add_system(spawn_damage_texts);
+        schedule.run(&mut world);
+
+        let texts: Vec<(Vec3, String)> = world
+            .query::<(&Transform, &FloatingText)>()
+            .iter(&world)
+            .map(|(transform, text)| (transform.translation, text.text.clone()))
+            .collect();
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].1, "37");
+        assert!(texts[0].0.truncate().distance(Vec2::new(100., 50.)) < 20.);
+    }
+
+    #[test]
+    fn rapid_hits_on_one_target_merge() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        world.insert_resource(ParticleSettings::default());
+        world.insert_resource(Time::default());
+        let target = world.spawn(Transform::default()).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(spawn_damage_texts);
+        for amount in [10., 15.] {
+            world.resource_mut::<Events<DamageEvent>>().send(DamageEvent { amount, absorbed: 0., target, damage_type: DamageType::Magic, critical: false });
+            schedule.run(&mut world);
+            world.resource_mut::<Events<DamageEvent>>().update();
+        }
+
+        let texts: Vec<String> = world.query::<&FloatingText>().iter(&world).map(|text| text.text.clone()).collect();
+        assert_eq!(texts, vec!["25".to_string()]);
+    }
+
+    #[test]
+    fn valuable_attackers_spill_more_coins() {
+        use crate::world::attackers::{GOLEM_STATS, SPIDER_STATS};
+
+        assert!(death_coin_count(GOLEM_STATS.bounty) > death_coin_count(SPIDER_STATS.bounty));
+        assert_eq!(death_coin_count(0), 1);
+        assert_eq!(death_coin_count(1000), MAX_DEATH_COINS as usize);
+        assert!(death_splatter_scale(GOLEM_STATS.size) > death_splatter_scale(SPIDER_STATS.size));
+    }
+
+    #[test]
+    fn killing_a_high_bounty_attacker_spawns_more_coin_particles() {
+        use crate::world::attackers::{GOLEM_STATS, SPIDER_STATS};
+
+        let textures = TextureResource::with_animations(&[("blood_splatter", "primary"), ("coin", "primary")]);
+        let settings = ParticleSettings::default();
+        let particles = |bounty: i32, with_coins: bool| {
+            let mut world = World::new();
+            let mut queue = CommandQueue::default();
+            let mut commands = Commands::new(&mut queue, &world);
+            spawn_death_effects(&mut commands, &Transform::default(), bounty, Vec2::ONE, with_coins, &textures, &settings);
+            queue.apply(&mut world);
+            return world.query::<&Particle>().iter(&world).count();
+        };
+        // One of them is the splatter
+        assert_eq!(particles(SPIDER_STATS.bounty, true), 1 + death_coin_count(SPIDER_STATS.bounty));
+        assert_eq!(particles(GOLEM_STATS.bounty, true), 1 + death_coin_count(GOLEM_STATS.bounty));
+        assert!(particles(GOLEM_STATS.bounty, true) > particles(SPIDER_STATS.bounty, true));
+        // Gold that has to be collected is shown by the pickup itself
+        assert_eq!(particles(GOLEM_STATS.bounty, false), 1);
+    }
+}
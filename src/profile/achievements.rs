@@ -0,0 +1,227 @@
+use bevy::{ecs::change_detection::DetectChanges, prelude::{Plugin, App, Resource, Res, ResMut, EventReader, EventWriter, Query, Added, IntoSystemConfig}};
+
+use crate::world::{
+    attacker_controller::ComboTracker,
+    attackers::AttackerType,
+    building_configuration::BuildingType,
+    endless::EndlessScore,
+    events::{BlinkEvent, EntityReachedEnd, GameOverEvent, GoldStolenEvent, KillEvent, RemovedStructureEvent, RoundOverEvent, RoundStartEvent},
+    mutators::{activate_mutators, RoundModifiers},
+    rounds::RoundResource,
+    sudden_death::SuddenDeathState,
+};
+
+use super::PlayerProfile;
+
+/* What has to happen in a single match to unlock an achievement */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Requirement {
+    LeaksInOneRound(u32),
+    WinWithout(AttackerType),
+    WinWithinRounds(u32),
+    DefenderSells(u32),
+    GoldStolen(i32),
+    Blinks(u32),
+    Combo(u32),
+    UnitsKilled(u32),
+    ReachSuddenDeath,
+    EndlessScore(i32),
+    RoundsWithMutators(u32),
+}
+
+pub struct Achievement {
+    /* Stored in the profile, never change it once released */
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub requirement: Requirement,
+}
+
+pub const ACHIEVEMENTS: [Achievement; 11] = [
+    Achievement { id: "flood_gate", name: "Flood Gate", description: "Leak 10 units in one round", requirement: Requirement::LeaksInOneRound(10) },
+    Achievement { id: "lightweight", name: "Lightweight", description: "Win without sending a single Golem", requirement: Requirement::WinWithout(AttackerType::Golem) },
+    Achievement { id: "blitz", name: "Blitz", description: "Win within 10 rounds", requirement: Requirement::WinWithinRounds(10) },
+    Achievement { id: "eviction", name: "Eviction", description: "Force the defender to sell 5 towers", requirement: Requirement::DefenderSells(5) },
+    Achievement { id: "pickpocket", name: "Pickpocket", description: "Steal 100 gold with Thieves", requirement: Requirement::GoldStolen(100) },
+    Achievement { id: "now_you_see_me", name: "Now You See Me", description: "Blink 25 times with Phantoms", requirement: Requirement::Blinks(25) },
+    Achievement { id: "chain_reaction", name: "Chain Reaction", description: "Leak 8 units in one combo", requirement: Requirement::Combo(8) },
+    Achievement { id: "cannon_fodder", name: "Cannon Fodder", description: "Lose 100 units in one match", requirement: Requirement::UnitsKilled(100) },
+    Achievement { id: "overtime", name: "Overtime", description: "Drag a match into sudden death", requirement: Requirement::ReachSuddenDeath },
+    Achievement { id: "marathon", name: "Marathon", description: "Score 100 in endless mode", requirement: Requirement::EndlessScore(100) },
+    Achievement { id: "mad_scientist", name: "Mad Scientist", description: "Play 5 rounds with mutators", requirement: Requirement::RoundsWithMutators(5) },
+];
+
+/* Everything the requirements look at, counted over the running match */
+#[derive(Resource, Default)]
+pub struct AchievementStats {
+    pub leaks_this_round: u32,
+    pub most_leaks_in_a_round: u32,
+    pub units_sent: Vec<AttackerType>,
+    pub towers_sold: u32,
+    pub gold_stolen: i32,
+    pub blinks: u32,
+    pub best_combo: u32,
+    pub units_killed: u32,
+    pub reached_sudden_death: bool,
+    pub endless_score: i32,
+    pub rounds_with_mutators: u32,
+    /* Set once the match is over, with the round it ended in */
+    pub won_in_round: Option<u32>,
+}
+
+impl Requirement {
+    pub fn is_met(&self, stats: &AchievementStats) -> bool {
+        return match *self {
+            Requirement::LeaksInOneRound(leaks) => stats.most_leaks_in_a_round >= leaks,
+            Requirement::WinWithout(attacker_type) => stats.won_in_round.is_some() && !stats.units_sent.contains(&attacker_type),
+            Requirement::WinWithinRounds(rounds) => stats.won_in_round.is_some_and(|round| round <= rounds),
+            Requirement::DefenderSells(sells) => stats.towers_sold >= sells,
+            Requirement::GoldStolen(gold) => stats.gold_stolen >= gold,
+            Requirement::Blinks(blinks) => stats.blinks >= blinks,
+            Requirement::Combo(combo) => stats.best_combo >= combo,
+            Requirement::UnitsKilled(units) => stats.units_killed >= units,
+            Requirement::ReachSuddenDeath => stats.reached_sudden_death,
+            Requirement::EndlessScore(score) => stats.endless_score >= score,
+            Requirement::RoundsWithMutators(rounds) => stats.rounds_with_mutators >= rounds,
+        };
+    }
+}
+
+/* Index into ACHIEVEMENTS of an achievement that was just unlocked */
+pub struct AchievementUnlocked(pub usize);
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AchievementStats>()
+            .add_event::<AchievementUnlocked>()
+            .add_system(count_achievement_events)
+            .add_system(sample_achievement_stats.after(activate_mutators))
+            .add_system(unlock_achievements.after(count_achievement_events).after(sample_achievement_stats));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn count_achievement_events(
+    mut stats: ResMut<AchievementStats>,
+    mut leaks: EventReader<EntityReachedEnd>,
+    mut round_end: EventReader<RoundOverEvent>,
+    mut removals: EventReader<RemovedStructureEvent>,
+    mut stolen: EventReader<GoldStolenEvent>,
+    mut blinks: EventReader<BlinkEvent>,
+    mut kills: EventReader<KillEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    round: Res<RoundResource>
+) {
+    stats.leaks_this_round += leaks.iter().count() as u32;
+    stats.most_leaks_in_a_round = stats.most_leaks_in_a_round.max(stats.leaks_this_round);
+    if round_end.iter().count() > 0 {
+        stats.leaks_this_round = 0;
+    }
+    // Walls are sold to reroute the path, only towers count
+    stats.towers_sold += removals.iter().filter(|ev| ev.building_type != BuildingType::Wall && !ev.destroyed).count() as u32;
+    stats.gold_stolen += stolen.iter().map(|ev| ev.amount).sum::<i32>();
+    stats.blinks += blinks.iter().count() as u32;
+    stats.units_killed += kills.iter().count() as u32;
+    for ev in game_over.iter() {
+        if ev.attacker_won {
+            stats.won_in_round = Some(round.get_round_number());
+        }
+    }
+}
+
+fn sample_achievement_stats(
+    mut stats: ResMut<AchievementStats>,
+    spawned: Query<&AttackerType, Added<AttackerType>>,
+    mut round_start: EventReader<RoundStartEvent>,
+    combo: Res<ComboTracker>,
+    sudden_death: Res<SuddenDeathState>,
+    endless_score: Res<EndlessScore>,
+    modifiers: Res<RoundModifiers>
+) {
+    for attacker_type in &spawned {
+        if !stats.units_sent.contains(attacker_type) {
+            stats.units_sent.push(*attacker_type);
+        }
+    }
+    // Mutators are activated by the same event, a round counts if any were picked for it
+    if round_start.iter().count() > 0 && !modifiers.get_active().is_empty() {
+        stats.rounds_with_mutators += 1;
+    }
+    stats.best_combo = stats.best_combo.max(combo.max_this_round);
+    stats.reached_sudden_death |= sudden_death.active;
+    stats.endless_score = stats.endless_score.max(endless_score.score);
+}
+
+pub(crate) fn unlock_achievements(
+    stats: Res<AchievementStats>,
+    mut profile: ResMut<PlayerProfile>,
+    mut unlocked: EventWriter<AchievementUnlocked>
+) {
+    if !stats.is_changed() {
+        return;
+    }
+    for (index, achievement) in ACHIEVEMENTS.iter().enumerate() {
+        if !profile.achievements.contains(achievement.id) && achievement.requirement.is_met(&stats) {
+            profile.achievements.insert(achievement.id.to_string());
+            unlocked.send(AchievementUnlocked(index));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, Events};
+
+    use crate::{tests::{schedule_of, system_test_world}, world::attackers::LeakEffect};
+
+    use super::*;
+
+    #[test]
+    fn wins_only_count_without_the_excluded_unit() {
+        let mut stats = AchievementStats { won_in_round: Some(14), ..Default::default() };
+        stats.units_sent.push(AttackerType::Spider);
+        assert!(Requirement::WinWithout(AttackerType::Golem).is_met(&stats));
+        assert!(!Requirement::WinWithinRounds(10).is_met(&stats));
+        stats.units_sent.push(AttackerType::Golem);
+        assert!(!Requirement::WinWithout(AttackerType::Golem).is_met(&stats));
+        // Not winning at all never counts as winning without golems
+        assert!(!Requirement::WinWithout(AttackerType::Golem).is_met(&AchievementStats::default()));
+    }
+
+    #[test]
+    fn leaks_of_one_round_unlock_flood_gate_once() {
+        let mut world = system_test_world();
+        world.init_resource::<AchievementStats>();
+        world.init_resource::<PlayerProfile>();
+        world.init_resource::<RoundResource>();
+        world.init_resource::<Events<AchievementUnlocked>>();
+        let mut schedule = schedule_of(count_achievement_events);
+        schedule.add_system(unlock_achievements.after(count_achievement_events));
+        let leak = || EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None };
+
+        // Leaks of two rounds don't add up
+        for _ in 0..6 {
+            world.send_event(leak());
+        }
+        schedule.run(&mut world);
+        world.send_event(RoundOverEvent);
+        schedule.run(&mut world);
+        for _ in 0..6 {
+            world.send_event(leak());
+        }
+        schedule.run(&mut world);
+        assert!(world.resource::<PlayerProfile>().achievements.is_empty());
+
+        for _ in 0..4 {
+            world.send_event(leak());
+        }
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert!(world.resource::<PlayerProfile>().achievements.contains("flood_gate"));
+        let unlocked = world.resource::<Events<AchievementUnlocked>>();
+        assert_eq!(unlocked.get_reader().iter(unlocked).count(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{ecs::change_detection::DetectChanges, log::warn, prelude::{Plugin, App, Resource, Res, ResMut, EventReader, Query, Added, IntoSystemConfig}};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    util::storage,
+    world::{
+        attacker_controller::{AttackerResource, RoundHistory},
+        attackers::{AttackerStats, AttackerType, UpgradeType},
+        events::GameOverEvent,
+        rounds::RoundResource,
+    },
+};
+
+use self::achievements::{unlock_achievements, AchievementsPlugin};
+
+pub mod achievements;
+
+const PROFILE_FILE: &str = "player_profile.json";
+
+/* Lifetime stats of the attacker over every match played on this machine */
+#[derive(Resource, Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlayerProfile {
+    pub matches_played: u32,
+    pub matches_won: u32,
+    /* Fewest rounds a won match took */
+    pub fastest_victory_rounds: Option<u32>,
+    pub units_sent: BTreeMap<AttackerType, u32>,
+    pub upgrades_bought: BTreeMap<UpgradeType, u32>,
+    pub gold_earned: i64,
+    /* Ids of every unlocked achievement, see achievements::ACHIEVEMENTS */
+    pub achievements: BTreeSet<String>,
+}
+
+impl PlayerProfile {
+    /* Anything unreadable starts a fresh profile, fields missing from older profiles start at zero */
+    pub fn from_json(json: &str) -> Self {
+        return serde_json::from_str(json).unwrap_or_default();
+    }
+
+    pub fn load() -> Self {
+        return storage::load(PROFILE_FILE).map(|json| Self::from_json(&json)).unwrap_or_default();
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string_pretty(self).map_err(|err| err.to_string()).and_then(|json| storage::save(PROFILE_FILE, &json));
+        if let Err(err) = result {
+            warn!("Failed to save the player profile: {}", err);
+        }
+    }
+
+    /* Most bought upgrade, ties go to the one listed first */
+    // Option::is_none_or would need Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn get_favorite_upgrade(&self) -> Option<UpgradeType> {
+        let mut favorite: Option<(UpgradeType, u32)> = None;
+        for (upgrade, count) in &self.upgrades_bought {
+            if favorite.map_or(true, |(_, most)| *count > most) {
+                favorite = Some((*upgrade, *count));
+            }
+        }
+        return favorite.map(|(upgrade, _)| upgrade);
+    }
+
+    pub fn get_win_rate(&self) -> f32 {
+        if self.matches_played == 0 {
+            return 0.;
+        }
+        return self.matches_won as f32 / self.matches_played as f32;
+    }
+
+    pub fn record_match(&mut self, record: &MatchRecord, won: bool) {
+        self.matches_played += 1;
+        if won {
+            self.matches_won += 1;
+            self.fastest_victory_rounds = Some(self.fastest_victory_rounds.map_or(record.rounds, |fastest| fastest.min(record.rounds)));
+        }
+        for (attacker_type, count) in &record.units_sent {
+            *self.units_sent.entry(*attacker_type).or_default() += count;
+        }
+        for (upgrade, count) in &record.upgrades_bought {
+            *self.upgrades_bought.entry(*upgrade).or_default() += count;
+        }
+        self.gold_earned += record.gold_earned as i64;
+    }
+}
+
+/* What the running match adds to the profile once it is over */
+#[derive(Resource, Default)]
+pub struct MatchRecord {
+    pub rounds: u32,
+    pub units_sent: BTreeMap<AttackerType, u32>,
+    pub upgrades_bought: BTreeMap<UpgradeType, u32>,
+    pub gold_earned: i32,
+}
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(PlayerProfile::load())
+            .init_resource::<MatchRecord>()
+            .add_plugin(AchievementsPlugin)
+            .add_system(count_units_sent)
+            .add_system(record_game_over)
+            .add_system(save_changed_profile.after(record_game_over).after(unlock_achievements));
+    }
+}
+
+/* Every spawned attacker counts, including each member of a group */
+pub(crate) fn count_units_sent(spawned: Query<&AttackerType, Added<AttackerType>>, mut record: ResMut<MatchRecord>) {
+    for attacker_type in &spawned {
+        *record.units_sent.entry(*attacker_type).or_default() += 1;
+    }
+}
+
+pub(crate) fn record_game_over(
+    mut game_over: EventReader<GameOverEvent>,
+    mut profile: ResMut<PlayerProfile>,
+    mut record: ResMut<MatchRecord>,
+    stats: Res<AttackerStats>,
+    round: Res<RoundResource>,
+    history: Res<RoundHistory>,
+    attacker_resource: Res<AttackerResource>
+) {
+    for ev in game_over.iter() {
+        record.rounds = round.get_round_number();
+        record.upgrades_bought = stats.get_upgrades_bought().iter().map(|(upgrade, count)| (*upgrade, *count)).collect();
+        // The round the match ended in isn't in the history yet
+        record.gold_earned = history.rounds.iter().map(|summary| summary.gold_earned).sum::<i32>() + attacker_resource.gold_earned_this_round;
+        profile.record_match(&record, ev.attacker_won);
+        *record = MatchRecord::default();
+    }
+}
+
+/* Written on every change, a match can end by simply closing the window */
+fn save_changed_profile(profile: Res<PlayerProfile>) {
+    if profile.is_changed() && !profile.is_added() {
+        profile.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_profiles_start_fresh() {
+        assert_eq!(PlayerProfile::from_json("not json"), PlayerProfile::default());
+        let old = PlayerProfile::from_json(r#"{ "matches_played": 3 }"#);
+        assert_eq!((old.matches_played, old.matches_won, old.gold_earned), (3, 0, 0));
+    }
+
+    #[test]
+    fn matches_add_up_across_the_profile() {
+        let mut profile = PlayerProfile::default();
+        let mut record = MatchRecord { rounds: 12, gold_earned: 300, ..Default::default() };
+        record.units_sent.insert(AttackerType::Golem, 4);
+        record.upgrades_bought.insert(UpgradeType::Health, 2);
+        profile.record_match(&record, true);
+        record.rounds = 9;
+        record.upgrades_bought.insert(UpgradeType::Speed, 3);
+        profile.record_match(&record, true);
+        profile.record_match(&MatchRecord { rounds: 5, ..Default::default() }, false);
+
+        assert_eq!((profile.matches_played, profile.matches_won), (3, 2));
+        assert_eq!(profile.fastest_victory_rounds, Some(9));
+        assert_eq!(profile.units_sent[&AttackerType::Golem], 8);
+        assert_eq!(profile.get_favorite_upgrade(), Some(UpgradeType::Health));
+        assert_eq!(profile.gold_earned, 600);
+
+        // Survives a round trip through storage
+        assert_eq!(PlayerProfile::from_json(&serde_json::to_string(&profile).unwrap()), profile);
+    }
+}
@@ -0,0 +1,596 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::event::Events,
+    input::{mouse::MouseWheel, Input},
+    prelude::{App, Camera, CoreSchedule, MinimalPlugins, Color, Commands, Entity, EventReader, EventWriter, IntoSystemConfig, KeyCode, Res, ResMut, Resource, Schedule, Transform, Vec2, Vec3, With, World},
+    time::{fixed_timestep::FixedTime, Time, TimeUpdateStrategy, Timer, TimerMode},
+    utils::Instant,
+};
+
+use crate::{
+    camera::{frame_field, move_camera, update_camera_animation, CameraAnimation, MainCamera, FRAME_KEY},
+    error::FatalError,
+    textures::TextureResource,
+    util::GameRng,
+    ui::event_history::EventHistoryPlugin,
+    particle::{spawn_floating_text, ParticlePlugin, spawn_lightning, update_floating_texts, update_lightning, FloatingText, Lightning, ParticleSettings},
+    world::{
+        attacker_controller::GoldPickup,
+        attackers::{
+            apply_slow_events, check_reached_end, set_initial_pathfinding, tick_slow_effects, update_flow_field, update_path_finding, update_positions,
+            Attacker, AttackerStats, AttackerType, AttackersPlugin, Dying, FlowFieldFallback, PathfindingStats, SlowEffect, StartPoint, UseFlowField,
+            GOLEM_STATS, ORC_WARRIOR_STATS,
+        },
+        building_configuration::{BuildingResource, BuildingType},
+        building_registry::BuildingRegistry,
+        damage_matrix::DamageMatrix,
+        defender_strategy::{ActiveStrategy, DefenderStrategy},
+        defender_controller::{DefenderControl, DefenderController},
+        mutators::RoundModifiers,
+        rounds::RoundResource,
+        events::{DamageEvent, EntityReachedEnd, EventsPlugin, FieldModified, KillEvent, SlowEvent},
+        path_finding::{a_star, FlowField, Node, Path, PathCache, PathSmoothing},
+        towers::{apply_slow_auras, calculate_damage, tick_damage_zones, DamageBatchAccumulator, DAMAGE_ZONE_TICK_SECONDS, DamageBatchPlugin, DamageConsumerSet, DamageProducerSet, tick_marks, DamageType, DamageZone, Marked, Projectile, ProjectileMotion, SlowAura, Structure, Target, TowerField, TowersPlugin, Footprint, SLOT_SIZE},
+    },
+};
+
+/* Systems are tested in an App without any plugins, time only moves when `advance` says so */
+
+const START: Node = Node { x: 2, y: 0 };
+const END: Node = Node { x: 14, y: 15 };
+
+pub fn build_test_tower_field() -> TowerField {
+    return TowerField::new(16, 16, Vec2::ZERO, START, END);
+}
+
+/* Every slot is blocked except for the start and the end */
+pub fn build_blocked_tower_field() -> TowerField {
+    let mut field = build_test_tower_field();
+    for y in 0..field.get_height() {
+        for x in 0..field.get_width() {
+            let node = Node::new(x as i32, y as i32);
+            if node != START && node != END {
+                field.add_structure(Entity::PLACEHOLDER, true, slot_position(node), Footprint::SINGLE);
+            }
+        }
+    }
+    return field;
+}
+
+/* A world for tests of single systems, every game event is registered and the test adds the resources the system reads */
+pub fn system_test_world() -> World {
+    let mut app = App::new();
+    app.add_plugin(EventsPlugin);
+    return std::mem::take(&mut app.world);
+}
+
+/* A schedule running only the system under test, any systems it works with are added to it */
+pub fn schedule_of<M>(system: impl IntoSystemConfig<M>) -> Schedule {
+    let mut schedule = Schedule::new();
+    schedule.add_system(system);
+    return schedule;
+}
+
+fn slot_position(node: Node) -> Vec2 {
+    return Vec2::new(node.x as f32, node.y as f32) * SLOT_SIZE as f32;
+}
+
+fn test_app() -> App {
+    let mut app = App::new();
+    app.insert_resource(Time::default());
+    return app;
+}
+
+/* Moves time forward by `seconds` and runs one frame */
+fn advance(app: &mut App, seconds: f32) {
+    let mut time = app.world.resource_mut::<Time>();
+    // The first update only sets the starting point, it never has a delta
+    let last = match time.last_update() {
+        Some(last) => last,
+        None => {
+            let startup = time.startup();
+            time.update_with_instant(startup);
+            startup
+        }
+    };
+    time.update_with_instant(last + Duration::from_secs_f32(seconds));
+    app.update();
+}
+
+/* Frames of 0.1 seconds the defender harness plays per round, enough for four defender actions */
+const HARNESS_FRAMES_PER_ROUND: usize = 60;
+const HARNESS_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/* Units the scripted attacker sends, rounds past the end of the list start over from the first one */
+#[derive(Resource)]
+pub struct AttackerScript {
+    rounds: Vec<Vec<AttackerType>>,
+    played: usize,
+    due: bool,
+}
+
+/* The attacker side of the defender harness: each round the whole script dies on the field and pays its bounty */
+fn play_attacker_script(mut script: ResMut<AttackerScript>, mut kills: EventWriter<KillEvent>, stats: Res<AttackerStats>) {
+    if !script.due || script.rounds.is_empty() {
+        return;
+    }
+    script.due = false;
+    let round = script.rounds[script.played % script.rounds.len()].clone();
+    script.played += 1;
+    for attacker_type in round {
+        kills.send(KillEvent {
+            target: Entity::PLACEHOLDER,
+            source: Entity::PLACEHOLDER,
+            tower: Entity::PLACEHOLDER,
+            bounty: stats.get_stats(attacker_type).bounty,
+            original_cost: stats.get_cost(attacker_type),
+            group_size: 1,
+            death_position: Vec2::ZERO,
+            size: Vec2::ONE,
+        });
+    }
+}
+
+/* The computer defender as the game runs it, headless and against a scripted attacker.
+   Structures are drawn from a stand-in atlas, so only the sprites named here can be built */
+pub fn defender_harness(strategy: Box<dyn DefenderStrategy>, buildings: &str, sprites: &[(&str, usize)], rounds: Vec<Vec<AttackerType>>) -> App {
+    let registry = BuildingRegistry::default();
+    let mut buildings = BuildingResource::from_json(buildings).unwrap();
+    registry.apply_default_sprites(&mut buildings);
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        // A manual duration is added to the current instant, frames would only last as long as they took to run
+        .insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()))
+        .add_plugin(EventsPlugin)
+        .add_plugin(TowersPlugin)
+        .add_plugin(DefenderController)
+        .insert_resource(build_test_tower_field())
+        .insert_resource(buildings)
+        .insert_resource(registry)
+        .insert_resource(TextureResource::with_named_sprites("towers", sprites))
+        .insert_resource(ActiveStrategy(strategy))
+        .insert_resource(DefenderControl::Computer)
+        .insert_resource(AttackerScript { rounds, played: 0, due: false })
+        .init_resource::<AttackerStats>()
+        .init_resource::<RoundResource>()
+        .init_resource::<FatalError>()
+        .init_resource::<GameRng>()
+        .init_resource::<DamageMatrix>()
+        // The stand-in atlas has no particle sprites, kills must not spawn any
+        .insert_resource(ParticleSettings { density: 0., ..Default::default() })
+        .init_resource::<RoundModifiers>()
+        .init_resource::<GoldPickup>()
+        .add_system(play_attacker_script);
+    return app;
+}
+
+/* Runs one frame of the defender harness, 0.1 seconds after the last one */
+pub fn step_harness(app: &mut App) {
+    if let TimeUpdateStrategy::ManualInstant(instant) = &mut *app.world.resource_mut::<TimeUpdateStrategy>() {
+        *instant += HARNESS_FRAME_TIME;
+    }
+    app.update();
+}
+
+/* Starts a scripted round and plays it out, the defender acts whenever its cooldown runs out */
+pub fn play_harness_rounds(app: &mut App, rounds: usize) {
+    for _ in 0..rounds {
+        app.world.resource_mut::<AttackerScript>().due = true;
+        for _ in 0..HARNESS_FRAMES_PER_ROUND {
+            step_harness(app);
+        }
+        assert!(!app.world.resource::<FatalError>().is_set(), "{}", app.world.resource::<FatalError>().get_details());
+    }
+}
+
+fn arrow() -> Projectile {
+    return Projectile {
+        target: Target::Ground(Vec2::ZERO),
+        source: Entity::PLACEHOLDER,
+        projectile_motion: ProjectileMotion::Velocity(100.),
+        damage: 20.,
+        damage_type: DamageType::Piercing,
+        splash_radius: 0.,
+        velocity: Vec2::ZERO,
+        size: Vec2::ONE,
+        dead: false,
+        age: Duration::ZERO,
+        piercing: 0,
+        hit_entities: Vec::new(),
+        forks_on_hit: 0,
+        mark: None,
+    };
+}
+
+#[test]
+fn a_star_finds_a_path_on_the_empty_field() {
+    let path = a_star(&build_test_tower_field(), START, END).expect("an empty field always has a path");
+    assert_eq!(path.get_node(path.get_size() - 1), END);
+}
+
+#[test]
+fn a_star_finds_nothing_on_the_blocked_field() {
+    assert!(a_star(&build_blocked_tower_field(), START, END).is_none());
+}
+
+#[test]
+fn calculate_damage_applies_armor() {
+    let matrix = DamageMatrix::from_json(r#"{ "Piercing": { "Light": 1.25, "Heavy": 0.5 } }"#).unwrap();
+    assert_eq!(calculate_damage(&arrow(), &ORC_WARRIOR_STATS, None, &matrix), 25.);
+    assert_eq!(calculate_damage(&arrow(), &GOLEM_STATS, None, &matrix), 10.);
+    let marked = Marked { bonus_mult: 2., timer: Timer::from_seconds(1., TimerMode::Once) };
+    assert_eq!(calculate_damage(&arrow(), &GOLEM_STATS, Some(&marked), &matrix), 20.);
+}
+
+#[test]
+fn tick_slow_effects_restores_speed() {
+    let mut app = test_app();
+    app.init_resource::<RoundModifiers>().init_resource::<FlowField>().add_system(tick_slow_effects).add_system(update_path_finding);
+    let field = build_test_tower_field();
+    let attacker = app.world.spawn((
+        ORC_WARRIOR_STATS,
+        a_star(&field, START, END).unwrap(),
+        field.get_start_transform(),
+        SlowEffect { factor: 0.5, timer: Timer::from_seconds(1., TimerMode::Once) },
+    )).id();
+    let speed = ORC_WARRIOR_STATS.movement_speed;
+
+    advance(&mut app, 0.1);
+    let slowed = app.world.get::<Attacker>(attacker).unwrap().velocity.length();
+    assert!((slowed - speed * 0.5).abs() < 1e-3);
+
+    for _ in 0..3 {
+        advance(&mut app, 0.5);
+    }
+    assert!(app.world.get::<SlowEffect>(attacker).is_none());
+    let restored = app.world.get::<Attacker>(attacker).unwrap();
+    assert_eq!(restored.movement_speed, speed);
+    assert!((restored.velocity.length() - speed).abs() < 1e-3);
+}
+
+#[test]
+fn apply_slow_events_keeps_the_strongest_slow() {
+    let mut app = test_app();
+    app.add_event::<SlowEvent>().add_system(apply_slow_events);
+    let attacker = app.world.spawn(ORC_WARRIOR_STATS).id();
+    let mut events = app.world.resource_mut::<Events<SlowEvent>>();
+    events.send(SlowEvent { target: attacker, factor: 0.7, duration_secs: 1. });
+    events.send(SlowEvent { target: attacker, factor: 0.4, duration_secs: 1. });
+    advance(&mut app, 0.1);
+    assert_eq!(app.world.get::<SlowEffect>(attacker).unwrap().factor, 0.4);
+
+    // A weaker slow arriving later leaves the stronger one's expiry alone
+    app.world.get_mut::<SlowEffect>(attacker).unwrap().timer.tick(Duration::from_secs_f32(0.6));
+    app.world.send_event(SlowEvent { target: attacker, factor: 0.7, duration_secs: 5. });
+    advance(&mut app, 0.1);
+    let slow = app.world.get::<SlowEffect>(attacker).unwrap();
+    assert_eq!(slow.factor, 0.4);
+    assert!((slow.timer.remaining_secs() - 0.4).abs() < 1e-3);
+
+    // An equally strong one refreshes it
+    app.world.send_event(SlowEvent { target: attacker, factor: 0.4, duration_secs: 2. });
+    advance(&mut app, 0.1);
+    assert!((app.world.get::<SlowEffect>(attacker).unwrap().timer.remaining_secs() - 2.).abs() < 1e-3);
+}
+
+#[test]
+fn slow_auras_only_reach_attackers_in_radius() {
+    let mut app = test_app();
+    app.add_event::<SlowEvent>()
+        .add_system(apply_slow_auras)
+        .add_system(apply_slow_events.after(apply_slow_auras));
+    app.world.spawn((SlowAura { radius: 100., factor: 0.6 }, Transform::default()));
+    let near = app.world.spawn((ORC_WARRIOR_STATS, Transform::from_xyz(50., 0., 0.))).id();
+    let far = app.world.spawn((ORC_WARRIOR_STATS, Transform::from_xyz(500., 0., 0.))).id();
+    advance(&mut app, 0.1);
+    assert_eq!(app.world.get::<SlowEffect>(near).unwrap().factor, 0.6);
+    assert!(app.world.get::<SlowEffect>(far).is_none());
+}
+
+#[test]
+fn damage_zones_hurt_attackers_inside_in_pulses() {
+    let mut app = test_app();
+    app.init_resource::<DamageMatrix>()
+        .add_event::<DamageEvent>()
+        .add_event::<KillEvent>()
+        .add_system(tick_damage_zones.in_set(DamageProducerSet))
+        .add_plugin(DamageBatchPlugin);
+    let zone = app.world.spawn((DamageZone::new(40., 100.), Transform::default())).id();
+    let near = app.world.spawn((ORC_WARRIOR_STATS, Transform::from_xyz(50., 0., 0.))).id();
+    let far = app.world.spawn((ORC_WARRIOR_STATS, Transform::from_xyz(500., 0., 0.))).id();
+    let health = ORC_WARRIOR_STATS.health;
+    // Nothing happens between two pulses
+    advance(&mut app, DAMAGE_ZONE_TICK_SECONDS * 0.4);
+    assert_eq!(app.world.get::<Attacker>(near).unwrap().health, health);
+    assert!(app.world.resource::<Events<DamageEvent>>().is_empty());
+    advance(&mut app, DAMAGE_ZONE_TICK_SECONDS);
+    assert!((app.world.get::<Attacker>(near).unwrap().health - (health - 20.)).abs() < 1e-3);
+    assert_eq!(app.world.get::<Attacker>(far).unwrap().health, health);
+
+    // The zone itself is credited with the kill
+    app.world.get_mut::<Attacker>(near).unwrap().health = 5.;
+    advance(&mut app, DAMAGE_ZONE_TICK_SECONDS);
+    assert!(app.world.get::<Dying>(near).is_some());
+    let kills = app.world.resource::<Events<KillEvent>>();
+    let towers: Vec<Entity> = kills.get_reader().iter(kills).map(|ev| ev.tower).collect();
+    assert_eq!(towers, vec![zone]);
+}
+
+#[test]
+fn shields_soak_up_damage_before_health() {
+    let mut app = test_app();
+    app.init_resource::<DamageMatrix>()
+        .add_event::<DamageEvent>()
+        .add_event::<KillEvent>()
+        .add_system(tick_damage_zones.in_set(DamageProducerSet))
+        .add_plugin(DamageBatchPlugin);
+    // Magic against light armor goes through in full
+    let zone = app.world.spawn((DamageZone::new(30., 100.), Transform::default())).id();
+    let attacker = app.world.spawn((Attacker { shield: 50., ..ORC_WARRIOR_STATS }, Transform::default())).id();
+    let health = ORC_WARRIOR_STATS.health;
+
+    advance(&mut app, 1.);
+    let hit = app.world.get::<Attacker>(attacker).unwrap();
+    assert_eq!(hit.health, health);
+    assert!((hit.shield - 20.).abs() < 1e-3);
+
+    app.world.get_mut::<DamageZone>(zone).unwrap().damage_per_second = 40.;
+    advance(&mut app, 1.);
+    let broken = app.world.get::<Attacker>(attacker).unwrap();
+    assert_eq!(broken.shield, 0.);
+    assert!((broken.health - (health - 20.)).abs() < 1e-3);
+    // The full hits still count as damage dealt
+    let events = app.world.resource::<Events<DamageEvent>>();
+    let hits: Vec<(f32, f32)> = events.get_reader().iter(events).map(|ev| (ev.amount, ev.absorbed)).collect();
+    assert_eq!(hits.len(), 2);
+    assert!((hits[0].0 - 30.).abs() < 1e-3 && (hits[0].1 - 30.).abs() < 1e-3);
+    assert!((hits[1].0 - 40.).abs() < 1e-3 && (hits[1].1 - 20.).abs() < 1e-3);
+}
+
+/* Damage seen by the stand-in consumers, frame by frame */
+#[derive(Resource, Default)]
+struct SeenDamage(Vec<f32>);
+
+fn record_damage(mut damage: EventReader<DamageEvent>, mut seen: ResMut<SeenDamage>) {
+    seen.0.extend(damage.iter().map(|ev| ev.amount));
+}
+
+#[test]
+fn damage_batches_are_flushed_before_any_consumer() {
+    let mut app = test_app();
+    // Consumers are added first, only the plugin's set ordering can put them after the flush
+    app.add_event::<DamageEvent>()
+        .init_resource::<SeenDamage>()
+        .add_system(record_damage.in_set(DamageConsumerSet))
+        .add_plugin(DamageBatchPlugin);
+    let target = app.world.spawn_empty().id();
+    for _ in 0..5 {
+        let mut batches = app.world.resource_mut::<DamageBatchAccumulator>();
+        batches.add(DamageEvent { amount: 10., absorbed: 0., target, damage_type: DamageType::Explosive, critical: false });
+        batches.add(DamageEvent { amount: 15., absorbed: 5., target, damage_type: DamageType::Explosive, critical: false });
+        batches.add(DamageEvent { amount: 30., absorbed: 0., target, damage_type: DamageType::Explosive, critical: true });
+        advance(&mut app, 0.1);
+        // Alike hits arrive as one event in the frame they were made, the critical one stays apart
+        assert_eq!(app.world.resource_mut::<SeenDamage>().0.drain(..).collect::<Vec<f32>>(), vec![25., 30.]);
+    }
+    assert!(app.world.resource::<DamageBatchAccumulator>().pending.is_empty());
+}
+
+#[test]
+fn every_damage_producer_and_consumer_is_in_its_set() {
+    let mut app = test_app();
+    app.add_plugin(DefenderController)
+        .add_plugin(AttackersPlugin)
+        .add_plugin(ParticlePlugin)
+        .add_plugin(EventHistoryPlugin)
+        .add_plugin(TowersPlugin);
+    let graph = app.get_schedule(CoreSchedule::Main).unwrap().graph();
+    let find_set = |name: &str| graph.system_sets().find(|(_, set, _, _)| format!("{:?}", set) == name).unwrap().0;
+    let find_system = |name: &str| graph.systems().find(|(_, system, _, _)| system.name().ends_with(&format!("::{}", name))).unwrap().0;
+    let producer_set = find_set("DamageProducerSet");
+    for producer in ["find_targets", "update_projectiles", "handle_kill_effects", "tick_damage_zones"] {
+        assert!(graph.hierarchy().graph().contains_edge(producer_set, find_system(producer)), "{} adds damage outside of DamageProducerSet", producer);
+    }
+    let consumer_set = find_set("DamageConsumerSet");
+    for consumer in ["collect_event_stats", "start_hit_flashes", "spawn_damage_texts", "log_game_events"] {
+        assert!(graph.hierarchy().graph().contains_edge(consumer_set, find_system(consumer)), "{} reads DamageEvents outside of DamageConsumerSet", consumer);
+    }
+}
+
+#[test]
+fn update_positions_moves_by_velocity() {
+    let mut app = test_app();
+    app.insert_resource(FixedTime::new_from_secs(0.5)).add_system(update_positions);
+    let mut stats = ORC_WARRIOR_STATS;
+    stats.velocity = Vec2::new(10., -4.);
+    let attacker = app.world.spawn((stats, Transform::default())).id();
+    advance(&mut app, 0.5);
+    let position = app.world.get::<Transform>(attacker).unwrap().translation.truncate();
+    assert!(position.distance(Vec2::new(5., -2.)) < 1e-3);
+}
+
+#[test]
+fn check_reached_end_sends_attackers_back_to_the_start() {
+    let mut app = test_app();
+    let field = build_test_tower_field();
+    let start = field.get_start_transform();
+    app.add_event::<EntityReachedEnd>().insert_resource(field).add_system(check_reached_end);
+    let attacker = app.world.spawn((
+        ORC_WARRIOR_STATS,
+        Path::empty(),
+        Transform::from_translation(slot_position(END).extend(0.)),
+    )).id();
+    advance(&mut app, 0.1);
+
+    let events = app.world.resource::<Events<EntityReachedEnd>>();
+    let reached: Vec<Entity> = events.get_reader().iter(events).map(|ev| ev.entity).collect();
+    assert_eq!(reached, vec![attacker]);
+    assert_eq!(app.world.get::<Transform>(attacker).unwrap().translation, start.translation);
+    assert!(app.world.get::<Path>(attacker).is_none());
+}
+
+#[test]
+fn check_reached_end_sends_attackers_back_to_their_own_start() {
+    let mut app = test_app();
+    let mut field = build_test_tower_field();
+    field.add_start(Node::new(13, 0));
+    let second_start = field.get_start_transform_of(1);
+    app.add_event::<EntityReachedEnd>().insert_resource(field).add_system(check_reached_end);
+    let attacker = app.world.spawn((
+        ORC_WARRIOR_STATS,
+        Path::empty(),
+        StartPoint(1),
+        Transform::from_translation(slot_position(END).extend(0.)),
+    )).id();
+    advance(&mut app, 0.1);
+    assert_eq!(app.world.get::<Transform>(attacker).unwrap().translation, second_start.translation);
+}
+
+#[test]
+fn tick_marks_removes_expired_marks() {
+    let mut app = test_app();
+    app.add_system(tick_marks);
+    let attacker = app.world.spawn(Marked { bonus_mult: 1.5, timer: Timer::from_seconds(1., TimerMode::Once) }).id();
+    advance(&mut app, 0.5);
+    assert!(app.world.get::<Marked>(attacker).is_some());
+    advance(&mut app, 0.6);
+    assert!(app.world.get::<Marked>(attacker).is_none());
+}
+
+#[test]
+fn floating_texts_despawn_after_their_lifetime() {
+    let mut app = test_app();
+    app.add_startup_system(|mut commands: Commands| spawn_floating_text(&mut commands, &Transform::default(), "MISS", Color::GRAY))
+        .add_system(update_floating_texts);
+    advance(&mut app, 0.5);
+    assert_eq!(app.world.query_filtered::<Entity, With<FloatingText>>().iter(&app.world).count(), 1);
+    advance(&mut app, 0.5);
+    assert_eq!(app.world.query_filtered::<Entity, With<FloatingText>>().iter(&app.world).count(), 0);
+}
+
+#[test]
+fn lightning_fades_out() {
+    let mut app = test_app();
+    app.insert_resource(ParticleSettings::default())
+        .add_startup_system(|mut commands: Commands, settings: Res<ParticleSettings>| {
+            spawn_lightning(&mut commands, Vec2::ZERO, Vec2::new(100., 0.), &settings)
+        })
+        .add_system(update_lightning);
+    advance(&mut app, 0.05);
+    assert_eq!(app.world.query_filtered::<Entity, With<Lightning>>().iter(&app.world).count(), 1);
+    advance(&mut app, 0.2);
+    assert_eq!(app.world.query_filtered::<Entity, With<Lightning>>().iter(&app.world).count(), 0);
+}
+
+fn camera_app(translation: Vec3) -> (App, Entity) {
+    let mut app = test_app();
+    app.insert_resource(build_test_tower_field())
+        .init_resource::<Input<KeyCode>>()
+        .init_resource::<CameraAnimation>()
+        .add_event::<MouseWheel>()
+        .add_system(move_camera)
+        .add_system(frame_field.after(move_camera))
+        .add_system(update_camera_animation.after(frame_field));
+    let camera = app.world.spawn((Camera::default(), Transform::from_translation(translation), MainCamera)).id();
+    return (app, camera);
+}
+
+#[test]
+fn the_frame_key_moves_the_camera_to_the_field_center() {
+    let (mut app, camera) = camera_app(Vec3::new(-500., 900., 10.));
+    let center = build_test_tower_field().get_center();
+    app.world.resource_mut::<Input<KeyCode>>().press(FRAME_KEY);
+    advance(&mut app, 0.);
+    let start_distance = Vec2::new(-500., 900.).distance(center);
+
+    app.world.resource_mut::<Input<KeyCode>>().clear();
+    advance(&mut app, 0.5);
+    let halfway = app.world.get::<Transform>(camera).unwrap().translation;
+    assert!(halfway.truncate().distance(center) < start_distance);
+    assert_eq!(halfway.z, 10.);
+
+    advance(&mut app, 2.);
+    let framed = app.world.get::<Transform>(camera).unwrap().translation;
+    assert!(framed.truncate().distance(center) < 0.01);
+    assert!(!app.world.resource::<CameraAnimation>().active);
+}
+
+#[test]
+fn moving_the_camera_by_hand_cancels_framing() {
+    let (mut app, camera) = camera_app(Vec3::new(-500., 900., 10.));
+    app.world.resource_mut::<Input<KeyCode>>().press(FRAME_KEY);
+    advance(&mut app, 0.);
+    app.world.resource_mut::<Input<KeyCode>>().clear();
+    advance(&mut app, 0.2);
+
+    app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::W);
+    advance(&mut app, 0.2);
+    assert!(!app.world.resource::<CameraAnimation>().active);
+    let y = app.world.get::<Transform>(camera).unwrap().translation.y;
+    advance(&mut app, 0.5);
+    // Only the key moves the camera now, upwards
+    assert!(app.world.get::<Transform>(camera).unwrap().translation.y > y);
+}
+
+#[test]
+fn flow_field_users_fall_back_to_a_star_on_blocked_slots() {
+    let mut app = test_app();
+    app.insert_resource(build_test_tower_field())
+        .init_resource::<FlowField>()
+        .init_resource::<RoundModifiers>()
+        .init_resource::<PathSmoothing>()
+        .init_resource::<PathfindingStats>()
+        .init_resource::<PathCache>()
+        .add_system(update_flow_field)
+        .add_system(set_initial_pathfinding.after(update_flow_field))
+        .add_system(update_path_finding.after(set_initial_pathfinding));
+    app.world.resource_mut::<FlowField>().enabled = true;
+    let attacker = app.world.spawn((ORC_WARRIOR_STATS, Transform::from_xyz(slot_position(START).x, slot_position(START).y, 1.))).id();
+
+    advance(&mut app, 0.1);
+    assert!(app.world.get::<UseFlowField>(attacker).is_some() && app.world.get::<Path>(attacker).is_none());
+    advance(&mut app, 0.1);
+    let velocity = app.world.get::<Attacker>(attacker).unwrap().velocity;
+    assert!((velocity.length() - ORC_WARRIOR_STATS.movement_speed).abs() < 1e-3);
+    assert_eq!(app.world.resource::<PathfindingStats>().computations, 0);
+
+    // A wall right where the attacker stands, as if it was placed mid-step
+    app.world.resource_mut::<TowerField>().add_structure(Entity::PLACEHOLDER, true, slot_position(START), Footprint::SINGLE);
+    advance(&mut app, 0.1);
+    assert!(app.world.get::<FlowFieldFallback>(attacker).is_some());
+    advance(&mut app, 0.1);
+    let path = app.world.get::<Path>(attacker).unwrap();
+    assert!(!app.world.resource::<TowerField>().is_node_blocked(path.get_node(0)));
+    assert!(app.world.get::<UseFlowField>(attacker).is_none() && app.world.get::<FlowFieldFallback>(attacker).is_none());
+    assert_eq!(app.world.resource::<PathfindingStats>().computations, 1);
+}
+
+/* The tower plugin as the game runs it, only without rendering */
+#[test]
+fn placed_structures_register_into_the_field() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(EventsPlugin)
+        .add_plugin(TowersPlugin)
+        .insert_resource(build_test_tower_field())
+        .insert_resource(BuildingResource::empty())
+        .init_resource::<TextureResource>()
+        .init_resource::<GameRng>()
+        .init_resource::<DamageMatrix>()
+        .init_resource::<ParticleSettings>()
+        .init_resource::<RoundModifiers>()
+        .init_resource::<GoldPickup>();
+    let node = Node::new(5, 5);
+    let wall = app.world.spawn((Structure { building_type: BuildingType::Wall, blocking: true }, Transform::from_translation(slot_position(node).extend(10.)))).id();
+    app.update();
+    assert_eq!(app.world.resource::<Events<FieldModified>>().len(), 1);
+
+    // Registered once, later frames leave the field alone
+    app.update();
+    app.update();
+    let field = app.world.resource::<TowerField>();
+    assert!(field.is_node_blocked(node));
+    assert_eq!(field.get_slot(node).unwrap().entity, wall);
+    assert!(app.world.resource::<Events<FieldModified>>().is_empty());
+}
@@ -1,30 +1,118 @@
-use std::fs;
+use std::{fs, time::Duration};
 
 use bevy::{
-    prelude::{App, AssetServer, Assets, Commands, Handle, Plugin, Res, ResMut, Resource, Vec2, Color},
+    prelude::{App, AssetServer, Assets, Handle, Plugin, Res, ResMut, Resource, Vec2, Color, IntoSystemConfig, StartupSet},
     sprite::{TextureAtlas, TextureAtlasSprite},
     utils::HashMap,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::world::attackers::AnimationIndices;
+use crate::{world::attackers::{AnimationIndices, AnimationMode}, error::FatalError};
+
+/* Atlases and animations the game looks up by name, checked at startup so a bad definitions.json is reported instead of panicking mid game */
+const REQUIRED_ATLASES: [&str; 2] = ["towers", "outside"];
+const REQUIRED_ANIMATIONS: [(&str, &str); 18] = [
+    ("orc1", "orc1_down_walk"),
+    ("orc1", "orc1_left_walk"),
+    ("orc1", "orc1_right_walk"),
+    ("orc1", "orc1_up_walk"),
+    ("orc1", "orc1_idle"),
+    ("monster1", "spider1_down_walk"),
+    ("monster1", "spider1_left_walk"),
+    ("monster1", "spider1_right_walk"),
+    ("monster1", "spider1_up_walk"),
+    ("monster1", "spider1_idle"),
+    ("golem1", "golem1_down_walk"),
+    ("golem1", "golem1_left_walk"),
+    ("golem1", "golem1_right_walk"),
+    ("golem1", "golem1_up_walk"),
+    ("golem1", "golem1_idle"),
+    ("large_explosion", "primary"),
+    ("blood_splatter", "primary"),
+    ("coin", "primary"),
+];
 
 #[derive(Resource)]
 pub struct TextureResource {
     named_handles: HashMap<String, Handle<TextureAtlas>>,
     named_animations: HashMap<(String, String), AnimationIndices>,
+    named_sprites: HashMap<(String, String), usize>,
+    animation_timings: HashMap<(String, String), (Option<f32>, Vec<f32>)>,
 }
 
 impl Default for TextureResource {
     fn default() -> Self {
-        Self { named_handles: HashMap::new(), named_animations: HashMap::new() }
+        Self { named_handles: HashMap::new(), named_animations: HashMap::new(), named_sprites: HashMap::new(), animation_timings: HashMap::new() }
+    }
+}
+
+/* How long each frame of an animation is shown, frames without an override use frame_time */
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationTiming {
+    frame_time: Duration,
+    frame_times: Vec<Duration>,
+}
+
+impl AnimationTiming {
+    pub fn uniform(frame_time: f32) -> Self {
+        return Self { frame_time: Duration::from_secs_f32(frame_time), frame_times: Vec::new() };
+    }
+    /* frame is counted from the start of the animation */
+    pub fn get_frame_time(&self, frame: usize) -> Duration {
+        return self.frame_times.get(frame).copied().unwrap_or(self.frame_time);
     }
 }
 
 impl TextureResource {
+    /* Named sprites in a stand-in atlas, for tests that build structures without the real assets */
+    #[cfg(test)]
+    pub fn with_named_sprites(atlas_name: &str, sprites: &[(&str, usize)]) -> Self {
+        let mut textures = Self::default();
+        textures.named_handles.insert(atlas_name.to_string(), Handle::default());
+        for (sprite_name, index) in sprites {
+            textures.named_sprites.insert((atlas_name.to_string(), sprite_name.to_string()), *index);
+        }
+        return textures;
+    }
+
+    /* Single frame animations in stand-in atlases, for tests that spawn particles without the real assets */
+    #[cfg(test)]
+    pub fn with_animations(animations: &[(&str, &str)]) -> Self {
+        let mut textures = Self::default();
+        for (atlas_name, animation_name) in animations {
+            textures.named_handles.insert(atlas_name.to_string(), Handle::default());
+            textures.named_animations.insert((atlas_name.to_string(), animation_name.to_string()), AnimationIndices::default());
+        }
+        return textures;
+    }
+
     pub fn get_atlas(&self, name: &str) -> &Handle<TextureAtlas> {
         return self.named_handles.get(name).unwrap();
     }
+    pub fn has_atlas(&self, name: &str) -> bool {
+        return self.named_handles.contains_key(name);
+    }
+    pub fn has_animation(&self, atlas_name: &str, animation_name: &str) -> bool {
+        return self.named_animations.contains_key(&(atlas_name.to_string(), animation_name.to_string()));
+    }
+    pub fn has_named_sprite(&self, atlas_name: &str, sprite_name: &str) -> bool {
+        return self.named_sprites.contains_key(&(atlas_name.to_string(), sprite_name.to_string()));
+    }
+    /* None if the atlas definition has no sprite of that name */
+    pub fn get_named_sprite(&self, atlas_name: &str, sprite_name: &str) -> Option<(&Handle<TextureAtlas>, TextureAtlasSprite)> {
+        let index = self.named_sprites.get(&(atlas_name.to_string(), sprite_name.to_string()))?;
+        return Some((self.named_handles.get(atlas_name)?, TextureAtlasSprite::new(*index)));
+    }
+    /* Falls back to default_frame_time for definitions that don't specify one */
+    pub fn get_animation_timing(&self, atlas_name: &str, animation_name: &str, default_frame_time: f32) -> AnimationTiming {
+        return match self.animation_timings.get(&(atlas_name.to_string(), animation_name.to_string())) {
+            Some((frame_time, frame_times)) => AnimationTiming {
+                frame_time: Duration::from_secs_f32(frame_time.unwrap_or(default_frame_time)),
+                frame_times: frame_times.iter().map(|e| Duration::from_secs_f32(*e)).collect(),
+            },
+            None => AnimationTiming::uniform(default_frame_time)
+        };
+    }
     pub fn get_sprite(&self, name: &str, index: usize) -> (&Handle<TextureAtlas>, TextureAtlasSprite) {
         return (self.get_atlas(name), TextureAtlasSprite::new(index));
     }
@@ -57,20 +145,34 @@ impl TextureResource {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 struct AtlasDefintion {
     path: String,
     name: String,
     tile_size: [f32; 2],
     num_tiles: [usize; 2],
-    animations: Option<Vec<AnimationDefinition>>
+    animations: Option<Vec<AnimationDefinition>>,
+    #[serde(default)]
+    sprites: Option<Vec<SpriteDefinition>>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 struct AnimationDefinition {
     name: String,
     start: usize,
-    end: usize
+    end: usize,
+    #[serde(default)]
+    frame_time: Option<f32>,
+    #[serde(default)]
+    frame_times: Option<Vec<f32>>,
+    #[serde(default)]
+    mode: AnimationMode
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct SpriteDefinition {
+    name: String,
+    index: usize
 }
 
 pub struct TexturePlugin;
@@ -78,16 +180,23 @@ pub struct TexturePlugin;
 impl Plugin for TexturePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<TextureResource>()
-            .add_startup_system(setup);
+            .add_startup_system(setup.in_base_set(StartupSet::PreStartup));
     }
 }
 
-fn setup(
+pub fn setup(
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut named_textures: ResMut<TextureResource>
+    mut named_textures: ResMut<TextureResource>,
+    mut fatal_error: ResMut<FatalError>
 ) {
-    let atlas_definitions = read_atlas_definitions();
+    let atlas_definitions = match read_atlas_definitions() {
+        Ok(definitions) => definitions,
+        Err(err) => {
+            fatal_error.report(err);
+            return;
+        }
+    };
     for atlas_definition in atlas_definitions {
         let texture_handle = asset_server.load(atlas_definition.path);
         let texture_atlas = TextureAtlas::from_grid(
@@ -100,29 +209,99 @@ fn setup(
         );
         let texture_atlas_handle = texture_atlases.add(texture_atlas);
         named_textures.named_handles.insert(atlas_definition.name.clone(), texture_atlas_handle);
+        if let Some(sprites) = atlas_definition.sprites {
+            for sprite_definition in sprites {
+                named_textures.named_sprites.insert((atlas_definition.name.clone(), sprite_definition.name), sprite_definition.index);
+            }
+        }
         if let Some(animations) = atlas_definition.animations {
             for animation_definition in animations {
+                named_textures.animation_timings.insert(
+                    (atlas_definition.name.clone(), animation_definition.name.clone()),
+                    (animation_definition.frame_time, animation_definition.frame_times.unwrap_or_default())
+                );
                 named_textures.named_animations.insert(
                     (atlas_definition.name.clone(), animation_definition.name), 
                     AnimationIndices::new(
                         animation_definition.start, 
                         animation_definition.end
-                    )
+                    ).with_mode(animation_definition.mode)
                 );
             }
         }
 
     }
+
+    for atlas in REQUIRED_ATLASES {
+        if !named_textures.has_atlas(atlas) {
+            fatal_error.report(format!("Missing atlas '{}' in assets/definitions.json", atlas));
+        }
+    }
+    for (atlas, animation) in REQUIRED_ANIMATIONS {
+        if !named_textures.has_animation(atlas, animation) {
+            fatal_error.report(format!("Missing animation '{}' in atlas '{}' in assets/definitions.json", animation, atlas));
+        }
+    }
 }
 
-fn read_atlas_definitions() -> Vec<AtlasDefintion> {
+fn read_atlas_definitions() -> Result<Vec<AtlasDefintion>, String> {
     return match fs::read_to_string("assets/definitions.json") {
-        Ok(contents) => {
-            match serde_json::from_str::<Vec<AtlasDefintion>>(&contents) {
-                Ok(definitions) => definitions,
-                Err(err) => panic!("Failed to parse json {}", err)
+        Ok(contents) => atlas_definitions_from_json(&contents),
+        Err(err) => Err(format!("Failed to read assets/definitions.json: {}", err))
+    }
+}
+
+fn atlas_definitions_from_json(contents: &str) -> Result<Vec<AtlasDefintion>, String> {
+    return serde_json::from_str::<Vec<AtlasDefintion>>(contents)
+        .map_err(|err| format!("Failed to parse assets/definitions.json: {}", err));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atlas_definitions_to_json(definitions: &[AtlasDefintion]) -> Result<String, String> {
+        return serde_json::to_string_pretty(definitions)
+            .map_err(|err| format!("Failed to write atlas definitions: {}", err));
+    }
+
+    #[test]
+    fn atlas_definitions_round_trip() {
+        let definitions = vec![
+            AtlasDefintion {
+                path: "sprites/towers.png".to_string(),
+                name: "towers".to_string(),
+                tile_size: [32., 48.],
+                num_tiles: [8, 1],
+                animations: None,
+                sprites: Some(vec![
+                    SpriteDefinition { name: "wall".to_string(), index: 0 },
+                    SpriteDefinition { name: "arrow".to_string(), index: 4 }
+                ])
+            },
+            AtlasDefintion {
+                path: "sprites/orc1.png".to_string(),
+                name: "orc1".to_string(),
+                tile_size: [32., 32.],
+                num_tiles: [4, 5],
+                animations: Some(vec![
+                    AnimationDefinition { name: "orc1_idle".to_string(), start: 0, end: 3, frame_time: None, frame_times: None, mode: AnimationMode::PingPong },
+                    AnimationDefinition { name: "orc1_down_walk".to_string(), start: 4, end: 7, frame_time: Some(0.1), frame_times: Some(vec![0.2]), mode: AnimationMode::Loop }
+                ]),
+                sprites: None
             }
-        },
-        Err(err) => panic!("Failed to read file {}", err)
+        ];
+        let json = atlas_definitions_to_json(&definitions).unwrap();
+        assert_eq!(atlas_definitions_from_json(&json).unwrap(), definitions);
+    }
+
+    #[test]
+    fn frame_overrides_fall_back_to_frame_time() {
+        let mut textures = TextureResource::default();
+        textures.animation_timings.insert(("orc1".to_string(), "orc1_idle".to_string()), (Some(0.2), vec![0.5]));
+        let timing = textures.get_animation_timing("orc1", "orc1_idle", 0.1);
+        assert_eq!(timing.get_frame_time(0), Duration::from_secs_f32(0.5));
+        assert_eq!(timing.get_frame_time(1), Duration::from_secs_f32(0.2));
+        assert_eq!(textures.get_animation_timing("orc1", "missing", 0.1), AnimationTiming::uniform(0.1));
     }
 }
@@ -0,0 +1,111 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    prelude::{App, EventReader, IntoSystemConfig, Plugin, Res, ResMut, Resource},
+    time::Time,
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::profile::{achievements::{AchievementUnlocked, ACHIEVEMENTS}, PlayerProfile};
+
+use super::GOLD_COLOR;
+
+const TOAST_SECONDS: f32 = 4.;
+/* Time the toast takes to slide in from the right edge, and out again at the end */
+const TOAST_SLIDE_SECONDS: f32 = 0.3;
+const TOAST_WIDTH: f32 = 240.;
+
+#[derive(Resource, Default)]
+pub struct AchievementsWindow {
+    pub open: bool,
+}
+
+/* Unlocks waiting to be shown, one toast at a time, the front one is on screen */
+#[derive(Resource, Default)]
+struct AchievementToasts {
+    queue: VecDeque<usize>,
+    shown_for: Duration,
+}
+
+pub struct AchievementsUiPlugin;
+
+impl Plugin for AchievementsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AchievementsWindow>()
+            .init_resource::<AchievementToasts>()
+            .add_system(queue_toasts)
+            .add_system(achievement_toast.after(queue_toasts))
+            .add_system(achievements_window);
+    }
+}
+
+fn queue_toasts(mut unlocked: EventReader<AchievementUnlocked>, mut toasts: ResMut<AchievementToasts>) {
+    for ev in unlocked.iter() {
+        toasts.queue.push_back(ev.0);
+    }
+}
+
+/* How far the toast is pushed out to the right, 0 while it is fully shown */
+fn slide_offset(shown_for: f32) -> f32 {
+    let visible = (shown_for / TOAST_SLIDE_SECONDS).min((TOAST_SECONDS - shown_for) / TOAST_SLIDE_SECONDS).clamp(0., 1.);
+    return (1. - visible) * TOAST_WIDTH;
+}
+
+/* Runs on real time, the unlocks of the last frame of a match are shown while the game is paused */
+fn achievement_toast(mut contexts: EguiContexts, mut toasts: ResMut<AchievementToasts>, time: Res<Time>) {
+    let Some(index) = toasts.queue.front().copied() else {
+        return;
+    };
+    toasts.shown_for += time.raw_delta();
+    let shown_for = toasts.shown_for.as_secs_f32();
+    if shown_for >= TOAST_SECONDS {
+        toasts.queue.pop_front();
+        toasts.shown_for = Duration::ZERO;
+        return;
+    }
+    let achievement = &ACHIEVEMENTS[index];
+    egui::Area::new("achievement_toast").anchor(egui::Align2::RIGHT_TOP, [slide_offset(shown_for) - 8., 40.]).show(contexts.ctx_mut(), |area| {
+        egui::Frame::popup(area.style()).show(area, |frame| {
+            frame.set_width(TOAST_WIDTH);
+            frame.colored_label(GOLD_COLOR, "Achievement unlocked!");
+            frame.heading(achievement.name);
+            frame.label(achievement.description);
+        });
+    });
+}
+
+fn achievements_window(mut contexts: EguiContexts, mut window_state: ResMut<AchievementsWindow>, profile: Res<PlayerProfile>) {
+    if !window_state.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Achievements").open(&mut open).default_width(300.).show(contexts.ctx_mut(), |window| {
+        let unlocked = ACHIEVEMENTS.iter().filter(|achievement| profile.achievements.contains(achievement.id)).count();
+        window.label(format!("{}/{} unlocked", unlocked, ACHIEVEMENTS.len()));
+        window.separator();
+        for achievement in ACHIEVEMENTS.iter() {
+            let done = profile.achievements.contains(achievement.id);
+            window.horizontal(|row| {
+                if done {
+                    row.colored_label(GOLD_COLOR, format!("✔ {}", achievement.name));
+                } else {
+                    row.weak(achievement.name);
+                }
+                row.label(egui::RichText::new(achievement.description).small());
+            });
+        }
+    });
+    window_state.open = open;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_slide_in_and_out() {
+        assert_eq!(slide_offset(0.), TOAST_WIDTH);
+        assert_eq!(slide_offset(TOAST_SECONDS / 2.), 0.);
+        assert!((slide_offset(TOAST_SECONDS - TOAST_SLIDE_SECONDS / 2.) - TOAST_WIDTH / 2.).abs() < 1e-3);
+    }
+}
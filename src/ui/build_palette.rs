@@ -0,0 +1,191 @@
+use bevy::{
+    input::Input,
+    prelude::{default, App, Camera, Color, Commands, Component, EventWriter, GlobalTransform, Handle, IntoSystemConfig, KeyCode, MouseButton, Plugin, Query, Res, ResMut, Resource, Transform, Vec2, Visibility, With},
+    sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
+    window::{PrimaryWindow, Window},
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    camera::MainCamera,
+    error::GameplaySet,
+    localization::Localization,
+    textures::TextureResource,
+    util::can_afford,
+    world::{
+        building_configuration::{BuildingResource, BuildingType},
+        defender_controller::{human_defends, DefenderControl, ResourceStore},
+        events::PlaceStructureRequest,
+        path_finding::{verify_footprint_placement_valid, Node},
+        towers::{TowerField, SLOT_SIZE},
+    },
+};
+
+use super::{setup_closed, util::BuildingIcons, GOLD_COLOR};
+
+/* Above structures, below projectiles */
+const GHOST_Z: f32 = 15.;
+const PALETTE_ICON_SIZE: f32 = 20.;
+
+/* Building picked in the palette, placed on the field with a left click */
+#[derive(Resource, Default)]
+pub struct BuildPalette {
+    pub selected: Option<BuildingType>,
+}
+
+/* Preview of the selected building under the cursor */
+#[derive(Component)]
+struct PlacementGhost;
+
+pub struct BuildPalettePlugin;
+
+impl Plugin for BuildPalettePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildPalette>()
+            .add_startup_system(spawn_placement_ghost)
+            .add_system(build_palette_window.in_set(GameplaySet).run_if(human_defends).run_if(setup_closed))
+            .add_system(place_selected_building.in_set(GameplaySet).after(build_palette_window).run_if(setup_closed));
+    }
+}
+
+/* Gray when the defender is broke, red where the building doesn't fit or would seal the path */
+pub fn ghost_color(affordable: bool, valid: bool) -> Color {
+    return if !affordable {
+        Color::rgba(0.5, 0.5, 0.5, 0.45)
+    } else if !valid {
+        Color::rgba(1., 0.2, 0.2, 0.45)
+    } else {
+        Color::rgba(0.3, 1., 0.3, 0.45)
+    };
+}
+
+fn spawn_placement_ghost(mut commands: Commands) {
+    commands.spawn((
+        SpriteSheetBundle { visibility: Visibility::Hidden, ..default() },
+        PlacementGhost,
+    ));
+}
+
+fn build_palette_window(
+    mut contexts: EguiContexts,
+    mut palette: ResMut<BuildPalette>,
+    store: Res<ResourceStore>,
+    buildings: Res<BuildingResource>,
+    icons: BuildingIcons,
+    localization: Res<Localization>,
+) {
+    let sheet = icons.sheet(&mut contexts);
+    egui::Window::new(localization.t("Build")).id(egui::Id::new("build_palette")).anchor(egui::Align2::LEFT_BOTTOM, [8., -8.]).resizable(false).show(contexts.ctx_mut(), |window| {
+        window.colored_label(GOLD_COLOR, localization.t_args("Gold: {}", &[&store.gold]));
+        for building_type in icons.get_building_types() {
+            let building_type = *building_type;
+            if buildings.get_building_config(&building_type).is_none() {
+                continue;
+            }
+            let cost = buildings.get_cost(&building_type);
+            let selected = palette.selected == Some(building_type);
+            let label = format!("{} ({})", icons.get_name(building_type), cost);
+            let clicked = window.horizontal(|row| {
+                if let Some(image) = sheet.as_ref().and_then(|sheet| sheet.image(building_type, PALETTE_ICON_SIZE)) {
+                    row.add(image);
+                }
+                return row.add_enabled(can_afford(store.gold, cost), egui::SelectableLabel::new(selected, label))
+                    .on_hover_text(localization.t_args("Cost: {}", &[&cost]))
+                    .on_disabled_hover_text(localization.t_args("Needs {} more gold", &[&(cost - store.gold)]))
+                    .clicked();
+            }).inner;
+            if clicked {
+                palette.selected = if selected { None } else { Some(building_type) };
+            }
+        }
+        if palette.selected.is_some() {
+            window.small(localization.t("Left click to build, Escape to cancel"));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn place_selected_building(
+    mut contexts: EguiContexts,
+    mut palette: ResMut<BuildPalette>,
+    mut requests: EventWriter<PlaceStructureRequest>,
+    mut ghost: Query<(&mut Transform, &mut TextureAtlasSprite, &mut Handle<TextureAtlas>, &mut Visibility), With<PlacementGhost>>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    field: Res<TowerField>,
+    store: Res<ResourceStore>,
+    buildings: Res<BuildingResource>,
+    textures: Res<TextureResource>,
+    control: Res<DefenderControl>,
+    icons: BuildingIcons,
+    localization: Res<Localization>,
+) {
+    let Ok((mut transform, mut sprite, mut atlas, mut visibility)) = ghost.get_single_mut() else {
+        return;
+    };
+    *visibility = Visibility::Hidden;
+    if keys.just_pressed(KeyCode::Escape) || !human_defends(control) {
+        palette.selected = None;
+    }
+    let Some(building_type) = palette.selected else {
+        return;
+    };
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position().and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) else {
+        return;
+    };
+    let sheet = icons.sheet(&mut contexts);
+    let ctx = contexts.ctx_mut();
+    if ctx.wants_pointer_input() {
+        return;
+    }
+    // Structures stand centered on their slot
+    let slot = (cursor - field.field_transform) / SLOT_SIZE as f32;
+    let node = Node::new(slot.x.round() as i32, slot.y.round() as i32);
+    if node.x < 0 || node.y < 0 || node.x as usize >= field.get_width() || node.y as usize >= field.get_height() {
+        return;
+    }
+    let footprint = building_type.get_footprint();
+    let cost = buildings.get_cost(&building_type);
+    let affordable = can_afford(store.gold, cost);
+    let valid = field.is_footprint_free(node, footprint) && verify_footprint_placement_valid(&field, node, footprint, buildings.get_blocking(&building_type));
+
+    *visibility = Visibility::Visible;
+    // Validation reports a missing icon at startup, the ghost just stays a plain square then
+    if let Some((icon_atlas, icon)) = buildings.get_icon(&building_type).and_then(|icon| textures.get_named_sprite("towers", icon)) {
+        *atlas = icon_atlas.clone();
+        sprite.index = icon.index;
+    }
+    sprite.color = ghost_color(affordable, valid);
+    sprite.custom_size = Some(Vec2::new(footprint.width as f32, footprint.height as f32) * SLOT_SIZE as f32);
+    let position = Vec2::new(node.x as f32, node.y as f32) * SLOT_SIZE as f32 + field.field_transform + footprint.center_offset();
+    transform.translation = position.extend(GHOST_Z);
+    egui::show_tooltip_at_pointer(ctx, egui::Id::new("placement_cost"), |tooltip| {
+        match &sheet {
+            Some(sheet) => sheet.label(tooltip, building_type, PALETTE_ICON_SIZE),
+            None => { tooltip.label(icons.get_name(building_type)); }
+        }
+        tooltip.colored_label(if affordable { GOLD_COLOR } else { egui::Color32::GRAY }, localization.t_args("Cost: {}", &[&cost]));
+    });
+
+    if mouse.just_pressed(MouseButton::Left) && affordable && valid {
+        requests.send(PlaceStructureRequest { building_type, node });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_is_gray_whenever_the_building_cant_be_afforded() {
+        assert_eq!(ghost_color(false, true), ghost_color(false, false));
+        assert_ne!(ghost_color(true, true), ghost_color(false, true));
+        assert_ne!(ghost_color(true, false), ghost_color(true, true));
+        assert!(!can_afford(9, 10) && can_afford(10, 10));
+    }
+}
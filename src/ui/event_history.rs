@@ -0,0 +1,259 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::Input,
+    prelude::{App, EventReader, IntoSystemConfig, KeyCode, Plugin, Res, ResMut, Resource},
+    time::Time,
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::world::{
+    events::{
+        ActivateAbility, BlinkEvent, BoardClearedEvent, ClearBoardRequest, DamageEvent, DefenderPanicEvent, EntityReachedEnd, FieldModified, GameOverEvent, GoldStolenEvent, KillEvent,
+        PlaceStructureRequest, RemoveStructureRequest, RemovedStructureEvent, RequestRoundStart, RoundOverEvent, RoundStartEvent, SlowEvent, SplashImpactEvent,
+    },
+    towers::DamageConsumerSet,
+};
+
+/* Older entries are dropped once the history holds this many, hidden kinds aren't recorded so they can't push the rest out */
+const HISTORY_CAPACITY: usize = 100;
+/* Entries shown in the window, the newest ones */
+const HISTORY_SHOWN: usize = 50;
+const TOGGLE_KEY: KeyCode = KeyCode::F8;
+
+/* Tag written after the timestamp of every entry, one per event type in events.rs */
+pub const EVENT_KINDS: [&str; 19] = [
+    "DAMAGE", "KILL", "SLOW", "SPLASH", "REACHED_END", "BLINK", "GOLD_STOLEN", "ROUND_OVER", "ROUND_START", "REQUEST_ROUND_START",
+    "FIELD_MODIFIED", "ACTIVATE_ABILITY", "REMOVE_REQUEST", "PLACE_REQUEST", "REMOVED", "CLEAR_REQUEST", "BOARD_CLEARED", "GAME_OVER", "PANIC",
+];
+
+/* Raw record of every event sent, for tracking down the order things happened in. Unlike EventLog it isn't meant for players */
+#[derive(Resource, Default)]
+pub struct EventHistoryLog {
+    pub entries: VecDeque<(f32, String)>,
+    hidden: Vec<&'static str>,
+    pub open: bool,
+}
+
+impl EventHistoryLog {
+    pub fn push(&mut self, time: f32, kind: &'static str, details: String) {
+        if !self.is_shown(kind) {
+            return;
+        }
+        if self.entries.len() >= HISTORY_CAPACITY {
+            self.entries.pop_front();
+        }
+        let text = if details.is_empty() { format!("T={:.1} {}", time, kind) } else { format!("T={:.1} {} {}", time, kind, details) };
+        self.entries.push_back((time, text));
+    }
+
+    pub fn is_shown(&self, kind: &str) -> bool {
+        return !self.hidden.contains(&kind);
+    }
+
+    pub fn set_shown(&mut self, kind: &'static str, shown: bool) {
+        self.hidden.retain(|hidden| *hidden != kind);
+        if !shown {
+            self.hidden.push(kind);
+        }
+    }
+
+    /* The last entries with a shown kind, oldest first */
+    pub fn get_shown(&self) -> Vec<&(f32, String)> {
+        let mut shown: Vec<&(f32, String)> = self.entries.iter().rev().filter(|(_, text)| self.is_shown(entry_kind(text))).take(HISTORY_SHOWN).collect();
+        shown.reverse();
+        return shown;
+    }
+}
+
+fn entry_kind(text: &str) -> &str {
+    return text.split(' ').nth(1).unwrap_or_default();
+}
+
+/* Readers for the events caused by attackers and towers */
+#[derive(SystemParam)]
+struct CombatEvents<'w, 's> {
+    damage: EventReader<'w, 's, DamageEvent>,
+    kills: EventReader<'w, 's, KillEvent>,
+    slows: EventReader<'w, 's, SlowEvent>,
+    splashes: EventReader<'w, 's, SplashImpactEvent>,
+    reached_end: EventReader<'w, 's, EntityReachedEnd>,
+    blinks: EventReader<'w, 's, BlinkEvent>,
+    thefts: EventReader<'w, 's, GoldStolenEvent>,
+    abilities: EventReader<'w, 's, ActivateAbility>,
+}
+
+/* Readers for rounds, the field and the match */
+#[derive(SystemParam)]
+struct FlowEvents<'w, 's> {
+    round_overs: EventReader<'w, 's, RoundOverEvent>,
+    round_starts: EventReader<'w, 's, RoundStartEvent>,
+    round_requests: EventReader<'w, 's, RequestRoundStart>,
+    field_modified: EventReader<'w, 's, FieldModified>,
+    remove_requests: EventReader<'w, 's, RemoveStructureRequest>,
+    place_requests: EventReader<'w, 's, PlaceStructureRequest>,
+    removed: EventReader<'w, 's, RemovedStructureEvent>,
+    clear_requests: EventReader<'w, 's, ClearBoardRequest>,
+    board_cleared: EventReader<'w, 's, BoardClearedEvent>,
+    game_overs: EventReader<'w, 's, GameOverEvent>,
+    panics: EventReader<'w, 's, DefenderPanicEvent>,
+}
+
+pub struct EventHistoryPlugin;
+
+impl Plugin for EventHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        // Not in the gameplay set, events sent right before a pause or a fatal error are the interesting ones
+        app.init_resource::<EventHistoryLog>()
+            .add_system(log_game_events.in_set(DamageConsumerSet))
+            .add_system(render_event_log.after(log_game_events));
+    }
+}
+
+fn log_game_events(mut history: ResMut<EventHistoryLog>, mut combat: CombatEvents, mut flow: FlowEvents, time: Res<Time>) {
+    let now = time.elapsed_seconds();
+    for ev in combat.damage.iter() {
+        history.push(now, "DAMAGE", format!("target={:?} amount={:.1} absorbed={:.1} type={:?} critical={}", ev.target, ev.amount, ev.absorbed, ev.damage_type, ev.critical));
+    }
+    for ev in combat.kills.iter() {
+        history.push(now, "KILL", format!("target={:?} tower={:?} bounty={}", ev.target, ev.tower, ev.bounty));
+    }
+    for ev in combat.slows.iter() {
+        history.push(now, "SLOW", format!("target={:?} factor={:.2} duration={:.1}", ev.target, ev.factor, ev.duration_secs));
+    }
+    for ev in combat.splashes.iter() {
+        history.push(now, "SPLASH", format!("position=({:.0}, {:.0}) radius={:.0}", ev.position.x, ev.position.y, ev.radius));
+    }
+    for ev in combat.reached_end.iter() {
+        history.push(now, "REACHED_END", format!("entity={:?} bounty={} effect={:?}", ev.entity, ev.bounty, ev.leak_effect));
+    }
+    for ev in combat.blinks.iter() {
+        history.push(now, "BLINK", format!("entity={:?} from=({:.0}, {:.0}) to=({:.0}, {:.0})", ev.entity, ev.from.x, ev.from.y, ev.to.x, ev.to.y));
+    }
+    for ev in combat.thefts.iter() {
+        history.push(now, "GOLD_STOLEN", format!("entity={:?} amount={}", ev.entity, ev.amount));
+    }
+    for ev in combat.abilities.iter() {
+        history.push(now, "ACTIVATE_ABILITY", format!("tower={:?}", ev.tower));
+    }
+    for _ in flow.round_requests.iter() {
+        history.push(now, "REQUEST_ROUND_START", String::new());
+    }
+    for _ in flow.round_starts.iter() {
+        history.push(now, "ROUND_START", String::new());
+    }
+    for ev in flow.place_requests.iter() {
+        history.push(now, "PLACE_REQUEST", format!("building={:?} node={}", ev.building_type, ev.node));
+    }
+    for ev in flow.remove_requests.iter() {
+        history.push(now, "REMOVE_REQUEST", format!("node={} destroyed={}", ev.node, ev.destroyed));
+    }
+    for ev in flow.removed.iter() {
+        history.push(now, "REMOVED", format!("building={:?} node={} destroyed={}", ev.building_type, ev.node, ev.destroyed));
+    }
+    for ev in flow.clear_requests.iter() {
+        history.push(now, "CLEAR_REQUEST", format!("refund={:.2}", ev.refund_fraction));
+    }
+    for ev in flow.board_cleared.iter() {
+        history.push(now, "BOARD_CLEARED", format!("removed={} refund={:.2}", ev.removed.len(), ev.refund_fraction));
+    }
+    for ev in flow.field_modified.iter() {
+        history.push(now, "FIELD_MODIFIED", format!("nodes={}", ev.nodes.len()));
+    }
+    for ev in flow.panics.iter() {
+        history.push(now, "PANIC", format!("panicking={}", ev.panicking));
+    }
+    for _ in flow.round_overs.iter() {
+        history.push(now, "ROUND_OVER", String::new());
+    }
+    for ev in flow.game_overs.iter() {
+        history.push(now, "GAME_OVER", format!("attacker_won={}", ev.attacker_won));
+    }
+}
+
+fn render_event_log(mut contexts: EguiContexts, mut history: ResMut<EventHistoryLog>, keys: Res<Input<KeyCode>>) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        history.open = !history.open;
+    }
+    if !history.open {
+        return;
+    }
+    let mut open = true;
+    egui::Window::new("Event History").open(&mut open).default_width(420.).show(contexts.ctx_mut(), |window| {
+        window.horizontal_wrapped(|row| {
+            for kind in EVENT_KINDS {
+                let mut shown = history.is_shown(kind);
+                if row.checkbox(&mut shown, kind).changed() {
+                    history.set_shown(kind, shown);
+                }
+            }
+        });
+        window.separator();
+        egui::ScrollArea::vertical().max_height(300.).stick_to_bottom(true).show(window, |scroll| {
+            for (_, text) in history.get_shown() {
+                scroll.label(egui::RichText::new(text).monospace());
+            }
+        });
+    });
+    history.open = open;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, MinimalPlugins, Vec2};
+
+    use super::*;
+    use crate::world::events::EventsPlugin;
+
+    #[test]
+    fn kills_are_logged_with_target_and_bounty() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugin(EventsPlugin).init_resource::<EventHistoryLog>().add_system(log_game_events);
+        let target = Entity::from_raw(5);
+        app.world.send_event(KillEvent {
+            target,
+            source: Entity::PLACEHOLDER,
+            tower: Entity::PLACEHOLDER,
+            bounty: 10,
+            original_cost: 40,
+            group_size: 1,
+            death_position: Vec2::ZERO,
+            size: Vec2::ONE,
+        });
+        app.world.send_event(RoundOverEvent);
+        app.update();
+
+        let history = app.world.resource::<EventHistoryLog>();
+        let texts: Vec<&str> = history.entries.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(texts[0], format!("T=0.0 KILL target={:?} tower={:?} bounty=10", target, Entity::PLACEHOLDER));
+        assert_eq!(texts[1], "T=0.0 ROUND_OVER");
+    }
+
+    #[test]
+    fn hidden_kinds_are_filtered_and_the_history_is_capped() {
+        let mut history = EventHistoryLog::default();
+        for i in 0..HISTORY_CAPACITY + 10 {
+            history.push(i as f32, if i % 2 == 0 { "DAMAGE" } else { "KILL" }, String::new());
+        }
+        assert_eq!(history.entries.len(), HISTORY_CAPACITY);
+        assert_eq!(history.entries[0].0, 10.);
+        history.set_shown("DAMAGE", false);
+        let shown = history.get_shown();
+        assert_eq!(shown.len(), HISTORY_CAPACITY / 2);
+        assert!(shown.iter().all(|(_, text)| entry_kind(text) == "KILL"));
+        assert_eq!(shown.last().unwrap().0, (HISTORY_CAPACITY + 9) as f32);
+    }
+
+    #[test]
+    fn hidden_kinds_do_not_push_shown_entries_out() {
+        let mut history = EventHistoryLog::default();
+        history.set_shown("DAMAGE", false);
+        history.push(0., "KILL", String::new());
+        for i in 1..HISTORY_CAPACITY * 2 {
+            history.push(i as f32, "DAMAGE", String::new());
+        }
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.get_shown().len(), 1);
+    }
+}
@@ -0,0 +1,262 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{
+    prelude::{Added, App, EventReader, IntoSystemConfig, Plugin, Query, Res, ResMut, Resource, Transform},
+    time::Time,
+};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    error::GameplaySet,
+    world::{
+        attackers::AttackerType,
+        building_configuration::BuildingType,
+        building_registry::BuildingRegistry,
+        events::{BoardClearedEvent, DefenderPanicEvent, EntityReachedEnd, GoldStolenEvent, KillEvent, RemovedStructureEvent, RoundOverEvent, RoundStartEvent},
+        rounds::RoundResource,
+        towers::{Structure, TowerField},
+    },
+};
+
+use super::util::{format_duration_short, BuildingIcons};
+
+/* Older lines are dropped once the log holds this many */
+const EVENT_LOG_CAPACITY: usize = 200;
+const LOG_ICON_SIZE: f32 = 14.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogCategory {
+    Kill,
+    Leak,
+    Structure,
+    Round,
+    Defender,
+}
+
+impl LogCategory {
+    pub const ALL: [LogCategory; 5] = [LogCategory::Kill, LogCategory::Leak, LogCategory::Structure, LogCategory::Round, LogCategory::Defender];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            LogCategory::Kill => "Kills",
+            LogCategory::Leak => "Leaks",
+            LogCategory::Structure => "Buildings",
+            LogCategory::Round => "Rounds",
+            LogCategory::Defender => "Defender",
+        };
+    }
+
+    fn color(&self) -> egui::Color32 {
+        return match self {
+            LogCategory::Kill => egui::Color32::from_rgb(230, 230, 230),
+            LogCategory::Leak => egui::Color32::from_rgb(230, 90, 70),
+            LogCategory::Structure => egui::Color32::from_rgb(140, 180, 230),
+            LogCategory::Round => egui::Color32::from_rgb(255, 215, 0),
+            LogCategory::Defender => egui::Color32::from_rgb(240, 150, 40),
+        };
+    }
+}
+
+pub struct LogLine {
+    pub time: Duration,
+    pub category: LogCategory,
+    /* Building shown in front of the line */
+    pub icon: Option<BuildingType>,
+    pub text: String,
+}
+
+/* Human readable history of what happened on the field, newest last */
+#[derive(Resource)]
+pub struct EventLog {
+    lines: VecDeque<LogLine>,
+    hidden: Vec<LogCategory>,
+    pub open: bool,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        return Self { lines: VecDeque::with_capacity(EVENT_LOG_CAPACITY), hidden: Vec::new(), open: false };
+    }
+}
+
+impl EventLog {
+    pub fn push(&mut self, time: Duration, category: LogCategory, text: impl Into<String>) {
+        self.push_line(LogLine { time, category, icon: None, text: text.into() });
+    }
+
+    pub fn push_with_icon(&mut self, time: Duration, category: LogCategory, icon: BuildingType, text: impl Into<String>) {
+        self.push_line(LogLine { time, category, icon: Some(icon), text: text.into() });
+    }
+
+    fn push_line(&mut self, line: LogLine) {
+        if self.lines.len() >= EVENT_LOG_CAPACITY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn get_lines(&self) -> impl Iterator<Item = &LogLine> {
+        return self.lines.iter();
+    }
+
+    pub fn is_shown(&self, category: LogCategory) -> bool {
+        return !self.hidden.contains(&category);
+    }
+
+    pub fn set_shown(&mut self, category: LogCategory, shown: bool) {
+        self.hidden.retain(|hidden| *hidden != category);
+        if !shown {
+            self.hidden.push(category);
+        }
+    }
+}
+
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>()
+            .add_system(record_events.in_set(GameplaySet))
+            .add_system(event_log_window.in_set(GameplaySet).after(record_events));
+    }
+}
+
+fn attacker_name(attacker_type: Option<&AttackerType>) -> &'static str {
+    return attacker_type.map_or("An attacker", |attacker_type| attacker_type.get_name());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_events(
+    mut log: ResMut<EventLog>,
+    mut kills: EventReader<KillEvent>,
+    mut leaks: EventReader<EntityReachedEnd>,
+    mut thefts: EventReader<GoldStolenEvent>,
+    mut removals: EventReader<RemovedStructureEvent>,
+    mut clears: EventReader<BoardClearedEvent>,
+    mut round_starts: EventReader<RoundStartEvent>,
+    mut round_ends: EventReader<RoundOverEvent>,
+    mut panics: EventReader<DefenderPanicEvent>,
+    placed: Query<(&Structure, &Transform), Added<Structure>>,
+    structures: Query<&Structure>,
+    attacker_types: Query<&AttackerType>,
+    field: Res<TowerField>,
+    round: Res<RoundResource>,
+    registry: Res<BuildingRegistry>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    for _ in round_starts.iter() {
+        log.push(now, LogCategory::Round, format!("Round {} started", round.get_round_number()));
+    }
+    for (structure, transform) in placed.iter() {
+        let node = field.node_at(transform.translation.truncate());
+        log.push_with_icon(now, LogCategory::Structure, structure.building_type, format!("Built {} at ({}, {})", registry.get_name(structure.building_type), node.x, node.y));
+    }
+    for ev in removals.iter() {
+        let verb = if ev.destroyed { "Destroyed" } else { "Removed" };
+        log.push_with_icon(now, LogCategory::Structure, ev.building_type, format!("{} {} at ({}, {})", verb, registry.get_name(ev.building_type), ev.node.x, ev.node.y));
+    }
+    for ev in clears.iter() {
+        log.push(now, LogCategory::Structure, format!("Board cleared, {} buildings removed", ev.removed.len()));
+    }
+    for ev in kills.iter() {
+        // The tower may have been sold while its projectile was still flying
+        let text = |tower: String| format!("{} killed {} (+{}g)", tower, attacker_name(attacker_types.get(ev.target).ok()), ev.bounty);
+        match structures.get(ev.tower) {
+            Ok(structure) => log.push_with_icon(now, LogCategory::Kill, structure.building_type, text(format!("{} tower", registry.get_name(structure.building_type)))),
+            Err(_) => log.push(now, LogCategory::Kill, text("A removed tower".to_string()))
+        }
+    }
+    for ev in leaks.iter() {
+        log.push(now, LogCategory::Leak, format!("{} leaked! -1 life", attacker_name(attacker_types.get(ev.entity).ok())));
+    }
+    for ev in thefts.iter() {
+        log.push(now, LogCategory::Leak, format!("{} stole {}g", attacker_name(attacker_types.get(ev.entity).ok()), ev.amount));
+    }
+    for ev in panics.iter() {
+        log.push(now, LogCategory::Defender, if ev.panicking { "Defender panics, an attacker is about to leak" } else { "Defender calmed down" });
+    }
+    for _ in round_ends.iter() {
+        log.push(now, LogCategory::Round, format!("Round {} over", round.get_round_number()));
+    }
+}
+
+fn event_log_window(mut contexts: EguiContexts, mut log: ResMut<EventLog>, icons: BuildingIcons) {
+    if !log.open {
+        return;
+    }
+    let sheet = icons.sheet(&mut contexts);
+    let mut open = true;
+    egui::Window::new("Event Log").open(&mut open).default_width(320.).show(contexts.ctx_mut(), |window| {
+        window.horizontal(|row| {
+            for category in LogCategory::ALL {
+                let mut shown = log.is_shown(category);
+                if row.checkbox(&mut shown, category.get_name()).changed() {
+                    log.set_shown(category, shown);
+                }
+            }
+        });
+        window.separator();
+        egui::ScrollArea::vertical().max_height(300.).stick_to_bottom(true).show(window, |scroll| {
+            for line in log.get_lines().filter(|line| log.is_shown(line.category)) {
+                scroll.horizontal(|row| {
+                    row.label(egui::RichText::new(format_duration_short(line.time)).weak().monospace());
+                    if let Some(image) = line.icon.zip(sheet.as_ref()).and_then(|(icon, sheet)| sheet.image(icon, LOG_ICON_SIZE)) {
+                        row.add(image);
+                    }
+                    row.colored_label(line.category.color(), &line.text);
+                });
+            }
+        });
+    });
+    log.open = open;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, Vec2};
+
+    use crate::tests::{build_test_tower_field, schedule_of, system_test_world};
+
+    use super::*;
+
+    #[test]
+    fn kills_name_the_tower_and_the_attacker() {
+        let mut world = system_test_world();
+        world.init_resource::<EventLog>();
+        world.init_resource::<RoundResource>();
+        world.init_resource::<BuildingRegistry>();
+        world.insert_resource(build_test_tower_field());
+        world.init_resource::<Time>();
+        let tower = world.spawn(Structure { building_type: BuildingType::Arrow, blocking: true }).id();
+        let spider = world.spawn(AttackerType::Spider).id();
+        world.send_event(KillEvent {
+            target: spider,
+            source: Entity::PLACEHOLDER,
+            tower,
+            bounty: 15,
+            original_cost: 60,
+            group_size: 3,
+            death_position: Vec2::ZERO,
+            size: Vec2::ONE,
+        });
+        let mut schedule = schedule_of(record_events);
+        schedule.run(&mut world);
+
+        let log = world.resource::<EventLog>();
+        let lines: Vec<&str> = log.get_lines().map(|line| line.text.as_str()).collect();
+        assert_eq!(lines, vec!["Arrow tower killed Spider (+15g)"]);
+        assert_eq!(log.get_lines().next().unwrap().category, LogCategory::Kill);
+        assert_eq!(log.get_lines().next().unwrap().icon, Some(BuildingType::Arrow));
+    }
+
+    #[test]
+    fn oldest_lines_are_dropped() {
+        let mut log = EventLog::default();
+        for i in 0..EVENT_LOG_CAPACITY + 5 {
+            log.push(Duration::ZERO, LogCategory::Round, format!("Round {} started", i));
+        }
+        assert_eq!(log.get_lines().count(), EVENT_LOG_CAPACITY);
+        assert_eq!(log.get_lines().next().unwrap().text, "Round 5 started");
+    }
+}
@@ -0,0 +1,115 @@
+use bevy::{log::warn, prelude::{App, Plugin, Res, ResMut, Resource}};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{localization::Localization, util::storage};
+
+use super::GOLD_COLOR;
+
+const HELP_SEEN_FILE: &str = "help_seen.txt";
+
+/* Key or widget and what it does, extend these as features are added */
+const CONTROLS: [(&str, &str); 11] = [
+    ("W A S D", "Move the camera"),
+    ("Shift", "Hold to move the camera faster"),
+    ("Mouse wheel", "Zoom in and out"),
+    ("Home", "Frame the whole field"),
+    ("Side panel", "Queue attackers for the next round and buy upgrades"),
+    ("Start Round", "Send the queued attackers"),
+    ("Left click", "Select a tower to see its range and stats"),
+    ("Right click", "Activate the special ability of a tower"),
+    ("- / +", "Slow down or speed up the game"),
+    ("P", "Pause and resume the game"),
+    ("F8", "Show the raw history of game events, for tracking down bugs"),
+];
+
+const MECHANICS: [(&str, &str); 4] = [
+    ("Goal", "Every attacker reaching the end costs the defender a life, take all of them to win"),
+    ("Gold", "Units cost gold, kills and leaks add to the bounty paid out when the round is over"),
+    ("Defender", "Builds towers along the path and walls to make it longer, it adapts to what you send"),
+    ("Upgrades", "Health, speed and more for each unit type, they last for the whole match"),
+];
+
+/* Shown by itself on the first launch, reopened from the menu afterwards */
+#[derive(Resource)]
+pub struct HelpOverlay {
+    pub open: bool,
+    seen: bool,
+}
+
+impl HelpOverlay {
+    pub fn from_saved(saved: Option<String>) -> Self {
+        let seen = saved.is_some();
+        return Self { open: !seen, seen };
+    }
+
+    pub fn is_seen(&self) -> bool {
+        return self.seen;
+    }
+
+    /* Closes the overlay, true the first time so the caller can remember it */
+    pub fn dismiss(&mut self) -> bool {
+        self.open = false;
+        let first = !self.seen;
+        self.seen = true;
+        return first;
+    }
+}
+
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HelpOverlay::from_saved(storage::load(HELP_SEEN_FILE)))
+            .add_system(help_window);
+    }
+}
+
+fn help_section(ui: &mut egui::Ui, id: &str, entries: &[(&'static str, &'static str)], localization: &Localization) {
+    egui::Grid::new(id).num_columns(2).striped(true).show(ui, |grid| {
+        for (control, description) in entries {
+            grid.colored_label(GOLD_COLOR, localization.t(control));
+            grid.label(localization.t(description));
+            grid.end_row();
+        }
+    });
+}
+
+fn help_window(mut contexts: EguiContexts, mut help: ResMut<HelpOverlay>, localization: Res<Localization>) {
+    if !help.open {
+        return;
+    }
+    let mut open = true;
+    let mut dismissed = false;
+    egui::Window::new(localization.t("Help")).id(egui::Id::new("help")).open(&mut open).collapsible(false).default_width(420.).anchor(egui::Align2::CENTER_TOP, [0., 60.]).show(contexts.ctx_mut(), |window| {
+        window.heading(localization.t("Controls"));
+        help_section(window, "help_controls", &CONTROLS, &localization);
+        window.separator();
+        window.heading(localization.t("How it works"));
+        help_section(window, "help_mechanics", &MECHANICS, &localization);
+        window.separator();
+        dismissed = window.button(localization.t("Got it")).clicked();
+    });
+    if (dismissed || !open) && help.dismiss() {
+        if let Err(err) = storage::save(HELP_SEEN_FILE, "1") {
+            warn!("Failed to remember the help was seen: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_is_only_shown_by_itself_until_dismissed() {
+        let mut help = HelpOverlay::from_saved(None);
+        assert!(help.open);
+        assert!(help.dismiss());
+        assert!(!help.open && help.is_seen());
+
+        // Reopened from the menu, closing it again has nothing new to remember
+        help.open = true;
+        assert!(!help.dismiss());
+        assert!(!HelpOverlay::from_saved(Some("1".to_string())).open);
+    }
+}
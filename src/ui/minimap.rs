@@ -0,0 +1,250 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::{
+        default, App, Camera, Camera2d, Camera2dBundle, Color, Commands, Component, Entity, EventReader, IntoSystemConfig,
+        Local, OrthographicProjection, Plugin, Query, Res, Transform, Vec2, Vec3, With, Without,
+    },
+    render::{
+        camera::{ScalingMode, Viewport},
+        view::RenderLayers,
+    },
+    sprite::{Sprite, SpriteBundle},
+    utils::HashSet,
+    window::{PrimaryWindow, Window},
+};
+use bevy_egui::EguiContexts;
+
+use crate::{
+    camera::MainCamera,
+    world::{
+        attackers::Attacker,
+        building_configuration::BuildingType,
+        building_registry::BuildingRegistry,
+        events::FieldModified,
+        path_finding::Node,
+        towers::{Structure, TowerField, SLOT_SIZE},
+    },
+};
+
+/* Only the minimap camera renders this layer, the main camera keeps rendering layer 0 */
+pub const MINIMAP_LAYER: u8 = 1;
+const MINIMAP_PIXELS: u32 = 128;
+const MINIMAP_MARGIN: f32 = 8.;
+const FRUSTUM_THICKNESS: f32 = 0.15;
+const BACKGROUND_COLOR: Color = Color::rgb(0.02, 0.02, 0.02);
+/* Just in front of the camera's far plane, behind the slots at 0 */
+const BACKGROUND_Z: f32 = -0.05;
+
+#[derive(Component)]
+pub struct MinimapCamera;
+
+/* One per grid slot, in minimap space every slot is one unit wide */
+#[derive(Component)]
+struct MinimapSlot;
+
+#[derive(Component)]
+struct MinimapMarker {
+    attacker: Entity,
+}
+
+/* Bottom, top, left and right edge of the main camera's view */
+#[derive(Component)]
+struct MinimapFrustumEdge(u8);
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_minimap)
+            .add_system(position_minimap_camera.after(super::side_unit_panel))
+            .add_system(update_minimap_sprites)
+            .add_system(update_minimap_markers)
+            .add_system(update_minimap_frustum);
+    }
+}
+
+fn setup_minimap(mut commands: Commands, field: Res<TowerField>) {
+    let width = field.get_width() as f32;
+    let height = field.get_height() as f32;
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: 1,
+                // Activated once there is room for it next to the panels
+                is_active: false,
+                ..default()
+            },
+            // Clearing would wipe the main view the minimap is drawn over, its background is a sprite instead
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::None,
+            },
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::AutoMin { min_width: width, min_height: height },
+                far: 1000.,
+                ..default()
+            },
+            transform: Transform::from_xyz((width - 1.) / 2., (height - 1.) / 2., 999.9),
+            ..default()
+        },
+        RenderLayers::layer(MINIMAP_LAYER),
+        MinimapCamera,
+    ));
+    // Larger than the field so the margins the scaling mode leaves are covered too
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite { color: BACKGROUND_COLOR, custom_size: Some(Vec2::new(width, height) * 2.), ..default() },
+            transform: Transform::from_xyz((width - 1.) / 2., (height - 1.) / 2., BACKGROUND_Z),
+            ..default()
+        },
+        RenderLayers::layer(MINIMAP_LAYER),
+    ));
+    for edge in 0..4 {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color: Color::WHITE, custom_size: Some(Vec2::ONE), ..default() },
+                transform: Transform::from_xyz(0., 0., 2.),
+                ..default()
+            },
+            RenderLayers::layer(MINIMAP_LAYER),
+            MinimapFrustumEdge(edge),
+        ));
+    }
+}
+
+fn slot_color(building_type: Option<BuildingType>, registry: &BuildingRegistry) -> Color {
+    return match building_type {
+        None => Color::rgb(0.18, 0.42, 0.16),
+        Some(building_type) => registry.get_minimap_color(building_type),
+    };
+}
+
+/* World position to minimap space, where slot (x, y) sits at (x, y) */
+fn to_minimap(position: Vec2, field: &TowerField) -> Vec2 {
+    return (position - field.field_transform) / SLOT_SIZE as f32;
+}
+
+/* Keeps the minimap in the bottom right corner of whatever the egui panels leave free */
+fn position_minimap_camera(
+    mut contexts: EguiContexts,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut minimap_q: Query<&mut Camera, With<MinimapCamera>>,
+) {
+    let (Ok(window), Ok(mut camera)) = (windows.get_single(), minimap_q.get_single_mut()) else {
+        return;
+    };
+    let ctx = contexts.ctx_mut();
+    let pixels_per_point = ctx.pixels_per_point();
+    let free = ctx.available_rect();
+    let size = MINIMAP_PIXELS as f32;
+    let x = free.max.x * pixels_per_point - size - MINIMAP_MARGIN * pixels_per_point;
+    let y = free.max.y * pixels_per_point - size - MINIMAP_MARGIN * pixels_per_point;
+    // A viewport outside of the window is not allowed, so the minimap is hidden on tiny windows
+    let fits = x >= free.min.x * pixels_per_point
+        && y >= free.min.y * pixels_per_point
+        && x + size <= window.physical_width() as f32
+        && y + size <= window.physical_height() as f32;
+    camera.is_active = fits;
+    if fits {
+        camera.viewport = Some(Viewport {
+            physical_position: bevy::prelude::UVec2::new(x as u32, y as u32),
+            physical_size: bevy::prelude::UVec2::new(MINIMAP_PIXELS, MINIMAP_PIXELS),
+            ..default()
+        });
+    }
+}
+
+fn update_minimap_sprites(
+    mut commands: Commands,
+    mut field_modified: EventReader<FieldModified>,
+    mut is_initialized: Local<bool>,
+    slots: Query<Entity, With<MinimapSlot>>,
+    structures: Query<&Structure>,
+    field: Res<TowerField>,
+    registry: Res<BuildingRegistry>,
+) {
+    if field_modified.is_empty() && *is_initialized {
+        return;
+    }
+    field_modified.clear();
+    *is_initialized = true;
+    for entity in &slots {
+        commands.entity(entity).despawn();
+    }
+    for y in 0..field.get_height() {
+        for x in 0..field.get_width() {
+            let building_type = field
+                .get_slot(Node::new(x as i32, y as i32))
+                .filter(|_| field.is_occupied(x, y))
+                .and_then(|slot| structures.get(slot.entity).ok())
+                .map(|structure| structure.building_type);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite { color: slot_color(building_type, &registry), custom_size: Some(Vec2::ONE), ..default() },
+                    transform: Transform::from_xyz(x as f32, y as f32, 0.),
+                    ..default()
+                },
+                RenderLayers::layer(MINIMAP_LAYER),
+                MinimapSlot,
+            ));
+        }
+    }
+}
+
+fn update_minimap_markers(
+    mut commands: Commands,
+    attackers: Query<(Entity, &Transform), With<Attacker>>,
+    mut markers: Query<(Entity, &MinimapMarker, &mut Transform), Without<Attacker>>,
+    field: Res<TowerField>,
+) {
+    let mut tracked: HashSet<Entity> = HashSet::new();
+    for (entity, marker, mut transform) in markers.iter_mut() {
+        match attackers.get(marker.attacker) {
+            Ok((_, attacker_transform)) => {
+                transform.translation = to_minimap(attacker_transform.translation.truncate(), &field).extend(1.);
+                tracked.insert(marker.attacker);
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+    for (attacker, transform) in &attackers {
+        if tracked.contains(&attacker) {
+            continue;
+        }
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color: Color::RED, custom_size: Some(Vec2::splat(0.5)), ..default() },
+                transform: Transform::from_translation(to_minimap(transform.translation.truncate(), &field).extend(1.)),
+                ..default()
+            },
+            RenderLayers::layer(MINIMAP_LAYER),
+            MinimapMarker { attacker },
+        ));
+    }
+}
+
+fn update_minimap_frustum(
+    camera_q: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut edges: Query<(&MinimapFrustumEdge, &mut Transform), Without<MainCamera>>,
+    field: Res<TowerField>,
+) {
+    let Ok((camera_transform, projection)) = camera_q.get_single() else {
+        return;
+    };
+    let center = camera_transform.translation.truncate();
+    let scale = camera_transform.scale.truncate();
+    let min = to_minimap(center + projection.area.min * scale, &field);
+    let max = to_minimap(center + projection.area.max * scale, &field);
+    let middle = (min + max) / 2.;
+    let size = max - min;
+    for (edge, mut transform) in edges.iter_mut() {
+        let (position, edge_size) = match edge.0 {
+            0 => (Vec2::new(middle.x, min.y), Vec2::new(size.x, FRUSTUM_THICKNESS)),
+            1 => (Vec2::new(middle.x, max.y), Vec2::new(size.x, FRUSTUM_THICKNESS)),
+            2 => (Vec2::new(min.x, middle.y), Vec2::new(FRUSTUM_THICKNESS, size.y)),
+            _ => (Vec2::new(max.x, middle.y), Vec2::new(FRUSTUM_THICKNESS, size.y)),
+        };
+        transform.translation = position.extend(2.);
+        // The sprites are one unit large, scaling stretches them into lines
+        transform.scale = Vec3::new(edge_size.x, edge_size.y, 1.);
+    }
+}
@@ -2,10 +2,21 @@
 
 use core::fmt;
 
-use bevy::{prelude::{Plugin, App, Res, EventWriter, ResMut, Handle, Image, World, FromWorld, Resource, AssetServer, Local, Vec2, IntoSystemConfig, Events}, time::Time};
-use bevy_egui::{egui::{self, style, Color32, Ui, RichText, Align}, EguiContexts};
+use bevy::{ecs::{system::SystemParam, change_detection::DetectChanges}, log::warn, prelude::{Plugin, App, Res, EventReader, EventWriter, ResMut, Handle, Image, World, FromWorld, Resource, AssetServer, Local, Vec2, IntoSystemConfig, Events, Query, Transform, Camera, GlobalTransform, Vec3, Entity, Input, KeyCode, MouseButton, With, Or}, time::Time, window::{Window, PrimaryWindow}};
+use bevy_egui::{egui::{self, Color32, Ui, RichText, Stroke}, EguiContexts, EguiSettings};
 
-use crate::world::{attacker_controller::AttackerResource, events::RequestRoundStart, rounds::RoundResource, attackers::{Attacker, AttackerStats, AttackerType, UpgradeType}, defender_controller::{ResourceStore, RoundStats, DefenderConfiguration}};
+use crate::{camera::{CameraShake, MainCamera}, error::GameplaySet, localization::{Localization, LANGUAGES}, particle::{ParticleQuality, ParticleSettings}, profile::PlayerProfile, util::can_afford};
+use crate::world::{abilities::{SpecialAbility, ABILITY_UNLOCK_LEVEL}, interpolation::InterpolationSettings, attacker_ai::AttackerControl, game_config::GameConfig, attacker_controller::{AttackerResource, AttackerSuggestion, DefenderIntel, GoldPickup, PersistentComboBonus, RoundHistory, WavePrediction}, events::{RequestRoundStart, ActivateAbility, RoundOverEvent, RoundStartEvent, FieldModified, ClearBoardRequest, GameOverEvent}, rounds::{PrepTimer, RoundResource, ScheduledSpawns, SpawnCadence, SpawnPolicy}, attackers::{AttackerStats, AttackerType, LeakEffect, UpgradeInfo, UpgradeType, PHANTOM_BLINK_DISTANCE, PHANTOM_BLINK_SECONDS, GOLEM_INTERCEPT_COOLDOWN, GOLEM_INTERCEPT_RANGE}, defender_controller::{ResourceStore, RoundStats, DefenderConfiguration, DefenderControl, SAVING_PATIENCE}, defender_strategy::{get_strategies, ActiveStrategy}, path_finding::{nodes_on_all_paths, FlowField, Node, PathSmoothing}, sudden_death::{SuddenDeathState, SUDDEN_DEATH_DRAIN_SECONDS}, mutators::{Mutator, RoundModifiers}, endless::{EndlessScore, GameMode}, building_registry::BuildingRegistry, towers::{covered_nodes, MAX_BANNER_BONUS, BannerAura, Buffed, Defender, Structure, SlowAura, TowerField, SLOT_SIZE}};
+use self::{achievements::{AchievementsUiPlugin, AchievementsWindow}, build_palette::BuildPalettePlugin, event_history::EventHistoryPlugin, event_log::{EventLog, EventLogPlugin}, help::{HelpOverlay, HelpPlugin}, minimap::MinimapPlugin, objective::{update_win_condition, ObjectivePlugin, WinConditionDisplay}, util::{format_duration, format_duration_short, world_to_egui, BuildingIcons}};
+
+pub mod util;
+pub mod minimap;
+pub mod event_log;
+pub mod event_history;
+pub mod objective;
+pub mod achievements;
+pub mod help;
+pub mod build_palette;
 
 
 const GOLD_COLOR: Color32 = Color32::from_rgb(255, 215, 0);
@@ -15,7 +26,15 @@ const LIVES_COLOR: Color32 = Color32::from_rgb(155, 16, 3);
 struct Images {
     rock_icon: Handle<Image>,
     coin_icon: Handle<Image>,
-    heart_icon: Handle<Image>
+    heart_icon: Handle<Image>,
+    orc_warrior_icon: Handle<Image>,
+    spider_icon: Handle<Image>,
+    golem_icon: Handle<Image>,
+    thief_icon: Handle<Image>,
+    phantom_icon: Handle<Image>,
+    speed_icon: Handle<Image>,
+    amount_icon: Handle<Image>,
+    critical_icon: Handle<Image>
 }
 
 impl FromWorld for Images {
@@ -25,21 +44,129 @@ impl FromWorld for Images {
             rock_icon: asset_server.load("icons/rock.png"),
             coin_icon: asset_server.load("icons/coin.png"),
             heart_icon: asset_server.load("icons/heart.png"),
+            orc_warrior_icon: asset_server.load("icons/orc_warrior.png"),
+            spider_icon: asset_server.load("icons/spider.png"),
+            golem_icon: asset_server.load("icons/golem.png"),
+            thief_icon: asset_server.load("icons/thief.png"),
+            phantom_icon: asset_server.load("icons/phantom.png"),
+            speed_icon: asset_server.load("icons/speed.png"),
+            amount_icon: asset_server.load("icons/amount.png"),
+            critical_icon: asset_server.load("icons/critical.png"),
         }
     }
 }
 
 #[derive(Resource)]
 struct State {
-    pub show_defender_params: bool
+    pub show_defender_params: bool,
+    pub show_round_summary: bool,
+    pub show_round_history: bool,
+    pub confirm_clear_board: bool,
+    pub show_mutators: bool,
+    /* Part of the building cost the defender gets back when the board is cleared */
+    pub clear_board_refund: f32
 }
 
 impl Default for State {
     fn default() -> Self {
-        Self { show_defender_params: false }
+        Self { show_defender_params: false, show_round_summary: false, show_round_history: false, confirm_clear_board: false, show_mutators: false, clear_board_refund: 0.5 }
+    }
+}
+
+/* The setup window is open until the game is started, time stands still while it is */
+#[derive(Resource)]
+struct GameSetupState {
+    open: bool
+}
+
+impl Default for GameSetupState {
+    fn default() -> Self {
+        Self { open: true }
+    }
+}
+
+/* Nothing can be bought or built behind the setup window */
+fn setup_closed(setup: Res<GameSetupState>) -> bool {
+    return !setup.open;
+}
+
+#[derive(Resource, Default)]
+struct VictoryState {
+    has_paused: bool
+}
+
+#[derive(Resource, Default)]
+pub struct GridOverlayState {
+    pub visible: bool
+}
+
+/* Path nodes the queued units will walk through in range of a tower, drawn with the grid overlay */
+#[derive(Resource, Default)]
+pub struct InterceptionOverlay {
+    pub danger_nodes: Vec<Node>
+}
+
+/* Windows narrower than this get the compact side panel */
+const COMPACT_PANEL_WIDTH: f32 = 900.;
+const TOWER_ICON_SIZE: f32 = 24.;
+
+/* Compact swaps the text buttons of the side panel for icons, their tooltips still carry the full info */
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PanelMode {
+    #[default]
+    Full,
+    Compact
+}
+
+impl PanelMode {
+    pub fn for_width(width: f32) -> Self {
+        if width < COMPACT_PANEL_WIDTH {
+            return PanelMode::Compact;
+        }
+        return PanelMode::Full;
     }
 }
 
+#[derive(Default)]
+struct PanelIcons {
+    orc_warrior: egui::TextureId,
+    spider: egui::TextureId,
+    golem: egui::TextureId,
+    thief: egui::TextureId,
+    phantom: egui::TextureId,
+    health: egui::TextureId,
+    speed: egui::TextureId,
+    amount: egui::TextureId,
+    critical: egui::TextureId
+}
+
+impl PanelIcons {
+    fn get_attacker(&self, attacker_type: AttackerType) -> egui::TextureId {
+        return match attacker_type {
+            AttackerType::OrcWarrior => self.orc_warrior,
+            AttackerType::Spider => self.spider,
+            AttackerType::Golem => self.golem,
+            AttackerType::Thief => self.thief,
+            AttackerType::Phantom => self.phantom,
+        };
+    }
+
+    fn get_upgrade(&self, upgrade: UpgradeType) -> egui::TextureId {
+        return match upgrade {
+            UpgradeType::Health => self.health,
+            UpgradeType::Speed => self.speed,
+            UpgradeType::Amount => self.amount,
+            UpgradeType::Critical => self.critical,
+            // Only Golems can buy it
+            UpgradeType::Intercept => self.golem,
+        };
+    }
+}
+
+/* Tower picked with a left click, shown in the tower info window */
+#[derive(Resource, Default)]
+pub struct SelectedTower(pub Option<Entity>);
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
@@ -47,223 +174,1083 @@ impl Plugin for UiPlugin {
         app
             .init_resource::<Images>()
             .init_resource::<State>()
-            .add_system(top_panel)
-            .add_system(defender_params)
-            .add_system(side_unit_panel.after(top_panel))
-            .add_system(check_victory);
+            .init_resource::<GridOverlayState>()
+            .init_resource::<InterceptionOverlay>()
+            .init_resource::<SelectedTower>()
+            .init_resource::<PanelMode>()
+            .init_resource::<VictoryState>()
+            .init_resource::<GameSetupState>()
+            .add_plugin(MinimapPlugin)
+            .add_plugin(EventLogPlugin)
+            .add_plugin(EventHistoryPlugin)
+            .add_plugin(ObjectivePlugin)
+            .add_plugin(AchievementsUiPlugin)
+            .add_plugin(HelpPlugin)
+            .add_plugin(BuildPalettePlugin)
+            .add_system(select_tower.in_set(GameplaySet))
+            .add_system(tower_info.in_set(GameplaySet))
+            .add_system(update_interception_overlay.in_set(GameplaySet))
+            .add_system(draw_grid_overlay.in_set(GameplaySet).after(update_interception_overlay))
+            .add_system(draw_selected_range.in_set(GameplaySet))
+            .add_system(top_panel.in_set(GameplaySet))
+            .add_system(defender_params.in_set(GameplaySet))
+            .add_system(round_summary.in_set(GameplaySet))
+            .add_system(round_history.in_set(GameplaySet))
+            .add_system(sudden_death_banner.in_set(GameplaySet))
+            .add_system(prep_countdown.in_set(GameplaySet))
+            .add_system(clear_board_dialog.in_set(GameplaySet))
+            .add_system(mutators_window.in_set(GameplaySet))
+            .add_system(check_window_size)
+            .add_system(side_unit_panel.after(top_panel).after(check_window_size).in_set(GameplaySet).run_if(setup_closed))
+            .add_system(update_victory_pause.after(update_win_condition))
+            .add_system(game_setup.in_set(GameplaySet))
+            .add_system(toggle_pause.after(game_setup).after(update_victory_pause))
+            .add_system(check_victory.in_set(GameplaySet).after(update_win_condition));
     }
 }
 
-fn check_victory(
+#[allow(clippy::type_complexity)]
+fn select_tower(
     mut contexts: EguiContexts,
-    defender_resource: Res<ResourceStore>,
+    mut selected: ResMut<SelectedTower>,
+    mut activations: EventWriter<ActivateAbility>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    towers: Query<(Entity, &Transform), Or<(With<Defender>, With<BannerAura>)>>
+) {
+    let left = mouse.just_pressed(MouseButton::Left);
+    let right = mouse.just_pressed(MouseButton::Right);
+    if !(left || right) || contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position().and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) else {
+        return;
+    };
+    let half_slot = SLOT_SIZE as f32 / 2.;
+    let clicked = towers.iter().find(|(_, transform)| {
+        let offset = (transform.translation.truncate() - cursor).abs();
+        offset.x <= half_slot && offset.y <= half_slot
+    }).map(|(entity, _)| entity);
+    if left {
+        selected.0 = clicked;
+    }
+    if let (true, Some(tower)) = (right, clicked) {
+        activations.send(ActivateAbility { tower });
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn tower_info(
+    mut contexts: EguiContexts,
+    mut selected: ResMut<SelectedTower>,
+    mut activations: EventWriter<ActivateAbility>,
+    towers: Query<(&Structure, Option<&Defender>, Option<&SpecialAbility>, Option<&Buffed>)>,
+    buffed: Query<(&Structure, &Buffed, &Transform)>,
+    attackers: Res<AttackerStats>,
+    icons: BuildingIcons,
+    localization: Res<Localization>
+) {
+    let Some(tower) = selected.0 else {
+        return;
+    };
+    let Ok((structure, defender, ability, buff)) = towers.get(tower) else {
+        // Sold or destroyed since it was selected
+        selected.0 = None;
+        return;
+    };
+    let sheet = icons.sheet(&mut contexts);
+    let mut open = true;
+    egui::Window::new(localization.t("Tower")).id(egui::Id::new("tower_info")).open(&mut open).show(contexts.ctx_mut(), |window| {
+        window.horizontal(|row| {
+            if let Some(image) = sheet.as_ref().and_then(|sheet| sheet.image(structure.building_type, TOWER_ICON_SIZE)) {
+                row.add(image);
+            }
+            row.heading(icons.get_name(structure.building_type));
+        });
+        let Some(defender) = defender else {
+            // Banners have nothing to show but the towers they buff
+            window.label(localization.t("Buffing"));
+            let mut towers: Vec<(String, Vec2)> = buffed.iter()
+                .filter(|(_, buff, _)| buff.banners.contains(&tower))
+                .map(|(structure, _, transform)| (icons.get_name(structure.building_type).to_string(), transform.translation.truncate() / SLOT_SIZE as f32))
+                .collect();
+            towers.sort_by(|a, b| a.1.x.total_cmp(&b.1.x).then(a.1.y.total_cmp(&b.1.y)));
+            if towers.is_empty() {
+                window.label(RichText::new(localization.t("No towers in range")).weak());
+            }
+            for (name, slot) in towers {
+                window.label(format!("{} at ({:.0}, {:.0})", name, slot.x, slot.y));
+            }
+            return;
+        };
+        window.columns(2, |cols| {
+            cols[0].label(localization.t("Level"));
+            cols[1].label(defender.get_level().to_string());
+        });
+        window.columns(2, |cols| {
+            cols[0].label(localization.t("Kills"));
+            cols[1].label(defender.kill_count.to_string());
+        });
+        window.columns(2, |cols| {
+            cols[0].label(localization.t("Range"));
+            cols[1].label(format!("{:.0}", defender.attack_range));
+        });
+        window.columns(2, |cols| {
+            cols[0].label(localization.t("Damage type")).on_hover_text(localization.t("How well this does against each armor class is set in damage_matrix.json"));
+            cols[1].label(localization.t(defender.attack.get_damage_type().get_name()));
+        });
+        let immune: Vec<&str> = AttackerType::ALL
+            .into_iter()
+            .filter(|attacker_type| attackers.get_stats(*attacker_type).is_immune_to(defender.attack.get_damage_type()))
+            .map(|attacker_type| localization.t(attacker_type.get_name()))
+            .collect();
+        if !immune.is_empty() {
+            window.columns(2, |cols| {
+                cols[0].label(localization.t("No effect on")).on_hover_text(localization.t("These attackers are immune to the damage type"));
+                cols[1].label(immune.join(", "));
+            });
+        }
+        if defender.min_range > 0. {
+            window.columns(2, |cols| {
+                cols[0].label(localization.t("Min range")).on_hover_text(localization.t("Attackers closer than this can't be targeted"));
+                cols[1].label(format!("{:.0}", defender.min_range));
+            });
+        }
+        if let Some(buff) = buff {
+            window.columns(2, |cols| {
+                cols[0].label(localization.t("Banner bonus")).on_hover_text(localization.t_args("Attack speed and damage, at most +{}% each", &[&format!("{:.0}", MAX_BANNER_BONUS * 100.)]));
+                cols[1].label(format!("+{:.0}% / +{:.0}%", buff.attack_speed_bonus * 100., buff.damage_bonus * 100.));
+            });
+        }
+        if let Some(ability) = ability {
+            window.separator();
+            window.label(localization.t(ability.get_name())).on_hover_text(ability.get_description(&localization));
+            if defender.get_level() < ABILITY_UNLOCK_LEVEL {
+                window.label(localization.t_args("Unlocks at level {}", &[&ABILITY_UNLOCK_LEVEL]));
+            } else {
+                window.add(egui::ProgressBar::new(ability.cooldown.percent()).show_percentage());
+                if window.add_enabled(ability.charged, egui::Button::new(localization.t("USE ABILITY"))).on_hover_text(localization.t("Right-click the tower to use it too")).clicked() {
+                    activations.send(ActivateAbility { tower });
+                }
+            }
+        }
+    });
+    if !open {
+        selected.0 = None;
+    }
+}
+
+fn update_interception_overlay(
+    mut overlay: ResMut<InterceptionOverlay>,
+    mut field_modified: EventReader<FieldModified>,
+    round: Res<RoundResource>,
+    field: Res<TowerField>,
+    towers: Query<(&Defender, &Transform)>
+) {
+    let field_changed = !field_modified.is_empty();
+    field_modified.clear();
+    // Starting a round empties the pending queue, which clears the overlay
+    if !round.has_pending_units() {
+        if !overlay.danger_nodes.is_empty() {
+            overlay.danger_nodes.clear();
+        }
+        return;
+    }
+    if !field_changed && !round.is_changed() {
+        return;
+    }
+    overlay.danger_nodes = covered_nodes(&nodes_on_all_paths(&field), &field, towers.iter());
+}
+
+fn draw_grid_overlay(
+    mut contexts: EguiContexts,
+    grid_overlay: Res<GridOverlayState>,
+    interception: Res<InterceptionOverlay>,
+    field: Res<TowerField>,
+    auras: Query<(&SlowAura, &Transform)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    egui_settings: Res<EguiSettings>
+) {
+    if !grid_overlay.visible {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera_q.get_single() else {
+        return;
+    };
+    let scale_factor = egui_settings.scale_factor;
+    let to_screen = |x: f32, y: f32| world_to_egui(camera, camera_transform, Vec3::new(x, y, 0.), scale_factor);
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("grid_overlay")));
+
+    // Slot transforms are centered, so lines sit half a slot off
+    let size = SLOT_SIZE as f32;
+    let origin = field.field_transform - Vec2::splat(size / 2.);
+    let width = field.get_width() as f32 * size;
+    let height = field.get_height() as f32 * size;
+    let grid_stroke = Stroke::new(1., Color32::from_white_alpha(20));
+    for x in 0..=field.get_width() {
+        let x = origin.x + x as f32 * size;
+        if let (Some(from), Some(to)) = (to_screen(x, origin.y), to_screen(x, origin.y + height)) {
+            painter.line_segment([from, to], grid_stroke);
+        }
+    }
+    for y in 0..=field.get_height() {
+        let y = origin.y + y as f32 * size;
+        if let (Some(from), Some(to)) = (to_screen(origin.x, y), to_screen(origin.x + width, y)) {
+            painter.line_segment([from, to], grid_stroke);
+        }
+    }
+
+    for (aura, transform) in auras.iter() {
+        let center = transform.translation.truncate();
+        if let (Some(screen_center), Some(edge)) = (to_screen(center.x, center.y), to_screen(center.x + aura.radius, center.y)) {
+            painter.circle_stroke(screen_center, screen_center.distance(edge), Stroke::new(1., Color32::from_rgba_unmultiplied(90, 160, 255, 60)));
+        }
+    }
+
+    for node in interception.danger_nodes.iter() {
+        let min = origin + Vec2::new(node.x as f32, node.y as f32) * size;
+        if let (Some(bottom_left), Some(top_right)) = (to_screen(min.x, min.y), to_screen(min.x + size, min.y + size)) {
+            painter.rect_filled(egui::Rect::from_two_pos(bottom_left, top_right), 0., Color32::from_rgba_unmultiplied(255, 220, 0, 40));
+        }
+    }
+}
+
+/* Range of the selected tower as a ring, the hole in the middle is its dead zone */
+fn draw_selected_range(
+    mut contexts: EguiContexts,
+    selected: Res<SelectedTower>,
+    towers: Query<(&Defender, &Transform)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    egui_settings: Res<EguiSettings>
+) {
+    let Some((defender, transform)) = selected.0.and_then(|tower| towers.get(tower).ok()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else {
+        return;
+    };
+    let scale_factor = egui_settings.scale_factor;
+    let center = transform.translation;
+    let (Some(screen_center), Some(outer_edge), Some(inner_edge)) = (
+        world_to_egui(camera, camera_transform, center, scale_factor),
+        world_to_egui(camera, camera_transform, center + Vec3::X * defender.attack_range, scale_factor),
+        world_to_egui(camera, camera_transform, center + Vec3::X * defender.min_range, scale_factor)
+    ) else {
+        return;
+    };
+    let outer = screen_center.distance(outer_edge);
+    let inner = screen_center.distance(inner_edge);
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("selected_range")));
+    if let Some((facing, half_width)) = defender.arc {
+        // Outline of the covered sector, screen y points down so angles are mirrored
+        let point = |radius: f32, angle: f32| screen_center + egui::vec2(angle.cos(), -angle.sin()) * radius;
+        let steps = 24;
+        let angle = |i: usize| facing - half_width + 2. * half_width * i as f32 / steps as f32;
+        let mut outline: Vec<egui::Pos2> = (0..=steps).map(|i| point(outer, angle(i))).collect();
+        outline.extend((0..=steps).rev().map(|i| point(inner, angle(i))));
+        outline.push(outline[0]);
+        painter.add(egui::Shape::line(outline, Stroke::new(1., Color32::from_white_alpha(90))));
+        return;
+    }
+    // A stroke as wide as the ring fills it without covering the dead zone
+    painter.circle_stroke(screen_center, (outer + inner) / 2., Stroke::new(outer - inner, Color32::from_rgba_unmultiplied(255, 255, 255, 20)));
+    painter.circle_stroke(screen_center, outer, Stroke::new(1., Color32::from_white_alpha(90)));
+    if defender.min_range > 0. {
+        painter.circle_stroke(screen_center, inner, Stroke::new(1., Color32::from_rgba_unmultiplied(255, 90, 60, 120)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn game_setup(
+    mut contexts: EguiContexts,
+    mut setup: ResMut<GameSetupState>,
+    mut config: ResMut<GameConfig>,
+    mut attacker_resource: ResMut<AttackerResource>,
+    mut defender_resource: ResMut<ResourceStore>,
+    mut mode: ResMut<GameMode>,
+    mut strategy: ResMut<ActiveStrategy>,
+    mut profile: ResMut<PlayerProfile>,
     mut time: ResMut<Time>,
-    mut app_exit_events: ResMut<Events<bevy::app::AppExit>>
+    localization: Res<Localization>
 ) {
-    if defender_resource.lives <= 0 {
-        egui::Window::new("Victory").title_bar(false).show(contexts.ctx_mut(), |ui| {
-            ui.label("You Won!");
-            if ui.button("Exit").clicked() {
-                app_exit_events.send(bevy::app::AppExit);
+    if !setup.open {
+        return;
+    }
+    time.pause();
+    egui::Window::new(localization.t("Game Setup")).id(egui::Id::new("game_setup")).collapsible(false).resizable(false).anchor(egui::Align2::CENTER_CENTER, [0., 0.]).show(contexts.ctx_mut(), |window| {
+        window.horizontal(|row| {
+            row.label(localization.t("Mode"));
+            for option in [GameMode::Classic, GameMode::Endless] {
+                row.selectable_value(&mut *mode, option, localization.t(option.get_name()));
+            }
+        }).response.on_hover_text(localization.t("Endless: the defender never runs out of lives and gets stronger every round, leaks in later rounds score more and the game ends once you can't afford a unit"));
+        window.add(egui::Slider::new(&mut config.attacker_starting_gold, 50..=500).text(localization.t("Attacker gold")));
+        window.add(egui::Slider::new(&mut config.defender_starting_gold, 50..=500).text(localization.t("Defender gold")));
+        window.add_enabled(*mode == GameMode::Classic, egui::Slider::new(&mut config.defender_starting_lives, 1..=200).text(localization.t("Defender lives")));
+        window.horizontal(|row| {
+            row.label(localization.t("Defender AI"));
+            strategy_picker(row, "defender_strategy", &mut strategy, &localization);
+        }).response.on_hover_text(localization.t("Default weighs walls against towers as the attackers come, Aggressive only builds towers, Turtle walls first and Mazer walls off the field into a long serpentine"));
+        if window.button(localization.t("Start Game")).clicked() {
+            attacker_resource.gold = config.attacker_starting_gold;
+            defender_resource.gold = config.defender_starting_gold;
+            defender_resource.lives = config.defender_starting_lives;
+            time.unpause();
+            setup.open = false;
+        }
+        window.separator();
+        egui::CollapsingHeader::new(localization.t("Profile")).id_source("profile").show(window, |section| {
+            section.label(localization.t_args("Matches won: {}/{} ({}%)", &[&profile.matches_won, &profile.matches_played, &format!("{:.0}", profile.get_win_rate() * 100.)]));
+            section.label(match profile.fastest_victory_rounds {
+                Some(rounds) => localization.t_args("Fastest victory: {} rounds", &[&rounds]),
+                None => localization.t("Fastest victory: -").to_string()
+            });
+            section.label(localization.t_args("Gold earned: {}", &[&profile.gold_earned]));
+            if let Some(upgrade) = profile.get_favorite_upgrade() {
+                section.label(localization.t_args("Favorite upgrade: {}", &[&localization.t(upgrade.get_name())]));
+            }
+            for (attacker_type, count) in &profile.units_sent {
+                section.label(localization.t_args("{} sent: {}", &[&localization.t(attacker_type.get_name()), &count]));
+            }
+            if section.button(localization.t("Reset Profile")).on_hover_text(localization.t("Forget every match played so far")).clicked() {
+                *profile = PlayerProfile::default();
             }
         });
+    });
+}
+
+/* P pauses and resumes, the setup window and the end of the game keep control of the pause while they are up */
+/* Combo box over every defender strategy, the pick takes over from the next action on */
+fn strategy_picker(ui: &mut Ui, id: &str, strategy: &mut ActiveStrategy, localization: &Localization) {
+    egui::ComboBox::from_id_source(id).selected_text(localization.t(strategy.0.get_name())).show_ui(ui, |combo| {
+        for option in get_strategies() {
+            let selected = option.get_name() == strategy.0.get_name();
+            if combo.selectable_label(selected, localization.t(option.get_name())).clicked() && !selected {
+                *strategy = ActiveStrategy(option);
+            }
+        }
+    });
+}
+
+fn toggle_pause(input: Res<Input<KeyCode>>, setup: Res<GameSetupState>, victory: Res<VictoryState>, mut time: ResMut<Time>) {
+    if !input.just_pressed(KeyCode::P) || setup.open || victory.has_paused {
+        return;
+    }
+    if time.is_paused() {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+/* Pauses time once when the game is over, by reaching the objective or by going bankrupt in endless, and unpauses it when that is undone, e.g. after a restart */
+fn update_victory_pause(
+    objective: Res<WinConditionDisplay>,
+    endless_score: Res<EndlessScore>,
+    mut time: ResMut<Time>,
+    mut victory: ResMut<VictoryState>,
+    mut game_over: EventWriter<GameOverEvent>
+) {
+    let won = objective.is_won() || endless_score.bankrupt;
+    if won && !victory.has_paused {
         time.pause();
+        victory.has_paused = true;
+        game_over.send(GameOverEvent { attacker_won: objective.is_won() });
+    } else if !won && victory.has_paused {
+        time.unpause();
+        victory.has_paused = false;
     }
 }
 
+fn check_victory(
+    mut contexts: EguiContexts,
+    objective: Res<WinConditionDisplay>,
+    endless_score: Res<EndlessScore>,
+    mut app_exit_events: ResMut<Events<bevy::app::AppExit>>,
+    localization: Res<Localization>
+) {
+    if objective.is_won() {
+        egui::Window::new(localization.t("Victory")).id(egui::Id::new("victory")).title_bar(false).show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.t("You Won!"));
+            if ui.button(localization.t("Exit")).clicked() {
+                app_exit_events.send(bevy::app::AppExit);
+            }
+        });
+    } else if endless_score.bankrupt {
+        egui::Window::new(localization.t("Bankrupt")).id(egui::Id::new("bankrupt")).title_bar(false).show(contexts.ctx_mut(), |ui| {
+            ui.label(localization.t("Out of gold, the endless run is over"));
+            ui.label(localization.t_args("Score: {}", &[&endless_score.score]));
+            if endless_score.score >= endless_score.best && endless_score.score > 0 {
+                ui.colored_label(GOLD_COLOR, localization.t("New best score!"));
+            } else {
+                ui.label(localization.t_args("Best: {}", &[&endless_score.best]));
+            }
+            if ui.button(localization.t("Exit")).clicked() {
+                app_exit_events.send(bevy::app::AppExit);
+            }
+        });
+    }
+}
+
+/* Everything the ":)" menu toggles, bundled to stay below Bevy's system parameter limit */
+#[derive(SystemParam)]
+struct MenuSettings<'w> {
+    smoothing: ResMut<'w, PathSmoothing>,
+    flow_field: ResMut<'w, FlowField>,
+    grid_overlay: ResMut<'w, GridOverlayState>,
+    particle_settings: ResMut<'w, ParticleSettings>,
+    camera_shake: ResMut<'w, CameraShake>,
+    gold_pickup: ResMut<'w, GoldPickup>,
+    attacker_control: ResMut<'w, AttackerControl>,
+    defender_control: ResMut<'w, DefenderControl>,
+    event_log: ResMut<'w, EventLog>,
+    achievements: ResMut<'w, AchievementsWindow>,
+    help: ResMut<'w, HelpOverlay>,
+    localization: ResMut<'w, Localization>,
+    interpolation: ResMut<'w, InterpolationSettings>,
+}
+
+/* Score shown in the top panel in endless mode */
+#[derive(SystemParam)]
+struct EndlessDisplay<'w> {
+    mode: Res<'w, GameMode>,
+    score: Res<'w, EndlessScore>,
+}
+
+/* Starting a round and its countdown */
+#[derive(SystemParam)]
+struct RoundControls<'w> {
+    start_round: EventWriter<'w, RequestRoundStart>,
+    prep: ResMut<'w, PrepTimer>,
+    prediction: Res<'w, WavePrediction>,
+}
+
+/* Green while the towers should hold, yellow when it gets close, red once the wave outmatches them */
+fn difficulty_color(difficulty: f32) -> Color32 {
+    return if difficulty < 0.7 {
+        Color32::from_rgb(60, 170, 60)
+    } else if difficulty <= 1. {
+        Color32::from_rgb(220, 180, 30)
+    } else {
+        Color32::from_rgb(200, 40, 30)
+    };
+}
+
 fn top_panel(
     mut contexts: EguiContexts,
     attacker_resource: Res<AttackerResource>,
     defender_resource: Res<ResourceStore>,
     attackers: Res<AttackerStats>,
-    round: Res<RoundResource>,
-    mut start_round: EventWriter<RequestRoundStart>,
+    mut round: ResMut<RoundResource>,
+    mut round_controls: RoundControls,
     mut coin_icon: Local<egui::TextureId>,
     mut heart_icon: Local<egui::TextureId>,
     mut is_initialized: Local<bool>,
     mut state: ResMut<State>,
     mut timing: ResMut<Time>,
-    images: Res<Images>
+    round_stats: Res<RoundStats>,
+    mut settings: MenuSettings,
+    images: Res<Images>,
+    endless: EndlessDisplay,
+    field: Res<TowerField>
 ) {
     if !*is_initialized {
         *is_initialized = true;
         *coin_icon = contexts.add_image(images.coin_icon.clone_weak());
         *heart_icon = contexts.add_image(images.heart_icon.clone_weak());
     }
+    // Switched once the panel is drawn, its texts borrow the current language until then
+    let mut switch_language = None;
+    let localization = &*settings.localization;
     egui::TopBottomPanel::top("top_resource_panel").show(contexts.ctx_mut(), |ui| {
         ui.horizontal(|bar| {
-            if bar.button("Start Round").clicked() {
-                start_round.send(RequestRoundStart);
+            let human = *settings.attacker_control == AttackerControl::Human;
+            if round_controls.prep.is_running() {
+                bar.add(egui::ProgressBar::new(round_controls.prep.get_progress()).desired_width(90.).text(localization.t("Preparing")));
+                if human && bar.button(localization.t("Skip")).on_hover_text(localization.t("Start the round without waiting for the countdown")).clicked() {
+                    round_controls.prep.skip();
+                }
+            } else if bar.add_enabled(human, egui::Button::new(localization.t("Start Round"))).clicked() {
+                round_controls.start_round.send(RequestRoundStart);
             }
+            if !round.is_round_active() {
+                let difficulty = round_controls.prediction.difficulty;
+                bar.add(egui::ProgressBar::new(difficulty.min(1.)).desired_width(110.).fill(difficulty_color(difficulty)).text(localization.t("Wave Difficulty")))
+                    .on_hover_text(localization.t_args("Queued units against the estimated damage of the towers: {}%", &[&((difficulty * 100.).round() as i32)]));
+            }
+            bar.add_enabled_ui(human, |bar| {
+                let mut cadence = round.get_cadence();
+                egui::ComboBox::from_id_source("spawn_cadence").width(70.).selected_text(localization.t(cadence.get_name())).show_ui(bar, |combo| {
+                    for option in SpawnCadence::ALL {
+                        combo.selectable_value(&mut cadence, option, localization.t(option.get_name()));
+                    }
+                }).response.on_hover_text(localization.t("How the units of the next round leave the start: one at a time, in pairs or in bursts of 4 with longer gaps"));
+                if cadence != round.get_cadence() {
+                    round.set_cadence(cadence);
+                }
+                if field.get_starts().len() > 1 {
+                    let mut policy = round.get_spawn_policy();
+                    egui::ComboBox::from_id_source("spawn_policy").width(100.).selected_text(localization.t(policy.get_name())).show_ui(bar, |combo| {
+                        for option in SpawnPolicy::ALL {
+                            combo.selectable_value(&mut policy, option, localization.t(option.get_name()));
+                        }
+                    }).response.on_hover_text(localization.t("Which start each unit enters from: taking turns, at random or where the fewest towers wait"));
+                    if policy != round.get_spawn_policy() {
+                        round.set_spawn_policy(policy);
+                    }
+                }
+                if bar.button(localization.t("Mutators")).on_hover_text(localization.t("Special rules for the next round")).clicked() {
+                    state.show_mutators = !state.show_mutators;
+                }
+            });
             bar.separator();
 
             bar.add(egui::widgets::Image::new(*coin_icon, [22., 22.]).tint(GOLD_COLOR));
             bar.colored_label(GOLD_COLOR, attacker_resource.gold.to_string()).on_hover_ui_at_pointer(|tooltip| {
-                tooltip.heading("Gold");
-                tooltip.label("Shows current amount of gold");
+                tooltip.heading(localization.t("Gold"));
+                tooltip.label(localization.t("Shows current amount of gold"));
             });
             bar.colored_label(GOLD_COLOR, format!(" + {}", attacker_resource.current_bounty)).on_hover_ui_at_pointer(|tooltip| {
-                tooltip.heading("Bounty");
-                tooltip.label("Shows current accumulated bounty that will be rewarded at the end of the round");
-                tooltip.label("Can be be increased by: ");
+                tooltip.heading(localization.t("Bounty"));
+                tooltip.label(localization.t("Shows current accumulated bounty that will be rewarded at the end of the round"));
+                tooltip.label(localization.t("Can be be increased by: "));
                 tooltip.indent(tooltip.id(), |indent| {
-                    indent.label("• Reaching the end");
-                    indent.label("• Having attackers die");
+                    indent.label(localization.t("• Reaching the end"));
+                    indent.label(localization.t("• Having attackers die"));
                 });
             });
             bar.spacing();
             bar.add(egui::widgets::Image::new(*heart_icon, [16., 16.]).tint(LIVES_COLOR));
-            bar.colored_label(LIVES_COLOR, defender_resource.lives.to_string()).on_hover_ui_at_pointer(|tooltip| {
-                tooltip.heading("Lives");
-                tooltip.label("Shows current defender lives. When this reaches 0 you win!");
-            });
+            if *endless.mode == GameMode::Endless {
+                bar.colored_label(LIVES_COLOR, "∞").on_hover_ui_at_pointer(|tooltip| {
+                    tooltip.heading(localization.t("Lives"));
+                    tooltip.label(localization.t("The defender never runs out of lives in endless mode"));
+                });
+                bar.separator();
+                bar.label(localization.t_args("Score: {}", &[&endless.score.score])).on_hover_ui_at_pointer(|tooltip| {
+                    tooltip.heading(localization.t("Score"));
+                    tooltip.label(localization.t("Every leak scores the number of the round it happened in"));
+                    tooltip.label(localization.t_args("Best: {}", &[&endless.score.best]));
+                });
+            } else {
+                bar.colored_label(LIVES_COLOR, defender_resource.lives.to_string()).on_hover_ui_at_pointer(|tooltip| {
+                    tooltip.heading(localization.t("Lives"));
+                    tooltip.label(localization.t("Shows current defender lives. When this reaches 0 you win!"));
+                });
+            }
 
             bar.separator();
             let current_speed = timing.relative_speed();
-            if bar.small_button("-").on_hover_text("Decrease game speed by 20%").clicked() {
+            if bar.small_button("-").on_hover_text(localization.t("Decrease game speed by 20%")).clicked() {
                 let new_speed = (current_speed - 0.2).clamp(0.4, 4.);
                 timing.set_relative_speed(new_speed);
             }
             bar.label(format!("{:.2}", current_speed));
-            if bar.small_button("+").on_hover_text("Increase game speed by 20%").clicked() {
+            if bar.small_button("+").on_hover_text(localization.t("Increase game speed by 20%")).clicked() {
                 let new_speed = (current_speed + 0.2).clamp(0.4, 4.);
                 timing.set_relative_speed(new_speed);
             }
+            bar.separator();
+            bar.label(format_duration_short(round_stats.round_duration)).on_hover_text(localization.t("Duration of the current round"));
 
 
             bar.with_layout(egui::Layout::right_to_left(egui::Align::Center), |bar| {
                 bar.menu_button(":)", |menu| {
-                    if menu.button("Defender Parameters").on_hover_text_at_pointer("Debug parameters for the defender AI").clicked() {
+                    if menu.button(localization.t("Defender Parameters")).on_hover_text_at_pointer(localization.t("Debug parameters for the defender AI")).clicked() {
                         state.show_defender_params = true;
                         menu.close_menu();
                     }
+                    if menu.button(localization.t("History")).on_hover_text_at_pointer(localization.t("Statistics of every round played so far")).clicked() {
+                        state.show_round_history = true;
+                        menu.close_menu();
+                    }
+                    if menu.button(localization.t("Event Log")).on_hover_text_at_pointer(localization.t("Kills, leaks, buildings and rounds as they happened")).clicked() {
+                        settings.event_log.open = true;
+                        menu.close_menu();
+                    }
+                    if menu.button(localization.t("Achievements")).on_hover_text_at_pointer(localization.t("Milestones unlocked over every match played")).clicked() {
+                        settings.achievements.open = true;
+                        menu.close_menu();
+                    }
+                    if menu.button(localization.t("Help")).on_hover_text_at_pointer(localization.t("Controls and the basics of the game")).clicked() {
+                        settings.help.open = true;
+                        menu.close_menu();
+                    }
+                    if menu.button(localization.t("Clear Board")).on_hover_text_at_pointer(localization.t("Sell every tower and wall at once to try another layout")).clicked() {
+                        state.confirm_clear_board = true;
+                        menu.close_menu();
+                    }
+                    menu.checkbox(&mut settings.grid_overlay.visible, localization.t("Grid overlay")).on_hover_text_at_pointer(localization.t("Show the tower grid, aura ranges and where queued units will be under fire"));
+                    menu.checkbox(&mut settings.particle_settings.show_footsteps, localization.t("Footsteps")).on_hover_text_at_pointer(localization.t("Leave dust behind walking attackers"));
+                    menu.checkbox(&mut settings.particle_settings.show_damage_numbers, localization.t("Damage numbers")).on_hover_text_at_pointer(localization.t("Show the damage of every hit above the target, rapid hits add up"));
+                    menu.horizontal(|row| {
+                        row.label(localization.t("Particles"));
+                        for quality in [ParticleQuality::Low, ParticleQuality::Medium, ParticleQuality::High] {
+                            if row.selectable_label(settings.particle_settings.quality == quality, localization.t(quality.get_name())).clicked() {
+                                settings.particle_settings.apply_quality(quality);
+                            }
+                        }
+                    }).response.on_hover_text_at_pointer(localization.t("Fewer particles help on slower machines during big waves"));
+                    menu.checkbox(&mut settings.camera_shake.enabled, localization.t("Screen shake")).on_hover_text_at_pointer(localization.t("Shake the camera on explosions and when attackers reach the end"));
+                    menu.add_enabled(settings.camera_shake.enabled, egui::Slider::new(&mut settings.camera_shake.intensity, 0.1..=2.).text(localization.t("Shake intensity")));
+                    menu.checkbox(&mut settings.gold_pickup.instant, localization.t("Instant refunds")).on_hover_text_at_pointer(localization.t("Credit refunds for killed attackers right away instead of dropping coins to collect with the camera"));
+                    // Switching sides mid game would hand over gold that was saved up for the other plan
+                    menu.add_enabled_ui(round.get_round_number() == 0, |menu| {
+                        let mut computer = *settings.attacker_control == AttackerControl::Computer;
+                        if menu.checkbox(&mut computer, localization.t("Computer attacker")).on_hover_text_at_pointer(localization.t("Let the computer send the waves and only watch the defense, can only be changed before the first round")).changed() {
+                            *settings.attacker_control = if computer { AttackerControl::Computer } else { AttackerControl::Human };
+                        }
+                        let mut computer = *settings.defender_control == DefenderControl::Computer;
+                        if menu.checkbox(&mut computer, localization.t("Computer defender")).on_hover_text_at_pointer(localization.t("Let the computer build and sell towers, turn off to defend yourself, can only be changed before the first round")).changed() {
+                            *settings.defender_control = if computer { DefenderControl::Computer } else { DefenderControl::Human };
+                        }
+                    });
+                    menu.checkbox(&mut settings.interpolation.enabled, localization.t("Smooth movement")).on_hover_text_at_pointer(localization.t("Blend attacker and projectile positions between simulation steps, turn off to see the raw steps"));
+                    menu.checkbox(&mut settings.smoothing.enabled, localization.t("Smooth paths")).on_hover_text_at_pointer(localization.t("Let attackers cut corners where nothing blocks them"));
+                    menu.checkbox(&mut settings.flow_field.enabled, localization.t("Flow field")).on_hover_text_at_pointer(localization.t("Attackers entering the field follow one shared map of the way to the end instead of searching their own path"));
+                    menu.separator();
+                    menu.horizontal(|row| {
+                        row.label(localization.t("Language"));
+                        for (code, name) in LANGUAGES {
+                            if row.selectable_label(localization.get_language() == code, name).clicked() && localization.get_language() != code {
+                                switch_language = Some(code);
+                            }
+                        }
+                    });
                 });
             });
         });
     });
+    if let Some(code) = switch_language {
+        match Localization::load(code) {
+            Ok(loaded) => *settings.localization = loaded,
+            Err(err) => warn!("{}", err)
+        }
+    }
 }
 
+fn check_window_size(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut mode: ResMut<PanelMode>
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let wanted = PanelMode::for_width(window.width());
+    // Only write on changes to keep change detection meaningful
+    if *mode != wanted {
+        *mode = wanted;
+    }
+}
+
+/* Text button in the full panel, bare icon in the compact one.
+   Dimmed and unclickable while there is a disabled_reason, the tooltip shows either way and ends with the reason */
+fn panel_button(ui: &mut Ui, mode: PanelMode, text: &str, icon: egui::TextureId, size: f32, disabled_reason: Option<String>, tooltip: impl Fn(&mut Ui)) -> egui::Response {
+    let enabled = disabled_reason.is_none();
+    let response = match mode {
+        PanelMode::Full => ui.add_enabled(enabled, egui::Button::new(text)),
+        PanelMode::Compact => ui.add_enabled(enabled, egui::ImageButton::new(icon, [size, size])),
+    };
+    return response.on_hover_ui(&tooltip).on_disabled_hover_ui(|hover| {
+        tooltip(hover);
+        if let Some(reason) = disabled_reason {
+            hover.label(reason);
+        }
+    });
+}
+
+/* Why a panel button costing `cost` is disabled, None while it can be afforded */
+fn missing_gold(gold: i32, cost: i32, localization: &Localization) -> Option<String> {
+    return (!can_afford(gold, cost)).then(|| localization.t_args("Needs {} more gold", &[&(cost - gold)]));
+}
+
+#[allow(clippy::too_many_arguments)]
 fn side_unit_panel(
     mut contexts: EguiContexts,
     mut attacker_resource: ResMut<AttackerResource>,
     mut round: ResMut<RoundResource>,
-    mut attackers: ResMut<AttackerStats>
+    mut attackers: ResMut<AttackerStats>,
+    mode: Res<PanelMode>,
+    suggestion: Res<AttackerSuggestion>,
+    intel: Res<DefenderIntel>,
+    mut scheduled: ResMut<ScheduledSpawns>,
+    mut schedule_round: Local<u32>,
+    images: Res<Images>,
+    mut icons: Local<Option<PanelIcons>>,
+    control: Res<AttackerControl>,
+    localization: Res<Localization>
 ) {
+    if icons.is_none() {
+        *icons = Some(PanelIcons {
+            orc_warrior: contexts.add_image(images.orc_warrior_icon.clone_weak()),
+            spider: contexts.add_image(images.spider_icon.clone_weak()),
+            golem: contexts.add_image(images.golem_icon.clone_weak()),
+            thief: contexts.add_image(images.thief_icon.clone_weak()),
+            phantom: contexts.add_image(images.phantom_icon.clone_weak()),
+            health: contexts.add_image(images.heart_icon.clone_weak()),
+            speed: contexts.add_image(images.speed_icon.clone_weak()),
+            amount: contexts.add_image(images.amount_icon.clone_weak()),
+            critical: contexts.add_image(images.critical_icon.clone_weak()),
+        });
+    }
+    let icons = icons.as_ref().unwrap();
+    let mode = *mode;
     egui::SidePanel::right("side_panel").show(contexts.ctx_mut(), |ui| {
-        let orc_warrior_cost = attackers.get_cost(AttackerType::OrcWarrior);
-        let spider_cost = attackers.get_cost(AttackerType::Spider);
-        let golem_cost = attackers.get_cost(AttackerType::Golem);
-        if ui.button("Orc Warrior")
-            .on_hover_ui(attacker_tooltip(AttackerType::OrcWarrior, &attackers))
-            .clicked() && orc_warrior_cost <= attacker_resource.gold {
-            attacker_resource.gold -= orc_warrior_cost;
-            round.queue(&AttackerType::OrcWarrior);
+        if *control == AttackerControl::Computer {
+            ui.label(RichText::new(localization.t("The computer is attacking")).strong());
+            ui.set_enabled(false);
         }
-        if ui.button("Spider")
-            .on_hover_ui(attacker_tooltip(AttackerType::Spider, &attackers))
-            .clicked() && spider_cost <= attacker_resource.gold {
-            attacker_resource.gold -= spider_cost;
-            round.queue(&AttackerType::Spider);
+        for attacker_type in AttackerType::ALL {
+            let cost = attackers.get_cost(attacker_type);
+            let disabled_reason = missing_gold(attacker_resource.gold, cost, &localization);
+            if panel_button(ui, mode, localization.t(attacker_type.get_name()), icons.get_attacker(attacker_type), 32., disabled_reason, attacker_tooltip(attacker_type, &attackers, &localization)).clicked() {
+                attacker_resource.spend(cost);
+                round.queue(&attacker_type);
+            }
         }
-        if ui.button("Golem")
-        .on_hover_ui(attacker_tooltip(AttackerType::Golem, &attackers))
-        .clicked() && golem_cost <= attacker_resource.gold {
-            attacker_resource.gold -= golem_cost;
-            round.queue(&AttackerType::Golem);
+
+        if !suggestion.units.is_empty() {
+            ui.separator();
+            let units: Vec<String> = suggestion.units.iter().map(|(attacker_type, amount)| format!("{}x {}", amount, localization.t(attacker_type.get_name()))).collect();
+            ui.label(localization.t_args("Suggested: {}", &[&units.join(", ")]));
+            if ui.button(localization.t("Use Suggestion")).on_hover_text(localization.t("Queue the suggested wave, based on the current path and defenses")).clicked() {
+                for (attacker_type, amount) in &suggestion.units {
+                    let cost = attackers.get_cost(*attacker_type);
+                    for _ in 0..*amount {
+                        if !can_afford(attacker_resource.gold, cost) {
+                            break;
+                        }
+                        attacker_resource.spend(cost);
+                        round.queue(attacker_type);
+                    }
+                }
+            }
         }
 
         ui.separator();
-        ui.label("Upgrade Orc Warrior");
-        ui.horizontal(|group| {
-            let health_cost = attackers.get_upgrade_cost(AttackerType::OrcWarrior, UpgradeType::Health);
-            let speed_cost = attackers.get_upgrade_cost(AttackerType::OrcWarrior, UpgradeType::Speed);
-            let amount_cost = attackers.get_upgrade_cost(AttackerType::OrcWarrior, UpgradeType::Amount);
-            let current_cold = attacker_resource.gold;
-            if group.button("Health").on_hover_text(format!("Boost health by 10%. Cost: {}", health_cost)).clicked() && current_cold >= health_cost {
-                attackers.apply_upgrade(AttackerType::OrcWarrior, UpgradeType::Health);
-                attacker_resource.gold -= health_cost;
-            }
-            if group.button("Speed").on_hover_text(format!("Boost speed by 20%. Cost: {}", speed_cost)).clicked() && current_cold >= speed_cost {
-                attackers.apply_upgrade(AttackerType::OrcWarrior, UpgradeType::Speed);
-                attacker_resource.gold -= speed_cost;
-            }
-            if group.button("Amount").on_hover_text(format!("Increase amount summoned by one. Cost: {}", amount_cost)).clicked() && current_cold >= amount_cost {
-                attackers.apply_upgrade(AttackerType::OrcWarrior, UpgradeType::Amount);
-                attacker_resource.gold -= amount_cost;
+        egui::CollapsingHeader::new(localization.t("Intel")).id_source("intel").show(ui, |section| {
+            section.label(localization.t_args("Path length: {}", &[&intel.path_length]));
+            section.label(localization.t_args("Walls: {}", &[&intel.walls]));
+            section.label(localization.t_args("Tower damage on the path: {}/s", &[&format!("{:.0}", intel.path_dps)]));
+            let pending = round.get_pending_units();
+            if !pending.is_empty() {
+                let survival = intel.estimate_survival(pending.iter().map(|attacker_type| attackers.get_stats(*attacker_type)));
+                section.label(localization.t_args("Estimated survival: {}%", &[&format!("{:.0}", survival * 100.)]))
+                    .on_hover_text(localization.t("Health of the queued units left after walking past every tower, a rough estimate"));
             }
         });
-        ui.separator();
-        ui.label("Upgrade Spider");
-        ui.horizontal(|group| {
-            let health_cost = attackers.get_upgrade_cost(AttackerType::Spider, UpgradeType::Health);
-            let speed_cost = attackers.get_upgrade_cost(AttackerType::Spider, UpgradeType::Speed);
-            let amount_cost = attackers.get_upgrade_cost(AttackerType::Spider, UpgradeType::Amount);
-            let current_cold = attacker_resource.gold;
-            if group.button("Health").on_hover_text(format!("Boost health by 20%. Cost: {}", health_cost)).clicked() && current_cold >= health_cost {
-                attackers.apply_upgrade(AttackerType::Spider, UpgradeType::Health);
-                attacker_resource.gold -= health_cost;
-            }
-            if group.button("Speed").on_hover_text(format!("Boost speed by 20%. Cost: {}", speed_cost)).clicked() && current_cold >= speed_cost {
-                attackers.apply_upgrade(AttackerType::Spider, UpgradeType::Speed);
-                attacker_resource.gold -= speed_cost;
+        egui::CollapsingHeader::new(localization.t("Schedule")).id_source("schedule").show(ui, |section| {
+            // Units queued above already go into the next round, scheduling targets the ones after it
+            let first_round = round.get_round_number() + 2;
+            *schedule_round = (*schedule_round).max(first_round);
+            section.horizontal(|row| {
+                row.label(localization.t("Round"));
+                row.add(egui::DragValue::new(&mut *schedule_round).clamp_range(first_round..=first_round + 98));
+            });
+            section.horizontal_wrapped(|row| {
+                for attacker_type in AttackerType::ALL {
+                    let cost = attackers.get_cost(attacker_type);
+                    if row.add_enabled(can_afford(attacker_resource.gold, cost), egui::Button::new(localization.t(attacker_type.get_name())).small())
+                        .on_hover_text(localization.t_args("Schedule for round {}. Cost: {}", &[&*schedule_round, &cost]))
+                        .on_disabled_hover_text(localization.t_args("Schedule for round {}. Cost: {}", &[&*schedule_round, &cost]))
+                        .clicked() {
+                        attacker_resource.spend(cost);
+                        scheduled.schedule(*schedule_round, attacker_type);
+                    }
+                }
+            });
+            if scheduled.by_round.is_empty() {
+                return;
             }
-            if group.button("Amount").on_hover_text(format!("Increase amount summoned by one. Cost: {}", amount_cost)).clicked() && current_cold >= amount_cost {
-                attackers.apply_upgrade(AttackerType::Spider, UpgradeType::Amount);
-                attacker_resource.gold -= amount_cost;
+            section.label(localization.t("Scheduled"));
+            for scheduled_round in scheduled.get_rounds() {
+                section.horizontal(|row| {
+                    let units = &scheduled.by_round[&scheduled_round];
+                    row.label(localization.t_args("Round {}: {} units", &[&scheduled_round, &units.len()]))
+                        .on_hover_text(units.iter().map(|unit| localization.t(unit.get_name())).collect::<Vec<&str>>().join(", "));
+                    if row.small_button(localization.t("Cancel")).on_hover_text(localization.t("Remove these units and refund their gold")).clicked() {
+                        let units = scheduled.by_round.remove(&scheduled_round).unwrap_or_default();
+                        let refund: i32 = units.iter().map(|unit| attackers.get_cost(*unit)).sum();
+                        attacker_resource.refund(refund);
+                    }
+                });
             }
         });
-        ui.separator();
-        ui.label("Upgrade Golem");
-        ui.horizontal(|group| {
-            let health_cost = attackers.get_upgrade_cost(AttackerType::Golem, UpgradeType::Health);
-            let speed_cost = attackers.get_upgrade_cost(AttackerType::Golem, UpgradeType::Speed);
-            let amount_cost = attackers.get_upgrade_cost(AttackerType::Golem, UpgradeType::Amount);
-            let current_cold = attacker_resource.gold;
-            if group.button("Health").on_hover_text(format!("Boost health by 10%. Cost: {}", health_cost)).clicked() && current_cold >= health_cost {
-                attackers.apply_upgrade(AttackerType::Golem, UpgradeType::Health);
-                attacker_resource.gold -= health_cost;
-            }
-            if group.button("Speed").on_hover_text(format!("Boost speed by 20%. Cost: {}", speed_cost)).clicked() && current_cold >= speed_cost {
-                attackers.apply_upgrade(AttackerType::Golem, UpgradeType::Speed);
-                attacker_resource.gold -= speed_cost;
-            }
-            if group.button("Amount").on_hover_text(format!("Increase amount summoned by one. Cost: {}", amount_cost)).clicked() && current_cold >= amount_cost {
-                attackers.apply_upgrade(AttackerType::Golem, UpgradeType::Amount);
-                attacker_resource.gold -= amount_cost;
-            }
-        })
 
+        for attacker_type in AttackerType::ALL {
+            ui.separator();
+            ui.label(localization.t_args("Upgrade {}", &[&localization.t(attacker_type.get_name())]));
+            ui.horizontal(|group| {
+                for upgrade in attackers.get_upgrades(attacker_type) {
+                    let cost = attackers.get_upgrade_cost(attacker_type, upgrade);
+                    let description = upgrade_description(attackers.get_upgrade(attacker_type, upgrade), upgrade, &localization);
+                    let hover = |tooltip: &mut Ui| {
+                        tooltip.label(description.as_str());
+                    };
+                    let disabled_reason = missing_gold(attacker_resource.gold, cost, &localization);
+                    if panel_button(group, mode, localization.t(upgrade.get_name()), icons.get_upgrade(upgrade), 16., disabled_reason, hover).clicked() {
+                        attackers.apply_upgrade(attacker_type, upgrade);
+                        attacker_resource.spend(cost);
+                    }
+                }
+            });
+        }
     });
 }
 
-fn attacker_tooltip<'a>(attacker_type: AttackerType, attackers: &'a AttackerStats) -> impl FnOnce(&mut Ui) -> () + 'a {
+/* Tooltip of an upgrade button, the numbers come from the upgrade itself since they differ between units */
+fn upgrade_description(info: &UpgradeInfo, upgrade: UpgradeType, localization: &Localization) -> String {
+    return match upgrade {
+        UpgradeType::Health => localization.t_args("Boost health by {}%. Cost: {}", &[&format!("{:.0}", (info.effect - 1.) * 100.), &info.cost]),
+        UpgradeType::Speed => localization.t_args("Boost speed by {}%. Cost: {}", &[&format!("{:.0}", (info.effect - 1.) * 100.), &info.cost]),
+        UpgradeType::Amount => localization.t_args("Increase amount summoned by one. Cost: {}", &[&info.cost]),
+        UpgradeType::Critical => localization.t_args("Increase critical hit chance by {}%. Cost: {}", &[&format!("{:.0}", info.effect * 100.), &info.cost]),
+        UpgradeType::Intercept => localization.t_args("Shoot down 1 projectile within {} pixels every {}s. Cost: {}", &[&GOLEM_INTERCEPT_RANGE, &GOLEM_INTERCEPT_COOLDOWN, &info.cost]),
+    };
+}
+
+fn attacker_tooltip<'a>(attacker_type: AttackerType, attackers: &'a AttackerStats, localization: &'a Localization) -> impl Fn(&mut Ui) + 'a {
     return move |tooltip| {
         let attacker = attackers.get_stats(attacker_type);
-        tooltip.heading(attacker_type.get_name());
+        tooltip.heading(localization.t(attacker_type.get_name()));
         tooltip.horizontal(|group| {
-            group.label("Spawn amount: ");
+            group.label(localization.t("Spawn amount: "));
             group.label(attacker.num_summoned.to_string());
         });
         tooltip.horizontal(|group| {
-            group.label("Cost: ");
+            group.label(localization.t("Cost: "));
             group.label(RichText::new(attacker.original_cost.to_string()).color(GOLD_COLOR));
         });
         tooltip.horizontal(|group| {
-            group.label("Defender bounty: ");
+            group.label(localization.t("Defender bounty: "));
             group.label(RichText::new(attacker.bounty.to_string()).color(GOLD_COLOR));
         });
         tooltip.horizontal(|group| {
-            group.label("Attacker bounty: ");
+            group.label(localization.t("Attacker bounty: "));
             group.label(RichText::new((attacker.original_cost / attacker.num_summoned).to_string()).color(GOLD_COLOR));
         });
         tooltip.horizontal(|group| {
-            group.label("Health: ");
+            group.label(localization.t("Health: "));
             group.label(RichText::new(attacker.max_health.to_string()));
         });
         tooltip.horizontal(|group| {
-            group.label("Speed: ");
-            group.label(format!("{} pixels/s", attacker.movement_speed));
+            group.label(localization.t("Armor: "));
+            group.label(localization.t(attacker.armor_class.get_name()));
+        });
+        if !attacker.immunities.is_empty() {
+            tooltip.horizontal(|group| {
+                group.label(localization.t("Immune to: "));
+                group.label(attacker.immunities.iter().map(|damage_type| localization.t(damage_type.get_name())).collect::<Vec<&str>>().join(", "));
+            });
+        }
+        if let LeakEffect::StealGold(amount) = attacker.leak_effect {
+            tooltip.horizontal(|group| {
+                group.label(localization.t("Steals on leak: "));
+                group.label(RichText::new(amount.to_string()).color(GOLD_COLOR));
+            });
+        }
+        if attacker.shield > 0. {
+            tooltip.horizontal(|group| {
+                group.label(localization.t("Shield: "));
+                group.label(localization.t_args("{}, taken before health", &[&attacker.shield]));
+            });
+        }
+        if attacker_type == AttackerType::Phantom {
+            tooltip.horizontal(|group| {
+                group.label(localization.t("Blinks: "));
+                group.label(localization.t_args("{} nodes every {}s", &[&PHANTOM_BLINK_DISTANCE, &PHANTOM_BLINK_SECONDS]));
+            });
+        }
+        if attacker.intercepts {
+            tooltip.horizontal(|group| {
+                group.label(localization.t("Intercepts: "));
+                group.label(localization.t_args("1 projectile within {} pixels every {}s", &[&GOLEM_INTERCEPT_RANGE, &GOLEM_INTERCEPT_COOLDOWN]));
+            });
+        }
+        tooltip.horizontal(|group| {
+            group.label(localization.t("Speed: "));
+            group.label(localization.t_args("{} pixels/s", &[&attacker.movement_speed]));
+        });
+        tooltip.horizontal(|group| {
+            group.label(localization.t("Dodge: "));
+            group.label(format!("{:.0}%", attacker.dodge_chance * 100.));
+        });
+        tooltip.horizontal(|group| {
+            group.label(localization.t("Critical: "));
+            group.label(localization.t_args("{}% for x{}", &[&format!("{:.0}", attacker.crit_chance * 100.), &attacker.crit_multiplier]));
+        });
+    }
+}
+
+fn sudden_death_banner(
+    mut contexts: EguiContexts,
+    sudden_death: Res<SuddenDeathState>,
+    time: Res<Time>,
+    localization: Res<Localization>
+) {
+    if !sudden_death.active {
+        return;
+    }
+    let pulse = 0.6 + 0.4 * (time.elapsed_seconds() * 4.).sin();
+    let color = Color32::from_rgba_unmultiplied(220, 30, 30, (pulse * 255.) as u8);
+    egui::Area::new("sudden_death").anchor(egui::Align2::CENTER_TOP, [0., 40.]).interactable(false).show(contexts.ctx_mut(), |area| {
+        area.label(RichText::new(localization.t("SUDDEN DEATH")).size(36. + pulse * 4.).strong().color(color)).on_hover_text(localization.t_args(
+            "Neither side made progress for a while: the defender loses a life every {} seconds and kills pay double bounty",
+            &[&SUDDEN_DEATH_DRAIN_SECONDS]
+        ));
+    });
+}
+
+fn prep_countdown(mut contexts: EguiContexts, prep: Res<PrepTimer>) {
+    if !prep.is_running() {
+        return;
+    }
+    egui::Area::new("prep_countdown").anchor(egui::Align2::CENTER_CENTER, [0., 0.]).interactable(false).show(contexts.ctx_mut(), |area| {
+        area.label(RichText::new(format!("{:.0}", prep.remaining.ceil())).size(64.).strong().color(GOLD_COLOR));
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn round_summary(
+    mut contexts: EguiContexts,
+    mut state: ResMut<State>,
+    mut round_end: EventReader<RoundOverEvent>,
+    mut round_start: EventReader<RoundStartEvent>,
+    round_stats: Res<RoundStats>,
+    round: Res<RoundResource>,
+    combo_bonus: Res<PersistentComboBonus>,
+    localization: Res<Localization>
+) {
+    if !round_end.is_empty() {
+        state.show_round_summary = true;
+        round_end.clear();
+    }
+    if !round_start.is_empty() {
+        state.show_round_summary = false;
+        round_start.clear();
+    }
+    if state.show_round_summary {
+        egui::Window::new(localization.t("Round Summary")).id(egui::Id::new("round_summary")).collapsible(false).show(contexts.ctx_mut(), |window| {
+            window.label(localization.t_args("Reached the end: {}", &[&round_stats.num_reached_end]));
+            window.colored_label(GOLD_COLOR, localization.t_args("Defender completion bonus: +{}", &[&round_stats.completion_bonus]))
+                .on_hover_text(localization.t("Paid to the defender for getting through the round, more for later rounds and for every life left"));
+            window.label(localization.t_args("Cadence: {}", &[&localization.t(round.get_active_cadence().get_name())]));
+            window.colored_label(GOLD_COLOR, localization.t_args("Combo Bonus: +{}", &[&combo_bonus.0])).on_hover_text(localization.t("Paid when the next round starts, 1 gold for every 5 attackers in the best chain of quick leaks"));
+            window.label(localization.t_args("Duration: {}", &[&format_duration(round_stats.round_duration)]));
+            window.label(localization.t_args(
+                "Gold efficiency: {} damage/gold (best: {})",
+                &[&format!("{:.1}", round_stats.gold_efficiency), &format!("{:.1}", round_stats.best_gold_efficiency)]
+            )).on_hover_text(localization.t("Pressure on the defender per gold spent since the last round: 10 per attacker reaching the end, 2 per attacker killed"));
+            if window.button(localization.t("Close")).clicked() {
+                state.show_round_summary = false;
+            }
+        });
+    }
+}
+
+fn round_history(
+    mut contexts: EguiContexts,
+    mut state: ResMut<State>,
+    history: Res<RoundHistory>,
+    localization: Res<Localization>
+) {
+    if !state.show_round_history {
+        return;
+    }
+    let max_kills = history.max_kills().max(1);
+    egui::Window::new(localization.t("Round History")).open(&mut state.show_round_history).show(contexts.ctx_mut(), |window| {
+        if history.rounds.is_empty() {
+            window.label(localization.t("No round has been played yet"));
+            return;
+        }
+        egui::ScrollArea::vertical().max_height(300.).show(window, |scroll| {
+            egui::Grid::new("round_history_table").striped(true).show(scroll, |grid| {
+                for header in ["Round", "Kills", "", "Reached end", "Gold", "Path", "Duration"] {
+                    grid.strong(localization.t(header));
+                }
+                grid.end_row();
+                for summary in &history.rounds {
+                    grid.label(summary.round.to_string());
+                    grid.label(summary.kills.to_string());
+                    // Kills relative to the best round of the session
+                    let (rect, _) = grid.allocate_exact_size(egui::vec2(80., 10.), egui::Sense::hover());
+                    let mut bar = rect;
+                    bar.set_width(rect.width() * summary.kills as f32 / max_kills as f32);
+                    grid.painter().rect_filled(bar, 2., LIVES_COLOR);
+                    grid.label(summary.breakthroughs.to_string());
+                    grid.label(RichText::new(summary.gold_earned.to_string()).color(GOLD_COLOR));
+                    grid.label(summary.path_length.to_string());
+                    grid.label(format_duration(summary.duration));
+                    grid.end_row();
+                }
+            });
+        });
+    });
+}
+
+fn mutators_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<State>,
+    mut modifiers: ResMut<RoundModifiers>,
+    mut attacker_resource: ResMut<AttackerResource>,
+    localization: Res<Localization>
+) {
+    if !state.show_mutators {
+        return;
+    }
+    egui::Window::new(localization.t("Mutators")).open(&mut state.show_mutators).resizable(false).show(contexts.ctx_mut(), |window| {
+        window.label(localization.t("Picked mutators apply to the next round only"));
+        egui::Grid::new("mutator_table").striped(true).show(window, |grid| {
+            for mutator in Mutator::ALL {
+                let mut selected = modifiers.is_selected(mutator);
+                let affordable = selected || can_afford(attacker_resource.gold, mutator.get_cost());
+                if grid.add_enabled(affordable, egui::Checkbox::new(&mut selected, localization.t(mutator.get_name()))).on_hover_text(localization.t(mutator.get_description())).changed() {
+                    modifiers.toggle(mutator, &mut attacker_resource);
+                }
+                grid.colored_label(GOLD_COLOR, mutator.get_cost().to_string());
+                grid.label(localization.t(mutator.get_description()));
+                grid.end_row();
+            }
+        });
+        window.separator();
+        window.checkbox(&mut modifiers.chaos_mode, localization.t("Chaos mode")).on_hover_text(localization.t("Add a random mutator to every round for free"));
+        let active: Vec<&str> = modifiers.get_active().iter().map(|mutator| localization.t(mutator.get_name())).collect();
+        if !active.is_empty() {
+            window.label(localization.t_args("Active this round: {}", &[&active.join(", ")]));
+        }
+    });
+}
+
+/* Clearing can't be undone, so it always asks first */
+fn clear_board_dialog(
+    mut contexts: EguiContexts,
+    mut state: ResMut<State>,
+    mut clear_board: EventWriter<ClearBoardRequest>,
+    structures: Query<(), With<Structure>>,
+    localization: Res<Localization>
+) {
+    if !state.confirm_clear_board {
+        return;
+    }
+    let mut confirmed = false;
+    let mut cancelled = false;
+    egui::Window::new(localization.t("Clear Board")).collapsible(false).resizable(false).anchor(egui::Align2::CENTER_CENTER, [0., 0.]).show(contexts.ctx_mut(), |window| {
+        window.label(localization.t_args("Remove all {} buildings from the field?", &[&structures.iter().count()]));
+        window.add(egui::Slider::new(&mut state.clear_board_refund, 0.0..=1.).text(localization.t("Refund")).custom_formatter(|value, _| format!("{:.0}%", value * 100.)));
+        window.horizontal(|row| {
+            confirmed = row.button(localization.t("Clear")).clicked();
+            cancelled = row.button(localization.t("Cancel")).clicked();
         });
+    });
+    if confirmed {
+        clear_board.send(ClearBoardRequest { refund_fraction: state.clear_board_refund });
+    }
+    if confirmed || cancelled {
+        state.confirm_clear_board = false;
     }
 }
 
@@ -272,14 +1259,36 @@ fn defender_params(
     state: Res<State>,
     resources: Res<ResourceStore>,
     round_stats: Res<RoundStats>,
-    defender_config: Res<DefenderConfiguration>
+    mut defender_config: ResMut<DefenderConfiguration>,
+    mut strategy: ResMut<ActiveStrategy>,
+    towers: Query<(&Structure, &Defender, &Transform)>,
+    localization: Res<Localization>,
+    registry: Res<BuildingRegistry>
 ) {
     if state.show_defender_params {
         egui::Window::new("Defender Params").title_bar(true).show(contexts.ctx_mut(), |window| {
+            window.columns(2, |cols| {
+                cols[0].label("Strategy");
+                strategy_picker(&mut cols[1], "defender_params_strategy", &mut strategy, &localization);
+            });
+            window.columns(2, |cols| {
+                cols[0].label("Panic");
+                if defender_config.panicking {
+                    cols[1].colored_label(egui::Color32::from_rgb(240, 150, 40), "Yes");
+                } else {
+                    cols[1].label("No");
+                }
+            });
             window.columns(2, |cols| {
                 cols[0].label("Gold");
                 cols[1].label(resources.gold.to_string());
             });
+            window.columns(2, |cols| {
+                cols[0].label("Saving for");
+                cols[1].label(defender_config.savings.map_or("-".to_string(), |savings| {
+                    format!("{}, {} short ({}/{})", registry.get_name(savings.building_type), savings.shortfall, savings.ticks, SAVING_PATIENCE)
+                }));
+            });
             window.columns(2, |cols| {
                 cols[0].label("Max APM");
                 cols[1].label(
@@ -310,6 +1319,27 @@ fn defender_params(
                 cols[0].label("Path Length");
                 cols[1].label(defender_config.path_length.to_string());
             });
+            window.columns(2, |cols| {
+                cols[0].label("Choke points");
+                cols[1].label(defender_config.choke_points.len().to_string());
+            });
+            window.columns(2, |cols| {
+                cols[0].label("Free wall / tower slots");
+                cols[1].label(format!("{} / {}", defender_config.free_wall_slots, defender_config.free_tower_slots));
+            });
+            window.columns(2, |cols| {
+                cols[0].label("Dominant threat");
+                cols[1].label(defender_config.dominant_threat.map_or("-", |threat| threat.get_name()));
+            });
+            window.columns(2, |cols| {
+                cols[0].label("Completion bonus").on_hover_text("Paid when a round is over: base + per round * round number + per life * lives left");
+                cols[1].horizontal(|row| {
+                    let bonus = &mut defender_config.completion_bonus;
+                    row.add(egui::DragValue::new(&mut bonus.base).clamp_range(0..=500));
+                    row.add(egui::DragValue::new(&mut bonus.per_round).clamp_range(0..=100));
+                    row.add(egui::DragValue::new(&mut bonus.per_life).clamp_range(0..=100));
+                });
+            });
             window.separator();
             window.label("Round stats");
             window.columns(2, |cols| {
@@ -318,7 +1348,7 @@ fn defender_params(
             });
             window.columns(2, |cols| {
                 cols[0].label("Round duration");
-                cols[1].label(format!("{}s", round_stats.round_duration.as_secs()));
+                cols[1].label(format_duration(round_stats.round_duration));
             });
             window.columns(2, |cols| {
                 cols[0].label("Number reached end");
@@ -332,6 +1362,62 @@ fn defender_params(
                 cols[0].label("Closest to end");
                 cols[1].label(round_stats.closest_distance_to_end.to_string());
             });
+            window.separator();
+            window.label("Towers");
+            window.columns(3, |cols| {
+                cols[0].label("Tower");
+                cols[1].label("Kills");
+                cols[2].label("Kill efficiency");
+            });
+            for (structure, defender, transform) in &towers {
+                let node = transform.translation.truncate() / SLOT_SIZE as f32;
+                window.columns(3, |cols| {
+                    cols[0].label(format!("{} ({}, {})", registry.get_name(structure.building_type), node.x as i32, node.y as i32));
+                    cols[1].label(defender.kill_count.to_string());
+                    cols[2].label(format!("{:.3}", defender.kill_efficiency));
+                });
+            }
         });
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Schedule;
+
+    use super::*;
+
+    fn victory_world(lives: i32) -> (World, Schedule) {
+        let mut world = World::new();
+        world.insert_resource(ResourceStore { gold: 0, lives });
+        world.insert_resource(Time::default());
+        world.init_resource::<GameConfig>();
+        world.init_resource::<WinConditionDisplay>();
+        world.init_resource::<VictoryState>();
+        world.init_resource::<GameMode>();
+        world.init_resource::<EndlessScore>();
+        world.init_resource::<Events<GameOverEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(update_win_condition);
+        schedule.add_system(update_victory_pause.after(update_win_condition));
+        return (world, schedule);
+    }
+
+    #[test]
+    fn victory_pauses_once_and_unpauses_when_left() {
+        let (mut world, mut schedule) = victory_world(0);
+        schedule.run(&mut world);
+        assert!(world.resource::<Time>().is_paused());
+
+        // Something else resuming time while the victory window is up is not overridden every frame
+        world.resource_mut::<Time>().unpause();
+        schedule.run(&mut world);
+        assert!(!world.resource::<Time>().is_paused());
+
+        world.resource_mut::<Time>().pause();
+        world.resource_mut::<ResourceStore>().lives = 50;
+        schedule.run(&mut world);
+        assert!(!world.resource::<Time>().is_paused());
+        assert!(!world.resource::<VictoryState>().has_paused);
+    }
+}
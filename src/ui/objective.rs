@@ -0,0 +1,148 @@
+use bevy::prelude::{App, IntoSystemConfig, Plugin, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{
+    error::GameplaySet,
+    localization::Localization,
+    world::{
+        defender_controller::ResourceStore,
+        endless::{EndlessScore, GameMode},
+        game_config::{GameConfig, VictoryCondition},
+    },
+};
+
+/* How far the attacker is towards winning, always shown at the bottom of the screen */
+#[derive(Resource)]
+pub struct WinConditionDisplay {
+    pub condition: VictoryCondition,
+    /* 0 at the start of the game, 1 or more once the attacker has won */
+    pub progress: f32,
+    pub current: i32,
+    pub goal: i32,
+}
+
+impl Default for WinConditionDisplay {
+    fn default() -> Self {
+        return Self { condition: VictoryCondition::DrainLives, progress: 0., current: 0, goal: 1 };
+    }
+}
+
+impl WinConditionDisplay {
+    pub fn is_won(&self) -> bool {
+        return self.progress >= 1.;
+    }
+
+    fn get_objective(&self, localization: &Localization) -> String {
+        return match self.condition {
+            VictoryCondition::DrainLives => localization.t_args("Objective: Drain all {} lives", &[&self.goal]),
+            VictoryCondition::ReachEndNTimes(n) => localization.t_args("Objective: Reach end {} times", &[&n]),
+        };
+    }
+}
+
+pub struct ObjectivePlugin;
+
+impl Plugin for ObjectivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WinConditionDisplay>()
+            .add_system(update_win_condition)
+            .add_system(objective_bar.in_set(GameplaySet).after(update_win_condition));
+    }
+}
+
+/* Every leak costs exactly one life, so the lost lives double as the number of times the end was reached. Lives drained in sudden death count as well.
+   Endless can't be won, it only ends in bankruptcy */
+pub fn update_win_condition(mut display: ResMut<WinConditionDisplay>, config: Res<GameConfig>, defender_resource: Res<ResourceStore>, mode: Res<GameMode>) {
+    if *mode == GameMode::Endless {
+        display.progress = 0.;
+        return;
+    }
+    let lives_lost = config.defender_starting_lives - defender_resource.lives;
+    let goal = match config.victory_condition {
+        VictoryCondition::DrainLives => config.defender_starting_lives,
+        VictoryCondition::ReachEndNTimes(n) => n,
+    };
+    display.condition = config.victory_condition;
+    display.current = lives_lost.clamp(0, goal.max(0));
+    display.goal = goal.max(1);
+    display.progress = if defender_resource.lives <= 0 && config.victory_condition == VictoryCondition::DrainLives {
+        1.
+    } else {
+        lives_lost.max(0) as f32 / display.goal as f32
+    };
+}
+
+fn progress_color(progress: f32) -> egui::Color32 {
+    return if progress > 0.75 {
+        egui::Color32::from_rgb(80, 180, 80)
+    } else if progress >= 0.5 {
+        egui::Color32::from_rgb(220, 190, 40)
+    } else {
+        egui::Color32::from_rgb(200, 70, 60)
+    };
+}
+
+fn objective_bar(mut contexts: EguiContexts, display: Res<WinConditionDisplay>, mode: Res<GameMode>, endless_score: Res<EndlessScore>, localization: Res<Localization>) {
+    egui::Area::new("objective").anchor(egui::Align2::CENTER_BOTTOM, [0., -8.]).show(contexts.ctx_mut(), |area| {
+        egui::Frame::popup(area.style()).show(area, |frame| {
+            frame.set_width(240.);
+            if *mode == GameMode::Endless {
+                frame.vertical_centered(|column| {
+                    column.label(localization.t("Objective: Leak as often as you can before the gold runs out"));
+                    column.label(localization.t_args("Score: {}   Best: {}", &[&endless_score.score, &endless_score.best]));
+                });
+                return;
+            }
+            frame.vertical_centered(|column| {
+                column.label(display.get_objective(&localization));
+                column.label(localization.t_args("Progress: {}/{}", &[&display.current, &display.goal]));
+            });
+            let progress = display.progress.clamp(0., 1.);
+            frame.add(egui::ProgressBar::new(progress).fill(progress_color(progress)));
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{schedule_of, system_test_world};
+
+    use super::*;
+
+    fn progress_after(condition: VictoryCondition, lives: i32) -> WinConditionDisplay {
+        let mut world = system_test_world();
+        world.insert_resource(GameConfig { victory_condition: condition, ..Default::default() });
+        world.insert_resource(ResourceStore { gold: 0, lives });
+        world.init_resource::<WinConditionDisplay>();
+        world.init_resource::<GameMode>();
+        let mut schedule = schedule_of(update_win_condition);
+        schedule.run(&mut world);
+        return world.remove_resource::<WinConditionDisplay>().unwrap();
+    }
+
+    #[test]
+    fn progress_follows_the_victory_condition() {
+        let drain = progress_after(VictoryCondition::DrainLives, 40);
+        assert_eq!((drain.current, drain.goal), (10, 50));
+        assert_eq!(drain.progress, 0.2);
+        assert!(progress_after(VictoryCondition::DrainLives, 0).is_won());
+
+        let reach = progress_after(VictoryCondition::ReachEndNTimes(5), 48);
+        assert_eq!((reach.current, reach.goal), (2, 5));
+        assert_eq!(reach.progress, 0.4);
+        assert_eq!(reach.get_objective(&Localization::default()), "Objective: Reach end 5 times");
+        assert!(progress_after(VictoryCondition::ReachEndNTimes(5), 45).is_won());
+    }
+
+    #[test]
+    fn endless_is_never_won_by_lives() {
+        let mut world = system_test_world();
+        world.init_resource::<GameConfig>();
+        world.insert_resource(ResourceStore { gold: 0, lives: 0 });
+        world.insert_resource(GameMode::Endless);
+        world.init_resource::<WinConditionDisplay>();
+        let mut schedule = schedule_of(update_win_condition);
+        schedule.run(&mut world);
+        assert!(!world.resource::<WinConditionDisplay>().is_won());
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, prelude::{Assets, Camera, GlobalTransform, Res, Vec3}, sprite::TextureAtlas};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{localization::Localization, textures::TextureResource, world::{building_configuration::{BuildingResource, BuildingType}, building_registry::BuildingRegistry}};
+
+pub fn format_duration(d: Duration) -> String {
+    // Rounded before picking the format, 59.96 seconds would otherwise show up as "60.0s"
+    let tenths = (d.as_secs_f64() * 10.).round() as u64;
+    if tenths >= 600 {
+        let secs = tenths / 10;
+        return format!("{}m {}s", secs / 60, secs % 60);
+    } else {
+        return format!("{}.{}s", tenths / 10, tenths % 10);
+    }
+}
+
+pub fn format_duration_short(d: Duration) -> String {
+    let secs = d.as_secs();
+    return format!("{}:{:02}", secs / 60, secs % 60);
+}
+
+/* Maps a world position to egui's screen space, which starts top left and is scaled by the egui scale factor */
+pub fn world_to_egui(camera: &Camera, camera_transform: &GlobalTransform, position: Vec3, scale_factor: f64) -> Option<egui::Pos2> {
+    let viewport_size = camera.logical_viewport_size()?;
+    let viewport_pos = camera.world_to_viewport(camera_transform, position)?;
+    let scale = scale_factor as f32;
+    return Some(egui::pos2(viewport_pos.x / scale, (viewport_size.y - viewport_pos.y) / scale));
+}
+
+/* Names and menu icons of the buildings, the icons are cells of the "towers" atlas */
+#[derive(SystemParam)]
+pub struct BuildingIcons<'w> {
+    registry: Res<'w, BuildingRegistry>,
+    buildings: Res<'w, BuildingResource>,
+    textures: Res<'w, TextureResource>,
+    atlases: Res<'w, Assets<TextureAtlas>>,
+    localization: Res<'w, Localization>,
+}
+
+/* Everything needed to draw the icons once the atlas texture is known to egui */
+pub struct IconSheet<'a, 'w> {
+    texture: egui::TextureId,
+    atlas: &'a TextureAtlas,
+    icons: &'a BuildingIcons<'w>,
+}
+
+impl<'w> BuildingIcons<'w> {
+    pub fn get_name(&self, building_type: BuildingType) -> &str {
+        return self.localization.t(self.registry.get_name(building_type));
+    }
+
+    pub fn get_building_types(&self) -> &[BuildingType] {
+        return self.registry.get_building_types();
+    }
+
+    /* Call before borrowing the egui context, None while the atlas is still loading */
+    pub fn sheet(&self, contexts: &mut EguiContexts) -> Option<IconSheet<'_, 'w>> {
+        if !self.textures.has_atlas("towers") {
+            return None;
+        }
+        let atlas = self.atlases.get(self.textures.get_atlas("towers"))?;
+        return Some(IconSheet { texture: contexts.add_image(atlas.texture.clone_weak()), atlas, icons: self });
+    }
+}
+
+impl<'a, 'w> IconSheet<'a, 'w> {
+    /* Validation reports missing icons at startup, they are just left out here */
+    pub fn image(&self, building_type: BuildingType, size: f32) -> Option<egui::Image> {
+        let icon = self.icons.buildings.get_icon(&building_type)?;
+        let (_, sprite) = self.icons.textures.get_named_sprite("towers", icon)?;
+        let rect = self.atlas.textures.get(sprite.index)?;
+        let size_in_atlas = self.atlas.size;
+        let uv = egui::Rect::from_min_max(
+            egui::pos2(rect.min.x / size_in_atlas.x, rect.min.y / size_in_atlas.y),
+            egui::pos2(rect.max.x / size_in_atlas.x, rect.max.y / size_in_atlas.y),
+        );
+        return Some(egui::Image::new(self.texture, [size, size]).uv(uv));
+    }
+
+    /* Icon followed by the building's name */
+    pub fn label(&self, ui: &mut egui::Ui, building_type: BuildingType, size: f32) {
+        ui.horizontal(|row| {
+            if let Some(image) = self.image(building_type, size) {
+                row.add(image);
+            }
+            row.label(self.icons.get_name(building_type));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_show_minutes_once_they_round_to_a_full_minute() {
+        assert_eq!(format_duration(Duration::from_secs(83)), "1m 23s");
+        assert_eq!(format_duration(Duration::from_millis(42_300)), "42.3s");
+        assert_eq!(format_duration(Duration::from_millis(59_940)), "59.9s");
+        // Rounds up to a full minute
+        assert_eq!(format_duration(Duration::from_millis(59_960)), "1m 0s");
+        assert_eq!(format_duration(Duration::from_secs(60)), "1m 0s");
+    }
+
+    #[test]
+    fn short_durations_are_formatted_like_a_clock() {
+        assert_eq!(format_duration_short(Duration::from_secs(83)), "1:23");
+        assert_eq!(format_duration_short(Duration::from_millis(59_999)), "0:59");
+        assert_eq!(format_duration_short(Duration::from_secs(60)), "1:00");
+    }
+}
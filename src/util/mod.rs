@@ -1,4 +1,7 @@
-use bevy::time::Timer;
+use bevy::{time::Timer, prelude::Resource};
+use rand::{rngs::StdRng, SeedableRng};
+
+pub mod storage;
 
 
 
@@ -20,4 +23,25 @@ impl<const TMILLIS: usize> Default for LocalTimer<TMILLIS> {
     fn default() -> Self {
         Self { timer: Timer::from_seconds(TMILLIS as f32 / 1000., bevy::time::TimerMode::Once) }
     }
-}
\ No newline at end of file
+}
+
+/* Shared by every buy button and placement, so the attacker and defender gate on gold the same way */
+pub fn can_afford(gold: i32, cost: i32) -> bool {
+    return cost <= gold;
+}
+
+/* Gameplay randomness goes through this so a round can be replayed from its seed */
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        return Self(StdRng::seed_from_u64(seed));
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        return Self::seeded(rand::random());
+    }
+}
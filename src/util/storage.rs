@@ -0,0 +1,46 @@
+/* Small text blobs that outlive the session: a file next to the executable natively, localStorage in the browser.
+   Both can be missing or refuse to write, callers fall back to their defaults */
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    return std::fs::read_to_string(name).ok();
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, contents: &str) -> Result<(), String> {
+    return std::fs::write(name, contents).map_err(|err| format!("Failed to write {}: {}", name, err));
+}
+
+#[cfg(target_arch = "wasm32")]
+const KEY_PREFIX: &str = "gmtk23/";
+
+/* localStorage is reached through js_sys, the browser may not offer it at all, e.g. in private mode */
+#[cfg(target_arch = "wasm32")]
+fn call_local_storage(method: &str, args: &js_sys::Array) -> Option<wasm_bindgen::JsValue> {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let storage = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("localStorage")).ok()?;
+    if storage.is_undefined() || storage.is_null() {
+        return None;
+    }
+    let function: js_sys::Function = js_sys::Reflect::get(&storage, &JsValue::from_str(method)).ok()?.dyn_into().ok()?;
+    return function.apply(&storage, args).ok();
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Option<String> {
+    return load_key(&format!("{}{}", KEY_PREFIX, name));
+}
+
+/* A localStorage key as it is, without the prefix, for values stored before they went through this module */
+#[cfg(target_arch = "wasm32")]
+pub fn load_key(key: &str) -> Option<String> {
+    let args = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(key));
+    return call_local_storage("getItem", &args).and_then(|value| value.as_string());
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(name: &str, contents: &str) -> Result<(), String> {
+    let args = js_sys::Array::of2(&wasm_bindgen::JsValue::from_str(&format!("{}{}", KEY_PREFIX, name)), &wasm_bindgen::JsValue::from_str(contents));
+    return call_local_storage("setItem", &args).map(|_| ()).ok_or(format!("Failed to write {} to localStorage", name));
+}
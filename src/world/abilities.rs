@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::{App, Commands, Component, Entity, EventReader, Plugin, Query, Res, Transform, Without, IntoSystemConfig},
+    time::{Time, Timer, TimerMode},
+};
+
+use crate::{error::GameplaySet, localization::Localization, textures::TextureResource};
+
+use super::{
+    attackers::Attacker,
+    building_configuration::BuildingResource,
+    events::ActivateAbility,
+    towers::{is_in_arc, spawn_attack, Buffed, Defender, Structure},
+};
+
+pub const ABILITY_UNLOCK_LEVEL: u32 = 3;
+const MEGA_SHOT_COOLDOWN: f32 = 20.;
+const MEGA_SHOT_MULTIPLIER: f32 = 5.;
+const RAPID_FIRE_COOLDOWN: f32 = 25.;
+const RAPID_FIRE_DURATION: f32 = 4.;
+/* Attack timer while rapid fire is active, relative to the tower's configured one */
+const RAPID_FIRE_ATTACK_FACTOR: f32 = 0.2;
+
+#[derive(Clone)]
+pub enum AbilityType {
+    MegaShot { multiplier: f32 },
+    RapidFire { duration: Timer },
+}
+
+#[derive(Component)]
+pub struct SpecialAbility {
+    pub cooldown: Timer,
+    pub ability_type: AbilityType,
+    pub charged: bool,
+}
+
+impl SpecialAbility {
+    pub fn mega_shot() -> Self {
+        return Self {
+            cooldown: Timer::from_seconds(MEGA_SHOT_COOLDOWN, TimerMode::Once),
+            ability_type: AbilityType::MegaShot { multiplier: MEGA_SHOT_MULTIPLIER },
+            charged: false,
+        };
+    }
+
+    pub fn rapid_fire() -> Self {
+        // Starts out finished, a running duration timer means rapid fire is active
+        let mut duration = Timer::from_seconds(RAPID_FIRE_DURATION, TimerMode::Once);
+        duration.tick(Duration::from_secs_f32(RAPID_FIRE_DURATION));
+        return Self {
+            cooldown: Timer::from_seconds(RAPID_FIRE_COOLDOWN, TimerMode::Once),
+            ability_type: AbilityType::RapidFire { duration },
+            charged: false,
+        };
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        return match self.ability_type {
+            AbilityType::MegaShot { .. } => "Mega Shot",
+            AbilityType::RapidFire { .. } => "Rapid Fire",
+        };
+    }
+
+    pub fn get_description(&self, localization: &Localization) -> String {
+        return match &self.ability_type {
+            AbilityType::MegaShot { multiplier } => localization.t_args("Fires one shot dealing {}x damage at the nearest enemy", &[multiplier]),
+            AbilityType::RapidFire { duration } => localization.t_args("Attacks {}x as fast for {}s", &[&(1. / RAPID_FIRE_ATTACK_FACTOR), &duration.duration().as_secs_f32()]),
+        };
+    }
+}
+
+pub struct AbilitiesPlugin;
+
+impl Plugin for AbilitiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(charge_abilities.in_set(GameplaySet))
+            .add_system(update_rapid_fire.in_set(GameplaySet))
+            .add_system(activate_abilities.after(charge_abilities).in_set(GameplaySet));
+    }
+}
+
+fn charge_abilities(
+    mut towers: Query<(&mut SpecialAbility, &Defender)>,
+    time: Res<Time>,
+) {
+    for (mut ability, defender) in towers.iter_mut() {
+        ability.cooldown.tick(time.delta());
+        ability.charged = ability.cooldown.finished() && defender.get_level() >= ABILITY_UNLOCK_LEVEL;
+    }
+}
+
+fn update_rapid_fire(
+    mut towers: Query<(&mut SpecialAbility, &mut Defender, &Structure)>,
+    buildings: Res<BuildingResource>,
+    time: Res<Time>,
+) {
+    for (mut ability, mut defender, structure) in towers.iter_mut() {
+        if let AbilityType::RapidFire { duration } = &mut ability.ability_type {
+            if duration.finished() {
+                continue;
+            }
+            duration.tick(time.delta());
+            if duration.just_finished() {
+                let base = buildings.get_building_config(&structure.building_type).map(|e| e.get_attack_timer()).unwrap_or_default();
+                defender.attack_timer.set_duration(Duration::from_secs_f32(base));
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn activate_abilities(
+    mut commands: Commands,
+    mut activations: EventReader<ActivateAbility>,
+    mut towers: Query<(Entity, &mut SpecialAbility, &mut Defender, &Transform, &Structure, Option<&Buffed>), Without<Attacker>>,
+    enemies: Query<(Entity, &Transform, &Attacker)>,
+    buildings: Res<BuildingResource>,
+    textures: Res<TextureResource>,
+) {
+    for ev in activations.iter() {
+        let Ok((entity, mut ability, mut defender, transform, structure, buff)) = towers.get_mut(ev.tower) else {
+            continue;
+        };
+        if !ability.charged {
+            continue;
+        }
+        match &mut ability.ability_type {
+            AbilityType::MegaShot { multiplier } => {
+                let position = transform.translation.truncate();
+                let nearest = enemies
+                    .iter()
+                    .filter(|e| {
+                        let distance = e.1.translation.truncate().distance(position);
+                        distance >= defender.min_range && distance <= defender.attack_range
+                            && is_in_arc(defender.arc, position, e.1.translation.truncate())
+                    })
+                    .min_by(|a, b| {
+                        a.1.translation.truncate().distance(position).total_cmp(&b.1.translation.truncate().distance(position))
+                    });
+                // Nothing in range keeps the charge for later
+                let Some((target, target_transform, attacker)) = nearest else {
+                    continue;
+                };
+                let damage_factor = *multiplier * (1. + buff.map(|buff| buff.damage_bonus).unwrap_or(0.));
+                spawn_attack(&mut commands, entity, &defender.attack, damage_factor, transform, target, target_transform, attacker.velocity, &textures);
+            }
+            AbilityType::RapidFire { duration } => {
+                duration.reset();
+                let base = buildings.get_building_config(&structure.building_type).map(|e| e.get_attack_timer()).unwrap_or_default();
+                defender.attack_timer.set_duration(Duration::from_secs_f32(base * RAPID_FIRE_ATTACK_FACTOR));
+            }
+        }
+        ability.cooldown.reset();
+        ability.charged = false;
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use bevy::{prelude::{Plugin, App, Resource, Res, ResMut, EventWriter, Local, IntoSystemConfig}, time::Time};
+
+use crate::error::GameplaySet;
+
+use super::{attacker_controller::{AttackerAdvisor, AttackerResource}, attackers::AttackerStats, defender_controller::DefenderConfiguration, events::RequestRoundStart, rounds::{PrepTimer, RoundResource}, towers::TowerField};
+
+/* Who buys units and starts rounds, only one side may do it so the two never spend the same gold */
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttackerControl {
+    Human,
+    Computer
+}
+
+impl Default for AttackerControl {
+    fn default() -> Self {
+        return Self::Human;
+    }
+}
+
+#[derive(Resource)]
+pub struct AttackerAiSettings {
+    /* Time between the end of a round and the next wave, the defender builds in between */
+    pub wave_delay: Duration
+}
+
+impl Default for AttackerAiSettings {
+    fn default() -> Self {
+        return Self { wave_delay: Duration::from_secs(5) };
+    }
+}
+
+/* Plays the attacker side the same way DefenderController plays the defender */
+pub struct AttackerAi;
+
+impl Plugin for AttackerAi {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AttackerControl>()
+            .init_resource::<AttackerAiSettings>()
+            .add_system(send_waves.in_set(GameplaySet).run_if(computer_attacks));
+    }
+}
+
+pub fn computer_attacks(control: Res<AttackerControl>) -> bool {
+    return *control == AttackerControl::Computer;
+}
+
+/* Spends all gold on what the advisor suggests for the current defense and starts the round */
+#[allow(clippy::too_many_arguments)]
+fn send_waves(
+    mut round_start: EventWriter<RequestRoundStart>,
+    mut round: ResMut<RoundResource>,
+    prep: Res<PrepTimer>,
+    mut attacker_resource: ResMut<AttackerResource>,
+    field: Res<TowerField>,
+    config: Res<DefenderConfiguration>,
+    stats: Res<AttackerStats>,
+    settings: Res<AttackerAiSettings>,
+    mut idle: Local<Duration>,
+    time: Res<Time>
+) {
+    // The wave of a counting down round is bought already
+    if round.is_round_active() || prep.is_running() {
+        *idle = Duration::ZERO;
+        return;
+    }
+    *idle += time.delta();
+    if *idle < settings.wave_delay {
+        return;
+    }
+    *idle = Duration::ZERO;
+    for (attacker_type, amount) in AttackerAdvisor::suggest(&field, &config, &stats, attacker_resource.gold) {
+        for _ in 0..amount {
+            attacker_resource.spend(stats.get_cost(attacker_type));
+            round.queue(&attacker_type);
+        }
+    }
+    // Started even without units, the round end bounty is the only income left then
+    round_start.send(RequestRoundStart);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Events, Schedule, World};
+
+    use crate::world::{events::RoundStartEvent, game_config::GameConfig, path_finding::Node, rounds::{process_request_round_start, ScheduledSpawns}};
+
+    use super::*;
+
+    fn ai_world(control: AttackerControl) -> (World, Schedule) {
+        let mut world = World::new();
+        world.insert_resource(control);
+        world.insert_resource(AttackerAiSettings { wave_delay: Duration::ZERO });
+        world.insert_resource(AttackerResource { gold: 200, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 });
+        world.insert_resource(TowerField::new(16, 16, bevy::prelude::Vec2::ZERO, Node::new(2, 0), Node::new(14, 15)));
+        world.init_resource::<RoundResource>();
+        world.init_resource::<ScheduledSpawns>();
+        world.init_resource::<PrepTimer>();
+        world.insert_resource(GameConfig { prep_duration: 0., ..Default::default() });
+        world.init_resource::<DefenderConfiguration>();
+        world.init_resource::<AttackerStats>();
+        world.init_resource::<Time>();
+        world.init_resource::<Events<RequestRoundStart>>();
+        world.init_resource::<Events<RoundStartEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(send_waves.run_if(computer_attacks));
+        schedule.add_system(process_request_round_start.after(send_waves));
+        return (world, schedule);
+    }
+
+    #[test]
+    fn computer_queues_and_starts_a_wave() {
+        let (mut world, mut schedule) = ai_world(AttackerControl::Computer);
+        schedule.run(&mut world);
+
+        let gold = world.resource::<AttackerResource>();
+        assert!(gold.gold_spent_this_round > 0);
+        assert!(gold.gold >= 0);
+        let round = world.resource::<RoundResource>();
+        assert_eq!(round.get_round_number(), 1);
+        assert!(round.is_round_active());
+        assert!(!round.has_pending_units());
+    }
+
+    #[test]
+    fn human_control_leaves_the_attacker_alone() {
+        let (mut world, mut schedule) = ai_world(AttackerControl::Human);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<AttackerResource>().gold, 200);
+        assert_eq!(world.resource::<RoundResource>().get_round_number(), 0);
+    }
+}
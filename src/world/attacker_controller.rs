@@ -1,32 +1,393 @@
-use bevy::prelude::{Plugin, App, Resource, EventReader, ResMut, Local};
+use std::time::Duration;
 
-use super::events::{KillEvent, RoundOverEvent, EntityReachedEnd};
+use bevy::{prelude::{Plugin, App, Resource, EventReader, ResMut, Res, Local, IntoSystemConfig, Component, Commands, Entity, Query, Transform, With, Color, Vec2}, sprite::{SpriteSheetBundle, TextureAtlasSprite}, time::{Time, Timer, TimerMode}, utils::HashSet};
+use serde::{Serialize, Serializer};
+
+use crate::{camera::MainCamera, error::GameplaySet, particle::spawn_floating_text, textures::TextureResource};
+
+use super::{events::{KillEvent, RoundOverEvent, RoundStartEvent, EntityReachedEnd, FieldModified, GoldStolenEvent}, defender_controller::{RoundStats, DefenderConfiguration, DefenderControl, path_nodes_in_reach}, game_config::GameConfig, towers::{Defender, Structure, TowerField, SLOT_SIZE}, attackers::{Attacker, AttackerStats, AttackerType}, building_configuration::{BuildingResource, BuildingType}, path_finding::{a_star, Node}, rounds::RoundResource, sudden_death::SuddenDeathState, mutators::RoundModifiers};
 
 
 #[derive(Resource)]
 pub struct AttackerResource {
     pub gold: i32,
-    pub current_bounty: i32
+    pub current_bounty: i32,
+    /* Everything spent since the last round ended, purchases happen before the round they are used in */
+    pub gold_spent_this_round: i32,
+    pub gold_earned_this_round: i32
+}
+
+impl AttackerResource {
+    pub fn spend(&mut self, amount: i32) {
+        self.gold -= amount;
+        self.gold_spent_this_round += amount;
+    }
+
+    /* Gives back gold for a purchase that was undone */
+    pub fn refund(&mut self, amount: i32) {
+        self.gold += amount;
+        self.gold_spent_this_round -= amount;
+    }
+
+    pub fn earn(&mut self, amount: i32) {
+        self.gold += amount;
+        self.gold_earned_this_round += amount;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoundSummary {
+    pub round: u32,
+    pub kills: i32,
+    pub breakthroughs: i32,
+    pub gold_earned: i32,
+    pub path_length: usize,
+    /* Written in seconds like before, headless reports are read by scripts */
+    #[serde(rename = "duration_secs", serialize_with = "serialize_secs")]
+    pub duration: Duration
+}
+
+fn serialize_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    return serializer.serialize_f32(duration.as_secs_f32());
+}
+
+/* Every finished round of the session, oldest first */
+#[derive(Resource, Default)]
+pub struct RoundHistory {
+    pub rounds: Vec<RoundSummary>
+}
+
+impl RoundHistory {
+    pub fn max_kills(&self) -> i32 {
+        return self.rounds.iter().map(|summary| summary.kills).max().unwrap_or(0);
+    }
+}
+
+/* Leaks this close after each other count as one combo */
+const COMBO_WINDOW_SECONDS: f32 = 2.;
+/* Gold carried into the next round per this many combo leaks */
+const COMBO_PER_BONUS_GOLD: u32 = 5;
+const MAX_COMBO_BONUS: u32 = 50;
+
+/* Attackers reaching the end in quick succession */
+#[derive(Resource)]
+pub struct ComboTracker {
+    pub count: u32,
+    pub timer: Timer,
+    pub max_this_round: u32
+}
+
+impl Default for ComboTracker {
+    fn default() -> Self {
+        return Self { count: 0, timer: Timer::from_seconds(COMBO_WINDOW_SECONDS, TimerMode::Once), max_this_round: 0 };
+    }
+}
+
+/* Earned from the best combo of the last round, paid out when the next one starts */
+#[derive(Resource, Default)]
+pub struct PersistentComboBonus(pub i32);
+
+const COIN_TTL_SECONDS: f32 = 6.;
+/* How close the center of the screen has to get to a coin to pick it up, in world units */
+const COIN_PICKUP_RADIUS: f32 = 48.;
+
+/* With instant credit off, refunds for killed attackers drop as coins that have to be collected */
+#[derive(Resource)]
+pub struct GoldPickup {
+    pub instant: bool
+}
+
+impl Default for GoldPickup {
+    fn default() -> Self {
+        Self { instant: true }
+    }
+}
+
+/* Collected when the camera passes over it, or on its own once the timer runs out */
+#[derive(Component)]
+pub struct Coin {
+    pub value: i32,
+    timer: Timer
+}
+
+impl Coin {
+    pub fn new(value: i32) -> Self {
+        return Self { value, timer: Timer::from_seconds(COIN_TTL_SECONDS, TimerMode::Once) };
+    }
+}
+
+/* Paths shorter than this give towers little time, tanky units make it through */
+const SHORT_PATH_LENGTH: f32 = 20.;
+/* Above this much defender damage single units melt, many cheap ones overwhelm */
+const HIGH_DAMAGE_POTENTIAL: f32 = 500.;
+const MAX_SUGGESTED_UNITS: u32 = 30;
+
+pub struct AttackerAdvisor;
+
+impl AttackerAdvisor {
+    /* Wave that fits into `gold`, as amounts per attacker type in the order they were picked */
+    pub fn suggest(field: &TowerField, config: &DefenderConfiguration, stats: &AttackerStats, gold: i32) -> Vec<(AttackerType, u32)> {
+        let path_length = if config.path_length > 0. {
+            config.path_length
+        } else {
+            // The defender has not looked at the field yet
+            a_star(field, field.get_start(), field.get_end()).map_or(0., |path| path.get_size() as f32)
+        };
+        let pattern: &[AttackerType] = if path_length > 0. && path_length < SHORT_PATH_LENGTH {
+            &[AttackerType::Golem]
+        } else if config.estimated_damage_potential > HIGH_DAMAGE_POTENTIAL {
+            &[AttackerType::Spider]
+        } else {
+            &[AttackerType::OrcWarrior, AttackerType::OrcWarrior, AttackerType::Spider]
+        };
+        let mut cheapest = AttackerType::ALL;
+        cheapest.sort_by_key(|attacker_type| stats.get_cost(*attacker_type));
+
+        let mut suggestion: Vec<(AttackerType, u32)> = Vec::new();
+        let mut remaining = gold;
+        let mut total = 0;
+        let mut step = 0;
+        while total < MAX_SUGGESTED_UNITS {
+            let preferred = pattern[step % pattern.len()];
+            step += 1;
+            // Leftover gold goes to whatever is still affordable
+            let affordable = |attacker_type: &AttackerType| {
+                let cost = stats.get_cost(*attacker_type);
+                return cost > 0 && cost <= remaining;
+            };
+            let Some(attacker_type) = Some(preferred).filter(affordable).or_else(|| cheapest.iter().copied().find(affordable)) else {
+                break;
+            };
+            remaining -= stats.get_cost(attacker_type);
+            total += 1;
+            match suggestion.iter_mut().find(|(existing, _)| *existing == attacker_type) {
+                Some((_, amount)) => *amount += 1,
+                None => suggestion.push((attacker_type, 1))
+            }
+        }
+        return suggestion;
+    }
+}
+
+/* Latest AttackerAdvisor suggestion, shown in the side panel */
+#[derive(Resource, Default)]
+pub struct AttackerSuggestion {
+    pub units: Vec<(AttackerType, u32)>
+}
+
+/* What the attacker walks into with the next round, shown in the side panel */
+#[derive(Resource, Default)]
+pub struct DefenderIntel {
+    pub path_length: usize,
+    pub walls: usize,
+    /* Damage per second of all towers reaching the path */
+    pub path_dps: f32,
+    /* Damage the towers deal to something walking the path at 1 pixel/s, divided by the actual speed */
+    exposure: f32,
+}
+
+impl DefenderIntel {
+    /* `towers` are damage per second, attack range and position in slots of every tower */
+    pub fn gather(path: &[Node], towers: impl IntoIterator<Item = (f32, f32, Vec2)>, walls: usize) -> Self {
+        let path_hash: HashSet<Node> = path.iter().copied().collect();
+        let mut intel = Self { path_length: path.len(), walls, path_dps: 0., exposure: 0. };
+        for (dps, attack_range, slot) in towers {
+            let reach = path_nodes_in_reach(&path_hash, slot, attack_range);
+            if reach > 0 {
+                intel.path_dps += dps;
+                intel.exposure += dps * (reach as usize * SLOT_SIZE) as f32;
+            }
+        }
+        return intel;
+    }
+
+    /* Share of the units' total health left after walking past every tower at their average speed, 1 without any units.
+       Only an estimate, it ignores dodging, armor, targeting and how the units are spaced out */
+    pub fn estimate_survival<'a>(&self, units: impl IntoIterator<Item = &'a Attacker>) -> f32 {
+        let units: Vec<&Attacker> = units.into_iter().collect();
+        let health: f32 = units.iter().map(|unit| unit.get_effective_health()).sum();
+        if units.is_empty() || health <= 0. {
+            return 1.;
+        }
+        let damage = self.get_damage_at_speed(average_speed(&units));
+        return ((health - damage) / health).clamp(0., 1.);
+    }
+
+    /* Damage the towers deal to something walking the whole path at `speed` pixels per second */
+    pub fn get_damage_at_speed(&self, speed: f32) -> f32 {
+        return self.exposure / speed.max(1.);
+    }
+}
+
+fn average_speed(units: &[&Attacker]) -> f32 {
+    if units.is_empty() {
+        return 0.;
+    }
+    return units.iter().map(|unit| unit.movement_speed).sum::<f32>() / units.len() as f32;
+}
+
+/* Queued units against the defense, above 1 the defense is expected to be overloaded. Shown in the top panel */
+#[derive(Resource, Default)]
+pub struct WavePrediction {
+    pub difficulty: f32
+}
+
+impl WavePrediction {
+    /* Path lengths are in slots, `base_path_length` is the straight distance from the start to the end */
+    pub fn predict<'a>(units: impl IntoIterator<Item = &'a Attacker>, path_length: f32, base_path_length: f32, damage_potential: f32) -> Self {
+        let path_cost_factor = if base_path_length > 0. { path_length / base_path_length } else { 1. };
+        let predicted_incoming: f32 = units.into_iter().map(|unit| unit.max_health * path_cost_factor).sum();
+        // Without any towers even a single unit overloads the defense
+        return Self { difficulty: predicted_incoming / damage_potential.max(1.) };
+    }
 }
 
 pub struct AttackerController;
 
 impl Plugin for AttackerController {
     fn build(&self, app: &mut App) {
+        let game_config = app.world.get_resource::<GameConfig>().cloned().unwrap_or_default();
         app
-            .insert_resource(AttackerResource {gold: 200, current_bounty: 0})
-            .add_system(listen_to_deaths)
-            .add_system(listen_to_reached_end)
-            .add_system(calculate_round_end_bounty);
+            .insert_resource(AttackerResource {gold: game_config.attacker_starting_gold, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0})
+            .init_resource::<RoundHistory>()
+            .init_resource::<AttackerSuggestion>()
+            .init_resource::<DefenderIntel>()
+            .init_resource::<WavePrediction>()
+            .init_resource::<GoldPickup>()
+            .init_resource::<ComboTracker>()
+            .init_resource::<PersistentComboBonus>()
+            .add_system(track_combos.in_set(GameplaySet))
+            .add_system(pay_combo_bonus.in_set(GameplaySet))
+            .add_system(listen_to_deaths.in_set(GameplaySet))
+            .add_system(collect_coins.in_set(GameplaySet))
+            .add_system(update_suggestion.in_set(GameplaySet))
+            .add_system(update_intel.in_set(GameplaySet))
+            .add_system(predict_wave_difficulty.in_set(GameplaySet).after(update_intel))
+            .add_system(listen_to_reached_end.in_set(GameplaySet))
+            .add_system(listen_to_stolen_gold.in_set(GameplaySet))
+            .add_system(calculate_round_end_bounty.in_set(GameplaySet))
+            .add_system(record_round_result.after(calculate_round_end_bounty).in_set(GameplaySet));
     }
 }
 
 fn listen_to_deaths(
+    mut commands: Commands,
     mut deaths: EventReader<KillEvent>,
-    mut attacker_resource: ResMut<AttackerResource>
+    mut attacker_resource: ResMut<AttackerResource>,
+    pickup: Res<GoldPickup>,
+    textures: Res<TextureResource>
 ) {
     for ev in deaths.iter() {
-        attacker_resource.gold += ev.original_cost / ev.group_size;
+        let refund = ev.original_cost / ev.group_size;
+        if pickup.instant {
+            attacker_resource.earn(refund);
+            continue;
+        }
+        let transform = Transform::from_translation(ev.death_position.extend(20.));
+        let coin = commands.spawn((Coin::new(refund), transform)).id();
+        // The coin atlas is only needed to show it, collecting works without
+        if textures.has_animation("coin", "primary") {
+            let (atlas, animation) = textures.get_animation("coin", "primary");
+            commands.entity(coin).insert(SpriteSheetBundle {
+                sprite: TextureAtlasSprite::new(animation.start),
+                texture_atlas: atlas.clone_weak(),
+                transform,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+fn update_suggestion(
+    mut field_modified: EventReader<FieldModified>,
+    field: Res<TowerField>,
+    config: Res<DefenderConfiguration>,
+    stats: Res<AttackerStats>,
+    attacker_resource: Res<AttackerResource>,
+    mut suggestion: ResMut<AttackerSuggestion>,
+    mut last_gold: Local<Option<i32>>
+) {
+    // Gold changes as well so the suggestion never asks for more than can be afforded.
+    // The resource is borrowed mutably every frame, so the gold itself is compared instead of change detection
+    if field_modified.is_empty() && *last_gold == Some(attacker_resource.gold) {
+        return;
+    }
+    field_modified.clear();
+    *last_gold = Some(attacker_resource.gold);
+    suggestion.units = AttackerAdvisor::suggest(&field, &config, &stats, attacker_resource.gold);
+}
+
+fn update_intel(
+    mut field_modified: EventReader<FieldModified>,
+    field: Res<TowerField>,
+    buildings: Res<BuildingResource>,
+    structures: Query<(&Structure, Option<&Defender>, &Transform)>,
+    mut intel: ResMut<DefenderIntel>,
+    mut is_initialized: Local<bool>
+) {
+    if field_modified.is_empty() && *is_initialized {
+        return;
+    }
+    field_modified.clear();
+    *is_initialized = true;
+    // Searched here instead of taken from DefenderConfiguration, that path is only kept up to date for the computer defender
+    let path = a_star(&field, field.get_start(), field.get_end()).map_or(Vec::new(), |path| path.get_nodes());
+    let walls = structures.iter().filter(|(structure, _, _)| structure.building_type == BuildingType::Wall && structure.blocking).count();
+    let towers = structures.iter().filter_map(|(structure, defender, transform)| {
+        let defender = defender?;
+        return Some((buildings.get_dps(&structure.building_type), defender.attack_range, transform.translation.truncate() / SLOT_SIZE as f32));
+    });
+    *intel = DefenderIntel::gather(&path, towers, walls);
+}
+
+/* Only runs when units were queued or sent out or the defender built something */
+#[allow(clippy::too_many_arguments)]
+fn predict_wave_difficulty(
+    mut field_modified: EventReader<FieldModified>,
+    round: Res<RoundResource>,
+    attackers: Res<AttackerStats>,
+    field: Res<TowerField>,
+    intel: Res<DefenderIntel>,
+    defender_config: Res<DefenderConfiguration>,
+    control: Res<DefenderControl>,
+    mut prediction: ResMut<WavePrediction>,
+    mut queue_length: Local<Option<usize>>
+) {
+    let pending = round.get_pending_units();
+    if *queue_length == Some(pending.len()) && field_modified.is_empty() {
+        return;
+    }
+    field_modified.clear();
+    *queue_length = Some(pending.len());
+    let base_path_length = field.get_start_transform().translation.truncate().distance(field.get_end_transform().translation.truncate()) / SLOT_SIZE as f32;
+    // The start node is counted too, the steps between the nodes are what is walked
+    let path_length = intel.path_length.saturating_sub(1) as f32;
+    let units: Vec<&Attacker> = pending.iter().map(|attacker_type| attackers.get_stats(*attacker_type)).collect();
+    // Only the computer defender keeps its own estimate up to date, a human's towers are judged by the intel
+    let damage_potential = match *control {
+        DefenderControl::Computer => defender_config.estimated_damage_potential,
+        DefenderControl::Human => intel.get_damage_at_speed(average_speed(&units)),
+    };
+    *prediction = WavePrediction::predict(units, path_length, base_path_length, damage_potential);
+}
+
+fn collect_coins(
+    mut commands: Commands,
+    mut coins: Query<(Entity, &mut Coin, &Transform)>,
+    camera_q: Query<&Transform, With<MainCamera>>,
+    mut attacker_resource: ResMut<AttackerResource>,
+    time: Res<Time>
+) {
+    let camera_position = camera_q.get_single().ok().map(|transform| transform.translation.truncate());
+    for (entity, mut coin, transform) in coins.iter_mut() {
+        coin.timer.tick(time.delta());
+        let position = transform.translation.truncate();
+        let passed_over = camera_position.is_some_and(|camera| camera.distance(position) <= COIN_PICKUP_RADIUS);
+        if coin.timer.finished() || passed_over {
+            attacker_resource.earn(coin.value);
+            spawn_floating_text(&mut commands, transform, format!("+{}", coin.value), Color::GOLD);
+            commands.entity(entity).despawn();
+        }
     }
 }
 
@@ -35,15 +396,60 @@ fn listen_to_reached_end(
     mut attacker_resource: ResMut<AttackerResource>
 ) {
     for ev in reached_end.iter() {
-        attacker_resource.gold += ev.bounty;
+        attacker_resource.earn(ev.bounty);
+    }
+}
+
+fn track_combos(
+    mut reached_end: EventReader<EntityReachedEnd>,
+    mut round_end: EventReader<RoundOverEvent>,
+    mut combo: ResMut<ComboTracker>,
+    mut bonus: ResMut<PersistentComboBonus>,
+    time: Res<Time>
+) {
+    combo.timer.tick(time.delta());
+    for _ in reached_end.iter() {
+        combo.count = if combo.timer.finished() { 1 } else { combo.count + 1 };
+        combo.timer.reset();
+        combo.max_this_round = combo.max_this_round.max(combo.count);
+    }
+    if !round_end.is_empty() {
+        round_end.clear();
+        bonus.0 = (combo.max_this_round / COMBO_PER_BONUS_GOLD).min(MAX_COMBO_BONUS) as i32;
+        *combo = ComboTracker::default();
+    }
+}
+
+fn pay_combo_bonus(
+    mut round_start: EventReader<RoundStartEvent>,
+    mut bonus: ResMut<PersistentComboBonus>,
+    mut attacker_resource: ResMut<AttackerResource>
+) {
+    if !round_start.is_empty() {
+        round_start.clear();
+        attacker_resource.earn(bonus.0);
+        bonus.0 = 0;
+    }
+}
+
+fn listen_to_stolen_gold(
+    mut stolen: EventReader<GoldStolenEvent>,
+    mut attacker_resource: ResMut<AttackerResource>
+) {
+    for ev in stolen.iter() {
+        attacker_resource.earn(ev.amount);
     }
 }
 
-fn calculate_round_end_bounty(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn calculate_round_end_bounty(
     mut round_end: EventReader<RoundOverEvent>,
     mut reached_end: EventReader<EntityReachedEnd>,
     mut killed: EventReader<KillEvent>,
     mut attacker_resource: ResMut<AttackerResource>,
+    mut stats: ResMut<RoundStats>,
+    sudden_death: Res<SuddenDeathState>,
+    modifiers: Res<RoundModifiers>,
     mut num_killed: Local<i32>,
     mut num_reached_end: Local<i32>
     
@@ -54,12 +460,251 @@ fn calculate_round_end_bounty(
     for _ in killed.iter() {
         *num_killed += 1;
     }
-    attacker_resource.current_bounty = *num_killed * 2 + *num_reached_end * 10;
+    attacker_resource.current_bounty = modifiers.scale_bounty(*num_killed * 2 * sudden_death.get_kill_bounty_factor() + *num_reached_end * 10);
     if !round_end.is_empty() {
-        attacker_resource.gold += attacker_resource.current_bounty;
+        // The bounty is exactly the pressure put on the defender: 10 per unit reaching the end, 2 per unit killed
+        if attacker_resource.gold_spent_this_round > 0 {
+            stats.gold_efficiency = attacker_resource.current_bounty as f32 / attacker_resource.gold_spent_this_round as f32;
+            stats.best_gold_efficiency = stats.best_gold_efficiency.max(stats.gold_efficiency);
+        } else {
+            stats.gold_efficiency = 0.;
+        }
+        attacker_resource.gold_spent_this_round = 0;
+        let bounty = attacker_resource.current_bounty;
+        attacker_resource.earn(bounty);
+        stats.gold_earned = attacker_resource.gold_earned_this_round;
+        attacker_resource.gold_earned_this_round = 0;
         attacker_resource.current_bounty = 0;
         *num_killed = 0;
         *num_reached_end = 0;
         round_end.clear();
     }
-}
\ No newline at end of file
+}
+
+pub fn record_round_result(
+    mut round_end: EventReader<RoundOverEvent>,
+    stats: Res<RoundStats>,
+    defender_config: Res<DefenderConfiguration>,
+    mut history: ResMut<RoundHistory>,
+    mut kills_before: Local<i32>
+) {
+    for _ in round_end.iter() {
+        // RoundStats counts kills over the whole session
+        let kills = stats.num_killed - *kills_before;
+        *kills_before = stats.num_killed;
+        let round = history.rounds.len() as u32 + 1;
+        history.rounds.push(RoundSummary {
+            round,
+            kills,
+            breakthroughs: stats.num_reached_end,
+            gold_earned: stats.gold_earned,
+            path_length: defender_config.path_length as usize,
+            duration: stats.round_duration
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::prelude::{Schedule, World};
+
+    use crate::{tests::{schedule_of, system_test_world}, world::attackers::{LeakEffect, ORC_WARRIOR_STATS}};
+
+    use super::*;
+
+    fn coin_world(start: Instant) -> (World, Schedule) {
+        let mut world = system_test_world();
+        world.insert_resource(AttackerResource { gold: 0, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 });
+        let mut time = Time::default();
+        time.update_with_instant(start);
+        world.insert_resource(time);
+        world.spawn((Coin::new(7), Transform::from_xyz(500., 500., 20.)));
+        let mut schedule = schedule_of(collect_coins);
+        return (world, schedule);
+    }
+
+    #[test]
+    fn longer_paths_and_weaker_defenses_raise_the_wave_difficulty() {
+        let units = [ORC_WARRIOR_STATS, ORC_WARRIOR_STATS];
+        let direct = WavePrediction::predict(&units, 10., 10., 560.);
+        assert!((direct.difficulty - 0.5).abs() < 1e-4);
+        let detour = WavePrediction::predict(&units, 20., 10., 560.);
+        assert!((detour.difficulty - 1.).abs() < 1e-4);
+        assert!(WavePrediction::predict(&units, 10., 10., 0.).difficulty > 1.);
+        assert_eq!(WavePrediction::predict(std::iter::empty(), 10., 10., 560.).difficulty, 0.);
+    }
+
+    #[test]
+    fn uncollected_coin_credits_after_ttl() {
+        let start = Instant::now();
+        let (mut world, mut schedule) = coin_world(start);
+        world.spawn((MainCamera, Transform::default()));
+
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_secs(1));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<AttackerResource>().gold, 0);
+
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_secs_f32(COIN_TTL_SECONDS + 1.));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<AttackerResource>().gold, 7);
+        assert_eq!(world.query::<&Coin>().iter(&world).count(), 0);
+    }
+
+    #[test]
+    fn coin_under_the_camera_credits_immediately() {
+        let start = Instant::now();
+        let (mut world, mut schedule) = coin_world(start);
+        world.spawn((MainCamera, Transform::from_xyz(510., 490., 999.)));
+
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_millis(16));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<AttackerResource>().gold, 7);
+    }
+
+    fn advisor_field() -> TowerField {
+        return TowerField::new(16, 16, bevy::prelude::Vec2::ZERO, crate::world::path_finding::Node::new(2, 0), crate::world::path_finding::Node::new(14, 15));
+    }
+
+    fn total_cost(suggestion: &[(AttackerType, u32)], stats: &AttackerStats) -> i32 {
+        return suggestion.iter().map(|(attacker_type, amount)| stats.get_cost(*attacker_type) * *amount as i32).sum();
+    }
+
+    #[test]
+    fn advisor_prefers_golems_on_short_paths() {
+        let stats = AttackerStats::default();
+        let mut config = DefenderConfiguration::default();
+        config.path_length = 12.;
+        let suggestion = AttackerAdvisor::suggest(&advisor_field(), &config, &stats, 400);
+        assert_eq!(suggestion[0], (AttackerType::Golem, 2));
+        assert!(total_cost(&suggestion, &stats) <= 400);
+    }
+
+    #[test]
+    fn advisor_prefers_spiders_against_strong_defenses() {
+        let stats = AttackerStats::default();
+        let mut config = DefenderConfiguration::default();
+        config.path_length = 40.;
+        config.estimated_damage_potential = 800.;
+        let suggestion = AttackerAdvisor::suggest(&advisor_field(), &config, &stats, 300);
+        assert_eq!(suggestion, vec![(AttackerType::Spider, 5)]);
+    }
+
+    #[test]
+    fn advisor_mixes_orcs_and_stays_within_gold() {
+        let stats = AttackerStats::default();
+        let config = DefenderConfiguration::default();
+        let suggestion = AttackerAdvisor::suggest(&advisor_field(), &config, &stats, 250);
+        assert_eq!(suggestion[0].0, AttackerType::OrcWarrior);
+        assert!(suggestion.iter().any(|(attacker_type, _)| *attacker_type == AttackerType::Spider));
+        let cost = total_cost(&suggestion, &stats);
+        assert!(cost <= 250 && 250 - cost < stats.get_cost(AttackerType::OrcWarrior));
+        assert!(AttackerAdvisor::suggest(&advisor_field(), &config, &stats, 10).is_empty());
+    }
+
+    #[test]
+    fn the_suggestion_waits_for_new_gold_or_a_changed_field() {
+        let mut world = system_test_world();
+        world.insert_resource(advisor_field());
+        world.init_resource::<DefenderConfiguration>();
+        world.init_resource::<AttackerStats>();
+        world.insert_resource(AttackerResource { gold: 100, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 });
+        world.init_resource::<AttackerSuggestion>();
+        let mut schedule = schedule_of(update_suggestion);
+        schedule.run(&mut world);
+
+        // A stale stand-in survives as long as neither gold nor the field change, even if the resource was borrowed mutably
+        let stale = vec![(AttackerType::Golem, 99)];
+        world.resource_mut::<AttackerSuggestion>().units = stale.clone();
+        world.resource_mut::<AttackerResource>().gold += 0;
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<AttackerSuggestion>().units, stale);
+
+        world.resource_mut::<AttackerResource>().gold += 50;
+        schedule.run(&mut world);
+        assert_ne!(world.resource::<AttackerSuggestion>().units, stale);
+
+        world.resource_mut::<AttackerSuggestion>().units = stale.clone();
+        world.send_event(FieldModified { nodes: vec![crate::world::path_finding::Node::new(5, 5)] });
+        schedule.run(&mut world);
+        assert_ne!(world.resource::<AttackerSuggestion>().units, stale);
+    }
+
+    #[test]
+    fn intel_counts_only_towers_reaching_the_path() {
+        let stats = AttackerStats::default();
+        let path: Vec<Node> = (0..10).map(|y| Node::new(2, y)).collect();
+        // One tower right next to the path, one far off in the corner
+        let intel = DefenderIntel::gather(&path, [(20., 64., Vec2::new(3., 4.)), (50., 64., Vec2::new(14., 14.))], 3);
+        assert_eq!(intel.path_length, 10);
+        assert_eq!(intel.walls, 3);
+        assert_eq!(intel.path_dps, 20.);
+
+        let orcs = [stats.get_stats(AttackerType::OrcWarrior); 2];
+        let golems = [stats.get_stats(AttackerType::Golem); 2];
+        assert_eq!(DefenderIntel::gather(&path, [], 0).estimate_survival(orcs.iter().copied()), 1.);
+        assert_eq!(intel.estimate_survival([]), 1.);
+        let survival = intel.estimate_survival(orcs.iter().copied());
+        assert!(survival < 1.);
+        assert!(intel.estimate_survival(golems.iter().copied()) > survival);
+    }
+
+    #[test]
+    fn round_end_reports_everything_earned_during_the_round() {
+        let mut world = system_test_world();
+        let mut resource = AttackerResource { gold: 0, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 };
+        resource.spend(20);
+        resource.earn(5);
+        world.insert_resource(resource);
+        world.init_resource::<RoundStats>();
+        world.init_resource::<SuddenDeathState>();
+        world.init_resource::<RoundModifiers>();
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        world.send_event(RoundOverEvent);
+        let mut schedule = schedule_of(calculate_round_end_bounty);
+        schedule.run(&mut world);
+
+        // 5 from the refund and 10 round end bounty for the one that reached the end
+        assert_eq!(world.resource::<RoundStats>().gold_earned, 15);
+        let resource = world.resource::<AttackerResource>();
+        assert_eq!(resource.gold, -5);
+        assert_eq!(resource.gold_earned_this_round, 0);
+    }
+
+    #[test]
+    fn best_combo_pays_out_at_the_next_round_start() {
+        let mut world = system_test_world();
+        let start = Instant::now();
+        let mut time = Time::default();
+        time.update_with_instant(start);
+        world.insert_resource(time);
+        world.insert_resource(AttackerResource { gold: 0, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 });
+        world.init_resource::<ComboTracker>();
+        world.init_resource::<PersistentComboBonus>();
+        let mut schedule = schedule_of(track_combos);
+        schedule.add_system(pay_combo_bonus.after(track_combos));
+
+        for _ in 0..12 {
+            world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        }
+        schedule.run(&mut world);
+        // A leak after the window starts a new combo
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_secs_f32(COMBO_WINDOW_SECONDS + 1.));
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        schedule.run(&mut world);
+        let combo = world.resource::<ComboTracker>();
+        assert_eq!((combo.count, combo.max_this_round), (1, 12));
+
+        world.send_event(RoundOverEvent);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PersistentComboBonus>().0, 2);
+        assert_eq!(world.resource::<ComboTracker>().max_this_round, 0);
+        assert_eq!(world.resource::<AttackerResource>().gold, 0);
+
+        world.send_event(RoundStartEvent);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<AttackerResource>().gold, 2);
+        assert_eq!(world.resource::<PersistentComboBonus>().0, 0);
+    }
+}
@@ -1,38 +1,222 @@
+use std::time::Duration;
+
 use bevy::{
     prelude::{
-        App, Bundle, Commands, Component, Deref, DerefMut, Entity, EventReader, EventWriter, Local,
-        Plugin, Query, Res, ResMut, Resource, Timer, Transform, Vec2, With, Without,
+        Added, App, DetectChanges, Bundle, Color, Commands, Component, Deref, DerefMut, Entity, EventReader, EventWriter,
+        Plugin, Query, Res, ResMut, Resource, Timer, Transform, Vec2, With, Without, IntoSystemConfig, IntoSystemAppConfig, CoreSchedule,
     },
     sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
-    time::{Time, TimerMode},
+    time::{fixed_timestep::FixedTime, Time, TimerMode},
     utils::HashMap,
 };
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    textures::TextureResource,
+    error::GameplaySet,
+    particle::Footstep,
+    textures::{AnimationTiming, TextureResource},
     util::{LocalTimer, RepeatingLocalTimer},
 };
 
 use super::{
-    events::{EntityReachedEnd, FieldModified},
-    path_finding::{a_star, Path},
-    towers::{TowerField, SLOT_SIZE},
+    events::{BlinkEvent, DamageEvent, EntityReachedEnd, FieldModified, SlowEvent},
+    interpolation::FixedMovementSet,
+    mutators::RoundModifiers,
+    path_finding::{a_star, get_successors, FlowField, Path, PathCache, PathSmoothing},
+    towers::{DamageConsumerSet, DamageType, TowerField, SLOT_SIZE},
 };
 
 #[derive(Component, Clone, Copy)]
 pub struct Attacker {
     pub health: f32,
     pub max_health: f32,
+    /* Soaks up damage before health does and doesn't come back */
+    pub shield: f32,
     pub movement_speed: f32,
     pub velocity: Vec2,
     pub size: Vec2,
     pub bounty: i32,
     pub original_cost: i32,
     pub num_summoned: i32,
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
+    pub dodge_chance: f32,
+    pub armor_class: ArmorClass,
+    /* Hits of these types deal no damage at all, a static slice keeps the stats Copy and usable in consts */
+    pub immunities: &'static [DamageType],
+    pub leak_effect: LeakEffect,
+    pub walk_animation: WalkAnimation,
+    /* Shoots down defender projectiles, only ever set by the Golem's intercept upgrade */
+    pub intercepts: bool,
+}
+
+impl Attacker {
+    pub fn is_immune_to(&self, damage_type: DamageType) -> bool {
+        return self.immunities.contains(&damage_type);
+    }
+
+    /* What it takes to kill the attacker */
+    pub fn get_effective_health(&self) -> f32 {
+        return self.health + self.shield;
+    }
+
+    /* Takes `damage` off the shield first and the rest off health, returns how much the shield absorbed */
+    pub fn take_damage(&mut self, damage: f32) -> f32 {
+        let absorbed = damage.clamp(0., self.shield);
+        self.shield -= absorbed;
+        self.health -= damage - absorbed;
+        return absorbed;
+    }
+}
+
+/* Frame timing of the walk cycle, frames without an override in the texture metadata are shown for frame_time */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WalkAnimation {
+    pub frame_time: f32,
+    /* How much the animation follows the actual speed, 0 always plays at frame_time and 1 doubles the rate at twice reference_speed */
+    pub speed_coupling: f32,
+    /* Speed the frame time was drawn for, upgrades don't change it */
+    pub reference_speed: f32,
+}
+
+impl WalkAnimation {
+    /* Factor on the animation time at `speed`, standing units play their idle animation at the normal rate */
+    pub fn get_rate(&self, speed: f32) -> f32 {
+        if speed <= 0. || self.reference_speed <= 0. {
+            return 1.;
+        }
+        return (1. + self.speed_coupling * (speed / self.reference_speed - 1.)).max(0.1);
+    }
+}
+
+/* What happens besides the lost life when an attacker reaches the end */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LeakEffect {
+    None,
+    /* Takes up to this much gold from the defender and hands it to the attacker */
+    StealGold(i32),
+}
+
+/* Decides how much of each damage type gets through, see DamageMatrix */
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Deserialize, Serialize)]
+pub enum ArmorClass {
+    Unarmored,
+    Light,
+    Heavy,
+    Mechanical,
+    Ethereal,
+}
+
+impl ArmorClass {
+    pub const ALL: [ArmorClass; 5] = [ArmorClass::Unarmored, ArmorClass::Light, ArmorClass::Heavy, ArmorClass::Mechanical, ArmorClass::Ethereal];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            ArmorClass::Unarmored => "Unarmored",
+            ArmorClass::Light => "Light",
+            ArmorClass::Heavy => "Heavy",
+            ArmorClass::Mechanical => "Mechanical",
+            ArmorClass::Ethereal => "Ethereal",
+        };
+    }
+}
+
+/* Multiplies movement speed until the timer runs out, kept alive by whatever keeps sending SlowEvents */
+#[derive(Component)]
+pub struct SlowEffect {
+    pub factor: f32,
+    pub timer: Timer,
+}
+
+/* Jumps up to `distance` nodes ahead on the path whenever the cooldown runs out, only over free slots */
+#[derive(Component)]
+pub struct Blink {
+    pub cooldown: Timer,
+    pub distance: usize,
+}
+
+pub const GOLEM_INTERCEPT_RANGE: f32 = 64.;
+pub const GOLEM_INTERCEPT_COOLDOWN: f32 = 4.;
+
+/* Shoots down the nearest defender projectile in range whenever the cooldown runs out */
+#[derive(Component)]
+pub struct Interceptor {
+    pub range: f32,
+    pub cooldown: Timer,
+}
+
+impl Interceptor {
+    pub fn new(range: f32, cooldown_secs: f32) -> Self {
+        // Ready right away, the first shot is blocked as soon as one comes close
+        let mut cooldown = Timer::from_seconds(cooldown_secs, TimerMode::Once);
+        cooldown.tick(Duration::from_secs_f32(cooldown_secs));
+        return Self { range, cooldown };
+    }
+}
+
+/* Killed attackers keep their sprite until the death animation played, everything gameplay related is already stripped */
+#[derive(Component)]
+pub struct Dying;
+
+/* Used instead of despawning on a kill, so targeting and the round end check stop seeing the attacker right away */
+pub fn kill_attacker(commands: &mut Commands, entity: Entity) {
+    commands
+        .entity(entity)
+        .remove::<(Attacker, Path, SlowEffect, Footstep, HitFlash)>()
+        .insert(Dying);
+}
+
+const HIT_FLASH_SECONDS: f32 = 0.15;
+const LOW_HEALTH_FRACTION: f32 = 0.25;
+
+/* The color an attacker has without any feedback applied, hit and health feedback is multiplied on top */
+#[derive(Component, Clone, Copy)]
+pub struct AttackerTint(pub Color);
+
+impl Default for AttackerTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
 }
 
+#[derive(Component)]
+pub struct HitFlash {
+    timer: Timer,
+}
+
+/* flash_t goes from 1 right after a hit down to 0, below a quarter of max health attackers stay slightly red */
+pub fn attacker_color(base: Color, flash_t: f32, health_fraction: f32) -> Color {
+    let [r, g, b, a] = base.as_rgba_f32();
+    let health_tint = if health_fraction < LOW_HEALTH_FRACTION { 0.7 } else { 1. };
+    let flash_t = flash_t.clamp(0., 1.);
+    // Sprite colors only multiply the texture, so the flash darkens green and blue instead of brightening red
+    let flash_tint = 1. - 0.75 * flash_t;
+    return Color::rgba(r, g * health_tint * flash_tint, b * health_tint * flash_tint, a);
+}
+
+/* Attackers spawned together share their path computations, the id is the first entity of the spawn */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GroupId(pub Entity);
+
+/* Index into TowerField::get_starts() of the start the attacker entered from, it goes back there after a leak */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StartPoint(pub usize);
+
+/* Number of path searches run for attackers, mostly interesting for benchmarks and tests */
+#[derive(Resource, Default)]
+pub struct PathfindingStats {
+    pub computations: usize,
+}
+
+/* Steers by the shared FlowField instead of a Path of its own */
+#[derive(Component)]
+pub struct UseFlowField;
+
+/* A flow field user that ended up on a blocked slot, it gets an A* path out of there instead */
+#[derive(Component)]
+pub struct FlowFieldFallback;
+
 #[derive(Component)]
 pub struct Flying;
 #[derive(Component)]
@@ -41,29 +225,103 @@ pub struct Grounded;
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
-#[derive(Clone, Copy, Deserialize, Serialize, Component)]
+#[derive(Clone, Copy, Deserialize, Serialize, Component, PartialEq, Debug)]
 pub struct AnimationIndices {
     pub start: usize,
     pub end: usize,
+    #[serde(default)]
+    pub mode: AnimationMode,
 }
 
 impl Default for AnimationIndices {
     fn default() -> Self {
-        Self { start: 0, end: 0 }
+        Self { start: 0, end: 0, mode: AnimationMode::Loop }
     }
 }
 
 impl AnimationIndices {
     pub fn new(start: usize, end: usize) -> Self {
-        return Self { start, end };
+        return Self { start, end, mode: AnimationMode::Loop };
+    }
+    pub fn with_mode(&self, mode: AnimationMode) -> Self {
+        return Self { mode, ..*self };
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Debug, Default)]
+pub enum AnimationMode {
+    #[default]
+    Loop,
+    /* Stops on the last frame */
+    Once,
+    OnceThenDespawn,
+    PingPong,
+}
+
+/* Which way a ping-pong animation is currently playing */
+#[derive(Component, Default)]
+pub struct AnimationPlayback {
+    pub reversing: bool,
+}
+
+pub enum AnimationStep {
+    Frame(usize),
+    Despawn,
+}
+
+/* Shared frame advancement for everything animated, so every mode behaves the same on attackers, particles and projectiles */
+pub fn advance_animation(animation: &AnimationIndices, index: usize, playback: &mut AnimationPlayback) -> AnimationStep {
+    if index > animation.end || index < animation.start {
+        playback.reversing = false;
+        return AnimationStep::Frame(animation.start);
+    }
+    return match animation.mode {
+        AnimationMode::Loop => AnimationStep::Frame(if index >= animation.end { animation.start } else { index + 1 }),
+        AnimationMode::Once => AnimationStep::Frame((index + 1).min(animation.end)),
+        AnimationMode::OnceThenDespawn => {
+            if index >= animation.end {
+                AnimationStep::Despawn
+            } else {
+                AnimationStep::Frame(index + 1)
+            }
+        }
+        AnimationMode::PingPong => {
+            if animation.start == animation.end {
+                return AnimationStep::Frame(animation.start);
+            }
+            if playback.reversing && index <= animation.start {
+                playback.reversing = false;
+            } else if !playback.reversing && index >= animation.end {
+                playback.reversing = true;
+            }
+            AnimationStep::Frame(if playback.reversing { index - 1 } else { index + 1 })
+        }
+    };
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Deserialize, Serialize)]
 pub enum UpgradeType {
     Speed,
     Health,
     Amount,
+    Critical,
+    /* Bought once, see Interceptor */
+    Intercept,
+}
+
+impl UpgradeType {
+    /* In the order the upgrade buttons are shown */
+    pub const ALL: [UpgradeType; 5] = [UpgradeType::Health, UpgradeType::Speed, UpgradeType::Amount, UpgradeType::Critical, UpgradeType::Intercept];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            UpgradeType::Speed => "Speed",
+            UpgradeType::Health => "Health",
+            UpgradeType::Amount => "Amount",
+            UpgradeType::Critical => "Critical",
+            UpgradeType::Intercept => "Intercept"
+        };
+    }
 }
 
 
@@ -101,10 +359,15 @@ pub enum UpgradeEffectType {
 #[derive(Resource)]
 pub struct AttackerStats {
     stats: HashMap<AttackerType, Attacker>,
-    upgrade_map: HashMap<(AttackerType, UpgradeType), UpgradeInfo>
+    upgrade_map: HashMap<(AttackerType, UpgradeType), UpgradeInfo>,
+    /* Upgrades bought this game, of any unit */
+    upgrades_bought: HashMap<UpgradeType, u32>
 }
 
 impl AttackerStats {
+    pub fn get_upgrades_bought(&self) -> &HashMap<UpgradeType, u32> {
+        return &self.upgrades_bought;
+    }
     pub fn get_stats(&self, attacker_type: AttackerType) -> &Attacker {
         return self.stats.get(&attacker_type).unwrap();
     }
@@ -117,10 +380,15 @@ impl AttackerStats {
     pub fn get_upgrade_cost(&self, attacker_type: AttackerType, upgrade: UpgradeType) -> i32 {
         return self.get_upgrade(attacker_type, upgrade).cost;
     }
+    /* Upgrades the unit can still buy, not every unit has every upgrade and one-off upgrades are gone once bought */
+    pub fn get_upgrades(&self, attacker_type: AttackerType) -> Vec<UpgradeType> {
+        return UpgradeType::ALL.into_iter().filter(|upgrade| self.upgrade_map.contains_key(&(attacker_type, *upgrade))).collect();
+    }
     pub fn apply_upgrade(&mut self, attacker_type: AttackerType, upgrade: UpgradeType) {
         let stats = self.stats.get_mut(&attacker_type).unwrap();
         let upgrade_info = self.upgrade_map.get_mut(&(attacker_type, upgrade)).unwrap();
         upgrade_info.cost = (upgrade_info.cost as f32 * 1.3).round() as i32;
+        *self.upgrades_bought.entry(upgrade).or_default() += 1;
         match upgrade {
             UpgradeType::Amount => {
                 stats.num_summoned = upgrade_info.apply_value(stats.num_summoned);
@@ -132,6 +400,15 @@ impl AttackerStats {
                 stats.max_health = upgrade_info.apply_value_f32(stats.max_health);
                 stats.health = upgrade_info.apply_value_f32(stats.health);
             },
+            UpgradeType::Critical => {
+                stats.crit_chance = upgrade_info.apply_value_f32(stats.crit_chance).min(1.);
+            },
+            UpgradeType::Intercept => {
+                stats.intercepts = true;
+            },
+        }
+        if upgrade == UpgradeType::Intercept {
+            self.upgrade_map.remove(&(attacker_type, upgrade));
         }
     }
 
@@ -145,20 +422,36 @@ impl Default for AttackerStats {
         stats.insert(AttackerType::OrcWarrior, ORC_WARRIOR_STATS.clone());
         stats.insert(AttackerType::Spider, SPIDER_STATS.clone());
         stats.insert(AttackerType::Golem, GOLEM_STATS.clone());
+        stats.insert(AttackerType::Thief, THIEF_STATS);
+        stats.insert(AttackerType::Phantom, PHANTOM_STATS);
         
         upgrade_map.insert((AttackerType::OrcWarrior, UpgradeType::Amount), UpgradeInfo { effect: 1., cost: 200, effect_type: UpgradeEffectType::Flat, description: "Increase spawn amount by 1".to_string() } );
         upgrade_map.insert((AttackerType::Spider, UpgradeType::Amount), UpgradeInfo { effect: 1., cost: 150, effect_type: UpgradeEffectType::Flat, description: "Increase spawn amount by 1".to_string() } );
         upgrade_map.insert((AttackerType::Golem, UpgradeType::Amount), UpgradeInfo { effect: 1., cost: 300, effect_type: UpgradeEffectType::Flat, description: "Increase spawn amount by 1".to_string() } );
+        upgrade_map.insert((AttackerType::Thief, UpgradeType::Amount), UpgradeInfo { effect: 1., cost: 180, effect_type: UpgradeEffectType::Flat, description: "Increase spawn amount by 1".to_string() } );
+        upgrade_map.insert((AttackerType::Phantom, UpgradeType::Amount), UpgradeInfo { effect: 1., cost: 250, effect_type: UpgradeEffectType::Flat, description: "Increase spawn amount by 1".to_string() } );
         
         upgrade_map.insert((AttackerType::OrcWarrior, UpgradeType::Health), UpgradeInfo { effect: 1.2, cost: 120, effect_type: UpgradeEffectType::Factor, description: "Increase health by 10%".to_string() } );
         upgrade_map.insert((AttackerType::Spider, UpgradeType::Health), UpgradeInfo { effect: 1.2, cost: 150, effect_type: UpgradeEffectType::Factor, description: "Increase health by 20%".to_string() });
         upgrade_map.insert((AttackerType::Golem, UpgradeType::Health), UpgradeInfo { effect: 1.1, cost: 110, effect_type: UpgradeEffectType::Factor, description: "Increase health by 10%".to_string() });
+        upgrade_map.insert((AttackerType::Thief, UpgradeType::Health), UpgradeInfo { effect: 1.2, cost: 120, effect_type: UpgradeEffectType::Factor, description: "Increase health by 20%".to_string() });
+        upgrade_map.insert((AttackerType::Phantom, UpgradeType::Health), UpgradeInfo { effect: 1.2, cost: 140, effect_type: UpgradeEffectType::Factor, description: "Increase health by 20%".to_string() });
         
         upgrade_map.insert((AttackerType::OrcWarrior, UpgradeType::Speed), UpgradeInfo { effect: 1.2, cost: 100, effect_type: UpgradeEffectType::Factor, description: "Increase speed by 20%".to_string() });
         upgrade_map.insert((AttackerType::Spider, UpgradeType::Speed), UpgradeInfo { effect: 1.2, cost: 200, effect_type: UpgradeEffectType::Factor, description: "Increase speed by 20%".to_string() } );
         upgrade_map.insert((AttackerType::Golem, UpgradeType::Speed), UpgradeInfo { effect: 1.2, cost: 100, effect_type: UpgradeEffectType::Factor, description: "Increase speed by 20%".to_string() } );
+        upgrade_map.insert((AttackerType::Thief, UpgradeType::Speed), UpgradeInfo { effect: 1.2, cost: 160, effect_type: UpgradeEffectType::Factor, description: "Increase speed by 20%".to_string() } );
+        upgrade_map.insert((AttackerType::Phantom, UpgradeType::Speed), UpgradeInfo { effect: 1.2, cost: 120, effect_type: UpgradeEffectType::Factor, description: "Increase speed by 20%".to_string() } );
+
+        upgrade_map.insert((AttackerType::OrcWarrior, UpgradeType::Critical), UpgradeInfo { effect: 0.05, cost: 120, effect_type: UpgradeEffectType::Flat, description: "Increase critical hit chance by 5%".to_string() });
+        upgrade_map.insert((AttackerType::Spider, UpgradeType::Critical), UpgradeInfo { effect: 0.05, cost: 150, effect_type: UpgradeEffectType::Flat, description: "Increase critical hit chance by 5%".to_string() } );
+        upgrade_map.insert((AttackerType::Golem, UpgradeType::Critical), UpgradeInfo { effect: 0.05, cost: 100, effect_type: UpgradeEffectType::Flat, description: "Increase critical hit chance by 5%".to_string() } );
+        upgrade_map.insert((AttackerType::Thief, UpgradeType::Critical), UpgradeInfo { effect: 0.05, cost: 120, effect_type: UpgradeEffectType::Flat, description: "Increase critical hit chance by 5%".to_string() } );
+        upgrade_map.insert((AttackerType::Phantom, UpgradeType::Critical), UpgradeInfo { effect: 0.05, cost: 120, effect_type: UpgradeEffectType::Flat, description: "Increase critical hit chance by 5%".to_string() } );
+
+        upgrade_map.insert((AttackerType::Golem, UpgradeType::Intercept), UpgradeInfo { effect: 1., cost: 250, effect_type: UpgradeEffectType::Flat, description: "Shoot down incoming projectiles".to_string() });
 
-        return Self { stats: stats, upgrade_map: upgrade_map };
+        return Self { stats, upgrade_map, upgrades_bought: HashMap::new() };
     }
 }
 
@@ -169,6 +462,8 @@ pub struct Animations {
     left: AnimationIndices,
     right: AnimationIndices,
     idle: AnimationIndices,
+    timing: AnimationTiming,
+    death: Option<(AnimationIndices, AnimationTiming)>,
 }
 
 impl Animations {
@@ -193,60 +488,208 @@ impl Animations {
     }
 }
 
+/* Death animations are optional, attackers without one disappear on the spot like before */
+fn get_death_animation(textures: &TextureResource, atlas: &str, animation: &str) -> Option<(AnimationIndices, AnimationTiming)> {
+    if !textures.has_animation(atlas, animation) {
+        return None;
+    }
+    let indices = textures.get_animation(atlas, animation).1.with_mode(AnimationMode::Once);
+    return Some((indices, textures.get_animation_timing(atlas, animation, 0.1)));
+}
+
 pub struct AttackersPlugin;
 
 impl Plugin for AttackersPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<AttackerStats>()
-            .add_system(update_animations)
-            .add_system(set_initial_pathfinding)
-            .add_system(update_path_finding)
-            .add_system(update_positions)
-            .add_system(set_updated_pathfinding)
-            .add_system(check_reached_end)
-            /*.add_system(spawn_entities) */;
+            .init_resource::<PathSmoothing>()
+            .init_resource::<PathfindingStats>()
+            .init_resource::<FlowField>()
+            .init_resource::<PathCache>()
+            .add_system(update_animations.in_set(GameplaySet))
+            .add_system(start_hit_flashes.in_set(GameplaySet).in_set(DamageConsumerSet))
+            .add_system(update_attacker_tints.in_set(GameplaySet).after(start_hit_flashes))
+            .add_system(start_death_animations.in_set(GameplaySet))
+            .add_system(update_death_animations.in_set(GameplaySet).after(start_death_animations))
+            .add_system(update_flow_field.in_set(GameplaySet).before(set_initial_pathfinding).before(update_path_finding))
+            .add_system(invalidate_path_cache.in_set(GameplaySet).before(set_initial_pathfinding))
+            .add_system(set_initial_pathfinding.in_set(GameplaySet))
+            .add_system(update_path_finding.in_set(GameplaySet))
+            .add_system(update_positions.in_set(FixedMovementSet).in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(set_updated_pathfinding.in_set(GameplaySet))
+            .add_system(check_reached_end.in_set(GameplaySet))
+            .add_system(apply_slow_events.in_set(GameplaySet))
+            .add_system(tick_slow_effects.in_set(GameplaySet))
+            .add_system(blink_attackers.in_set(GameplaySet).after(update_path_finding))
+            .add_system(equip_interceptors.in_set(GameplaySet))
+            /*.add_system(spawn_entities.in_set(GameplaySet)) */;
     }
 }
 
 fn update_animations(
+    mut commands: Commands,
     mut query: Query<(
+        Entity,
         &Attacker,
         &Animations,
         &mut AnimationTimer,
+        &mut AnimationPlayback,
         &mut TextureAtlasSprite,
     )>,
     time: Res<Time>,
 ) {
-    for (attacker, animations, mut timer, mut sprite) in query.iter_mut() {
-        timer.tick(time.delta());
+    for (entity, attacker, animations, mut timer, mut playback, mut sprite) in query.iter_mut() {
+        timer.tick(time.delta().mul_f32(attacker.walk_animation.get_rate(attacker.velocity.length())));
         if timer.just_finished() {
-            let index = sprite.index;
             let animation = animations.get_animation(attacker.velocity);
-            if index > animation.end || index < animation.start {
-                sprite.index = animation.start;
+            match advance_animation(animation, sprite.index, &mut playback) {
+                AnimationStep::Frame(index) => {
+                    sprite.index = index;
+                    timer.set_duration(animations.timing.get_frame_time(sprite.index - animation.start));
+                }
+                AnimationStep::Despawn => {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+    }
+}
+
+fn start_hit_flashes(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    attackers: Query<(), With<Attacker>>,
+) {
+    for ev in damage_events.iter() {
+        // Damage can target anything in theory, only attacker sprites get the feedback
+        if attackers.contains(ev.target) {
+            commands.entity(ev.target).insert(HitFlash {
+                timer: Timer::from_seconds(HIT_FLASH_SECONDS, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn update_attacker_tints(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Attacker, &AttackerTint, Option<&mut HitFlash>, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (entity, attacker, tint, flash, mut sprite) in query.iter_mut() {
+        let mut flash_t = 0.;
+        if let Some(mut flash) = flash {
+            flash.timer.tick(time.delta());
+            if flash.timer.finished() {
+                commands.entity(entity).remove::<HitFlash>();
             } else {
-                sprite.index = if sprite.index >= animation.end {
-                    animation.start
-                } else {
-                    sprite.index + 1
+                flash_t = 1. - flash.timer.percent();
+            }
+        }
+        sprite.color = attacker_color(tint.0, flash_t, attacker.health / attacker.max_health);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn start_death_animations(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Animations, &AttackerTint, &mut AnimationTimer, &mut AnimationPlayback, &mut TextureAtlasSprite), Added<Dying>>,
+) {
+    for (entity, animations, tint, mut timer, mut playback, mut sprite) in query.iter_mut() {
+        sprite.color = tint.0;
+        match &animations.death {
+            Some((animation, timing)) => {
+                sprite.index = animation.start;
+                playback.reversing = false;
+                timer.set_duration(timing.get_frame_time(0));
+                timer.reset();
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+fn update_death_animations(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Animations, &mut AnimationTimer, &mut AnimationPlayback, &mut TextureAtlasSprite), With<Dying>>,
+    time: Res<Time>,
+) {
+    for (entity, animations, mut timer, mut playback, mut sprite) in query.iter_mut() {
+        let Some((animation, timing)) = &animations.death else {
+            continue;
+        };
+        timer.tick(time.delta());
+        if timer.just_finished() {
+            // Once holds the last frame forever, so the corpse goes after that frame was shown for its full time
+            if sprite.index >= animation.end {
+                commands.entity(entity).despawn();
+                continue;
+            }
+            match advance_animation(animation, sprite.index, &mut playback) {
+                AnimationStep::Frame(index) => {
+                    sprite.index = index;
+                    timer.set_duration(timing.get_frame_time(index - animation.start));
                 }
+                AnimationStep::Despawn => commands.entity(entity).despawn(),
             }
         }
     }
 }
 
-fn set_initial_pathfinding(
+pub(crate) fn update_flow_field(mut flow: ResMut<FlowField>, field: Res<TowerField>) {
+    if field.is_changed() {
+        flow.rebuild(&field);
+    }
+}
+
+pub(crate) fn invalidate_path_cache(mut cache: ResMut<PathCache>, mut field_modified: EventReader<FieldModified>) {
+    if !field_modified.is_empty() {
+        field_modified.clear();
+        cache.invalidate();
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn set_initial_pathfinding(
     mut commands: Commands,
-    query: Query<Entity, (Without<Flying>, Without<Path>, With<Attacker>)>,
+    query: Query<(Entity, Option<&StartPoint>), (Without<Flying>, Without<Path>, Without<UseFlowField>, Without<FlowFieldFallback>, With<Attacker>)>,
+    fallbacks: Query<(Entity, &Transform), (With<FlowFieldFallback>, Without<Path>)>,
     field: Res<TowerField>,
+    flow: Res<FlowField>,
+    smoothing: Res<PathSmoothing>,
+    mut cache: ResMut<PathCache>,
+    mut stats: ResMut<PathfindingStats>,
 ) {
-    for entity in &query {
-        match a_star(&field, field.get_start(), field.get_end()) {
-            Some(path) => {
-                commands.entity(entity).insert(path);
-            }
-            None => {}
+    for (entity, transform) in &fallbacks {
+        let cell = FlowField::get_cell(transform.translation.truncate());
+        if flow.get_direction(cell).is_some() {
+            // The slot was cleared again before a path was needed
+            commands.entity(entity).remove::<FlowFieldFallback>().insert(UseFlowField);
+            continue;
+        }
+        stats.computations += 1;
+        // A* doesn't start on blocked slots, any open neighbour is a way out
+        let path = get_successors(cell).into_iter()
+            .filter(|node| !field.is_node_blocked(*node))
+            .find_map(|node| a_star(&field, node, field.get_end()));
+        if let Some(path) = path {
+            commands.entity(entity).remove::<FlowFieldFallback>().insert(smooth_if_enabled(path, &field, &smoothing));
+        }
+    }
+    if flow.enabled {
+        for (entity, _) in &query {
+            commands.entity(entity).insert(UseFlowField);
+        }
+        return;
+    }
+    // Everyone entering from the same start gets the same path until the field changes, groups included
+    for (entity, start_point) in &query {
+        let start = start_point.and_then(|start_point| field.get_starts().get(start_point.0)).copied().unwrap_or(field.get_start());
+        if !cache.is_valid() {
+            stats.computations += 1;
+        }
+        if let Some(path) = cache.get_path(&field, start) {
+            commands.entity(entity).insert(smooth_if_enabled(path, &field, &smoothing));
         }
     }
 }
@@ -254,49 +697,145 @@ fn set_initial_pathfinding(
 fn set_updated_pathfinding(
     mut commands: Commands,
     mut field_modified: EventReader<FieldModified>,
-    query: Query<(Entity, &Path), (Without<Flying>, With<Attacker>)>,
+    query: Query<(Entity, &Path, Option<&GroupId>), (Without<Flying>, With<Attacker>)>,
     field: Res<TowerField>,
+    smoothing: Res<PathSmoothing>,
+    mut stats: ResMut<PathfindingStats>,
 ) {
-    if !field_modified.is_empty() {
-        for (entity, path) in &query {
-            let mut index = path.get_current_index();
-            while index > 0 && field.is_node_blocked(path.get_node(index)) {
-                index -= 1;
-            }
-            match a_star(&field, path.get_node(index), field.get_end()) {
-                Some(path) => {
-                    commands.entity(entity).insert(path);
-                }
-                None => {}
+    if field_modified.is_empty() {
+        return;
+    }
+    field_modified.clear();
+    let mut groups: HashMap<Entity, Vec<(Entity, &Path)>> = HashMap::new();
+    for (entity, path, group) in &query {
+        groups.entry(group.map(|group| group.0).unwrap_or(entity)).or_default().push((entity, path));
+    }
+    for members in groups.values() {
+        // The rearmost member sees every obstruction the others could run into
+        let (_, rearmost) = *members.iter().min_by_key(|(_, path)| path.get_current_index()).unwrap();
+        let Some((rerouted, reroute_index)) = reroute(rearmost, &field, &smoothing, &mut stats) else {
+            continue;
+        };
+        for (entity, path) in members {
+            if path.get_nodes() == rearmost.get_nodes() && path.get_current_index() <= reroute_index {
+                let mut shared = rerouted.clone();
+                shared.set_current_index(path.get_current_index());
+                commands.entity(*entity).insert(shared);
+            } else if let Some((own, _)) = reroute(path, &field, &smoothing, &mut stats) {
+                // Members past the reroute point would have to walk back, and separated ones follow another route
+                commands.entity(*entity).insert(own);
             }
         }
-        field_modified.clear();
     }
 }
 
-fn check_reached_end(
+/* Keeps route[0..=unblocked_index] and only searches again from there, None if nothing ahead is obstructed */
+fn reroute(path: &Path, field: &TowerField, smoothing: &PathSmoothing, stats: &mut PathfindingStats) -> Option<(Path, usize)> {
+    let obstructed = path.first_obstructed_index(field)?;
+    let mut index = obstructed.saturating_sub(1);
+    while index > 0 && field.is_node_blocked(path.get_node(index)) {
+        index -= 1;
+    }
+    stats.computations += 1;
+    let suffix = a_star(field, path.get_node(index), field.get_end())?;
+    let mut rerouted = path.sub_path(0, index).concat(&smooth_if_enabled(suffix, field, smoothing));
+    rerouted.set_current_index(path.get_current_index().min(index));
+    return Some((rerouted, index));
+}
+
+fn smooth_if_enabled(path: Path, field: &TowerField, smoothing: &PathSmoothing) -> Path {
+    if smoothing.enabled {
+        return path.smoothed(field);
+    }
+    return path;
+}
+
+pub(crate) fn check_reached_end(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &Attacker)>,
+    mut query: Query<(Entity, &mut Transform, &Attacker, Option<&StartPoint>)>,
     mut reached_end: EventWriter<EntityReachedEnd>,
     tower_field: Res<TowerField>,
 ) {
-    for (entity, mut transform, attacker) in query.iter_mut() {
+    for (entity, mut transform, attacker, start_point) in query.iter_mut() {
         let goal = tower_field.get_end();
         let target_vec = Vec2::new(goal.x as f32, goal.y as f32) * SLOT_SIZE as f32;
         let entity_vec = transform.translation.truncate();
         if target_vec.distance(entity_vec) <= 5. {
-            transform.translation = tower_field.get_start_transform().translation;
+            transform.translation = tower_field.get_start_transform_of(start_point.map_or(0, |start_point| start_point.0)).translation;
             commands.entity(entity).remove::<Path>();
             reached_end.send(EntityReachedEnd {
                 entity: entity,
                 bounty: attacker.bounty,
+                leak_effect: attacker.leak_effect,
             })
         }
     }
 }
 
-fn update_path_finding(mut query: Query<(&mut Attacker, &mut Path, &Transform)>) {
-    for (mut attacker, mut path, transform) in query.iter_mut() {
+pub(crate) fn apply_slow_events(
+    mut commands: Commands,
+    mut slow_events: EventReader<SlowEvent>,
+    query: Query<Option<&SlowEffect>, With<Attacker>>,
+) {
+    // Several auras can reach the same attacker in one frame, only the strongest slow applies
+    let mut strongest: HashMap<Entity, (f32, f32)> = HashMap::new();
+    for ev in slow_events.iter() {
+        let entry = strongest.entry(ev.target).or_insert((ev.factor, ev.duration_secs));
+        if ev.factor < entry.0 {
+            *entry = (ev.factor, ev.duration_secs);
+        }
+    }
+    for (entity, (factor, duration_secs)) in strongest {
+        if let Ok(current) = query.get(entity) {
+            // A weaker slow neither replaces a running stronger one nor extends it, that one runs out on its own time
+            if let Some(current) = current {
+                if !current.timer.finished() && current.factor < factor {
+                    continue;
+                }
+            }
+            commands.entity(entity).insert(SlowEffect {
+                factor,
+                timer: Timer::from_seconds(duration_secs, TimerMode::Once),
+            });
+        }
+    }
+}
+
+pub(crate) fn tick_slow_effects(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut SlowEffect)>,
+    time: Res<Time>,
+) {
+    for (entity, mut slow) in query.iter_mut() {
+        slow.timer.tick(time.delta());
+        if slow.timer.finished() {
+            commands.entity(entity).remove::<SlowEffect>();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn update_path_finding(
+    mut commands: Commands,
+    mut query: Query<(&mut Attacker, &mut Path, &Transform, Option<&SlowEffect>)>,
+    mut flow_users: Query<(Entity, &mut Attacker, &Transform, Option<&SlowEffect>), (With<UseFlowField>, Without<Path>)>,
+    flow: Res<FlowField>,
+    modifiers: Res<RoundModifiers>,
+) {
+    for (entity, mut attacker, transform, slow) in flow_users.iter_mut() {
+        let position = transform.translation.truncate();
+        let cell = FlowField::get_cell(position);
+        let Some(direction) = flow.get_direction(cell) else {
+            // Walled in mid-step
+            commands.entity(entity).remove::<UseFlowField>().insert(FlowFieldFallback);
+            continue;
+        };
+        // Heading for the center of the next slot keeps attackers off the corners of blocked ones
+        let target = (Vec2::new(cell.x as f32, cell.y as f32) + direction) * SLOT_SIZE as f32;
+        let speed_factor = slow.map(|slow| slow.factor).unwrap_or(1.) * modifiers.get_speed_factor();
+        attacker.velocity = (target - position).normalize_or_zero() * attacker.movement_speed * speed_factor;
+    }
+    for (mut attacker, mut path, transform, slow) in query.iter_mut() {
         let position = transform.translation.truncate();
         let mut target = path.get_target_position();
         let sizef = SLOT_SIZE as f32;
@@ -304,29 +843,97 @@ fn update_path_finding(mut query: Query<(&mut Attacker, &mut Path, &Transform)>)
             path.increment_index();
         }
         target = path.get_target_position();
-        attacker.velocity = (target - position).normalize_or_zero() * attacker.movement_speed;
+        let speed_factor = slow.map(|slow| slow.factor).unwrap_or(1.) * modifiers.get_speed_factor();
+        attacker.velocity = (target - position).normalize_or_zero() * attacker.movement_speed * speed_factor;
+    }
+}
+
+/* Index of the node a blink from `path`'s current node ends on, the blink stops in front of the first blocked node */
+fn blink_target_index(path: &Path, distance: usize, field: &TowerField) -> usize {
+    let current = path.get_current_index();
+    let last = path.get_size().saturating_sub(1);
+    let mut index = current;
+    while index < last && index - current < distance && !field.is_node_blocked(path.get_node(index + 1)) {
+        index += 1;
+    }
+    return index;
+}
+
+/* The intercept upgrade is part of the stats a unit spawns with, the interceptor itself only exists on the field */
+pub(crate) fn equip_interceptors(mut commands: Commands, spawned: Query<(Entity, &Attacker), Added<Attacker>>) {
+    for (entity, attacker) in spawned.iter() {
+        if attacker.intercepts {
+            commands.entity(entity).insert(Interceptor::new(GOLEM_INTERCEPT_RANGE, GOLEM_INTERCEPT_COOLDOWN));
+        }
     }
 }
 
-fn update_positions(mut query: Query<(&Attacker, &mut Transform)>, time: Res<Time>) {
+pub(crate) fn blink_attackers(
+    mut query: Query<(Entity, &mut Blink, &mut Path, &mut Transform), With<Attacker>>,
+    mut flow_users: Query<(Entity, &mut Blink, &mut Transform), (With<Attacker>, With<UseFlowField>, Without<Path>)>,
+    mut blinks: EventWriter<BlinkEvent>,
+    field: Res<TowerField>,
+    flow: Res<FlowField>,
+    time: Res<Time>,
+) {
+    for (entity, mut blink, mut path, mut transform) in query.iter_mut() {
+        if !blink.cooldown.tick(time.delta()).just_finished() || path.get_size() == 0 {
+            continue;
+        }
+        let target = blink_target_index(&path, blink.distance, &field);
+        if target == path.get_current_index() {
+            continue;
+        }
+        let from = transform.translation.truncate();
+        path.set_current_index(target);
+        // Landing on the end node leaves the leak to check_reached_end like any other arrival
+        let to = path.get_target_position();
+        transform.translation = to.extend(transform.translation.z);
+        blinks.send(BlinkEvent { entity, from, to });
+    }
+    // Flow field users have no path to skip along, they jump ahead on the flow instead
+    for (entity, mut blink, mut transform) in flow_users.iter_mut() {
+        if !blink.cooldown.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let from = transform.translation.truncate();
+        let cell = FlowField::get_cell(from);
+        let landing = flow.walk(cell, blink.distance);
+        if landing == cell {
+            continue;
+        }
+        let to = Vec2::new(landing.x as f32, landing.y as f32) * SLOT_SIZE as f32;
+        transform.translation = to.extend(transform.translation.z);
+        blinks.send(BlinkEvent { entity, from, to });
+    }
+}
+
+/* Runs in the fixed step, see FixedMovementSet */
+pub(crate) fn update_positions(mut query: Query<(&Attacker, &mut Transform)>, fixed_time: Res<FixedTime>) {
     for (attacker, mut transform) in query.iter_mut() {
-        transform.translation += attacker.velocity.extend(0.) * time.delta_seconds();
+        transform.translation += attacker.velocity.extend(0.) * fixed_time.period.as_secs_f32();
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Component, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Deserialize, Serialize)]
 pub enum AttackerType {
     OrcWarrior,
     Spider,
     Golem,
+    Thief,
+    Phantom,
 }
 
 impl AttackerType {
+    pub const ALL: [AttackerType; 5] = [AttackerType::OrcWarrior, AttackerType::Spider, AttackerType::Golem, AttackerType::Thief, AttackerType::Phantom];
+
     pub fn get_name(&self) -> &'static str {
         return match self {
             AttackerType::OrcWarrior => "Orc Warrior",
             AttackerType::Spider => "Spider",
-            AttackerType::Golem => "Golem"
+            AttackerType::Golem => "Golem",
+            AttackerType::Thief => "Thief",
+            AttackerType::Phantom => "Phantom"
         };
     }
 }
@@ -334,72 +941,168 @@ impl AttackerType {
 pub const ORC_WARRIOR_STATS: Attacker = Attacker {
     health: 140.,
     max_health: 140.,
+    shield: 0.,
     movement_speed: 26.,
     velocity: Vec2::ZERO,
     size: Vec2::new(26., 36.),
     bounty: 10,
     original_cost: 20,
     num_summoned: 1,
+    crit_chance: 0.,
+    crit_multiplier: 2.,
+    dodge_chance: 0.05,
+    armor_class: ArmorClass::Light,
+    immunities: &[],
+    leak_effect: LeakEffect::None,
+    walk_animation: WalkAnimation { frame_time: 0.1, speed_coupling: 1., reference_speed: 26. },
+    intercepts: false,
 };
 //pub const ORC_WARRIOR: AttackerType = AttackerType::OrcWarrior(ORC_WARRIOR_STATS);
 
 pub const SPIDER_STATS: Attacker = Attacker {
     health: 56.,
     max_health: 56.,
+    shield: 0.,
     movement_speed: 51.,
     velocity: Vec2::ZERO,
     size: Vec2::new(14., 14.),
     bounty: 15,
     original_cost: 60,
     num_summoned: 3,
+    crit_chance: 0.,
+    crit_multiplier: 2.,
+    dodge_chance: 0.3,
+    armor_class: ArmorClass::Unarmored,
+    immunities: &[],
+    leak_effect: LeakEffect::None,
+    walk_animation: WalkAnimation { frame_time: 0.06, speed_coupling: 1., reference_speed: 51. },
+    intercepts: false,
 };
 //pub const SPIDER: AttackerType = AttackerType::Spider(SPIDER_STATS);
 
 
+/* Stone skin, the shield has to be broken before the golem gets hurt */
 pub const GOLEM_STATS: Attacker = Attacker {
     health: 400.,
     max_health: 400.,
+    shield: 80.,
     movement_speed: 13.,
     velocity: Vec2::ZERO,
     size: Vec2::new(47., 50.),
     bounty: 60,
     original_cost: 160,
     num_summoned: 1,
+    crit_chance: 0.,
+    crit_multiplier: 2.,
+    dodge_chance: 0.,
+    armor_class: ArmorClass::Heavy,
+    immunities: &[],
+    leak_effect: LeakEffect::None,
+    walk_animation: WalkAnimation { frame_time: 0.3, speed_coupling: 1., reference_speed: 13. },
+    intercepts: false,
+};
+
+/* Fragile and worth more to the defender than it costs, but a leak also empties the defender's purse */
+pub const THIEF_STATS: Attacker = Attacker {
+    health: 70.,
+    max_health: 70.,
+    shield: 0.,
+    movement_speed: 38.,
+    velocity: Vec2::ZERO,
+    size: Vec2::new(26., 36.),
+    bounty: 50,
+    original_cost: 40,
+    num_summoned: 1,
+    crit_chance: 0.,
+    crit_multiplier: 2.,
+    dodge_chance: 0.15,
+    armor_class: ArmorClass::Light,
+    immunities: &[],
+    leak_effect: LeakEffect::StealGold(40),
+    walk_animation: WalkAnimation { frame_time: 0.07, speed_coupling: 1., reference_speed: 38. },
+    intercepts: false,
 };
 
+/* Thieves have no sprites of their own, they are orcs dressed in gold */
+const THIEF_TINT: Color = Color::rgb(1., 0.85, 0.35);
+
+/* Slow walker that skips ahead on its path, see Blink */
+pub const PHANTOM_STATS: Attacker = Attacker {
+    health: 90.,
+    max_health: 90.,
+    shield: 0.,
+    movement_speed: 22.,
+    velocity: Vec2::ZERO,
+    size: Vec2::new(14., 14.),
+    bounty: 30,
+    original_cost: 70,
+    num_summoned: 1,
+    crit_chance: 0.,
+    crit_multiplier: 2.,
+    dodge_chance: 0.1,
+    armor_class: ArmorClass::Ethereal,
+    immunities: &[],
+    leak_effect: LeakEffect::None,
+    walk_animation: WalkAnimation { frame_time: 0.12, speed_coupling: 1., reference_speed: 22. },
+    intercepts: false,
+};
+pub const PHANTOM_BLINK_SECONDS: f32 = 4.;
+pub const PHANTOM_BLINK_DISTANCE: usize = 3;
+/* Pale see-through spiders */
+const PHANTOM_TINT: Color = Color::rgba(0.7, 0.8, 1., 0.6);
+
 trait AttackerSpawner
 where
     Self: Sized,
 {
-    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats) -> Vec<Self>;
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self>;
 }
 
-fn fuzzy_transform(field: &TowerField) -> Transform {
-    return field.get_start_transform_with_offset(Vec2::new(rand::thread_rng().gen_range(-16.0..16.0), rand::thread_rng().gen_range(-16.0..16.0)));
+/* Units spawned in the same frame get a lane each, lanes circle around the start so bursts don't stack */
+const SPAWN_LANE_SPACING: f32 = 24.;
+
+fn fuzzy_transform(field: &TowerField, start: usize, lane: usize) -> Transform {
+    let lane_offset = if lane == 0 {
+        Vec2::ZERO
+    } else {
+        Vec2::from_angle(lane as f32 * std::f32::consts::FRAC_PI_2) * SPAWN_LANE_SPACING
+    };
+    return field.get_start_transform_with_offset(start, lane_offset + Vec2::new(rand::thread_rng().gen_range(-16.0..16.0), rand::thread_rng().gen_range(-16.0..16.0)));
+}
+
+fn spawn_group<T: Bundle>(commands: &mut Commands, preset: AttackerType, start: usize, members: Vec<T>) {
+    let mut group: Option<GroupId> = None;
+    for member in members {
+        let entity = commands.spawn(member).id();
+        let group = *group.get_or_insert(GroupId(entity));
+        commands.entity(entity).insert((group, preset, StartPoint(start)));
+    }
 }
 
 pub fn spawn_attacker(
-    mut commands: Commands,
+    commands: &mut Commands,
     field: &TowerField,
     textures: &TextureResource,
     preset: AttackerType,
-    attackers: &AttackerStats
+    attackers: &AttackerStats,
+    start: usize,
+    lane: usize
 ) {
     match preset {
         AttackerType::OrcWarrior => {
-            for ele in OrcWarrior::spawn(field, textures, preset, attackers) {
-                commands.spawn(ele);
-            }
+            spawn_group(commands, preset, start, OrcWarrior::spawn(field, textures, preset, attackers, start, lane));
         }
         AttackerType::Spider => {
-            for ele in Spider::spawn(field, textures, preset, attackers) {
-                commands.spawn(ele);
-            }
+            spawn_group(commands, preset, start, Spider::spawn(field, textures, preset, attackers, start, lane));
         },
         AttackerType::Golem => {
-            for ele in Golem::spawn(field, textures, preset, attackers) {
-                commands.spawn(ele);
-            }
+            spawn_group(commands, preset, start, Golem::spawn(field, textures, preset, attackers, start, lane));
+        }
+        AttackerType::Thief => {
+            spawn_group(commands, preset, start, Thief::spawn(field, textures, preset, attackers, start, lane));
+        }
+        AttackerType::Phantom => {
+            spawn_group(commands, preset, start, Phantom::spawn(field, textures, preset, attackers, start, lane));
         }
     }
 }
@@ -409,13 +1112,16 @@ pub struct OrcWarrior {
     attacker: Attacker,
     grounded: Grounded,
     timer: AnimationTimer,
+    playback: AnimationPlayback,
+    tint: AttackerTint,
+    footstep: Footstep,
     animations: Animations,
     #[bundle]
     sprite: SpriteSheetBundle,
 }
 
 impl AttackerSpawner for OrcWarrior {
-    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats) -> Vec<Self> {
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self> {
         let animations = textures.get_animations(
             "orc1",
             [
@@ -426,34 +1132,36 @@ impl AttackerSpawner for OrcWarrior {
                 "orc1_idle",
             ],
         );
-        return match preset {
-            AttackerType::OrcWarrior => {
-                let attacker = attackers.get_stats(preset);
-                let mut results: Vec<Self> = Vec::new();
-                for i in 0..attacker.num_summoned {
-                    results.push(Self {
-                        attacker: attacker.clone(),
-                        animations: Animations {
-                            up: animations.1[3],
-                            down: animations.1[0],
-                            left: animations.1[1],
-                            right: animations.1[2],
-                            idle: animations.1[4],
-                        },
-                        sprite: SpriteSheetBundle {
-                            sprite: TextureAtlasSprite::new(animations.1[4].start),
-                            texture_atlas: animations.0.clone_weak(),
-                            transform: fuzzy_transform(field),
-                            ..Default::default()
-                        },
-                        grounded: Grounded,
-                        timer: AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                    });
-                }
-                results
-            }
-            _ => panic!(),
-        };
+        let timing = textures.get_animation_timing("orc1", "orc1_down_walk", attackers.get_stats(preset).walk_animation.frame_time);
+        let death = get_death_animation(textures, "orc1", "orc1_death");
+        let attacker = attackers.get_stats(preset);
+        let mut results: Vec<Self> = Vec::new();
+        for _ in 0..attacker.num_summoned {
+            results.push(Self {
+                attacker: attacker.clone(),
+                animations: Animations {
+                    up: animations.1[3],
+                    down: animations.1[0],
+                    left: animations.1[1],
+                    right: animations.1[2],
+                    idle: animations.1[4],
+                    timing: timing.clone(),
+                    death: death.clone(),
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(animations.1[4].start),
+                    texture_atlas: animations.0.clone_weak(),
+                    transform: fuzzy_transform(field, start, lane),
+                    ..Default::default()
+                },
+                grounded: Grounded,
+                timer: AnimationTimer(Timer::new(timing.get_frame_time(0), TimerMode::Repeating)),
+                footstep: Footstep::new(attacker.movement_speed),
+                playback: AnimationPlayback::default(),
+                tint: AttackerTint::default(),
+            });
+        }
+        return results;
     }
 }
 
@@ -462,6 +1170,9 @@ pub struct Spider {
     attacker: Attacker,
     grounded: Grounded,
     timer: AnimationTimer,
+    playback: AnimationPlayback,
+    tint: AttackerTint,
+    footstep: Footstep,
     animations: Animations,
     #[bundle]
     sprite: SpriteSheetBundle,
@@ -469,7 +1180,7 @@ pub struct Spider {
 
 
 impl AttackerSpawner for Spider {
-    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats) -> Vec<Self> {
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self> {
         let animations = textures.get_animations(
             "monster1",
             [
@@ -480,34 +1191,36 @@ impl AttackerSpawner for Spider {
                 "spider1_idle",
             ],
         );
-        return match preset {
-            AttackerType::Spider => {
-                let attacker = attackers.get_stats(preset);
-                let mut results: Vec<Self> = Vec::new();
-                for i in 0..attacker.num_summoned {
-                    results.push(Self {
-                        attacker: attacker.clone(),
-                        animations: Animations {
-                            up: animations.1[3],
-                            down: animations.1[0],
-                            left: animations.1[1],
-                            right: animations.1[2],
-                            idle: animations.1[4],
-                        },
-                        sprite: SpriteSheetBundle {
-                            sprite: TextureAtlasSprite::new(animations.1[4].start),
-                            texture_atlas: animations.0.clone_weak(),
-                            transform: fuzzy_transform(field),
-                            ..Default::default()
-                        },
-                        grounded: Grounded,
-                        timer: AnimationTimer(Timer::from_seconds(0.06, TimerMode::Repeating)),
-                    })
-                }
-                results
-            },
-            _ => panic!(),
-        };
+        let timing = textures.get_animation_timing("monster1", "spider1_down_walk", attackers.get_stats(preset).walk_animation.frame_time);
+        let death = get_death_animation(textures, "monster1", "spider1_death");
+        let attacker = attackers.get_stats(preset);
+        let mut results: Vec<Self> = Vec::new();
+        for _ in 0..attacker.num_summoned {
+            results.push(Self {
+                attacker: attacker.clone(),
+                animations: Animations {
+                    up: animations.1[3],
+                    down: animations.1[0],
+                    left: animations.1[1],
+                    right: animations.1[2],
+                    idle: animations.1[4],
+                    timing: timing.clone(),
+                    death: death.clone(),
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(animations.1[4].start),
+                    texture_atlas: animations.0.clone_weak(),
+                    transform: fuzzy_transform(field, start, lane),
+                    ..Default::default()
+                },
+                grounded: Grounded,
+                timer: AnimationTimer(Timer::new(timing.get_frame_time(0), TimerMode::Repeating)),
+                footstep: Footstep::new(attacker.movement_speed),
+                playback: AnimationPlayback::default(),
+                tint: AttackerTint::default(),
+            })
+        }
+        return results;
     }
 }
 
@@ -517,6 +1230,9 @@ pub struct Golem {
     attacker: Attacker,
     grounded: Grounded,
     timer: AnimationTimer,
+    playback: AnimationPlayback,
+    tint: AttackerTint,
+    footstep: Footstep,
     animations: Animations,
     #[bundle]
     sprite: SpriteSheetBundle,
@@ -524,7 +1240,7 @@ pub struct Golem {
 
 
 impl AttackerSpawner for Golem {
-    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats) -> Vec<Self> {
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self> {
         let animations = textures.get_animations(
             "golem1",
             [
@@ -535,33 +1251,436 @@ impl AttackerSpawner for Golem {
                 "golem1_idle",
             ],
         );
-        return match preset {
-            AttackerType::Golem => {
-                let attacker = attackers.get_stats(preset);
-                let mut results: Vec<Self> = Vec::new();
-                for i in 0..attacker.num_summoned {
-                    results.push(Self {
-                        attacker: attacker.clone(),
-                        animations: Animations {
-                            up: animations.1[3],
-                            down: animations.1[0],
-                            left: animations.1[1],
-                            right: animations.1[2],
-                            idle: animations.1[4],
-                        },
-                        sprite: SpriteSheetBundle {
-                            sprite: TextureAtlasSprite::new(animations.1[4].start),
-                            texture_atlas: animations.0.clone_weak(),
-                            transform: fuzzy_transform(field),
-                            ..Default::default()
-                        },
-                        grounded: Grounded,
-                        timer: AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
-                    })
-                }
-                results
-            },
-            _ => panic!(),
-        };
+        let timing = textures.get_animation_timing("golem1", "golem1_down_walk", attackers.get_stats(preset).walk_animation.frame_time);
+        let death = get_death_animation(textures, "golem1", "golem1_death");
+        let attacker = attackers.get_stats(preset);
+        let mut results: Vec<Self> = Vec::new();
+        for _ in 0..attacker.num_summoned {
+            results.push(Self {
+                attacker: attacker.clone(),
+                animations: Animations {
+                    up: animations.1[3],
+                    down: animations.1[0],
+                    left: animations.1[1],
+                    right: animations.1[2],
+                    idle: animations.1[4],
+                    timing: timing.clone(),
+                    death: death.clone(),
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(animations.1[4].start),
+                    texture_atlas: animations.0.clone_weak(),
+                    transform: fuzzy_transform(field, start, lane),
+                    ..Default::default()
+                },
+                grounded: Grounded,
+                timer: AnimationTimer(Timer::new(timing.get_frame_time(0), TimerMode::Repeating)),
+                footstep: Footstep::new(attacker.movement_speed),
+                playback: AnimationPlayback::default(),
+                tint: AttackerTint::default(),
+            })
+        }
+        return results;
+    }
+}
+
+#[derive(Bundle)]
+pub struct Thief {
+    attacker: Attacker,
+    grounded: Grounded,
+    timer: AnimationTimer,
+    playback: AnimationPlayback,
+    tint: AttackerTint,
+    footstep: Footstep,
+    animations: Animations,
+    #[bundle]
+    sprite: SpriteSheetBundle,
+}
+
+impl AttackerSpawner for Thief {
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self> {
+        let animations = textures.get_animations(
+            "orc1",
+            [
+                "orc1_down_walk",
+                "orc1_left_walk",
+                "orc1_right_walk",
+                "orc1_up_walk",
+                "orc1_idle",
+            ],
+        );
+        let timing = textures.get_animation_timing("orc1", "orc1_down_walk", attackers.get_stats(preset).walk_animation.frame_time);
+        let death = get_death_animation(textures, "orc1", "orc1_death");
+        let attacker = attackers.get_stats(preset);
+        let mut results: Vec<Self> = Vec::new();
+        for _ in 0..attacker.num_summoned {
+            results.push(Self {
+                attacker: attacker.clone(),
+                animations: Animations {
+                    up: animations.1[3],
+                    down: animations.1[0],
+                    left: animations.1[1],
+                    right: animations.1[2],
+                    idle: animations.1[4],
+                    timing: timing.clone(),
+                    death: death.clone(),
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        color: THIEF_TINT,
+                        ..TextureAtlasSprite::new(animations.1[4].start)
+                    },
+                    texture_atlas: animations.0.clone_weak(),
+                    transform: fuzzy_transform(field, start, lane),
+                    ..Default::default()
+                },
+                grounded: Grounded,
+                timer: AnimationTimer(Timer::new(timing.get_frame_time(0), TimerMode::Repeating)),
+                footstep: Footstep::new(attacker.movement_speed),
+                playback: AnimationPlayback::default(),
+                tint: AttackerTint(THIEF_TINT),
+            })
+        }
+        return results;
+    }
+}
+
+#[derive(Bundle)]
+pub struct Phantom {
+    attacker: Attacker,
+    blink: Blink,
+    grounded: Grounded,
+    timer: AnimationTimer,
+    playback: AnimationPlayback,
+    tint: AttackerTint,
+    footstep: Footstep,
+    animations: Animations,
+    #[bundle]
+    sprite: SpriteSheetBundle,
+}
+
+impl AttackerSpawner for Phantom {
+    fn spawn(field: &TowerField, textures: &TextureResource, preset: AttackerType, attackers: &AttackerStats, start: usize, lane: usize) -> Vec<Self> {
+        let animations = textures.get_animations(
+            "monster1",
+            [
+                "spider1_down_walk",
+                "spider1_left_walk",
+                "spider1_right_walk",
+                "spider1_up_walk",
+                "spider1_idle",
+            ],
+        );
+        let timing = textures.get_animation_timing("monster1", "spider1_down_walk", attackers.get_stats(preset).walk_animation.frame_time);
+        let death = get_death_animation(textures, "monster1", "spider1_death");
+        let attacker = attackers.get_stats(preset);
+        let mut results: Vec<Self> = Vec::new();
+        for _ in 0..attacker.num_summoned {
+            results.push(Self {
+                attacker: attacker.clone(),
+                blink: Blink {
+                    cooldown: Timer::from_seconds(PHANTOM_BLINK_SECONDS, TimerMode::Repeating),
+                    distance: PHANTOM_BLINK_DISTANCE,
+                },
+                animations: Animations {
+                    up: animations.1[3],
+                    down: animations.1[0],
+                    left: animations.1[1],
+                    right: animations.1[2],
+                    idle: animations.1[4],
+                    timing: timing.clone(),
+                    death: death.clone(),
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        color: PHANTOM_TINT,
+                        ..TextureAtlasSprite::new(animations.1[4].start)
+                    },
+                    texture_atlas: animations.0.clone_weak(),
+                    transform: fuzzy_transform(field, start, lane),
+                    ..Default::default()
+                },
+                grounded: Grounded,
+                timer: AnimationTimer(Timer::new(timing.get_frame_time(0), TimerMode::Repeating)),
+                footstep: Footstep::new(attacker.movement_speed),
+                playback: AnimationPlayback::default(),
+                tint: AttackerTint(PHANTOM_TINT),
+            })
+        }
+        return results;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::CommandQueue, prelude::{Events, Schedule, World}};
+
+    use crate::world::{path_finding::Node, towers::Footprint};
+
+    use super::*;
+
+    fn spawn_group_in<T: Bundle>(world: &mut World, preset: AttackerType, members: Vec<T>) {
+        let mut queue = CommandQueue::default();
+        spawn_group(&mut Commands::new(&mut queue, world), preset, 0, members);
+        queue.apply(world);
+    }
+
+    /* Frames an orc walking at `speed` advances in one second */
+    fn walked_frames(speed: f32, speed_coupling: f32) -> usize {
+        let mut world = World::new();
+        let mut time = Time::default();
+        let startup = time.startup();
+        time.update_with_instant(startup);
+        world.insert_resource(time);
+        let mut attacker = ORC_WARRIOR_STATS;
+        attacker.velocity = Vec2::new(speed, 0.);
+        attacker.walk_animation.speed_coupling = speed_coupling;
+        let walk = AnimationIndices::new(0, 100);
+        let orc = world.spawn((
+            attacker,
+            Animations { up: walk, down: walk, left: walk, right: walk, idle: walk, timing: AnimationTiming::uniform(0.1), death: None },
+            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            AnimationPlayback::default(),
+            TextureAtlasSprite::new(0),
+        )).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(update_animations);
+        for frame in 1..=60 {
+            world.resource_mut::<Time>().update_with_instant(startup + Duration::from_secs_f32(frame as f32 / 60.));
+            schedule.run(&mut world);
+        }
+        return world.get::<TextureAtlasSprite>(orc).unwrap().index;
+    }
+
+    #[test]
+    fn upgrades_add_flat_values_or_multiply_by_factors() {
+        let flat = UpgradeInfo { effect: 1., cost: 0, effect_type: UpgradeEffectType::Flat, description: String::new() };
+        let factor = UpgradeInfo { effect: 1.2, cost: 0, effect_type: UpgradeEffectType::Factor, description: String::new() };
+        assert_eq!(flat.apply_value(3), 4);
+        assert!((flat.apply_value_f32(0.05) - 1.05).abs() < 1e-6);
+        // Whole numbers are rounded, 3.6 summoned units are 4
+        assert_eq!(factor.apply_value(3), 4);
+        assert_eq!(factor.apply_value(1), 1);
+        assert!((factor.apply_value_f32(50.) - 60.).abs() < 1e-4);
+
+        let mut stats = AttackerStats::default();
+        let cost = stats.get_upgrade_cost(AttackerType::Golem, UpgradeType::Health);
+        stats.apply_upgrade(AttackerType::Golem, UpgradeType::Health);
+        assert_eq!(stats.get_stats(AttackerType::Golem).max_health, GOLEM_STATS.max_health * 1.1);
+        assert_eq!(stats.get_upgrade_cost(AttackerType::Golem, UpgradeType::Health), (cost as f32 * 1.3).round() as i32);
+        assert_eq!(stats.get_upgrades_bought()[&UpgradeType::Health], 1);
+        stats.apply_upgrade(AttackerType::Spider, UpgradeType::Critical);
+        assert_eq!(stats.get_stats(AttackerType::Spider).crit_chance, 0.05);
+    }
+
+    #[test]
+    fn only_golems_that_bought_the_upgrade_intercept() {
+        let mut stats = AttackerStats::default();
+        assert!(!stats.get_stats(AttackerType::Golem).intercepts);
+        assert!(!stats.get_upgrades(AttackerType::Spider).contains(&UpgradeType::Intercept));
+        assert!(stats.get_upgrades(AttackerType::Golem).contains(&UpgradeType::Intercept));
+
+        let mut world = World::new();
+        let plain = world.spawn(*stats.get_stats(AttackerType::Golem)).id();
+        stats.apply_upgrade(AttackerType::Golem, UpgradeType::Intercept);
+        // Bought once, it isn't offered again
+        assert!(!stats.get_upgrades(AttackerType::Golem).contains(&UpgradeType::Intercept));
+        let upgraded = world.spawn(*stats.get_stats(AttackerType::Golem)).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(equip_interceptors);
+        schedule.run(&mut world);
+        assert!(world.get::<Interceptor>(plain).is_none());
+        assert!(world.get::<Interceptor>(upgraded).is_some());
+    }
+
+    #[test]
+    fn walk_animations_follow_the_movement_speed() {
+        let speed = ORC_WARRIOR_STATS.walk_animation.reference_speed;
+        let normal = walked_frames(speed, 1.);
+        assert!((9..=10).contains(&normal));
+        assert!((18..=20).contains(&walked_frames(speed * 2., 1.)));
+        // Without coupling only the frame time counts
+        assert_eq!(walked_frames(speed * 2., 0.), normal);
+        assert_eq!(ORC_WARRIOR_STATS.walk_animation.get_rate(0.), 1.);
+    }
+
+    #[test]
+    fn blink_skips_free_nodes_and_stops_at_walls() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(15, 0));
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert!(path.get_nodes().iter().all(|node| node.y == 0));
+        assert_eq!(blink_target_index(&path, 3, &field), 3);
+
+        // The end is the furthest a blink can go
+        let mut near_end = path.clone();
+        near_end.set_current_index(path.get_size() - 2);
+        assert_eq!(blink_target_index(&near_end, 3, &field), path.get_size() - 1);
+
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(2., 0.) * SLOT_SIZE as f32, Footprint::SINGLE);
+        assert_eq!(blink_target_index(&path, 3, &field), 1);
+
+        let mut world = World::new();
+        world.insert_resource(field);
+        world.init_resource::<FlowField>();
+        let mut time = Time::default();
+        let startup = time.startup();
+        time.update_with_instant(startup);
+        time.update_with_instant(startup + std::time::Duration::from_secs(1));
+        world.insert_resource(time);
+        world.init_resource::<Events<BlinkEvent>>();
+        let phantom = world.spawn((
+            PHANTOM_STATS,
+            Blink { cooldown: Timer::from_seconds(1., TimerMode::Repeating), distance: 3 },
+            path,
+            Transform::default(),
+        )).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(blink_attackers);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Path>(phantom).unwrap().get_current_index(), 1);
+        let landed = Vec2::new(1., 0.) * SLOT_SIZE as f32;
+        assert_eq!(world.get::<Transform>(phantom).unwrap().translation.truncate(), landed);
+        let events = world.resource::<Events<BlinkEvent>>();
+        let blinks: Vec<(Vec2, Vec2)> = events.get_reader().iter(events).map(|ev| (ev.from, ev.to)).collect();
+        assert_eq!(blinks, vec![(Vec2::ZERO, landed)]);
+    }
+
+    #[test]
+    fn blink_follows_the_flow_field_without_a_path() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(15, 0));
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(2., 0.) * SLOT_SIZE as f32, Footprint::SINGLE);
+        let mut flow = FlowField::default();
+        flow.rebuild(&field);
+        // The flow leads around the wall, so does the blink
+        let landing = flow.walk(Node::new(0, 0), 3);
+        assert_ne!(landing, Node::new(0, 0));
+        assert!(!field.is_node_blocked(landing));
+        assert_eq!(flow.walk(field.get_end(), 3), field.get_end());
+
+        let mut world = World::new();
+        world.insert_resource(field);
+        world.insert_resource(flow);
+        let mut time = Time::default();
+        let startup = time.startup();
+        time.update_with_instant(startup);
+        time.update_with_instant(startup + std::time::Duration::from_secs(1));
+        world.insert_resource(time);
+        world.init_resource::<Events<BlinkEvent>>();
+        let phantom = world.spawn((
+            PHANTOM_STATS,
+            Blink { cooldown: Timer::from_seconds(1., TimerMode::Repeating), distance: 3 },
+            UseFlowField,
+            Transform::default(),
+        )).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(blink_attackers);
+        schedule.run(&mut world);
+
+        let landed = Vec2::new(landing.x as f32, landing.y as f32) * SLOT_SIZE as f32;
+        assert_eq!(world.get::<Transform>(phantom).unwrap().translation.truncate(), landed);
+        let events = world.resource::<Events<BlinkEvent>>();
+        assert_eq!(events.get_reader().iter(events).count(), 1);
+    }
+
+    #[test]
+    fn spider_group_shares_one_path_search() {
+        let mut world = World::new();
+        world.insert_resource(TowerField::new(16, 16, Vec2::ZERO, Node::new(2, 0), Node::new(14, 15)));
+        world.insert_resource(PathSmoothing::default());
+        world.insert_resource(PathfindingStats::default());
+        world.insert_resource(FlowField::default());
+        world.insert_resource(PathCache::default());
+        world.init_resource::<Events<FieldModified>>();
+        spawn_group_in(&mut world, AttackerType::Spider, vec![(SPIDER_STATS, Transform::default()); 3]);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(invalidate_path_cache.before(set_initial_pathfinding));
+        schedule.add_system(set_initial_pathfinding);
+        schedule.run(&mut world);
+
+        assert_eq!(world.resource::<PathfindingStats>().computations, 1);
+        let paths: Vec<Vec<Node>> = world.query::<&Path>().iter(&world).map(|path| path.get_nodes()).collect();
+        assert_eq!(paths.len(), 3);
+        assert!(paths.iter().all(|nodes| *nodes == paths[0]));
+
+        // Later spawns on the same field reuse the search, a change makes them search again
+        spawn_group_in(&mut world, AttackerType::OrcWarrior, vec![(ORC_WARRIOR_STATS, Transform::default())]);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PathfindingStats>().computations, 1);
+        world.send_event(FieldModified { nodes: vec![Node::new(5, 5)] });
+        spawn_group_in(&mut world, AttackerType::OrcWarrior, vec![(ORC_WARRIOR_STATS, Transform::default())]);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PathfindingStats>().computations, 2);
+    }
+
+    #[test]
+    fn group_members_past_the_reroute_point_keep_going() {
+        let mut world = World::new();
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(2, 0), Node::new(14, 15));
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        let mut ahead = path.clone();
+        ahead.set_current_index(8);
+        spawn_group_in(&mut world, AttackerType::OrcWarrior, vec![
+            (ORC_WARRIOR_STATS, Transform::default(), path.clone()),
+            (ORC_WARRIOR_STATS, Transform::default(), ahead),
+        ]);
+        let wall = path.get_node(4);
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(wall.x as f32, wall.y as f32) * SLOT_SIZE as f32, Footprint::SINGLE);
+        world.insert_resource(field);
+        world.insert_resource(PathSmoothing::default());
+        world.insert_resource(PathfindingStats::default());
+        world.init_resource::<Events<FieldModified>>();
+        world.send_event(FieldModified { nodes: vec![wall] });
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(set_updated_pathfinding);
+        schedule.run(&mut world);
+
+        let mut paths: Vec<(usize, Vec<Node>)> = world.query::<&Path>().iter(&world).map(|path| (path.get_current_index(), path.get_nodes())).collect();
+        paths.sort_by_key(|(index, _)| *index);
+        assert_eq!(paths[0].0, 0);
+        assert!(!paths[0].1.contains(&wall));
+        // The front member already passed the wall, its path and progress stay as they were
+        assert_eq!(paths[1], (8, path.get_nodes()));
+    }
+
+    #[test]
+    fn a_wave_entering_at_once_searches_once() {
+        let mut world = World::new();
+        world.insert_resource(TowerField::new(16, 16, Vec2::ZERO, Node::new(2, 0), Node::new(14, 15)));
+        world.insert_resource(PathSmoothing::default());
+        world.insert_resource(PathfindingStats::default());
+        world.insert_resource(FlowField::default());
+        world.insert_resource(PathCache::default());
+        // 20 attackers in separate groups used to cost one search per group
+        for _ in 0..4 {
+            spawn_group_in(&mut world, AttackerType::OrcWarrior, vec![(ORC_WARRIOR_STATS, Transform::default()); 5]);
+        }
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(set_initial_pathfinding);
+        schedule.run(&mut world);
+
+        assert_eq!(world.query::<&Path>().iter(&world).count(), 20);
+        assert_eq!(world.resource::<PathfindingStats>().computations, 1);
+    }
+
+    #[test]
+    fn healthy_attacker_keeps_base_color() {
+        let base = Color::rgba(0.5, 0.8, 1., 0.9);
+        assert_eq!(attacker_color(base, 0., 1.), base);
+    }
+
+    #[test]
+    fn flash_and_low_health_compose_with_base_tint() {
+        let base = Color::rgb(0.5, 0.8, 1.);
+        let flashed = attacker_color(base, 1., 1.);
+        assert_eq!(flashed.r(), 0.5);
+        assert!((flashed.g() - 0.2).abs() < 1e-5);
+        let hurt = attacker_color(base, 0., 0.1);
+        assert!(hurt.g() < base.g() && hurt.r() == base.r());
+        let both = attacker_color(base, 1., 0.1);
+        assert!(both.g() < flashed.g() && both.g() < hurt.g());
     }
 }
@@ -3,7 +3,14 @@ use std::fs;
 use bevy::{prelude::{Resource, Vec2}, utils::HashMap};
 use serde::{Deserialize, Serialize};
 
-use super::towers::{DefenderAttack, DamageType, ProjectileSprite};
+use crate::textures::TextureResource;
+
+use super::towers::{DefenderAttack, Footprint};
+
+/* Buildings the defender always builds, the others are only used when configured */
+const REQUIRED_BUILDINGS: [BuildingType; 3] = [BuildingType::Arrow, BuildingType::Wall, BuildingType::Cannon];
+/* Attackers rarely walk close enough together for a chain to use all of its hops */
+const EXPECTED_CHAIN_TARGETS: u8 = 3;
 
 
 
@@ -11,64 +18,166 @@ use super::towers::{DefenderAttack, DamageType, ProjectileSprite};
 pub enum BuildingType {
     Arrow,
     Wall,
-    Cannon
+    Cannon,
+    Ballista,
+    Splitter,
+    Marker,
+    Freeze,
+    Tesla,
+    Banner,
+    LargeCannon,
+    ZoneDamage,
+    FireArrow,
+    IceArrow
+}
+
+impl BuildingType {
+    /* DefenderAttack variant the tower is built around, None for buildings without an attack */
+    pub fn get_expected_attack(&self) -> Option<&'static str> {
+        return match self {
+            BuildingType::Arrow | BuildingType::Ballista | BuildingType::Splitter | BuildingType::Marker | BuildingType::FireArrow | BuildingType::IceArrow => Some("Projectile"),
+            BuildingType::Cannon | BuildingType::LargeCannon => Some("Splash"),
+            BuildingType::Tesla => Some("Chain"),
+            BuildingType::Wall | BuildingType::Freeze | BuildingType::Banner | BuildingType::ZoneDamage => None
+        };
+    }
+
+    /* Slots the building covers on the field */
+    pub fn get_footprint(&self) -> Footprint {
+        return match self {
+            BuildingType::LargeCannon => Footprint { width: 2, height: 2 },
+            _ => Footprint::SINGLE
+        };
+    }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct Building {
     pub building_type: BuildingType,
     pub config: BuildingConfig
 }
 
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct BuildingConfig {
     pub cost: i32,
     pub blocking: bool,
-    pub type_config: BuildingTypeConfig
+    pub type_config: BuildingTypeConfig,
+    /* Sprite in the "towers" atlas the building is drawn with, the one it was registered with if not set */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+    /* Sprite in the "towers" atlas shown in menus, the building's own sprite if not set */
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub enum BuildingTypeConfig {
     Defender {
         attack_timer: f32,
         attack: DefenderAttack,
-        attack_range: f32
+        attack_range: f32,
+        /* Attackers closer than this can't be targeted */
+        #[serde(default)]
+        min_range: f32,
+        /* Half width in degrees of the firing arc, the tower covers all directions if not set */
+        #[serde(default)]
+        arc_half_width: Option<f32>
+    },
+    Aura {
+        radius: f32,
+        /* Speed multiplier for attackers inside, 1 leaves them alone */
+        #[serde(default = "no_slow")]
+        factor: f32,
+        /* Bonuses for defenders inside, as a share of their own attack speed and damage */
+        #[serde(default)]
+        attack_speed_bonus: f32,
+        #[serde(default)]
+        damage_bonus: f32
+    },
+    /* Always on, everything inside takes damage every frame without any projectiles */
+    Zone {
+        damage_per_second: f32,
+        radius: f32
     },
     Wall
 }
 
+fn no_slow() -> f32 {
+    return 1.;
+}
+
+impl BuildingTypeConfig {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            BuildingTypeConfig::Defender { .. } => "Defender",
+            BuildingTypeConfig::Aura { .. } => "Aura",
+            BuildingTypeConfig::Zone { .. } => "Zone",
+            BuildingTypeConfig::Wall => "Wall"
+        };
+    }
+}
+
 impl BuildingConfig {
     pub fn get_damage(&self) -> f32 {
         return match &self.type_config {
-            BuildingTypeConfig::Defender { attack_timer, attack, attack_range } => match attack {
-                DefenderAttack::Projectile { damage_type, damage, projectile_speed, sprite } => *damage,
-                DefenderAttack::Splash { damage_type, damage, travel_time, sprite, splash_radius } => *damage
+            BuildingTypeConfig::Defender { attack, .. } => match attack {
+                DefenderAttack::Projectile { damage, .. } => *damage,
+                DefenderAttack::Splash { damage, .. } => *damage,
+                DefenderAttack::Chain { damage, .. } => *damage
             },
-            BuildingTypeConfig::Wall => 0.
+            BuildingTypeConfig::Aura { .. } | BuildingTypeConfig::Zone { .. } | BuildingTypeConfig::Wall => 0.
         }
     }
     pub fn get_dps(&self) -> f32 {
         return match &self.type_config {
-            BuildingTypeConfig::Defender { attack_timer, attack, attack_range } => match attack {
-                DefenderAttack::Projectile { damage_type, damage, projectile_speed, sprite } => *damage / *attack_timer,
-                DefenderAttack::Splash { damage_type, damage, travel_time, sprite, splash_radius } => *damage / *attack_timer
+            BuildingTypeConfig::Defender { attack_timer, attack, .. } => match attack {
+                DefenderAttack::Projectile { damage, .. } => *damage / *attack_timer,
+                DefenderAttack::Splash { damage, .. } => *damage / *attack_timer,
+                DefenderAttack::Chain { damage, falloff, max_targets, .. } => {
+                    /* Every hop deals falloff times the previous one, summed over the hops a chain usually makes */
+                    let hops = (*max_targets).min(EXPECTED_CHAIN_TARGETS);
+                    let total: f32 = (0..hops).map(|hop| falloff.powi(hop as i32)).sum();
+                    *damage * total / *attack_timer
+                }
             },
-            BuildingTypeConfig::Wall => 0.
+            BuildingTypeConfig::Zone { damage_per_second, .. } => *damage_per_second,
+            BuildingTypeConfig::Aura { .. } | BuildingTypeConfig::Wall => 0.
         }
     }
     pub fn get_cost(&self) -> i32 {
         return self.cost;
     }
+    pub fn get_attack_timer(&self) -> f32 {
+        return match &self.type_config {
+            BuildingTypeConfig::Defender { attack_timer, .. } => *attack_timer,
+            _ => 0.
+        }
+    }
     pub fn get_blocking(&self) -> bool {
         return self.blocking;
     }
+    pub fn get_attack_range(&self) -> f32 {
+        return match &self.type_config {
+            BuildingTypeConfig::Defender { attack_range, .. } => *attack_range,
+            _ => 0.
+        }
+    }
+    pub fn get_min_range(&self) -> f32 {
+        return match &self.type_config {
+            BuildingTypeConfig::Defender { min_range, .. } => *min_range,
+            _ => 0.
+        }
+    }
+    pub fn get_aura_radius(&self) -> f32 {
+        return match &self.type_config {
+            BuildingTypeConfig::Aura { radius, .. } | BuildingTypeConfig::Zone { radius, .. } => *radius,
+            _ => 0.
+        }
+    }
     pub fn is_aoe(&self) -> bool {
         return match &self.type_config {
-            BuildingTypeConfig::Defender { attack_timer, attack, attack_range } => match attack {
-                DefenderAttack::Splash { damage_type, damage, travel_time, sprite, splash_radius } => true,
-                _ => false
-            },
+            BuildingTypeConfig::Defender { attack, .. } => matches!(attack, DefenderAttack::Splash { .. } | DefenderAttack::Chain { .. }),
             _ => false
         }
     }
@@ -76,19 +185,179 @@ impl BuildingConfig {
 
 #[derive(Resource)]
 pub struct BuildingResource {
-    buildings: HashMap<BuildingType, BuildingConfig>
+    buildings: HashMap<BuildingType, BuildingConfig>,
+    /* Sprites from the building registry, used where the definition doesn't name one */
+    default_sprites: HashMap<BuildingType, &'static str>
 }
 
 impl BuildingResource {
-    pub fn new() -> Self {
-        let buildings: Vec<Building> = serde_json::from_str(&fs::read_to_string("assets/tower_definitions.json").unwrap()).unwrap();
+    pub fn load() -> Result<Self, String> {
+        let contents = fs::read_to_string("assets/tower_definitions.json")
+            .map_err(|err| format!("Failed to read assets/tower_definitions.json: {}", err))?;
+        return Self::from_json(&contents);
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        let entries: Vec<serde_json::Value> = serde_json::from_str(contents)
+            .map_err(|err| format!("Failed to parse assets/tower_definitions.json: {}", err))?;
+        // Entries are parsed one by one so a single bad building doesn't hide problems in the others
+        let mut errors = Vec::new();
         let mut map: HashMap<BuildingType, BuildingConfig> = HashMap::new();
-        for building in buildings {
-            map.insert(building.building_type, building.config);
+        for (i, entry) in entries.into_iter().enumerate() {
+            let name = entry.get("building_type").and_then(|e| e.as_str()).map(|e| e.to_string()).unwrap_or(format!("entry {}", i));
+            match serde_json::from_value::<Building>(entry) {
+                Ok(building) => {
+                    if map.insert(building.building_type, building.config).is_some() {
+                        errors.push(format!("{}: defined more than once", name));
+                    }
+                },
+                Err(err) => errors.push(format!("{}: {}", name, err))
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.iter().map(|e| format!("tower_definitions.json: {}", e)).collect::<Vec<String>>().join("\n"));
         }
+        return Ok(Self {
+            buildings: map,
+            default_sprites: HashMap::new()
+        });
+    }
+
+    /* Written in the same list format the file is loaded from, sorted so the output is stable */
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut entries: Vec<serde_json::Value> = Vec::new();
+        for (building_type, config) in self.buildings.iter() {
+            entries.push(serde_json::json!({
+                "building_type": building_type,
+                "config": config
+            }));
+        }
+        entries.sort_by_key(|entry| entry["building_type"].to_string());
+        return serde_json::to_string_pretty(&entries).map_err(|err| format!("Failed to write tower definitions: {}", err));
+    }
+
+    pub fn empty() -> Self {
         return Self {
-            buildings: map
+            buildings: HashMap::new(),
+            default_sprites: HashMap::new()
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.buildings.is_empty();
+    }
+
+    /* Collects every problem instead of stopping at the first, so one fix-and-reload round is enough */
+    pub fn validate(&self, textures: &TextureResource) -> Vec<String> {
+        let mut errors = Vec::new();
+        for building_type in REQUIRED_BUILDINGS {
+            if !self.buildings.contains_key(&building_type) {
+                errors.push(format!("{:?}: missing", building_type));
+            }
         }
+        let mut configured: Vec<(&BuildingType, &BuildingConfig)> = self.buildings.iter().collect();
+        configured.sort_by_key(|(building_type, _)| format!("{:?}", building_type));
+        for (building_type, config) in configured {
+            match self.get_sprite_name(building_type) {
+                Some(sprite) if !textures.has_named_sprite("towers", sprite) => {
+                    errors.push(format!("{:?}: no sprite named '{}' in the towers atlas in assets/definitions.json", building_type, sprite));
+                },
+                Some(_) => {},
+                None => errors.push(format!("{:?}.sprite: missing, the building was never registered", building_type))
+            }
+            if let Some(icon) = config.icon.as_deref().filter(|icon| !textures.has_named_sprite("towers", icon)) {
+                errors.push(format!("{:?}.icon: no sprite named '{}' in the towers atlas in assets/definitions.json", building_type, icon));
+            }
+            match (building_type, &config.type_config) {
+                (BuildingType::Wall, BuildingTypeConfig::Wall) => {},
+                (BuildingType::Wall, _) => {
+                    errors.push(format!("{:?}.type_config: expected Wall, found {}", building_type, config.type_config.get_name()));
+                },
+                (BuildingType::Freeze, BuildingTypeConfig::Aura { radius, factor, .. }) => {
+                    if *radius <= 0. {
+                        errors.push(format!("{:?}.radius: must be positive, found {}", building_type, radius));
+                    }
+                    if *factor <= 0. || *factor > 1. {
+                        errors.push(format!("{:?}.factor: must be in (0, 1], found {}", building_type, factor));
+                    }
+                },
+                (BuildingType::Banner, BuildingTypeConfig::Aura { radius, attack_speed_bonus, damage_bonus, .. }) => {
+                    if *radius <= 0. {
+                        errors.push(format!("{:?}.radius: must be positive, found {}", building_type, radius));
+                    }
+                    if *attack_speed_bonus < 0. || *damage_bonus < 0. {
+                        errors.push(format!("{:?}: bonuses can't be negative, found {} and {}", building_type, attack_speed_bonus, damage_bonus));
+                    }
+                    if *attack_speed_bonus == 0. && *damage_bonus == 0. {
+                        errors.push(format!("{:?}: needs an attack_speed_bonus or a damage_bonus", building_type));
+                    }
+                },
+                (BuildingType::Freeze | BuildingType::Banner, _) => {
+                    errors.push(format!("{:?}.type_config: expected Aura, found {}", building_type, config.type_config.get_name()));
+                },
+                (BuildingType::ZoneDamage, BuildingTypeConfig::Zone { damage_per_second, radius }) => {
+                    if *damage_per_second <= 0. {
+                        errors.push(format!("{:?}.damage_per_second: must be positive, found {}", building_type, damage_per_second));
+                    }
+                    if *radius <= 0. {
+                        errors.push(format!("{:?}.radius: must be positive, found {}", building_type, radius));
+                    }
+                },
+                (BuildingType::ZoneDamage, _) => {
+                    errors.push(format!("{:?}.type_config: expected Zone, found {}", building_type, config.type_config.get_name()));
+                },
+                (_, BuildingTypeConfig::Wall | BuildingTypeConfig::Aura { .. } | BuildingTypeConfig::Zone { .. }) => {
+                    errors.push(format!("{:?}.type_config: expected Defender, found {}", building_type, config.type_config.get_name()));
+                },
+                (_, BuildingTypeConfig::Defender { attack_timer, attack, attack_range, min_range, arc_half_width }) => {
+                    if *attack_timer <= 0. {
+                        errors.push(format!("{:?}.attack_timer: must be positive, found {}", building_type, attack_timer));
+                    }
+                    if *attack_range <= 0. {
+                        errors.push(format!("{:?}.attack_range: must be positive, found {}", building_type, attack_range));
+                    }
+                    if *min_range < 0. || *min_range >= *attack_range {
+                        errors.push(format!("{:?}.min_range: must be in [0, attack_range), found {}", building_type, min_range));
+                    }
+                    if let Some(half_width) = arc_half_width {
+                        if *half_width <= 0. || *half_width > 180. {
+                            errors.push(format!("{:?}.arc_half_width: must be in (0, 180], found {}", building_type, half_width));
+                        }
+                    }
+                    if let Some(expected) = building_type.get_expected_attack().filter(|expected| *expected != attack.get_name()) {
+                        errors.push(format!("{:?}.attack: expected {}, found {}", building_type, expected, attack.get_name()));
+                    }
+                    if let DefenderAttack::Projectile { turn_rate: Some(turn_rate), .. } = attack {
+                        if *turn_rate <= 0. {
+                            errors.push(format!("{:?}.attack.turn_rate: must be positive, found {}", building_type, turn_rate));
+                        }
+                    }
+                    if let DefenderAttack::Chain { falloff, max_targets, arc_range, .. } = attack {
+                        if *falloff <= 0. || *falloff > 1. {
+                            errors.push(format!("{:?}.attack.falloff: must be in (0, 1], found {}", building_type, falloff));
+                        }
+                        if *max_targets == 0 {
+                            errors.push(format!("{:?}.attack.max_targets: must be at least 1, found {}", building_type, max_targets));
+                        }
+                        if *arc_range <= 0. {
+                            errors.push(format!("{:?}.attack.arc_range: must be positive, found {}", building_type, arc_range));
+                        }
+                    }
+                    let (damage, sprite) = match attack {
+                        DefenderAttack::Projectile { damage, sprite, .. } => (damage, Some(sprite)),
+                        DefenderAttack::Splash { damage, sprite, .. } => (damage, Some(sprite)),
+                        DefenderAttack::Chain { damage, .. } => (damage, None)
+                    };
+                    if *damage <= 0. {
+                        errors.push(format!("{:?}.attack.damage: must be positive, found {}", building_type, damage));
+                    }
+                    if let Some(missing) = sprite.and_then(|sprite| sprite.find_missing(textures)) {
+                        errors.push(format!("{:?}.attack.sprite: unknown {} in assets/definitions.json", building_type, missing));
+                    }
+                }
+            }
+        }
+        return errors;
     }
 
     pub fn get_building_config(&self, building_type: &BuildingType) -> Option<&BuildingConfig> {
@@ -103,11 +372,231 @@ impl BuildingResource {
         return self.get_building_config(building_type).map(|e| e.get_dps()).unwrap_or(0.);
     }
 
+    pub fn get_attack_range(&self, building_type: &BuildingType) -> f32 {
+        return self.get_building_config(building_type).map(|e| e.get_attack_range()).unwrap_or_default();
+    }
+
+    pub fn get_min_range(&self, building_type: &BuildingType) -> f32 {
+        return self.get_building_config(building_type).map(|e| e.get_min_range()).unwrap_or_default();
+    }
+
     pub fn get_blocking(&self, building_type: &BuildingType) -> bool {
         return self.get_building_config(building_type).map(|e| e.get_blocking()).unwrap_or_default();
     }
 
+    pub fn get_aura_radius(&self, building_type: &BuildingType) -> f32 {
+        return self.get_building_config(building_type).map(|e| e.get_aura_radius()).unwrap_or_default();
+    }
+
     pub fn get_cost(&self, building_type: &BuildingType) -> i32 {
         return self.get_building_config(building_type).map(|e| e.get_cost()).unwrap_or_default();
     }
-}
\ No newline at end of file
+
+    pub fn set_default_sprite(&mut self, building_type: BuildingType, sprite: &'static str) {
+        self.default_sprites.insert(building_type, sprite);
+    }
+
+    /* Sprite name in the "towers" atlas the building is drawn with, None without a definition */
+    pub fn get_sprite_name(&self, building_type: &BuildingType) -> Option<&str> {
+        let config = self.get_building_config(building_type)?;
+        return config.sprite.as_deref().or(self.default_sprites.get(building_type).copied());
+    }
+
+    /* Sprite name in the "towers" atlas to show the building with in menus */
+    pub fn get_icon(&self, building_type: &BuildingType) -> Option<&str> {
+        return self.get_building_config(building_type)?.icon.as_deref().or(self.get_sprite_name(building_type));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::Vec2, time::{Timer, TimerMode}};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::world::{attackers::AnimationIndices, towers::{DamageType, MarkEffect, ProjectileSprite}};
+
+    fn static_sprite() -> ProjectileSprite {
+        return ProjectileSprite::Static { name: "towers".to_string(), index: 7, size: Vec2::new(8., 3.) };
+    }
+
+    fn animated_sprite() -> ProjectileSprite {
+        return ProjectileSprite::Animated {
+            name: "large_explosion".to_string(),
+            animation_name: "primary".to_string(),
+            animation: AnimationIndices::new(2, 5),
+            timer: Timer::from_seconds(0.15, TimerMode::Repeating),
+            size: Vec2::new(16., 16.),
+        };
+    }
+
+    fn projectile_attack(sprite: ProjectileSprite) -> DefenderAttack {
+        return DefenderAttack::Projectile {
+            damage_type: DamageType::Piercing,
+            damage: 12.5,
+            projectile_speed: 300.,
+            sprite,
+            piercing: 2,
+            forks_on_hit: 1,
+            mark: Some(MarkEffect { bonus_mult: 1.5, duration: 3. }),
+            homing: true,
+            turn_rate: Some(270.),
+        };
+    }
+
+    fn splash_attack(sprite: ProjectileSprite) -> DefenderAttack {
+        return DefenderAttack::Splash {
+            damage_type: DamageType::Explosive,
+            damage: 40.,
+            travel_time: 1.2,
+            splash_radius: 48.,
+            sprite,
+        };
+    }
+
+    fn chain_attack() -> DefenderAttack {
+        return DefenderAttack::Chain {
+            damage_type: DamageType::Magic,
+            damage: 20.,
+            falloff: 0.5,
+            max_targets: 5,
+            arc_range: 96.,
+        };
+    }
+
+    fn defender(building_type: BuildingType, attack: DefenderAttack) -> Building {
+        return Building {
+            building_type,
+            config: BuildingConfig {
+                cost: 50,
+                blocking: true,
+                type_config: BuildingTypeConfig::Defender { attack_timer: 0.8, attack, attack_range: 160., min_range: 0., arc_half_width: None },
+                sprite: None,
+                icon: None,
+            },
+        };
+    }
+
+    fn round_trip(building: &Building) -> Building {
+        let json = serde_json::to_string(building).unwrap();
+        return serde_json::from_str(&json).unwrap();
+    }
+
+    #[test]
+    fn every_attack_and_sprite_variant_round_trips() {
+        let buildings = vec![
+            defender(BuildingType::Arrow, projectile_attack(static_sprite())),
+            defender(BuildingType::Ballista, projectile_attack(animated_sprite())),
+            defender(BuildingType::Cannon, splash_attack(static_sprite())),
+            defender(BuildingType::Splitter, splash_attack(animated_sprite())),
+            defender(BuildingType::Tesla, chain_attack()),
+            Building {
+                building_type: BuildingType::Freeze,
+                config: BuildingConfig { cost: 80, blocking: true, type_config: BuildingTypeConfig::Aura { radius: 96., factor: 0.6, attack_speed_bonus: 0., damage_bonus: 0. }, sprite: None, icon: None },
+            },
+            Building {
+                building_type: BuildingType::Banner,
+                config: BuildingConfig { cost: 60, blocking: true, type_config: BuildingTypeConfig::Aura { radius: 100., factor: 1., attack_speed_bonus: 0.2, damage_bonus: 0.1 }, sprite: None, icon: None },
+            },
+            Building {
+                building_type: BuildingType::Wall,
+                config: BuildingConfig { cost: 5, blocking: true, type_config: BuildingTypeConfig::Wall, sprite: None, icon: None },
+            },
+        ];
+        for building in buildings {
+            assert_eq!(round_trip(&building), building);
+        }
+    }
+
+    #[test]
+    fn random_projectile_attacks_round_trip() {
+        let mut rng = StdRng::seed_from_u64(2113);
+        let damage_types = DamageType::ALL;
+        for _ in 0..100 {
+            let sprite = if rng.gen_bool(0.5) { static_sprite() } else { animated_sprite() };
+            let attack = DefenderAttack::Projectile {
+                damage_type: damage_types[rng.gen_range(0..damage_types.len())],
+                damage: rng.gen_range(0.1..500.),
+                projectile_speed: rng.gen_range(1.0..1000.),
+                sprite,
+                piercing: rng.gen(),
+                forks_on_hit: rng.gen(),
+                mark: if rng.gen_bool(0.5) { Some(MarkEffect { bonus_mult: rng.gen_range(1.0..3.), duration: rng.gen_range(0.1..10.) }) } else { None },
+                homing: rng.gen(),
+                turn_rate: if rng.gen_bool(0.5) { Some(rng.gen_range(1.0..720.)) } else { None },
+            };
+            let building = defender(BuildingType::Arrow, attack);
+            assert_eq!(round_trip(&building), building);
+        }
+    }
+
+    #[test]
+    fn animated_sprite_accepts_seconds_and_full_timers() {
+        let seconds = r#"{ "Animated": { "name": "a", "animation_name": "b", "animation": { "start": 0, "end": 3 }, "timer": 0.25, "size": [4.0, 4.0] } }"#;
+        let full = serde_json::to_string(&Timer::from_seconds(0.25, TimerMode::Repeating)).unwrap();
+        let legacy = seconds.replace("0.25", &full);
+        let from_seconds: ProjectileSprite = serde_json::from_str(seconds).unwrap();
+        let from_legacy: ProjectileSprite = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(from_seconds, from_legacy);
+    }
+
+    #[test]
+    fn resource_round_trips_through_json() {
+        let buildings = vec![
+            defender(BuildingType::Arrow, projectile_attack(static_sprite())),
+            defender(BuildingType::Cannon, splash_attack(animated_sprite())),
+        ];
+        let resource = BuildingResource::from_json(&serde_json::to_string(&buildings).unwrap()).unwrap();
+        let reloaded = BuildingResource::from_json(&resource.to_json().unwrap()).unwrap();
+        for building in buildings {
+            assert_eq!(reloaded.get_building_config(&building.building_type), Some(&building.config));
+        }
+    }
+
+    #[test]
+    fn elemental_arrows_round_trip_with_their_damage_type() {
+        for (building_type, damage_type) in [(BuildingType::FireArrow, DamageType::Fire), (BuildingType::IceArrow, DamageType::Ice)] {
+            let mut attack = projectile_attack(static_sprite());
+            if let DefenderAttack::Projectile { damage_type: attack_type, .. } = &mut attack {
+                *attack_type = damage_type;
+            }
+            let building = defender(building_type, attack);
+            assert_eq!(round_trip(&building), building);
+        }
+    }
+
+    #[test]
+    fn icons_fall_back_to_the_building_sprite() {
+        let mut resource = BuildingResource::from_json(r#"[
+            { "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } },
+            { "building_type": "Banner", "config": { "cost": 60, "blocking": true, "type_config": { "Aura": { "radius": 100.0, "damage_bonus": 0.1 } }, "icon": "banner_icon" } }
+        ]"#).unwrap();
+        resource.set_default_sprite(BuildingType::Wall, "wall");
+        assert_eq!(resource.get_icon(&BuildingType::Wall), Some("wall"));
+        assert_eq!(resource.get_icon(&BuildingType::Banner), Some("banner_icon"));
+        assert_eq!(resource.get_icon(&BuildingType::Tesla), None);
+        assert!(!resource.to_json().unwrap().contains("\"icon\": null"));
+    }
+
+    #[test]
+    fn validation_catches_an_attack_that_does_not_fit_the_building() {
+        let buildings = vec![
+            defender(BuildingType::Arrow, projectile_attack(static_sprite())),
+            defender(BuildingType::Cannon, chain_attack()),
+        ];
+        let resource = BuildingResource::from_json(&serde_json::to_string(&buildings).unwrap()).unwrap();
+        let errors = resource.validate(&TextureResource::default());
+        let attack_errors: Vec<&String> = errors.iter().filter(|error| error.contains(".attack:")).collect();
+        assert_eq!(attack_errors, vec!["Cannon.attack: expected Splash, found Chain"]);
+        // The file name is added once where the errors are reported
+        assert!(errors.iter().all(|error| !error.contains("tower_definitions.json")));
+    }
+
+    #[test]
+    fn chain_dps_counts_the_expected_hops() {
+        let tesla = defender(BuildingType::Tesla, chain_attack());
+        // 20 + 10 + 5 per 0.8 seconds, the last two of the five hops aren't expected to land
+        assert!((tesla.config.get_dps() - 35. / 0.8).abs() < 1e-4);
+        assert!(tesla.config.is_aoe());
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use bevy::{prelude::{Bundle, Color, Commands, Resource}, utils::HashMap};
+
+use crate::textures::TextureResource;
+
+use super::{building_configuration::{BuildingResource, BuildingType}, towers::{StructureBuilder, TowerField, DefenderBundle, BuildError, WallBundle, FreezeTower, BannerBundle, ZoneDamageTower}};
+
+pub type Spawner = Arc<dyn Fn(&mut Commands, BuildingType, &BuildingResource, &TowerField, &TextureResource, usize, usize) -> Result<(), String> + Send + Sync>;
+
+struct Registration {
+    spawner: Spawner,
+    /* Key for Localization::t, the name shown in menus and logs */
+    name: &'static str,
+    /* Sprite in the "towers" atlas for definitions that don't pick one */
+    sprite: &'static str,
+    minimap_color: Color
+}
+
+/* Knows how to spawn, name and draw every building type. A new building still needs its BuildingType variant, its definition in tower_definitions.json
+   and, depending on what it is, its arms in get_expected_attack, get_footprint and BuildingResource::validate before it is registered here */
+#[derive(Resource)]
+pub struct BuildingRegistry {
+    registrations: HashMap<BuildingType, Registration>,
+    /* Registration order, so menus and presets don't depend on the map's order */
+    order: Vec<BuildingType>
+}
+
+impl BuildingRegistry {
+    pub fn empty() -> Self {
+        return Self { registrations: HashMap::new(), order: Vec::new() };
+    }
+
+    /* Registering a type again replaces it but keeps its place */
+    pub fn register<B: StructureBuilder + Bundle>(&mut self, building_type: BuildingType, name: &'static str, sprite: &'static str, minimap_color: Color) -> &mut Self {
+        return self.insert(building_type, Registration { spawner: Arc::new(spawn_bundle::<B>), name, sprite, minimap_color });
+    }
+
+    /* Towers with an attack share one bundle, built from whatever attack their configuration has */
+    pub fn register_defender(&mut self, building_type: BuildingType, name: &'static str, sprite: &'static str, minimap_color: Color) -> &mut Self {
+        return self.insert(building_type, Registration { spawner: Arc::new(spawn_defender), name, sprite, minimap_color });
+    }
+
+    fn insert(&mut self, building_type: BuildingType, registration: Registration) -> &mut Self {
+        if self.registrations.insert(building_type, registration).is_none() {
+            self.order.push(building_type);
+        }
+        return self;
+    }
+
+    pub fn get_spawner(&self, building_type: BuildingType) -> Option<Spawner> {
+        return self.registrations.get(&building_type).map(|registration| registration.spawner.clone());
+    }
+
+    pub fn get_building_types(&self) -> &[BuildingType] {
+        return &self.order;
+    }
+
+    pub fn get_name(&self, building_type: BuildingType) -> &'static str {
+        return self.registrations.get(&building_type).map_or("Unknown building", |registration| registration.name);
+    }
+
+    pub fn get_sprite_name(&self, building_type: BuildingType) -> Option<&'static str> {
+        return self.registrations.get(&building_type).map(|registration| registration.sprite);
+    }
+
+    pub fn get_minimap_color(&self, building_type: BuildingType) -> Color {
+        return self.registrations.get(&building_type).map_or(Color::WHITE, |registration| registration.minimap_color);
+    }
+
+    /* Hands the registered sprites to the definitions, which only name a sprite when it differs */
+    pub fn apply_default_sprites(&self, buildings: &mut BuildingResource) {
+        for (building_type, registration) in self.registrations.iter() {
+            buildings.set_default_sprite(*building_type, registration.sprite);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(&self, commands: &mut Commands, defenders: &BuildingResource, tower_field: &TowerField, named_textures: &TextureResource, building_type: BuildingType, x: usize, y: usize) -> Result<(), String> {
+        let registration = self.registrations.get(&building_type)
+            .ok_or(format!("{:?} was never registered in the building registry", building_type))?;
+        return (registration.spawner)(commands, building_type, defenders, tower_field, named_textures, x, y);
+    }
+}
+
+impl Default for BuildingRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register::<WallBundle>(BuildingType::Wall, "Wall", "wall", Color::DARK_GRAY)
+            .register_defender(BuildingType::Arrow, "Arrow", "arrow", Color::YELLOW)
+            .register_defender(BuildingType::Cannon, "Cannon", "cannon", Color::ORANGE)
+            .register_defender(BuildingType::Ballista, "Ballista", "ballista", Color::rgb(0.9, 0.85, 0.6))
+            .register_defender(BuildingType::Splitter, "Splitter", "splitter", Color::PURPLE)
+            .register_defender(BuildingType::Marker, "Marker", "marker", Color::PINK)
+            .register::<FreezeTower>(BuildingType::Freeze, "Freeze", "freeze", Color::CYAN)
+            .register_defender(BuildingType::Tesla, "Tesla", "tesla", Color::rgb(0.5, 0.7, 1.))
+            .register::<BannerBundle>(BuildingType::Banner, "Banner", "banner", Color::rgb(0.8, 0.1, 0.1))
+            // Drawn over all four of its slots
+            .register_defender(BuildingType::LargeCannon, "Large Cannon", "cannon", Color::ORANGE)
+            .register::<ZoneDamageTower>(BuildingType::ZoneDamage, "Damage Zone", "zone", Color::rgb(0.6, 0.2, 0.9))
+            // Regular arrow towers until the definitions give them a sprite of their own
+            .register_defender(BuildingType::FireArrow, "Fire Arrow", "arrow", Color::rgb(1., 0.4, 0.1))
+            .register_defender(BuildingType::IceArrow, "Ice Arrow", "arrow", Color::rgb(0.6, 0.9, 1.));
+        return registry;
+    }
+}
+
+fn spawn_bundle<B: StructureBuilder + Bundle>(commands: &mut Commands, _building_type: BuildingType, defenders: &BuildingResource, tower_field: &TowerField, named_textures: &TextureResource, x: usize, y: usize) -> Result<(), String> {
+    commands.spawn(B::from_tower_field(defenders, tower_field, named_textures, x, y)?);
+    return Ok(());
+}
+
+fn spawn_defender(commands: &mut Commands, building_type: BuildingType, defenders: &BuildingResource, tower_field: &TowerField, named_textures: &TextureResource, x: usize, y: usize) -> Result<(), String> {
+    let config = defenders.get_building_config(&building_type).ok_or(BuildError::MissingConfig(building_type))?;
+    let sprite_name = defenders.get_sprite_name(&building_type).ok_or(BuildError::MissingConfig(building_type))?;
+    DefenderBundle::from_config(building_type, config, sprite_name, tower_field, named_textures, x, y)?.spawn(commands);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_building_type_is_registered_once() {
+        let registry = BuildingRegistry::default();
+        assert_eq!(registry.get_building_types().len(), 13);
+        for building_type in registry.get_building_types() {
+            assert_eq!(registry.get_building_types().iter().filter(|other| *other == building_type).count(), 1);
+            assert!(registry.get_spawner(*building_type).is_some(), "{:?} has no spawner", building_type);
+            assert!(registry.get_sprite_name(*building_type).is_some(), "{:?} has no sprite", building_type);
+        }
+    }
+
+    #[test]
+    fn registering_again_keeps_the_order() {
+        let mut registry = BuildingRegistry::empty();
+        registry
+            .register::<WallBundle>(BuildingType::Wall, "Wall", "wall", Color::DARK_GRAY)
+            .register_defender(BuildingType::Arrow, "Arrow", "arrow", Color::YELLOW)
+            .register::<WallBundle>(BuildingType::Wall, "Stone Wall", "stone_wall", Color::GRAY);
+        assert_eq!(registry.get_building_types(), &[BuildingType::Wall, BuildingType::Arrow]);
+        assert_eq!(registry.get_sprite_name(BuildingType::Wall), Some("stone_wall"));
+    }
+
+    #[test]
+    fn definitions_only_override_the_registered_sprite_when_they_name_one() {
+        let mut buildings = BuildingResource::from_json(r#"[
+            { "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } },
+            { "building_type": "Banner", "config": { "cost": 60, "blocking": true, "type_config": { "Aura": { "radius": 100.0, "damage_bonus": 0.1 } }, "sprite": "flag" } }
+        ]"#).unwrap();
+        assert_eq!(buildings.get_sprite_name(&BuildingType::Wall), None);
+        BuildingRegistry::default().apply_default_sprites(&mut buildings);
+        assert_eq!(buildings.get_sprite_name(&BuildingType::Wall), Some("wall"));
+        assert_eq!(buildings.get_sprite_name(&BuildingType::Banner), Some("flag"));
+        // The registered sprites aren't written back into the definitions
+        assert!(!buildings.to_json().unwrap().contains("\"wall\""));
+    }
+}
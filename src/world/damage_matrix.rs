@@ -0,0 +1,136 @@
+use std::{fs, io::ErrorKind};
+
+use bevy::{log::warn, prelude::Resource, utils::HashMap};
+
+use super::{attackers::ArmorClass, towers::DamageType};
+
+const MATRIX_PATH: &str = "assets/damage_matrix.json";
+
+/* How much of a hit's damage goes through, looked up by the damage type of the hit and the armor of the attacker */
+#[derive(Resource, Default)]
+pub struct DamageMatrix {
+    multipliers: HashMap<(DamageType, ArmorClass), f32>
+}
+
+impl DamageMatrix {
+    /* The file is optional, without it every hit deals normal damage apart from the elemental multipliers */
+    pub fn load() -> Result<Self, String> {
+        return match fs::read_to_string(MATRIX_PATH) {
+            Ok(contents) => Self::from_json(&contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                warn!("No {} found, armor classes make no difference", MATRIX_PATH);
+                Ok(Self::default())
+            },
+            Err(err) => Err(format!("Failed to read {}: {}", MATRIX_PATH, err))
+        };
+    }
+
+    /* Damage types map to armor classes, e.g. { "Piercing": { "Heavy": 0.5 } } */
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        let rows: HashMap<DamageType, HashMap<ArmorClass, f32>> = serde_json::from_str(contents)
+            .map_err(|err| format!("Failed to parse {}: {}", MATRIX_PATH, err))?;
+        let mut errors = Vec::new();
+        let mut multipliers = HashMap::new();
+        for (damage_type, row) in rows {
+            for (armor_class, multiplier) in row {
+                if multiplier < 0. || !multiplier.is_finite() {
+                    errors.push(format!("{:?} against {:?}: multiplier must be positive, got {}", damage_type, armor_class, multiplier));
+                }
+                multipliers.insert((damage_type, armor_class), multiplier);
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.iter().map(|e| format!("damage_matrix.json: {}", e)).collect::<Vec<String>>().join("\n"));
+        }
+        return Ok(Self { multipliers });
+    }
+
+    /* Combinations the file doesn't mention, they deal normal damage. Elemental damage always has its built-in multipliers */
+    pub fn missing_cells(&self) -> Vec<(DamageType, ArmorClass)> {
+        let mut missing = Vec::new();
+        for damage_type in DamageType::ALL.into_iter().filter(|damage_type| !damage_type.is_elemental()) {
+            for armor_class in ArmorClass::ALL {
+                if !self.multipliers.contains_key(&(damage_type, armor_class)) {
+                    missing.push((damage_type, armor_class));
+                }
+            }
+        }
+        return missing;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.multipliers.is_empty();
+    }
+
+    pub fn get_multiplier(&self, damage_type: DamageType, armor_class: ArmorClass) -> f32 {
+        return self.multipliers.get(&(damage_type, armor_class)).copied().unwrap_or(get_elemental_multiplier(damage_type, armor_class));
+    }
+}
+
+/* What the file falls back to, 1 for every non-elemental type.
+   Flesh is Unarmored or Light, armor such as the stone golems' is Heavy, machines are Mechanical
+   and phantoms, the only ones off the ground, are Ethereal */
+fn get_elemental_multiplier(damage_type: DamageType, armor_class: ArmorClass) -> f32 {
+    return match (damage_type, armor_class) {
+        (DamageType::Fire, ArmorClass::Unarmored | ArmorClass::Light) => 1.5,
+        (DamageType::Fire, ArmorClass::Mechanical) => 0.5,
+        (DamageType::Ice, ArmorClass::Unarmored | ArmorClass::Light) => 1.3,
+        (DamageType::Ice, ArmorClass::Heavy) => 0.8,
+        (DamageType::Lightning, ArmorClass::Heavy) => 1.8,
+        (DamageType::Earth, ArmorClass::Ethereal) => 2.,
+        (DamageType::Earth, _) => 1.2,
+        _ => 1.
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_cells_default_to_one() {
+        let matrix = DamageMatrix::from_json(r#"{ "Piercing": { "Heavy": 0.5, "Unarmored": 1.5 } }"#).unwrap();
+        assert_eq!(matrix.get_multiplier(DamageType::Piercing, ArmorClass::Heavy), 0.5);
+        assert_eq!(matrix.get_multiplier(DamageType::Piercing, ArmorClass::Unarmored), 1.5);
+        assert_eq!(matrix.get_multiplier(DamageType::Magic, ArmorClass::Ethereal), 1.);
+        let missing = matrix.missing_cells();
+        assert_eq!(missing.len(), (DamageType::ALL.len() - DamageType::ELEMENTS.len()) * ArmorClass::ALL.len() - 2);
+        assert!(!missing.contains(&(DamageType::Piercing, ArmorClass::Heavy)));
+    }
+
+    #[test]
+    fn elemental_damage_has_built_in_multipliers() {
+        let matrix = DamageMatrix::default();
+        let expected = [
+            (DamageType::Fire, [1.5, 1.5, 1., 0.5, 1.]),
+            (DamageType::Ice, [1.3, 1.3, 0.8, 1., 1.]),
+            (DamageType::Lightning, [1., 1., 1.8, 1., 1.]),
+            (DamageType::Earth, [1.2, 1.2, 1.2, 1.2, 2.]),
+        ];
+        for (damage_type, multipliers) in expected {
+            for (armor_class, multiplier) in ArmorClass::ALL.into_iter().zip(multipliers) {
+                assert_eq!(matrix.get_multiplier(damage_type, armor_class), multiplier, "{:?} against {:?}", damage_type, armor_class);
+            }
+        }
+        // The file still gets the last word
+        let matrix = DamageMatrix::from_json(r#"{ "Fire": { "Heavy": 0.25 } }"#).unwrap();
+        assert_eq!(matrix.get_multiplier(DamageType::Fire, ArmorClass::Heavy), 0.25);
+        assert_eq!(matrix.get_multiplier(DamageType::Fire, ArmorClass::Light), 1.5);
+    }
+
+    #[test]
+    fn mechanical_and_heavy_armor_take_elements_differently() {
+        let matrix = DamageMatrix::default();
+        // Machines shrug off fire, plate blunts ice but conducts lightning
+        assert!(matrix.get_multiplier(DamageType::Fire, ArmorClass::Mechanical) < matrix.get_multiplier(DamageType::Fire, ArmorClass::Heavy));
+        assert!(matrix.get_multiplier(DamageType::Ice, ArmorClass::Heavy) < matrix.get_multiplier(DamageType::Ice, ArmorClass::Mechanical));
+        assert!(matrix.get_multiplier(DamageType::Lightning, ArmorClass::Heavy) > matrix.get_multiplier(DamageType::Lightning, ArmorClass::Mechanical));
+        assert_eq!(DamageMatrix::from_json(r#"{ "Fire": { "Mechanical": 0.25 } }"#).unwrap().get_multiplier(DamageType::Fire, ArmorClass::Mechanical), 0.25);
+    }
+
+    #[test]
+    fn negative_multipliers_are_rejected() {
+        assert!(DamageMatrix::from_json(r#"{ "Magic": { "Light": -1 } }"#).is_err());
+        assert!(DamageMatrix::from_json(r#"{ "Magic": { "Plated": 1 } }"#).is_err());
+    }
+}
@@ -1,21 +1,20 @@
-use std::{marker::PhantomData, time::Duration, hash::Hash};
-use rand::Rng;
+use std::{collections::VecDeque, time::Duration};
 
-use bevy::{prelude::{Plugin, App, Component, Resource, Commands, ResMut, Res, EventReader, Local, Query, Transform, IntoSystemConfig, Vec3}, time::{Timer, Time}, utils::{HashSet, HashMap}};
+use bevy::{ecs::system::SystemParam, prelude::{Plugin, App, Resource, Commands, Entity, ResMut, Res, EventReader, EventWriter, Local, Query, Transform, IntoSystemConfig, Vec2, With, World}, time::{Timer, Time}, utils::{HashSet, HashMap}};
 
 
-use crate::textures::TextureResource;
+use crate::{textures::TextureResource, error::{FatalError, GameplaySet}, util::{can_afford, GameRng}};
 
-use super::{towers::{StructureBuilder, WallBundle, TowerField, ArrowTower, Defender, SLOT_SIZE, Structure, CannonTower}, building_configuration::{BuildingType, BuildingResource, BuildingConfig}, events::{RoundOverEvent, KillEvent, EntityReachedEnd, RoundStartEvent, DamageEvent, FieldModified, RemovedStructureEvent}, attackers::Attacker, path_finding::{a_star, Path, Node, a_star_with_blocked_node, get_successors, get_self_with_successors, get_all_neighbors}};
+use super::{towers::{TowerField, Defender, SLOT_SIZE, Structure, Buffed, Footprint, DamageZone, DamageConsumerSet, covered_nodes}, building_configuration::{BuildingType, BuildingResource}, building_registry::{BuildingRegistry, Spawner}, game_config::GameConfig, events::{RoundOverEvent, KillEvent, EntityReachedEnd, RoundStartEvent, DamageEvent, FieldModified, RemoveStructureRequest, PlaceStructureRequest, RemovedStructureEvent, GoldStolenEvent, BoardClearedEvent, DefenderPanicEvent}, attackers::{Attacker, AttackerType, LeakEffect}, mutators::RoundModifiers, rounds::RoundResource, path_finding::{Path, PathCache, Node, a_star_with_blocked_node, a_star_with_blocked_nodes, verify_placement_valid, verify_footprint_placement_valid, get_self_with_successors, get_all_neighbors, find_critical_nodes}, defender_strategy::{ActiveStrategy, DefenderAction, DefenderView, WaitReason}};
 
 #[derive(Debug)]
-struct WeightedNode {
-    node: Node,
-    weight: f32
+pub(crate) struct WeightedNode {
+    pub(crate) node: Node,
+    pub(crate) weight: f32
 }
 
 #[derive(Resource)]
-struct Buildings {
+pub(crate) struct Buildings {
     presets: HashMap<BuildingType, BuildingPreset>
 }
 
@@ -23,6 +22,10 @@ impl Buildings {
     pub fn get_preset(&self, building_type: BuildingType) -> &BuildingPreset {
         return self.presets.get(&building_type).unwrap();
     }
+
+    pub fn has_preset(&self, building_type: BuildingType) -> bool {
+        return self.presets.contains_key(&building_type);
+    }
 }
 
 impl Default for Buildings {
@@ -37,6 +40,58 @@ pub struct ResourceStore {
     pub lives: i32
 }
 
+/* Attackers with at most this many path nodes left make the defender panic */
+const PANIC_REMAINING_NODES: usize = 6;
+/* The action cooldown runs this many times faster while panicking */
+const PANIC_SPEEDUP: u32 = 2;
+/* Actions skipped to save up for an expensive tower before settling for an Arrow tower */
+pub const SAVING_PATIENCE: u32 = 6;
+/* Times a slot is razed before the defender stops putting the same building back */
+pub const MAX_REBUILDS: u32 = 3;
+
+/* Gold paid to the defender for getting through a round, so rounds with few kills still fund the rebuild */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompletionBonus {
+    pub base: i32,
+    pub per_round: i32,
+    /* Paid for every life the defender still has */
+    pub per_life: i32,
+}
+
+impl CompletionBonus {
+    pub fn get(&self, round_number: u32, lives: i32) -> i32 {
+        return self.base + self.per_round * round_number as i32 + self.per_life * lives.max(0);
+    }
+}
+
+impl Default for CompletionBonus {
+    fn default() -> Self {
+        return Self { base: 10, per_round: 2, per_life: 1 };
+    }
+}
+
+/* Tower the defender holds its gold back for */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SavingsTarget {
+    pub building_type: BuildingType,
+    /* Gold still missing when the last action was skipped */
+    pub shortfall: i32,
+    /* Actions skipped so far */
+    pub ticks: u32
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SavingsPlan {
+    Build(BuildingType),
+    Save(SavingsTarget)
+}
+
+/* Taken before anything is scored, one per action */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PriorityAction {
+    Rebuild { node: Node, building_type: BuildingType }
+}
+
 #[derive(Resource)]
 pub struct DefenderConfiguration {
     pub action_cooldown: Timer,
@@ -47,16 +102,74 @@ pub struct DefenderConfiguration {
     pub estimated_damage_potential: f32,
     pub path_length: f32,
     pub path_distance: f32,
-    pub path: Path,
+    /* One per start, in the order of TowerField::get_starts() */
+    pub paths: Vec<Path>,
     pub path_hash: HashSet<Node>,
-    pub can_build_wall: bool,
-    pub can_build_tower: bool,
+    /* Free slots a wall or tower could still take without cutting a start off from the end, recounted whenever the field changes.
+       Walls only count next to the path where they make it longer. Zero until the next change once the brain found no room */
+    pub free_wall_slots: usize,
+    pub free_tower_slots: usize,
     pub num_defenders: i32,
     pub num_walls: i32,
+    pub expected_dodge: f32,
+    /* Path nodes without a short way around them, recomputed whenever the field changes */
+    pub choke_points: Vec<Node>,
+    /* Path nodes no tower or zone reaches, zones are placed to cover them */
+    pub dead_spots: Vec<Node>,
+    /* Attacker type that soaked up the most damage last round, towers are picked to counter it */
+    pub dominant_threat: Option<AttackerType>,
+    /* An attacker is about to leak, actions come twice as fast and go to blocking it */
+    pub panicking: bool,
+    /* Free slot ahead of the nearest attacker that can be walled off without sealing the path */
+    pub panic_wall: Option<Node>,
+    pub savings: Option<SavingsTarget>,
+    pub priority_actions: VecDeque<PriorityAction>,
+    /* How often attackers tore down what stood on a slot */
+    pub razed: HashMap<Node, u32>,
+    pub completion_bonus: CompletionBonus,
     sell_values: Vec<WeightedNode>
 }
 
+impl Default for DefenderConfiguration {
+    fn default() -> Self {
+        Self {
+            action_cooldown: Timer::from_seconds(1.5, bevy::time::TimerMode::Repeating),
+            damage_weight: 1.4,
+            estimated_damage_needed: 1000.,
+            wall_weight: 1.0,
+            sell_weight: 1.0,
+            path_length: 0.,
+            path_distance: 0.,
+            paths: Vec::new(),
+            path_hash: HashSet::new(),
+            estimated_damage_potential: 0.,
+            sell_values: Vec::new(),
+            // Unknown until the field is first counted
+            free_wall_slots: usize::MAX,
+            free_tower_slots: usize::MAX,
+            num_defenders: 0,
+            num_walls: 0,
+            expected_dodge: 0.,
+            choke_points: Vec::new(),
+            dead_spots: Vec::new(),
+            dominant_threat: None,
+            panicking: false,
+            panic_wall: None,
+            savings: None,
+            priority_actions: VecDeque::new(),
+            razed: HashMap::new(),
+            completion_bonus: CompletionBonus::default()
+        }
+    }
+}
+
 impl DefenderConfiguration {
+    /* Nodes of every path, the ones several starts share only once */
+    pub fn get_path_nodes(&self) -> Vec<Node> {
+        let mut seen = HashSet::new();
+        return self.paths.iter().flat_map(|path| path.get_nodes()).filter(|node| seen.insert(*node)).collect();
+    }
+
     pub fn is_node_adjacent_to_or_on_path(&self, node: Node) -> bool {
         let x = node.x;
         let y = node.y;
@@ -71,6 +184,72 @@ impl DefenderConfiguration {
                 self.path_hash.contains(&Node::new(x - 1, y + 1));
     }
 
+    /* Forgets everything that was built, the path itself is recomputed from the FieldModified of the clear */
+    pub fn reset_layout(&mut self) {
+        self.num_defenders = 0;
+        self.num_walls = 0;
+        self.sell_values.clear();
+        self.priority_actions.clear();
+        self.razed.clear();
+        self.estimated_damage_potential = 0.;
+    }
+
+    pub fn count_free_slots(&mut self, field: &TowerField, cache: &mut PathCache) {
+        let on_path: HashSet<Node> = cache.get_nodes_on_all_paths(field).into_iter().collect();
+        let critical: HashSet<Node> = field.get_starts().iter()
+            .flat_map(|start| find_critical_nodes(field, *start, field.get_end()))
+            .collect();
+        self.free_wall_slots = 0;
+        self.free_tower_slots = 0;
+        for x in 0..field.get_width() as i32 {
+            for y in 0..field.get_height() as i32 {
+                let node = Node::new(x, y);
+                if field.is_node_occupied(node) || self.is_given_up(node) || critical.contains(&node) {
+                    continue;
+                }
+                self.free_tower_slots += 1;
+                if on_path.contains(&node) || get_all_neighbors(node).iter().any(|neighbor| on_path.contains(neighbor)) {
+                    self.free_wall_slots += 1;
+                }
+            }
+        }
+    }
+
+    /* Weight of the tower most worth selling, 0 without any */
+    pub fn get_best_sell_value(&self) -> f32 {
+        return self.sell_values.last().map(|e| e.weight).unwrap_or(0.);
+    }
+
+    pub fn get_best_sell_node(&self) -> Option<Node> {
+        return self.sell_values.last().map(|e| e.node);
+    }
+
+    /* Tower to build with this action, or the savings to keep while gold is put aside for `target` instead.
+       Arrow towers are the fallback once patience runs out, so they are never saved for */
+    pub fn plan_savings(&self, target: BuildingType, shortfall: i32) -> SavingsPlan {
+        if target != BuildingType::Arrow && shortfall > 0 {
+            let ticks = self.savings.map_or(0, |savings| savings.ticks);
+            if ticks < SAVING_PATIENCE {
+                return SavingsPlan::Save(SavingsTarget { building_type: target, shortfall, ticks: ticks + 1 });
+            }
+            return SavingsPlan::Build(BuildingType::Arrow);
+        }
+        return SavingsPlan::Build(target);
+    }
+
+    /* Puts back what attackers destroyed, slots razed too often are given up and mazed around instead */
+    pub fn queue_rebuild(&mut self, node: Node, building_type: BuildingType) {
+        let razed = self.razed.entry(node).or_insert(0);
+        *razed += 1;
+        if *razed < MAX_REBUILDS {
+            self.priority_actions.push_back(PriorityAction::Rebuild { node, building_type });
+        }
+    }
+
+    pub fn is_given_up(&self, node: Node) -> bool {
+        return self.razed.get(&node).is_some_and(|razed| *razed >= MAX_REBUILDS);
+    }
+
     pub fn get_wall_factor(&self) -> f32 {
         if self.num_walls == 0 {
             return 1.;
@@ -80,13 +259,21 @@ impl DefenderConfiguration {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct RoundStats {
     pub damage_dealt: f32,
     pub round_duration: Duration,
     pub num_reached_end: i32,
     pub closest_distance_to_end: f32,
-    pub num_killed: i32
+    pub num_killed: i32,
+    pub average_dodge: f32,
+    /* Pressure put on the defender per gold the attacker spent, set when a round ends */
+    pub gold_efficiency: f32,
+    pub best_gold_efficiency: f32,
+    /* Gold the attacker earned during the last finished round, including its end of round bounty */
+    pub gold_earned: i32,
+    /* Gold the defender was paid for finishing the last round */
+    pub completion_bonus: i32
 }
 
 pub struct BuildingPreset {
@@ -95,85 +282,88 @@ pub struct BuildingPreset {
     aoe: bool,
     cost: i32,
     blocking: bool,
+    spawner: Spawner
 }
 
 impl BuildingPreset {
-    pub fn new(building_type: BuildingType, cost: i32, blocking: bool, aoe: bool, dps: f32) -> Self {
-        return Self { cost, blocking, building_type, aoe, dps };
-    }
-    pub fn spawn(&self, mut commands: Commands, defenders: &BuildingResource, tower_field: &TowerField, named_textures: &TextureResource, x: usize, y: usize) {
-        match self.building_type {
-            BuildingType::Arrow => {
-                commands.spawn(ArrowTower::from_tower_field(defenders, tower_field, named_textures, x, y));
-            },
-            BuildingType::Wall => {
-                commands.spawn(WallBundle::from_tower_field(defenders, tower_field, named_textures, x, y));
-            },
-            BuildingType::Cannon => {
-                commands.spawn(CannonTower::from_tower_field(defenders, tower_field, named_textures, x, y));
-            }
-        }
+    pub fn new(building_type: BuildingType, cost: i32, blocking: bool, aoe: bool, dps: f32, spawner: Spawner) -> Self {
+        return Self { cost, blocking, building_type, aoe, dps, spawner };
+    }
+    pub fn spawn(&self, commands: &mut Commands, defenders: &BuildingResource, tower_field: &TowerField, named_textures: &TextureResource, x: usize, y: usize) -> Result<(), String> {
+        return (self.spawner)(commands, self.building_type, defenders, tower_field, named_textures, x, y);
     }
 }
 
+/* Who builds and sells for the defender, a human defender leaves the field to the player */
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DefenderControl {
+    Human,
+    Computer
+}
+
+impl Default for DefenderControl {
+    fn default() -> Self {
+        return Self::Computer;
+    }
+}
+
+pub fn computer_defends(control: Res<DefenderControl>) -> bool {
+    return *control == DefenderControl::Computer;
+}
+
+pub fn human_defends(control: Res<DefenderControl>) -> bool {
+    return *control == DefenderControl::Human;
+}
+
 pub struct DefenderController;
 
 impl Plugin for DefenderController {
     fn build(&self, app: &mut App) {
+        let game_config = app.world.get_resource::<GameConfig>().cloned().unwrap_or_default();
         app
             .init_resource::<Buildings>()
-            .insert_resource(DefenderConfiguration {
-                action_cooldown: Timer::from_seconds(1.5, bevy::time::TimerMode::Repeating),
-                damage_weight: 1.4,
-                estimated_damage_needed: 1000.,
-                wall_weight: 1.0,
-                sell_weight: 1.0,
-                path_length: 0.,
-                path_distance: 0.,
-                path: Path::empty(),
-                path_hash: HashSet::new(),
-                estimated_damage_potential: 0.,
-                sell_values: Vec::new(),
-                can_build_wall: true,
-                can_build_tower: true,
-                num_defenders: 0,
-                num_walls: 0
-            })
-            .insert_resource(ResourceStore {gold: 200, lives: 50})
-            .insert_resource(RoundStats {
-                damage_dealt: 0.,
-                round_duration: Duration::from_secs(0),
-                closest_distance_to_end: 0.,
-                num_reached_end: 0,
-                num_killed: 0
-            })
+            .init_resource::<DefenderConfiguration>()
+            .init_resource::<ActiveStrategy>()
+            .init_resource::<PathCache>()
+            .init_resource::<DefenderControl>()
+            .insert_resource(ResourceStore {gold: game_config.defender_starting_gold, lives: game_config.defender_starting_lives})
+            .init_resource::<RoundStats>()
             .add_startup_system(setup)
-            .add_system(collect_event_stats)
-            .add_system(inspect_enemies)
-            .add_system(perform_an_action)
-            .add_system(listen_removals)
-            .add_system(listen_kills)
-            .add_system(listen_goals);
+            .add_system(collect_event_stats.in_set(GameplaySet).in_set(DamageConsumerSet))
+            .add_system(inspect_enemies.in_set(GameplaySet))
+            // Stats and listeners keep running for a human defender, only the decisions are left out
+            .add_system(detect_panic.in_set(GameplaySet).run_if(computer_defends).before(perform_an_action))
+            .add_system(update_dead_spots.in_set(GameplaySet).run_if(computer_defends).after(perform_an_action))
+            .add_system(perform_an_action.in_set(GameplaySet).run_if(computer_defends))
+            .add_system(process_placement_requests.in_set(GameplaySet).run_if(human_defends))
+            .add_system(listen_removals.in_set(GameplaySet).before(perform_an_action))
+            .add_system(listen_board_cleared.in_set(GameplaySet).before(perform_an_action))
+            .add_system(listen_kills.in_set(GameplaySet))
+            .add_system(pay_completion_bonus.in_set(GameplaySet))
+            .add_system(listen_goals.in_set(GameplaySet));
     }
 }
 
 fn setup(
     mut res: ResMut<Buildings>,
-    buildings: Res<BuildingResource>
+    buildings: Res<BuildingResource>,
+    registry: Res<BuildingRegistry>
 ) {
-    if let Some(preset) = create_preset(&buildings, BuildingType::Arrow) { res.presets.insert(preset.building_type, preset); }
-    if let Some(preset) = create_preset(&buildings, BuildingType::Wall) { res.presets.insert(preset.building_type, preset); }
-    if let Some(preset) = create_preset(&buildings, BuildingType::Cannon) { res.presets.insert(preset.building_type, preset); }
+    for building_type in registry.get_building_types() {
+        if let Some(preset) = create_preset(&buildings, &registry, *building_type) { res.presets.insert(preset.building_type, preset); }
+    }
 }
 
-fn create_preset(buildings: &BuildingResource, building_type: BuildingType) -> Option<BuildingPreset> {
+fn create_preset(buildings: &BuildingResource, registry: &BuildingRegistry, building_type: BuildingType) -> Option<BuildingPreset> {
+    let spawner = registry.get_spawner(building_type)?;
     return buildings.get_building_config(&building_type).map(|config| {
         BuildingPreset::new(
             building_type,
             config.get_cost(), 
             config.get_blocking(), 
             config.is_aoe(), 
-            config.get_dps()
+            config.get_dps(),
+            spawner
         )
     });
 }
@@ -188,11 +378,21 @@ fn collect_event_stats(
     mut stats: ResMut<RoundStats>,
     mut config: ResMut<DefenderConfiguration>,
     mut round_active: Local<bool>,
+    mut damage_by_attacker: Local<HashMap<Entity, (AttackerType, f32)>>,
+    mut kill_damage_by_type: Local<HashMap<AttackerType, f32>>,
+    attacker_types: Query<&AttackerType>,
     field: Res<TowerField>,
     time: Res<Time>
 ) {
     if !round_end.is_empty() {
         config.estimated_damage_needed = stats.damage_dealt * 1.10;
+        config.expected_dodge = stats.average_dodge;
+        // A round nobody got hit in says nothing new about the attacker, the last threat stays
+        if let Some((threat, _)) = kill_damage_by_type.iter().max_by(|a, b| a.1.total_cmp(b.1)) {
+            config.dominant_threat = Some(*threat);
+        }
+        kill_damage_by_type.clear();
+        damage_by_attacker.clear();
         *round_active = false;
         round_end.clear();
     }
@@ -208,14 +408,22 @@ fn collect_event_stats(
     }
 
     if *round_active {
-        for _ in deaths.iter() {
+        // Damage is only held against an attacker type once the attacker died of it, the killing hit arrives in the same frame as the kill
+        for ev in damage.iter() {
+            stats.damage_dealt += ev.amount;
+            if let Ok(attacker_type) = attacker_types.get(ev.target) {
+                damage_by_attacker.entry(ev.target).or_insert((*attacker_type, 0.)).1 += ev.amount;
+            }
+        }
+        for ev in deaths.iter() {
             stats.num_killed += 1;
+            if let Some((attacker_type, taken)) = damage_by_attacker.remove(&ev.target) {
+                *kill_damage_by_type.entry(attacker_type).or_default() += taken;
+            }
         }
-        for _ in reached_end.iter() {
+        for ev in reached_end.iter() {
             stats.num_reached_end += 1;
-        }
-        for ev in damage.iter() {
-            stats.damage_dealt += ev.amount;
+            damage_by_attacker.remove(&ev.entity);
         }
         stats.round_duration = stats.round_duration + time.delta();
     }
@@ -232,34 +440,159 @@ fn inspect_enemies(
             stats.closest_distance_to_end = distance;
         }
     }
+    if !query.is_empty() {
+        stats.average_dodge = query.iter().map(|(attacker, _)| attacker.dodge_chance).sum::<f32>() / query.iter().len() as f32;
+    }
+}
+
+pub(crate) fn detect_panic(
+    attackers: Query<(&Path, &Transform), With<Attacker>>,
+    field: Res<TowerField>,
+    mut defender_config: ResMut<DefenderConfiguration>,
+    mut panics: EventWriter<DefenderPanicEvent>
+) {
+    let threat = attackers.iter()
+        .map(|(path, transform)| (path.get_size().saturating_sub(path.get_current_index()), path, transform))
+        .filter(|(remaining, _, _)| *remaining <= PANIC_REMAINING_NODES)
+        .min_by_key(|(remaining, _, _)| *remaining);
+    let panicking = threat.is_some();
+    if panicking != defender_config.panicking {
+        defender_config.panicking = panicking;
+        panics.send(DefenderPanicEvent { panicking });
+    }
+    let panic_wall = threat.and_then(|(_, path, transform)| find_panic_wall(&field, path, field.node_at(transform.translation.truncate())));
+    if panic_wall != defender_config.panic_wall {
+        defender_config.panic_wall = panic_wall;
+    }
+}
+
+/* Nearest free node ahead of the attacker that can be walled off while it, and every start, still has a way to the end */
+fn find_panic_wall(field: &TowerField, path: &Path, attacker_node: Node) -> Option<Node> {
+    let end = field.get_end();
+    return (path.get_current_index()..path.get_size())
+        .map(|index| path.get_node(index))
+        .filter(|node| *node != end && *node != attacker_node && !field.is_node_occupied(*node))
+        .find(|node| verify_placement_valid(field, *node, true) && a_star_with_blocked_node(field, attacker_node, end, Some(*node)).is_some());
 }
 
 fn listen_kills(
     mut resources: ResMut<ResourceStore>,
-    mut deaths: EventReader<KillEvent>
+    mut deaths: EventReader<KillEvent>,
+    modifiers: Res<RoundModifiers>
 ) {
     for ev in deaths.iter() {
-        resources.gold += ev.bounty;
+        resources.gold += modifiers.scale_bounty(ev.bounty);
+    }
+}
+
+fn pay_completion_bonus(
+    mut resources: ResMut<ResourceStore>,
+    mut stats: ResMut<RoundStats>,
+    mut round_end: EventReader<RoundOverEvent>,
+    config: Res<DefenderConfiguration>,
+    round: Res<RoundResource>
+) {
+    for _ in round_end.iter() {
+        let bonus = config.completion_bonus.get(round.get_round_number(), resources.lives);
+        resources.gold += bonus;
+        stats.completion_bonus = bonus;
     }
 }
 
-fn listen_goals(
+pub(crate) fn listen_goals(
     mut resources: ResMut<ResourceStore>,
-    mut goals: EventReader<EntityReachedEnd>
+    mut goals: EventReader<EntityReachedEnd>,
+    mut stolen: EventWriter<GoldStolenEvent>,
+    field: Res<TowerField>
 ) {
     for ev in goals.iter() {
         resources.lives -= 1;
+        if let LeakEffect::StealGold(amount) = ev.leak_effect {
+            // Never more than the defender has, gold can't go negative
+            let amount = amount.min(resources.gold).max(0);
+            resources.gold -= amount;
+            if amount > 0 {
+                stolen.send(GoldStolenEvent { entity: ev.entity, amount, position: field.get_end_transform().translation.truncate() });
+            }
+        }
     }
 }
 
-fn listen_removals(
+pub(crate) fn listen_removals(
     mut removals: EventReader<RemovedStructureEvent>,
     mut resources: ResMut<ResourceStore>,
+    mut defender_config: ResMut<DefenderConfiguration>,
     buildings: Res<BuildingResource>
 ) {
     for ev in removals.iter() {
-        resources.gold += buildings.get_cost(&ev.building_type) / 2;
+        if ev.building_type == BuildingType::Wall {
+            defender_config.num_walls -= 1;
+        } else {
+            defender_config.num_defenders -= 1;
+        }
+        if !ev.destroyed {
+            resources.gold += buildings.get_cost(&ev.building_type) / 2;
+            continue;
+        }
+        defender_config.queue_rebuild(ev.node, ev.building_type);
+    }
+}
+
+/* Runs after the path was updated for the same change, so the dead spots are those of the new path */
+pub(crate) fn update_dead_spots(
+    mut builds: EventReader<FieldModified>,
+    field: Res<TowerField>,
+    towers: Query<(&Defender, &Transform)>,
+    zones: Query<(&DamageZone, &Transform)>,
+    mut defender_config: ResMut<DefenderConfiguration>
+) {
+    if builds.is_empty() {
+        return;
     }
+    builds.clear();
+    let path = defender_config.get_path_nodes();
+    let covered: HashSet<Node> = covered_nodes(&path, &field, towers.iter()).into_iter().collect();
+    let size = SLOT_SIZE as f32;
+    defender_config.dead_spots = path
+        .into_iter()
+        .filter(|node| !covered.contains(node))
+        .filter(|node| {
+            let position = field.field_transform + Vec2::new(node.x as f32, node.y as f32) * size;
+            !zones.iter().any(|(zone, transform)| transform.translation.truncate().distance(position) <= zone.radius)
+        })
+        .collect();
+}
+
+pub(crate) fn listen_board_cleared(
+    mut cleared: EventReader<BoardClearedEvent>,
+    mut resources: ResMut<ResourceStore>,
+    mut defender_config: ResMut<DefenderConfiguration>,
+    buildings: Res<BuildingResource>,
+    field: Res<TowerField>,
+    mut cache: ResMut<PathCache>
+) {
+    for ev in cleared.iter() {
+        let total_cost: i32 = ev.removed.iter().map(|(_, building_type)| buildings.get_cost(building_type)).sum();
+        resources.gold += (total_cost as f32 * ev.refund_fraction) as i32;
+        defender_config.reset_layout();
+        // The FieldModified of the clear may not have reached invalidate_path_cache yet
+        cache.invalidate();
+        defender_config.count_free_slots(&field, &mut cache);
+    }
+}
+
+/* Everything on the field that deals damage */
+#[derive(SystemParam)]
+struct DamageDealers<'w, 's> {
+    towers: Query<'w, 's, (&'static Structure, &'static Defender, &'static Transform, Option<&'static Buffed>)>,
+    zones: Query<'w, 's, (&'static DamageZone, &'static Transform)>,
+}
+
+/* Structure changes since the last action and the paths on the field as it is */
+#[derive(SystemParam)]
+struct FieldChanges<'w, 's> {
+    builds: EventReader<'w, 's, FieldModified>,
+    cache: ResMut<'w, PathCache>,
 }
 
 fn perform_an_action(
@@ -268,208 +601,322 @@ fn perform_an_action(
     presets: Res<Buildings>,
     textures: Res<TextureResource>,
     mut resources: ResMut<ResourceStore>,
-    commands: Commands,
+    mut commands: Commands,
     mut defender_config: ResMut<DefenderConfiguration>,
     mut stats: ResMut<RoundStats>,
     /* Map for how many adjacent path nodes there are for every slot on the map. Used for placing towers on corners */
     mut adjacency_field: Local<HashMap<Node, i32>>,
-    mut builds: EventReader<FieldModified>,
+    mut changes: FieldChanges,
     mut initialized: Local<bool>,
-    mut next_tower: Local<Option<BuildingType>>,
-    query: Query<(&Structure, &Defender, &Transform)>,
+    mut planner: Planner,
+    dealers: DamageDealers,
+    mut fatal_error: ResMut<FatalError>,
+    modifiers: Res<RoundModifiers>,
     time: Res<Time>
 ) {
-    if !builds.is_empty() || !*initialized {
+    if !changes.builds.is_empty() || !*initialized {
         let actual_distance = field.get_start_transform().translation.truncate().distance(field.get_end_transform().translation.truncate());
-        if let Some(path) = a_star(&field, field.get_start(), field.get_end()) {
-            defender_config.path_hash.clear();
-            for node in path.get_nodes() {
-                defender_config.path_hash.insert(node);
+        // Structures registered after invalidate_path_cache ran this frame would otherwise leave the defender on stale paths
+        changes.cache.invalidate();
+        if !*initialized {
+            update_path(&field, &mut changes.cache, &mut defender_config);
+            rebuild_adjacency(&mut adjacency_field, &field, &defender_config.path_hash);
+        } else {
+            let changed: Vec<Node> = changes.builds.iter().flat_map(|ev| ev.nodes.iter().copied()).collect();
+            // A new structure next to the path leaves it as short as it was, only blocking it or freeing a slot can change it
+            let touches_path = changed.iter().any(|node| defender_config.path_hash.contains(node) || !field.is_node_occupied(*node));
+            if touches_path {
+                let path_changes = update_path(&field, &mut changes.cache, &mut defender_config);
+                update_adjacency(&mut adjacency_field, &field, &defender_config.path_hash, &path_changes);
             }
-            defender_config.path_length = path.get_size() as f32;
-            defender_config.path = path;
         }
+        // Structures off the path can still close the detours around it
+        defender_config.choke_points = field.compute_choke_points(&defender_config.paths);
+        // A sell or a razed structure makes room again
+        defender_config.count_free_slots(&field, &mut changes.cache);
         defender_config.path_distance = actual_distance;
         stats.closest_distance_to_end = actual_distance;
 
-        adjacency_field.clear();
-        for x in 0..field.get_width() as i32 {
-            for y in 0..field.get_height() as i32 {
-                let this_node = Node::new(x, y);
-                if defender_config.path_hash.contains(&this_node) {
-                    continue;
-                }
-                let mut adjacent = 0;
-                for node in get_all_neighbors(this_node) {
-                    if defender_config.path_hash.contains(&node) {
-                        adjacent += 1;
-                    }
-                    /*if field.is_node_occupied(node) {
-                        adjacent += 1;
-                    }*/
-                }
-                adjacency_field.insert(this_node, adjacent);
-            }
-        }
-
         defender_config.estimated_damage_potential = 0.;
+        // Sold and razed towers must not be offered for sale again
+        defender_config.sell_values.clear();
+        // Assume the average enemy speed, likely incorrect, but probably good enough.
+        // Blinking attackers skip part of the range entirely, so towers are overrated against them
+        let speed: f32 = 40.;
         // Roughly estimate total damage potential
-        for (structure, defender, transform) in &query {
+        for (structure, defender, transform, buff) in &dealers.towers {
             let defender_pos = transform.translation.truncate() / SLOT_SIZE as f32;
             let defender_node = Node::new(defender_pos.x as i32, defender_pos.y as i32);
-            let adjacent = (adjacency_field.get(&defender_node).copied().unwrap_or(0) as f32 * 0.4).max(1.);
-            // Assume the average enemy speed, likely incorrect, but probably good enough
-            let speed: f32 = 40.;
+            let adjacent_nodes = if defender.min_range > 0. {
+                // The neighbor ring lies in the dead zone, count the ring right outside of it instead
+                path_nodes_in_band(&defender_config.path_hash, defender_node, defender.min_range, defender.min_range + 1.5 * SLOT_SIZE as f32)
+            } else {
+                adjacency_field.get(&defender_node).copied().unwrap_or(0)
+            };
+            let adjacent = (adjacent_nodes as f32 * 0.4).max(1.);
             let time_to_travel = defender.attack_range / speed;
-            let dps = building_config.get_dps(&structure.building_type);
+            // Dodged shots deal nothing, so towers are only as good as the hits that land
+            let dps = building_config.get_dps(&structure.building_type) * (1. - defender_config.expected_dodge);
+            let dps = dps * buff.map(|buff| (1. + buff.attack_speed_bonus) * (1. + buff.damage_bonus)).unwrap_or(1.);
             //println!("DPS: {}, TTT: {}, Adjacency: {}, Attack Range: {}", dps, time_to_travel, adjacent, defender.attack_range);
             // Rough estimation using dps, time_to_travel in seconds, and a bonus for adjacent path nodes
             defender_config.estimated_damage_potential += dps * time_to_travel * adjacent;
 
             // Estimate the value of selling a tower by how many nodes in the current path it can reach
-            let mut sell_value = 1.;
-            let min_x = (defender_pos.x - defender.attack_range / SLOT_SIZE as f32).floor() as i32;
-            let max_x = (defender_pos.x + defender.attack_range / SLOT_SIZE as f32).ceil() as i32;
-            let min_y = (defender_pos.y - defender.attack_range / SLOT_SIZE as f32).floor() as i32;
-            let max_y = (defender_pos.y + defender.attack_range / SLOT_SIZE as f32).ceil() as i32;
-            for x in min_x..=max_x {
-                for y in min_y..=max_y {
-                    if defender_config.path_hash.contains(&Node::new(x, y)) {
-                        sell_value -= 0.1;
-                    }
-                }
-            }
-
-            
-            let mut index = -1;
-            let mut found = false;
-            for i in 0..defender_config.sell_values.len() {
-                if defender_config.sell_values[i].node == defender_node {
-                    index = i as i32;
-                    found = true;
-                    break;
-                }
-            }
-            if found {
-                defender_config.sell_values[index as usize].weight = sell_value;
-            } else {
-                defender_config.sell_values.push(WeightedNode { node: defender_node, weight: sell_value });
+            let mut sell_value = 1. - path_nodes_in_reach(&defender_config.path_hash, defender_pos, defender.attack_range) as f32 * 0.1;
+            // Towers that have paid for themselves in kills are worth keeping
+            sell_value -= (defender.kill_efficiency * 5.).min(1.);
+            defender_config.sell_values.push(WeightedNode { node: defender_node, weight: sell_value });
+        }
+        // Zones hurt whatever walks through them for as long as it takes to cross, nothing is dodged
+        for (zone, transform) in &dealers.zones {
+            let reached = path_nodes_in_reach(&defender_config.path_hash, transform.translation.truncate() / SLOT_SIZE as f32, zone.radius);
+            if reached > 0 {
+                let time_to_cross = 2. * zone.radius / speed;
+                defender_config.estimated_damage_potential += zone.damage_per_second * time_to_cross;
             }
         }
 
         defender_config.sell_values.sort_by(|a, b| a.weight.total_cmp(&b.weight));
 
-        builds.clear();
+        changes.builds.clear();
         *initialized = true;
     }
 
 
 
 
-    defender_config.action_cooldown.tick(time.delta());
-    if defender_config.action_cooldown.just_finished() {
+    let speedup = if defender_config.panicking { PANIC_SPEEDUP } else { 1 };
+    defender_config.action_cooldown.tick(modifiers.scale_defender_delta(time.delta()) * speedup);
+    if !defender_config.action_cooldown.just_finished() {
+        return;
+    }
 
-        if next_tower.is_none() {
-            *next_tower = Some(if rand::thread_rng().gen_ratio(1, 7) {BuildingType::Cannon} else {BuildingType::Arrow})
+    // A wall right ahead of the attacker about to leak beats anything a strategy could come up with
+    if let Some(node) = defender_config.panic_wall {
+        if buy_structure(&mut commands, &mut resources, &textures, &field, &presets, &building_config, BuildingType::Wall, node, &mut fatal_error) {
+            defender_config.num_walls += 1;
         }
-        //println!("Next tower will be {:?}", next_tower);
+        return;
+    }
 
-        let distance_factor = if defender_config.path_distance != 0. {
-            stats.closest_distance_to_end / defender_config.path_distance
-        } else {
-            1.
-        } + 1.;
-        // How far above (or below) estimated damage needed are we.
-        // If all slots are occupied on the map without disrupting path_finding we multiply the score by a large constant
-        let wall_score = ((defender_config.estimated_damage_potential / defender_config.estimated_damage_needed)) * if defender_config.can_build_wall { 
-            1. 
-        } else { 
-            -1000. 
-        } * (distance_factor * 0.5) / (defender_config.get_wall_factor() * 0.2).max(1.) * defender_config.wall_weight;
-        // How far below (or above) estimated damage needed are we, essentially the inverse of wall_score
-        let defender_score = (1. - (defender_config.estimated_damage_potential / defender_config.estimated_damage_needed)).max(1.) * if defender_config.can_build_tower { 
-            1. 
-        } else { 
-            -1000. 
-        } * distance_factor * (defender_config.get_wall_factor() * 0.2).max(1.) * defender_config.damage_weight;
-        let best_sell_score = defender_config.sell_values.last().map(|e| e.weight).unwrap_or(0.) * defender_config.sell_weight;
-
-        /*println!("Current scores: Wall ({}), Defender ({}), Sell ({}); Distance factor: {}; Wall factor: {}; Damage Factor: {}", 
-            wall_score, 
-            defender_score, 
-            best_sell_score,
-            distance_factor, 
-            defender_config.get_wall_factor(),
-            (defender_config.estimated_damage_potential / defender_config.estimated_damage_needed)
-        );*/
-
-        let best_score = max_index([wall_score, defender_score]);
-        if best_score == 0 {
-            // wall_score
-            let potential_walls = get_wall_build_actions::<5, 10>(&field, &defender_config);
-            if potential_walls.is_empty() {
-                defender_config.can_build_wall = false;
-            } else {
-                let weighted_node = &potential_walls[rand::thread_rng().gen_range(0..potential_walls.len())];
-                if buy_structure(commands, &mut resources, &textures, &field, &presets, &building_config, BuildingType::Wall, weighted_node.node) {
-                    defender_config.num_walls += 1;
-                }
-            }
-        } else if best_score == 1 {
-            let potential_defenders = get_defender_build_actions::<3, 10>(&adjacency_field, &field, &defender_config, next_tower.unwrap());
-            if potential_defenders.is_empty() {
-                defender_config.can_build_tower = false;
-            } else {
-                let action = &potential_defenders[rand::thread_rng().gen_range(0..potential_defenders.len())];
-                if buy_structure(commands, &mut resources, &textures, &field, &presets, &building_config, action.1, action.0) {
-                    defender_config.num_defenders += 1;
-                    *next_tower = None;
+    // Razed buildings go back up before anything new is planned.
+    // The ones whose slot is taken or would seal the path for now stay queued until that changes
+    defender_config.priority_actions.retain(|PriorityAction::Rebuild { building_type, .. }| presets.has_preset(*building_type));
+    let ready = defender_config.priority_actions.iter().position(|PriorityAction::Rebuild { node, building_type }| {
+        let footprint = building_type.get_footprint();
+        field.is_footprint_free(*node, footprint) && verify_footprint_placement_valid(&field, *node, footprint, presets.get_preset(*building_type).blocking)
+    });
+    if let Some(index) = ready {
+        let PriorityAction::Rebuild { node, building_type } = defender_config.priority_actions[index];
+        if !can_afford(resources.gold, presets.get_preset(building_type).cost) {
+            // Stays first in line while the gold for it is saved up
+            return;
+        }
+        defender_config.priority_actions.remove(index);
+        if buy_structure(&mut commands, &mut resources, &textures, &field, &presets, &building_config, building_type, node, &mut fatal_error) {
+            count_building(&mut defender_config, building_type);
+        }
+        return;
+    }
+
+    let defender_nodes: Vec<Node> = dealers.towers.iter().map(|(_, _, transform, _)| {
+        let pos = transform.translation.truncate() / SLOT_SIZE as f32;
+        Node::new(pos.x as i32, pos.y as i32)
+    }).collect();
+    let distance_factor = if defender_config.path_distance != 0. {
+        stats.closest_distance_to_end / defender_config.path_distance
+    } else {
+        1.
+    } + 1.;
+    let view = DefenderView {
+        field: &field,
+        config: &defender_config,
+        buildings: &building_config,
+        gold: resources.gold,
+        adjacency: &adjacency_field,
+        defender_nodes: &defender_nodes,
+        distance_factor
+    };
+    let action = planner.strategy.0.decide(view, &mut planner.next_tower, &mut planner.rng);
+
+    // Any other action ends the saving up
+    defender_config.savings = None;
+    match action {
+        Some(DefenderAction::Build { building_type, node }) => {
+            if presets.has_preset(building_type) && buy_structure(&mut commands, &mut resources, &textures, &field, &presets, &building_config, building_type, node, &mut fatal_error) {
+                count_building(&mut defender_config, building_type);
+                if building_type != BuildingType::Wall {
+                    *planner.next_tower = None;
                 }
             }
-        } else if best_score == 2 {
-            // best_sell_score
         }
+        // Half the cost comes back and the tower is counted out through listen_removals
+        Some(DefenderAction::Sell { node }) => commands.add(move |world: &mut World| world.send_event(RemoveStructureRequest { node, destroyed: false })),
+        Some(DefenderAction::Wait(WaitReason::Saving(savings))) => defender_config.savings = Some(savings),
+        Some(DefenderAction::Wait(WaitReason::NoRoomForWalls)) => defender_config.free_wall_slots = 0,
+        Some(DefenderAction::Wait(WaitReason::NoRoomForTowers)) => defender_config.free_tower_slots = 0,
+        None => {}
+    }
+}
+
+/* Buildings a human defender picked in the build palette, bought like the computer's own */
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn process_placement_requests(
+    mut commands: Commands,
+    mut requests: EventReader<PlaceStructureRequest>,
+    mut resources: ResMut<ResourceStore>,
+    mut defender_config: ResMut<DefenderConfiguration>,
+    mut fatal_error: ResMut<FatalError>,
+    textures: Res<TextureResource>,
+    field: Res<TowerField>,
+    presets: Res<Buildings>,
+    building_config: Res<BuildingResource>
+) {
+    for request in requests.iter() {
+        if presets.has_preset(request.building_type)
+            && buy_structure(&mut commands, &mut resources, &textures, &field, &presets, &building_config, request.building_type, request.node, &mut fatal_error) {
+            count_building(&mut defender_config, request.building_type);
+        }
+    }
+}
+
+fn count_building(defender_config: &mut DefenderConfiguration, building_type: BuildingType) {
+    if building_type == BuildingType::Wall {
+        defender_config.num_walls += 1;
+    } else {
+        defender_config.num_defenders += 1;
     }
 }
 
 fn buy_structure(
-    commands: Commands,
+    commands: &mut Commands,
     mut resources: &mut ResourceStore,
     textures: &TextureResource,
     field: &TowerField,
     buildings: &Buildings,
     building_config: &BuildingResource,
     building_type: BuildingType,
-    node: Node
+    node: Node,
+    fatal_error: &mut FatalError
 ) -> bool {
     let preset = buildings.get_preset(building_type);
-    if preset.cost <= resources.gold && node.x >= 0 && node.y >= 0 {
+    let footprint = building_type.get_footprint();
+    if !field.is_footprint_free(node, footprint) {
+        return false;
+    }
+    // The build actions mostly avoid sealing the path already, this catches the cases they miss
+    if !verify_footprint_placement_valid(field, node, footprint, preset.blocking) {
+        return false;
+    }
+    if can_afford(resources.gold, preset.cost) && node.x >= 0 && node.y >= 0 {
+        if let Err(err) = preset.spawn(commands, building_config, field, textures, node.x as usize, node.y as usize) {
+            fatal_error.report(err);
+            return false;
+        }
         resources.gold -= preset.cost;
-        preset.spawn(commands, building_config, field, textures, node.x as usize, node.y as usize);
         return true;
     }
     return false;
 }
 
-fn max_index<const TSIZE: usize>(arr: [f32; TSIZE]) -> usize {
-    let mut max: f32 = f32::MIN;
-    let mut index: usize = 0;
-    for i in 0..TSIZE {
-        if arr[i] > max {
-            max = arr[i];
-            index = i;
+/* The strategy in charge, the tower it settled on, kept until it is built, and the rng it decides with */
+#[derive(SystemParam)]
+struct Planner<'w, 's> {
+    strategy: ResMut<'w, ActiveStrategy>,
+    next_tower: Local<'s, Option<BuildingType>>,
+    rng: ResMut<'w, GameRng>,
+}
+
+/* Recomputes the paths from every start, returning the nodes that joined or left them. The old paths are kept while a start is cut off */
+pub(crate) fn update_path(field: &TowerField, cache: &mut PathCache, defender_config: &mut DefenderConfiguration) -> Vec<Node> {
+    let Some(paths) = cache.get_all_paths(field) else {
+        return Vec::new();
+    };
+    let path_hash: HashSet<Node> = paths.iter().flat_map(|path| path.get_nodes()).collect();
+    let changed: Vec<Node> = defender_config.path_hash.symmetric_difference(&path_hash).copied().collect();
+    defender_config.path_hash = path_hash;
+    // Measured from the main start
+    defender_config.path_length = paths[0].get_size() as f32;
+    defender_config.paths = paths;
+    return changed;
+}
+
+/* Number of path nodes around a slot, slots on the path have no entry */
+fn update_adjacency_slot(adjacency: &mut HashMap<Node, i32>, field: &TowerField, path_hash: &HashSet<Node>, node: Node) {
+    if node.x < 0 || node.y < 0 || node.x >= field.get_width() as i32 || node.y >= field.get_height() as i32 {
+        return;
+    }
+    if path_hash.contains(&node) {
+        adjacency.remove(&node);
+        return;
+    }
+    let adjacent = get_all_neighbors(node).iter().filter(|neighbor| path_hash.contains(neighbor)).count() as i32;
+    adjacency.insert(node, adjacent);
+}
+
+pub(crate) fn rebuild_adjacency(adjacency: &mut HashMap<Node, i32>, field: &TowerField, path_hash: &HashSet<Node>) {
+    adjacency.clear();
+    for x in 0..field.get_width() as i32 {
+        for y in 0..field.get_height() as i32 {
+            update_adjacency_slot(adjacency, field, path_hash, Node::new(x, y));
+        }
+    }
+}
+
+/* Only the changed nodes and their neighbors can have a different count */
+fn update_adjacency(adjacency: &mut HashMap<Node, i32>, field: &TowerField, path_hash: &HashSet<Node>, changed: &[Node]) {
+    for node in changed {
+        update_adjacency_slot(adjacency, field, path_hash, *node);
+        for neighbor in get_all_neighbors(*node) {
+            update_adjacency_slot(adjacency, field, path_hash, neighbor);
         }
     }
-    return index;
 }
 
-fn get_defender_build_actions<const TMAX_LEN: usize, const TITER: usize>(
+pub(crate) fn get_defender_build_actions<const TMAX_LEN: usize, const TITER: usize>(
     adjacency: &HashMap<Node, i32>, 
     field: &TowerField,
     defender_config: &DefenderConfiguration,
+    building_config: &BuildingResource,
+    defender_nodes: &[Node],
     building_type: BuildingType
 ) -> Vec<(Node, BuildingType)> {
-    return get_wall_build_actions::<TMAX_LEN, TITER>(field, defender_config).iter().map(|node| (node.node, building_type)).collect();
+    let min_range = building_config.get_min_range(&building_type);
+    if min_range > 0. {
+        let attack_range = building_config.get_attack_range(&building_type);
+        return get_banded_build_actions::<TMAX_LEN>(field, defender_config, min_range, attack_range)
+            .iter()
+            .map(|node| (node.node, building_type))
+            .collect();
+    }
+    if building_type == BuildingType::Banner {
+        let radius = building_config.get_aura_radius(&building_type);
+        return get_radius_build_actions::<TMAX_LEN>(field, defender_config, defender_nodes, radius)
+            .iter()
+            .map(|node| (node.node, building_type))
+            .collect();
+    }
+    if building_type == BuildingType::ZoneDamage {
+        let radius = building_config.get_aura_radius(&building_type);
+        let zones: Vec<(Node, BuildingType)> = get_radius_build_actions::<TMAX_LEN>(field, defender_config, &defender_config.dead_spots, radius)
+            .iter()
+            .filter(|node| node.weight > 0.)
+            .map(|node| (node.node, building_type))
+            .collect();
+        // Without dead spots in reach a zone still does damage next to the path
+        if !zones.is_empty() {
+            return zones;
+        }
+    }
+    let candidates = get_wall_build_actions::<TMAX_LEN, TITER>(field, defender_config, building_type.get_footprint());
+    if building_type == BuildingType::Marker {
+        // A marker only multiplies the damage of other towers, so place it where the most of them can benefit
+        let cluster_size = |node: Node| defender_nodes.iter().filter(|d| (d.x - node.x).abs() <= 2 && (d.y - node.y).abs() <= 2).count();
+        let best = candidates.iter().map(|c| cluster_size(c.node)).max().unwrap_or(0);
+        return candidates.iter().filter(|c| cluster_size(c.node) == best).map(|c| (c.node, building_type)).collect();
+    }
+    return candidates.iter().map(|node| (node.node, building_type)).collect();
     /*let mut vec: Vec<(Node, i32)> =  adjacency.iter()
         .map(|e| (*e.0, *e.1))
         .filter(|e| !field.is_node_occupied(e.0))
@@ -482,14 +929,108 @@ fn get_defender_build_actions<const TMAX_LEN: usize, const TITER: usize>(
     return vec.iter().take(TMAX_LEN).map(|e| (e.0, BuildingType::Arrow)).collect();*/
 }
 
-fn get_wall_build_actions<const TMAX_LEN: usize, const TITER: usize>(
+/* Path nodes in the square around a tower that its attack range spans, `slot` is the tower position in slots.
+   Generous on purpose, the corners are further away than the range */
+pub fn path_nodes_in_reach(path_hash: &HashSet<Node>, slot: Vec2, attack_range: f32) -> i32 {
+    let reach = attack_range / SLOT_SIZE as f32;
+    let mut count = 0;
+    for x in (slot.x - reach).floor() as i32..=(slot.x + reach).ceil() as i32 {
+        for y in (slot.y - reach).floor() as i32..=(slot.y + reach).ceil() as i32 {
+            if path_hash.contains(&Node::new(x, y)) {
+                count += 1;
+            }
+        }
+    }
+    return count;
+}
+
+/* Path nodes whose centers are between min_range and max_range away from the node, in world units */
+fn path_nodes_in_band(path_hash: &HashSet<Node>, node: Node, min_range: f32, max_range: f32) -> i32 {
+    let size = SLOT_SIZE as f32;
+    let position = Vec2::new(node.x as f32, node.y as f32) * size;
+    return path_hash.iter().filter(|path_node| {
+        let distance = (Vec2::new(path_node.x as f32, path_node.y as f32) * size).distance(position);
+        distance >= min_range && distance <= max_range
+    }).count() as i32;
+}
+
+/* Spots for towers with a dead zone, a few nodes back from the path and weighted by how much of it they can reach */
+fn get_banded_build_actions<const TMAX_LEN: usize>(
     field: &TowerField,
-    defender_config: &DefenderConfiguration
+    defender_config: &DefenderConfiguration,
+    min_range: f32,
+    attack_range: f32
+) -> Vec<WeightedNode> {
+    let reach = (attack_range / SLOT_SIZE as f32).ceil().min(3.) as i32;
+    let mut seen: HashSet<Node> = HashSet::new();
+    let mut results: Vec<WeightedNode> = Vec::new();
+    for path_node in defender_config.get_path_nodes() {
+        for x in path_node.x - reach..=path_node.x + reach {
+            for y in path_node.y - reach..=path_node.y + reach {
+                let node = Node::new(x, y);
+                if x < 0 || y < 0 || x >= field.get_width() as i32 || y >= field.get_height() as i32 || !seen.insert(node) {
+                    continue;
+                }
+                // Off the path nothing can be blocked, so no path check is needed
+                if defender_config.path_hash.contains(&node) || field.is_node_occupied(node) {
+                    continue;
+                }
+                let weight = path_nodes_in_band(&defender_config.path_hash, node, min_range, attack_range) as f32;
+                if weight > 0. {
+                    results.push(WeightedNode { node, weight });
+                }
+            }
+        }
+    }
+    results.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    results.truncate(TMAX_LEN);
+    return results;
+}
+
+/* Free slots off the path, weighted by how many of the targets something with this radius placed there would reach.
+   Banners look for defenders, zones for dead spots */
+fn get_radius_build_actions<const TMAX_LEN: usize>(
+    field: &TowerField,
+    defender_config: &DefenderConfiguration,
+    targets: &[Node],
+    radius: f32
+) -> Vec<WeightedNode> {
+    let size = SLOT_SIZE as f32;
+    let reach = (radius / size).floor() as i32;
+    let in_radius = |a: Node, b: Node| (Vec2::new((a.x - b.x) as f32, (a.y - b.y) as f32) * size).length() <= radius;
+    let mut seen: HashSet<Node> = HashSet::new();
+    let mut results: Vec<WeightedNode> = Vec::new();
+    for target in targets {
+        for x in target.x - reach..=target.x + reach {
+            for y in target.y - reach..=target.y + reach {
+                let node = Node::new(x, y);
+                if x < 0 || y < 0 || x >= field.get_width() as i32 || y >= field.get_height() as i32 || !seen.insert(node) {
+                    continue;
+                }
+                // Off the path nothing can be blocked
+                if defender_config.path_hash.contains(&node) || field.is_node_occupied(node) {
+                    continue;
+                }
+                let weight = targets.iter().filter(|other| in_radius(node, **other)).count() as f32;
+                results.push(WeightedNode { node, weight });
+            }
+        }
+    }
+    results.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    results.truncate(TMAX_LEN);
+    return results;
+}
+
+/* Also used for towers placed like walls, footprint is the building they are looking for room for */
+pub(crate) fn get_wall_build_actions<const TMAX_LEN: usize, const TITER: usize>(
+    field: &TowerField,
+    defender_config: &DefenderConfiguration,
+    footprint: Footprint
 ) -> Vec<WeightedNode> {
     let mut results: Vec<WeightedNode> = Vec::with_capacity(TMAX_LEN);
     let mut seen: HashSet<Node> = HashSet::new();
     let mut i = 0;
-    for node in defender_config.path.get_nodes() {
+    for node in defender_config.get_path_nodes() {
         for current_candidate in get_self_with_successors(node) {
             i+=1;
             if seen.contains(&current_candidate) {
@@ -498,11 +1039,11 @@ fn get_wall_build_actions<const TMAX_LEN: usize, const TITER: usize>(
                 seen.insert(current_candidate);
             }
             if results.len() < TMAX_LEN {
-                if let Some(weighted_node) = get_wall_build_action(field, defender_config, current_candidate) {
+                if let Some(weighted_node) = get_wall_build_action(field, defender_config, current_candidate, footprint) {
                     results.push(weighted_node);
                 }
             } else if i < TITER {
-                if let Some(weighted_node) = get_wall_build_action(field, defender_config, current_candidate) {
+                if let Some(weighted_node) = get_wall_build_action(field, defender_config, current_candidate, footprint) {
                     let mut index: i32 = -1;
                     let mut min: f32 = f32::MAX;
                     for j in 0..results.len() {
@@ -523,15 +1064,26 @@ fn get_wall_build_actions<const TMAX_LEN: usize, const TITER: usize>(
     return results;
 }
 
-fn get_wall_build_action(field: &TowerField, defender_config: &DefenderConfiguration, node: Node) -> Option<WeightedNode> {
-    if !defender_config.is_node_adjacent_to_or_on_path(node) || field.is_node_occupied(node) {
+fn get_wall_build_action(field: &TowerField, defender_config: &DefenderConfiguration, node: Node, footprint: Footprint) -> Option<WeightedNode> {
+    if !defender_config.is_node_adjacent_to_or_on_path(node) || !field.is_footprint_free(node, footprint) || defender_config.is_given_up(node) {
         return None;
     }
-    let weight = if let Some(path) = a_star_with_blocked_node(field, field.get_start(), field.get_end(), Some(node)) {
-        path.get_size()
-    } else {
-        0
-    } as f32;
+    // All slots of a large building block at once, so they are checked together
+    let blocked: Vec<Node> = footprint.nodes(node).collect();
+    // Every start has to keep a way to the end, the detours of all of them add up
+    let mut length = 0.;
+    for (index, start) in field.get_starts().iter().enumerate() {
+        // Off a path the cached one stays the shortest way, only slots on it need a new search
+        let cached = defender_config.paths.get(index).filter(|path| !blocked.iter().any(|node| path.contains(*node)));
+        let size = match cached {
+            Some(path) => path.get_size(),
+            None => a_star_with_blocked_nodes(field, *start, field.get_end(), &blocked)?.get_size(),
+        };
+        length += size as f32;
+    }
+    // Walls around choke points lengthen the detours that are already the longest
+    let choke_degree = get_all_neighbors(node).iter().filter(|neighbor| defender_config.choke_points.contains(neighbor)).count() as f32;
+    let weight = length * (1. + choke_degree * 0.4);
 
     if weight > 0. {
         return Some(WeightedNode {node, weight});
@@ -541,6 +1093,426 @@ fn get_wall_build_action(field: &TowerField, defender_config: &DefenderConfigura
     
 }
 
+/* Splash for the orc groups, fast shots for spiders and heavy bolts for golems */
+pub fn get_counter_tower(threat: AttackerType) -> Option<BuildingType> {
+    return match threat {
+        AttackerType::OrcWarrior => Some(BuildingType::Cannon),
+        AttackerType::Spider => Some(BuildingType::Arrow),
+        AttackerType::Golem => Some(BuildingType::Ballista),
+        AttackerType::Thief | AttackerType::Phantom => None
+    };
+}
+
 fn get_sell_actions() -> Vec<Node> {
     return Vec::new();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, Events};
+
+    use crate::{tests::{build_test_tower_field, defender_harness, schedule_of, step_harness, system_test_world}, world::{defender_strategy::{DefaultDefenderStrategy, DefenderStrategy}, path_finding::a_star}};
+
+    use super::*;
+
+    const WALLS: &str = r#"[{ "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } }]"#;
+
+    #[test]
+    fn thieves_steal_at_most_what_the_defender_has() {
+        let mut world = system_test_world();
+        world.insert_resource(ResourceStore { gold: 25, lives: 10 });
+        world.insert_resource(build_test_tower_field());
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::StealGold(40) });
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::StealGold(40) });
+        let mut schedule = schedule_of(listen_goals);
+        schedule.run(&mut world);
+
+        let resources = world.resource::<ResourceStore>();
+        assert_eq!((resources.gold, resources.lives), (0, 8));
+        let events = world.resource::<Events<GoldStolenEvent>>();
+        let stolen: Vec<i32> = events.get_reader().iter(events).map(|ev| ev.amount).collect();
+        assert_eq!(stolen, vec![25]);
+    }
+
+    fn straight_path_config(field: &TowerField) -> DefenderConfiguration {
+        let mut config = DefenderConfiguration::default();
+        let path = a_star(field, field.get_start(), field.get_end()).unwrap();
+        for node in path.get_nodes() {
+            config.path_hash.insert(node);
+        }
+        config.paths = vec![path];
+        return config;
+    }
+
+    #[test]
+    fn dead_zone_moves_the_adjacency_band_outwards() {
+        let field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 8), Node::new(15, 8));
+        let config = straight_path_config(&field);
+        let size = SLOT_SIZE as f32;
+        let adjacent = Node::new(7, 9);
+        let further_back = Node::new(7, 10);
+        // Without a dead zone the adjacent slot reaches more of the path
+        assert!(path_nodes_in_band(&config.path_hash, adjacent, 0., 2.5 * size) > path_nodes_in_band(&config.path_hash, further_back, 0., 2.5 * size));
+        assert!(path_nodes_in_band(&config.path_hash, adjacent, 1.5 * size, 2.5 * size) < path_nodes_in_band(&config.path_hash, further_back, 1.5 * size, 2.5 * size));
+    }
+
+    #[test]
+    fn dead_zone_towers_are_placed_back_from_the_path() {
+        let field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 8), Node::new(15, 8));
+        let config = straight_path_config(&field);
+        let size = SLOT_SIZE as f32;
+        let actions = get_banded_build_actions::<3>(&field, &config, 1.5 * size, 3. * size);
+        assert_eq!(actions.len(), 3);
+        for action in actions {
+            assert!((action.node.y - 8).abs() >= 2, "{} is right next to the path", action.node);
+        }
+    }
+
+    #[test]
+    fn incremental_adjacency_matches_a_full_rebuild() {
+        let mut field = TowerField::new(12, 12, Vec2::ZERO, Node::new(0, 6), Node::new(11, 6));
+        let mut config = DefenderConfiguration::default();
+        let mut adjacency = HashMap::new();
+        let mut cache = PathCache::default();
+        update_path(&field, &mut cache, &mut config);
+        rebuild_adjacency(&mut adjacency, &field, &config.path_hash);
+
+        // A wall on the path forces a detour around it
+        let size = SLOT_SIZE as f32;
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(5. * size, 6. * size), Footprint::SINGLE);
+        cache.invalidate();
+        let changes = update_path(&field, &mut cache, &mut config);
+        assert!(!changes.is_empty());
+        update_adjacency(&mut adjacency, &field, &config.path_hash, &changes);
+
+        let mut rebuilt = HashMap::new();
+        rebuild_adjacency(&mut rebuilt, &field, &config.path_hash);
+        assert_eq!(adjacency, rebuilt);
+    }
+
+    #[test]
+    fn cached_paths_weigh_wall_slots_like_a_full_search() {
+        let mut field = TowerField::new(12, 12, Vec2::ZERO, Node::new(0, 6), Node::new(11, 6));
+        field.add_start(Node::new(0, 0));
+        let mut config = DefenderConfiguration::default();
+        update_path(&field, &mut PathCache::default(), &mut config);
+        let actions = get_wall_build_actions::<50, 1000>(&field, &config, Footprint::SINGLE);
+        assert!(actions.iter().any(|action| !config.path_hash.contains(&action.node)));
+        // Without choke points the weight is the summed length of the ways around the wall
+        for action in actions {
+            let length: usize = field.get_starts().iter()
+                .map(|start| a_star_with_blocked_nodes(&field, *start, field.get_end(), &[action.node]).unwrap().get_size())
+                .sum();
+            assert_eq!(action.weight, length as f32, "{}", action.node);
+        }
+    }
+
+    #[test]
+    fn every_start_brings_its_own_path() {
+        let mut field = TowerField::new(12, 12, Vec2::ZERO, Node::new(0, 0), Node::new(11, 0));
+        field.add_start(Node::new(0, 11));
+        let mut config = DefenderConfiguration::default();
+        update_path(&field, &mut PathCache::default(), &mut config);
+        assert_eq!(config.paths.len(), 2);
+        assert!(config.path_hash.contains(&Node::new(0, 11)));
+        assert!(config.get_path_nodes().contains(&Node::new(0, 11)));
+        // The length stays the one of the main start
+        assert_eq!(config.path_length, 12.);
+    }
+
+    #[test]
+    fn banners_go_where_they_reach_the_most_defenders() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 8), Node::new(15, 8));
+        let config = straight_path_config(&field);
+        let size = SLOT_SIZE as f32;
+        let defender_nodes = vec![Node::new(4, 10), Node::new(6, 10), Node::new(12, 10)];
+        for node in defender_nodes.iter() {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(node.x as f32, node.y as f32) * size, Footprint::SINGLE);
+        }
+        let actions = get_radius_build_actions::<3>(&field, &config, &defender_nodes, 1.5 * size);
+        assert_eq!(actions[0].weight, 2.);
+        assert_eq!(actions[0].node.x, 5);
+    }
+
+    #[test]
+    fn damage_zones_count_toward_the_damage_potential() {
+        let mut app = defender_harness(Box::new(IdleStrategy), WALLS, &[("wall", 0)], Vec::new());
+        let size = SLOT_SIZE as f32;
+        // One sits on the start, the other one in a corner the path never gets close to
+        app.world.spawn((DamageZone::new(40., 100.), Transform::from_xyz(2. * size, 0., 0.)));
+        app.world.spawn((DamageZone::new(40., 30.), Transform::from_xyz(0., 15. * size, 0.)));
+        step_harness(&mut app);
+
+        // 40 damage per second for the 5 seconds it takes to cross 200 pixels
+        assert_eq!(app.world.resource::<DefenderConfiguration>().estimated_damage_potential, 200.);
+    }
+
+    /* Leaves every decision to the priority actions */
+    struct IdleStrategy;
+
+    impl DefenderStrategy for IdleStrategy {
+        fn get_name(&self) -> &'static str {
+            return "Idle";
+        }
+
+        fn decide(&mut self, _view: DefenderView, _next_tower: &mut Option<BuildingType>, _rng: &mut GameRng) -> Option<DefenderAction> {
+            return None;
+        }
+    }
+
+    #[test]
+    fn rebuilds_wait_for_gold_and_free_slots() {
+        let mut app = defender_harness(Box::new(IdleStrategy), WALLS, &[("wall", 0)], Vec::new());
+        let size = SLOT_SIZE as f32;
+        let free = Node::new(10, 3);
+        let taken = Node::new(12, 3);
+        app.world.resource_mut::<TowerField>().add_structure(Entity::PLACEHOLDER, true, Vec2::new(taken.x as f32, taken.y as f32) * size, Footprint::SINGLE);
+        app.world.resource_mut::<ResourceStore>().gold = 5;
+        let mut config = app.world.resource_mut::<DefenderConfiguration>();
+        config.queue_rebuild(taken, BuildingType::Wall);
+        config.queue_rebuild(free, BuildingType::Wall);
+        let rebuild = |node| PriorityAction::Rebuild { node, building_type: BuildingType::Wall };
+
+        for _ in 0..20 {
+            step_harness(&mut app);
+        }
+        assert_eq!(app.world.query::<&Structure>().iter(&app.world).count(), 0);
+        assert_eq!(app.world.resource::<DefenderConfiguration>().priority_actions, VecDeque::from([rebuild(taken), rebuild(free)]));
+
+        app.world.resource_mut::<ResourceStore>().gold = 100;
+        for _ in 0..20 {
+            step_harness(&mut app);
+        }
+        assert_eq!(app.world.query::<&Structure>().iter(&app.world).count(), 1);
+        assert_eq!(app.world.resource::<ResourceStore>().gold, 90);
+        // The taken slot is still waiting to be freed up
+        assert_eq!(app.world.resource::<DefenderConfiguration>().priority_actions, VecDeque::from([rebuild(taken)]));
+    }
+
+    #[test]
+    fn human_defender_gets_no_automatic_buildings() {
+        let mut app = defender_harness(Box::new(DefaultDefenderStrategy), WALLS, &[("wall", 0)], Vec::new());
+        app.insert_resource(DefenderControl::Human);
+        app.world.resource_mut::<ResourceStore>().gold = 1000;
+        for _ in 0..100 {
+            step_harness(&mut app);
+        }
+
+        assert_eq!(app.world.query::<&Structure>().iter(&app.world).count(), 0);
+        assert_eq!(app.world.resource::<ResourceStore>().gold, 1000);
+        assert_eq!(app.world.resource::<DefenderConfiguration>().num_walls, 0);
+        // Never even looked at the field
+        assert_eq!(app.world.resource::<DefenderConfiguration>().path_distance, 0.);
+
+        // The same schedule does act once the computer takes over
+        app.insert_resource(DefenderControl::Computer);
+        step_harness(&mut app);
+        assert!(app.world.resource::<DefenderConfiguration>().path_distance > 0.);
+    }
+
+    #[test]
+    fn finishing_a_round_pays_the_completion_bonus() {
+        let mut world = system_test_world();
+        world.insert_resource(ResourceStore { gold: 20, lives: 7 });
+        world.init_resource::<RoundStats>();
+        let completion_bonus = CompletionBonus { base: 15, per_round: 3, per_life: 2 };
+        world.insert_resource(DefenderConfiguration { completion_bonus, ..Default::default() });
+        world.init_resource::<RoundResource>();
+        let mut schedule = schedule_of(pay_completion_bonus);
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<ResourceStore>().gold, 20);
+
+        world.send_event(RoundOverEvent);
+        schedule.run(&mut world);
+        // No round was started, so only the base and the lives count
+        assert_eq!(world.resource::<ResourceStore>().gold, 20 + 15 + 7 * 2);
+        assert_eq!(world.resource::<RoundStats>().completion_bonus, 29);
+        assert_eq!(completion_bonus.get(10, 7), 15 + 30 + 14);
+        assert_eq!(completion_bonus.get(10, -1), 45);
+    }
+
+    #[test]
+    fn placement_requests_are_rejected_below_cost() {
+        let mut world = system_test_world();
+        world.insert_resource(build_test_tower_field());
+        world.insert_resource(BuildingResource::from_json(r#"[{ "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } }]"#).unwrap());
+        world.insert_resource(Buildings { presets: HashMap::from([(BuildingType::Wall, BuildingPreset::new(BuildingType::Wall, 10, true, false, 0., BuildingRegistry::default().get_spawner(BuildingType::Wall).unwrap()))]) });
+        world.init_resource::<TextureResource>();
+        world.insert_resource(ResourceStore { gold: 9, lives: 10 });
+        world.init_resource::<DefenderConfiguration>();
+        world.init_resource::<FatalError>();
+        world.send_event(PlaceStructureRequest { building_type: BuildingType::Wall, node: Node::new(8, 8) });
+        let mut schedule = schedule_of(process_placement_requests);
+        schedule.run(&mut world);
+
+        assert_eq!(world.query::<&Structure>().iter(&world).count(), 0);
+        assert_eq!(world.resource::<ResourceStore>().gold, 9);
+        assert_eq!(world.resource::<DefenderConfiguration>().num_walls, 0);
+    }
+
+    #[test]
+    fn the_type_soaking_up_most_damage_is_countered() {
+        use crate::world::{attackers::{GOLEM_STATS, ORC_WARRIOR_STATS}, towers::DamageType};
+
+        let mut world = system_test_world();
+        world.insert_resource(build_test_tower_field());
+        world.insert_resource(ResourceStore { gold: 0, lives: 10 });
+        world.init_resource::<DefenderConfiguration>();
+        world.init_resource::<RoundStats>();
+        world.init_resource::<Time>();
+        let golem = world.spawn((GOLEM_STATS, AttackerType::Golem)).id();
+        let orcs: Vec<Entity> = (0..3).map(|_| world.spawn((ORC_WARRIOR_STATS, AttackerType::OrcWarrior)).id()).collect();
+        let mut schedule = schedule_of(collect_event_stats);
+
+        world.send_event(RoundStartEvent);
+        schedule.run(&mut world);
+        world.send_event(DamageEvent { amount: 200., absorbed: 0., target: golem, damage_type: DamageType::Piercing, critical: false });
+        for orc in &orcs {
+            world.send_event(DamageEvent { amount: 80., absorbed: 0., target: *orc, damage_type: DamageType::Piercing, critical: false });
+            world.send_event(KillEvent { target: *orc, source: Entity::PLACEHOLDER, tower: Entity::PLACEHOLDER, bounty: 0, original_cost: 0, group_size: 1, death_position: Vec2::ZERO, size: Vec2::ONE });
+        }
+        schedule.run(&mut world);
+        // More damage than all orcs together, but the golem got away
+        world.send_event(DamageEvent { amount: 300., absorbed: 0., target: golem, damage_type: DamageType::Piercing, critical: false });
+        world.send_event(EntityReachedEnd { entity: golem, bounty: 0, leak_effect: LeakEffect::None });
+        schedule.run(&mut world);
+        world.send_event(RoundOverEvent);
+        schedule.run(&mut world);
+
+        let threat = world.resource::<DefenderConfiguration>().dominant_threat;
+        assert_eq!(threat, Some(AttackerType::OrcWarrior));
+        assert_eq!(threat.and_then(get_counter_tower), Some(BuildingType::Cannon));
+    }
+
+    #[test]
+    fn attackers_next_to_the_exit_trigger_a_panic_wall() {
+        use crate::world::attackers::GOLEM_STATS;
+
+        let mut world = system_test_world();
+        let field = build_test_tower_field();
+        let far = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        let near = a_star(&field, Node::new(13, 14), field.get_end()).unwrap();
+        world.insert_resource(field);
+        world.init_resource::<DefenderConfiguration>();
+        let size = SLOT_SIZE as f32;
+        world.spawn((GOLEM_STATS, far, Transform::from_xyz(2. * size, 0., 0.)));
+        let mut schedule = schedule_of(detect_panic);
+        schedule.run(&mut world);
+        assert!(!world.resource::<DefenderConfiguration>().panicking);
+
+        let attacker = world.spawn((GOLEM_STATS, near.clone(), Transform::from_xyz(13. * size, 14. * size, 0.))).id();
+        schedule.run(&mut world);
+        let config = world.resource::<DefenderConfiguration>();
+        assert!(config.panicking);
+        let wall = config.panic_wall.unwrap();
+        assert!(near.get_nodes().contains(&wall));
+        assert_ne!(wall, Node::new(13, 14));
+        assert!(verify_placement_valid(world.resource::<TowerField>(), wall, true));
+
+        // The threat passing restores the normal cadence
+        world.despawn(attacker);
+        schedule.run(&mut world);
+        let config = world.resource::<DefenderConfiguration>();
+        assert!(!config.panicking);
+        assert_eq!(config.panic_wall, None);
+        let panics = world.resource::<Events<DefenderPanicEvent>>();
+        let changes: Vec<bool> = panics.get_reader().iter(panics).map(|ev| ev.panicking).collect();
+        assert_eq!(changes, vec![true, false]);
+    }
+
+    #[test]
+    fn saving_up_gives_way_to_an_arrow_tower_after_a_while() {
+        let mut config = DefenderConfiguration::default();
+        for tick in 1..=SAVING_PATIENCE {
+            let savings = SavingsTarget { building_type: BuildingType::Cannon, shortfall: 40, ticks: tick };
+            assert_eq!(config.plan_savings(BuildingType::Cannon, 40), SavingsPlan::Save(savings));
+            config.savings = Some(savings);
+        }
+        assert_eq!(config.plan_savings(BuildingType::Cannon, 40), SavingsPlan::Build(BuildingType::Arrow));
+
+        // Affordable towers and the Arrow fallback are built right away
+        assert_eq!(config.plan_savings(BuildingType::Cannon, 0), SavingsPlan::Build(BuildingType::Cannon));
+        assert_eq!(config.plan_savings(BuildingType::Arrow, 15), SavingsPlan::Build(BuildingType::Arrow));
+    }
+
+    #[test]
+    fn razed_walls_are_rebuilt_until_the_slot_is_given_up() {
+        let mut world = system_test_world();
+        world.insert_resource(ResourceStore { gold: 100, lives: 10 });
+        world.insert_resource(BuildingResource::from_json(r#"[{ "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } }]"#).unwrap());
+        world.insert_resource(DefenderConfiguration { num_walls: MAX_REBUILDS as i32 + 1, ..Default::default() });
+        let mut schedule = schedule_of(listen_removals);
+        let node = Node::new(4, 4);
+
+        for _ in 0..MAX_REBUILDS {
+            world.send_event(RemovedStructureEvent { node, building_type: BuildingType::Wall, destroyed: true });
+        }
+        schedule.run(&mut world);
+        let config = world.resource::<DefenderConfiguration>();
+        let rebuild = PriorityAction::Rebuild { node, building_type: BuildingType::Wall };
+        assert_eq!(config.priority_actions, VecDeque::from([rebuild; (MAX_REBUILDS - 1) as usize]));
+        assert!(config.is_given_up(node));
+        assert_eq!(config.num_walls, 1);
+        // Nothing is refunded for what the attackers tore down, unlike a sale
+        assert_eq!(world.resource::<ResourceStore>().gold, 100);
+        world.send_event(RemovedStructureEvent { node: Node::new(5, 4), building_type: BuildingType::Wall, destroyed: false });
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<ResourceStore>().gold, 105);
+        assert_eq!(world.resource::<DefenderConfiguration>().num_walls, 0);
+    }
+
+    #[test]
+    fn wall_candidates_need_a_free_slot_next_to_the_path_that_keeps_it_open() {
+        let mut field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(7, 0));
+        let mut config = DefenderConfiguration::default();
+        update_path(&field, &mut PathCache::default(), &mut config);
+        let weight = |field: &TowerField, config: &DefenderConfiguration, node: Node, footprint: Footprint| {
+            get_wall_build_action(field, config, node, footprint).map(|action| action.weight)
+        };
+        // Next to the path nothing changes, on it the detour makes the path longer
+        assert_eq!(weight(&field, &config, Node::new(3, 1), Footprint::SINGLE), Some(8.));
+        assert_eq!(weight(&field, &config, Node::new(3, 0), Footprint::SINGLE), Some(10.));
+        assert_eq!(weight(&field, &config, Node::new(5, 5), Footprint::SINGLE), None);
+        // Hanging over the right edge
+        assert_eq!(weight(&field, &config, Node::new(7, 1), Footprint { width: 2, height: 2 }), None);
+
+        config.razed.insert(Node::new(3, 1), MAX_REBUILDS);
+        assert_eq!(weight(&field, &config, Node::new(3, 1), Footprint::SINGLE), None);
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(4., 1.) * SLOT_SIZE as f32, Footprint::SINGLE);
+        assert_eq!(weight(&field, &config, Node::new(4, 1), Footprint::SINGLE), None);
+
+        // A single lane has no way around, so nothing on it is worth anything
+        let lane = TowerField::new(8, 1, Vec2::ZERO, Node::new(0, 0), Node::new(7, 0));
+        let mut config = DefenderConfiguration::default();
+        update_path(&lane, &mut PathCache::default(), &mut config);
+        assert_eq!(weight(&lane, &config, Node::new(3, 0), Footprint::SINGLE), None);
+        assert!(get_wall_build_actions::<4, 100>(&lane, &config, Footprint::SINGLE).is_empty());
+    }
+
+    #[test]
+    fn free_slots_run_out_on_a_full_field_and_come_back_after_a_removal() {
+        let mut field = TowerField::new(3, 3, Vec2::ZERO, Node::new(0, 0), Node::new(2, 2));
+        let mut config = DefenderConfiguration::default();
+        let mut cache = PathCache::default();
+        config.count_free_slots(&field, &mut cache);
+        // Only the start and the end are taken on the empty field
+        assert_eq!(config.free_tower_slots, 9 - 2);
+        let mut placed = Vec::new();
+        while config.free_tower_slots > 0 {
+            let node = (0..9).map(|i| Node::new(i % 3, i / 3))
+                .find(|node| !field.is_node_occupied(*node) && verify_placement_valid(&field, *node, true))
+                .unwrap();
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(node.x as f32, node.y as f32) * SLOT_SIZE as f32, Footprint::SINGLE);
+            placed.push(node);
+            cache.invalidate();
+            config.count_free_slots(&field, &mut cache);
+        }
+        assert_eq!(config.free_wall_slots, 0);
+        assert!(DefaultDefenderStrategy.score_defender(&config, 1.) < 0.);
+
+        field.clear_slot(placed[0], Footprint::SINGLE);
+        cache.invalidate();
+        config.count_free_slots(&field, &mut cache);
+        assert!(config.free_wall_slots > 0 && config.free_tower_slots > 0);
+    }
+}
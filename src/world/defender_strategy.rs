@@ -0,0 +1,490 @@
+use bevy::{prelude::Resource, utils::HashMap};
+use rand::Rng;
+
+use crate::util::{can_afford, GameRng};
+
+use super::{
+    building_configuration::{BuildingResource, BuildingType},
+    defender_controller::{get_counter_tower, get_defender_build_actions, get_wall_build_actions, DefenderConfiguration, SavingsPlan, SavingsTarget},
+    path_finding::{verify_placement_valid, Node},
+    towers::{Footprint, TowerField},
+};
+
+/* Damage weight of the aggressive strategy on top of the configured one */
+const AGGRESSIVE_DAMAGE_FACTOR: f32 = 2.;
+const TURTLE_WALL_FACTOR: f32 = 3.;
+/* Walls the turtle places before it builds its first tower */
+const TURTLE_OPENING_WALLS: i32 = 6;
+/* Rows of the serpentine maze are this far apart, leaving a corridor of two slots between them */
+const MAZE_ROW_SPACING: i32 = 3;
+/* The mazer keeps this many walls per tower while its layout isn't finished */
+const MAZER_WALLS_PER_TOWER: i32 = 3;
+
+/* Everything a strategy gets to look at when it is its turn, nothing in here can be changed by it */
+pub struct DefenderView<'a> {
+    pub field: &'a TowerField,
+    pub config: &'a DefenderConfiguration,
+    pub buildings: &'a BuildingResource,
+    pub gold: i32,
+    /* Path nodes next to every free slot */
+    pub adjacency: &'a HashMap<Node, i32>,
+    /* Slots with a defender on them */
+    pub defender_nodes: &'a [Node],
+    /* Grows from 1 to 2 as the attackers get closer to the end */
+    pub distance_factor: f32,
+}
+
+impl DefenderView<'_> {
+    pub fn has_building(&self, building_type: BuildingType) -> bool {
+        return self.buildings.get_building_config(&building_type).is_some();
+    }
+
+    pub fn can_afford(&self, building_type: BuildingType) -> bool {
+        return self.has_building(building_type) && can_afford(self.gold, self.buildings.get_cost(&building_type));
+    }
+}
+
+/* Why a strategy passes its turn, the controller keeps the shared configuration up to date with it */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WaitReason {
+    Saving(SavingsTarget),
+    NoRoomForWalls,
+    NoRoomForTowers,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DefenderAction {
+    Build { building_type: BuildingType, node: Node },
+    /* Half the cost of the tower is refunded, like any other removal */
+    Sell { node: Node },
+    Wait(WaitReason),
+}
+
+/* Decides what the computer defender does whenever its action cooldown runs out.
+   Walls against leaks and rebuilding razed structures are handled by the controller before a strategy is asked.
+   Unless decide is replaced, the highest of the wall, defender and sell scores is acted on, distance_factor grows as attackers get closer to the end */
+pub trait DefenderStrategy: Send + Sync {
+    fn get_name(&self) -> &'static str;
+
+    /* How far above (or below) estimated damage needed are we.
+       If all slots are occupied on the map without disrupting path_finding we multiply the score by a large constant */
+    fn score_wall(&self, config: &DefenderConfiguration, distance_factor: f32) -> f32 {
+        return (config.estimated_damage_potential / config.estimated_damage_needed) * if config.free_wall_slots > 0 {
+            1.
+        } else {
+            -1000.
+        } * (distance_factor * 0.5) / (config.get_wall_factor() * 0.2).max(1.) * config.wall_weight;
+    }
+
+    /* How far below (or above) estimated damage needed are we, essentially the inverse of score_wall */
+    fn score_defender(&self, config: &DefenderConfiguration, distance_factor: f32) -> f32 {
+        return (1. - (config.estimated_damage_potential / config.estimated_damage_needed)).max(1.) * if config.free_tower_slots > 0 {
+            1.
+        } else {
+            -1000.
+        } * distance_factor * (config.get_wall_factor() * 0.2).max(1.) * config.damage_weight;
+    }
+
+    /* Only a tower that reaches little of the path and has barely killed anything is worth selling */
+    fn score_sell(&self, config: &DefenderConfiguration) -> f32 {
+        let value = config.get_best_sell_value();
+        if value <= 0. {
+            return f32::MIN;
+        }
+        return value * config.sell_weight;
+    }
+
+    /* Picked once and kept until that tower is built, has_preset tells which buildings can be bought at all */
+    fn next_tower_type(&self, config: &DefenderConfiguration, has_preset: &dyn Fn(BuildingType) -> bool, rng: &mut GameRng) -> BuildingType {
+        let counter = config.dominant_threat.and_then(get_counter_tower).filter(|tower| has_preset(*tower));
+        return if let Some(counter) = counter.filter(|_| rng.0.gen_ratio(1, 3)) {
+            counter
+        } else if counter.is_none() && rng.0.gen_ratio(1, 7) {
+            // Nothing to counter yet, e.g. in the first round
+            BuildingType::Cannon
+        } else if has_preset(BuildingType::Ballista) && rng.0.gen_ratio(1, 8) {
+            BuildingType::Ballista
+        } else if has_preset(BuildingType::LargeCannon) && config.num_defenders >= 4 && rng.0.gen_ratio(1, 10) {
+            // Takes four slots next to the path, only worth it once the basics stand
+            BuildingType::LargeCannon
+        } else if has_preset(BuildingType::Splitter) && rng.0.gen_ratio(1, 8) {
+            BuildingType::Splitter
+        } else if has_preset(BuildingType::Marker) && config.num_defenders >= 4 && rng.0.gen_ratio(1, 8) {
+            BuildingType::Marker
+        } else if has_preset(BuildingType::Freeze) && rng.0.gen_ratio(1, 10) {
+            BuildingType::Freeze
+        } else if has_preset(BuildingType::Tesla) && rng.0.gen_ratio(1, 8) {
+            BuildingType::Tesla
+        } else if has_preset(BuildingType::Banner) && config.num_defenders >= 4 && rng.0.gen_ratio(1, 8) {
+            BuildingType::Banner
+        } else if has_preset(BuildingType::ZoneDamage) && config.dead_spots.len() >= 4 && rng.0.gen_ratio(1, 6) {
+            // Stretches of the path no tower reaches
+            BuildingType::ZoneDamage
+        } else if has_preset(BuildingType::FireArrow) && rng.0.gen_ratio(1, 8) {
+            BuildingType::FireArrow
+        } else if has_preset(BuildingType::IceArrow) && rng.0.gen_ratio(1, 8) {
+            BuildingType::IceArrow
+        } else {
+            BuildingType::Arrow
+        };
+    }
+
+    /* None means there is nothing worth doing right now.
+       next_tower is the tower picked for the next build, the controller keeps it until that tower is built */
+    fn decide(&mut self, view: DefenderView, next_tower: &mut Option<BuildingType>, rng: &mut GameRng) -> Option<DefenderAction> {
+        let config = view.config;
+        let target = match *next_tower {
+            Some(target) => target,
+            None => self.next_tower_type(config, &|building_type| view.has_building(building_type), rng)
+        };
+        let shortfall = if view.has_building(target) { view.buildings.get_cost(&target) - view.gold } else { 0 };
+        let tower = match config.plan_savings(target, shortfall) {
+            SavingsPlan::Build(building_type) => building_type,
+            SavingsPlan::Save(savings) => {
+                *next_tower = Some(target);
+                return Some(DefenderAction::Wait(WaitReason::Saving(savings)));
+            }
+        };
+        *next_tower = Some(tower);
+
+        let wall_score = self.score_wall(config, view.distance_factor);
+        let defender_score = self.score_defender(config, view.distance_factor);
+        let sell_score = self.score_sell(config);
+        let best_score = max_index([wall_score, defender_score, sell_score]);
+        if best_score == 2 {
+            if let Some(node) = config.get_best_sell_node() {
+                return Some(DefenderAction::Sell { node });
+            }
+        }
+        if best_score == 0 {
+            let potential_walls = get_wall_build_actions::<5, 10>(view.field, config, Footprint::SINGLE);
+            if potential_walls.is_empty() {
+                return Some(DefenderAction::Wait(WaitReason::NoRoomForWalls));
+            }
+            let node = potential_walls[rng.0.gen_range(0..potential_walls.len())].node;
+            return Some(DefenderAction::Build { building_type: BuildingType::Wall, node });
+        }
+        let potential_defenders = get_defender_build_actions::<3, 10>(view.adjacency, view.field, config, view.buildings, view.defender_nodes, tower);
+        if potential_defenders.is_empty() {
+            return Some(DefenderAction::Wait(WaitReason::NoRoomForTowers));
+        }
+        let (node, building_type) = potential_defenders[rng.0.gen_range(0..potential_defenders.len())];
+        return Some(DefenderAction::Build { building_type, node });
+    }
+}
+
+/* Every strategy the game setup and the defender parameters window offer, the first one is the default */
+pub fn get_strategies() -> [Box<dyn DefenderStrategy>; 4] {
+    return [Box::new(DefaultDefenderStrategy), Box::new(AggressiveStrategy), Box::new(TurtleStrategy), Box::new(MazerStrategy::default())];
+}
+
+pub fn find_strategy(name: &str) -> Option<Box<dyn DefenderStrategy>> {
+    return get_strategies().into_iter().find(|strategy| strategy.get_name().eq_ignore_ascii_case(name));
+}
+
+/* Strategy of the computer defender for this match */
+#[derive(Resource)]
+pub struct ActiveStrategy(pub Box<dyn DefenderStrategy>);
+
+impl Default for ActiveStrategy {
+    fn default() -> Self {
+        return Self(Box::new(DefaultDefenderStrategy));
+    }
+}
+
+fn max_index<const TSIZE: usize>(arr: [f32; TSIZE]) -> usize {
+    let mut max: f32 = f32::MIN;
+    let mut index: usize = 0;
+    for (i, value) in arr.into_iter().enumerate() {
+        if value > max {
+            max = value;
+            index = i;
+        }
+    }
+    return index;
+}
+
+/* Balances walls against towers by how far the estimated damage is from what is needed */
+pub struct DefaultDefenderStrategy;
+
+impl DefenderStrategy for DefaultDefenderStrategy {
+    fn get_name(&self) -> &'static str {
+        return "Default";
+    }
+}
+
+/* Only builds towers, favouring the ones with the biggest hits */
+pub struct AggressiveStrategy;
+
+impl DefenderStrategy for AggressiveStrategy {
+    fn get_name(&self) -> &'static str {
+        return "Aggressive";
+    }
+
+    fn score_wall(&self, _config: &DefenderConfiguration, _distance_factor: f32) -> f32 {
+        return f32::MIN;
+    }
+
+    fn score_defender(&self, config: &DefenderConfiguration, distance_factor: f32) -> f32 {
+        return DefaultDefenderStrategy.score_defender(config, distance_factor) * AGGRESSIVE_DAMAGE_FACTOR;
+    }
+
+    fn next_tower_type(&self, config: &DefenderConfiguration, has_preset: &dyn Fn(BuildingType) -> bool, rng: &mut GameRng) -> BuildingType {
+        if rng.0.gen_ratio(1, 3) {
+            return BuildingType::Cannon;
+        }
+        if has_preset(BuildingType::Ballista) && rng.0.gen_ratio(1, 3) {
+            return BuildingType::Ballista;
+        }
+        return DefaultDefenderStrategy.next_tower_type(config, has_preset, rng);
+    }
+}
+
+/* Mazes the path with walls before anything else and keeps favouring them afterwards */
+pub struct TurtleStrategy;
+
+impl DefenderStrategy for TurtleStrategy {
+    fn get_name(&self) -> &'static str {
+        return "Turtle";
+    }
+
+    fn score_wall(&self, config: &DefenderConfiguration, distance_factor: f32) -> f32 {
+        if config.free_wall_slots > 0 && config.num_walls < TURTLE_OPENING_WALLS {
+            return f32::MAX;
+        }
+        return DefaultDefenderStrategy.score_wall(config, distance_factor) * TURTLE_WALL_FACTOR;
+    }
+}
+
+/* Walls off every third row except for a gap at alternating ends, so the path has to snake across the whole field */
+pub fn plan_serpentine(field: &TowerField) -> Vec<Node> {
+    let (width, height) = (field.get_width() as i32, field.get_height() as i32);
+    let mut plan = Vec::new();
+    for (row, y) in (MAZE_ROW_SPACING - 1..height - 1).step_by(MAZE_ROW_SPACING as usize).enumerate() {
+        let gap = if row % 2 == 0 { width - 1 } else { 0 };
+        for x in (0..width).filter(|x| *x != gap) {
+            let node = Node::new(x, y);
+            if node != field.get_start() && node != field.get_end() {
+                plan.push(node);
+            }
+        }
+    }
+    return plan;
+}
+
+/* Builds a serpentine of walls row by row and fills towers into the bends of the maze, nothing is scored */
+#[derive(Default)]
+pub struct MazerStrategy {
+    plan: Vec<Node>,
+}
+
+impl MazerStrategy {
+    /* Planned slots that would seal the path, e.g. next to structures built before, are skipped */
+    fn next_wall(&self, view: &DefenderView) -> Option<Node> {
+        return self.plan.iter().copied().find(|node| {
+            view.field.is_footprint_free(*node, Footprint::SINGLE) && !view.config.is_given_up(*node) && verify_placement_valid(view.field, *node, true)
+        });
+    }
+
+    /* Free slot off the path and off the plan that touches the most path nodes */
+    fn best_pocket(&self, view: &DefenderView, blocking: bool) -> Option<Node> {
+        let mut pockets: Vec<(Node, i32)> = view.adjacency
+            .iter()
+            .filter(|(node, adjacent)| **adjacent > 0 && !view.config.path_hash.contains(*node) && !self.plan.contains(*node))
+            .filter(|(node, _)| view.field.is_footprint_free(**node, Footprint::SINGLE))
+            .map(|(node, adjacent)| (*node, *adjacent))
+            .collect();
+        // Sorted all the way down so equally good pockets are always taken in the same order
+        pockets.sort_by_key(|(node, adjacent)| (-adjacent, node.y, node.x));
+        return pockets.into_iter().map(|(node, _)| node).find(|node| verify_placement_valid(view.field, *node, blocking));
+    }
+}
+
+impl DefenderStrategy for MazerStrategy {
+    fn get_name(&self) -> &'static str {
+        return "Mazer";
+    }
+
+    fn decide(&mut self, view: DefenderView, _next_tower: &mut Option<BuildingType>, _rng: &mut GameRng) -> Option<DefenderAction> {
+        if self.plan.is_empty() {
+            self.plan = plan_serpentine(view.field);
+        }
+        let config = view.config;
+        if config.num_walls < (config.num_defenders + 1) * MAZER_WALLS_PER_TOWER {
+            if let Some(node) = self.next_wall(&view) {
+                return view.can_afford(BuildingType::Wall).then_some(DefenderAction::Build { building_type: BuildingType::Wall, node });
+            }
+        }
+        let building_type = if config.num_defenders % 3 == 2 && view.has_building(BuildingType::Cannon) {
+            BuildingType::Cannon
+        } else {
+            BuildingType::Arrow
+        };
+        let Some(node) = self.best_pocket(&view, view.buildings.get_blocking(&building_type)) else {
+            return Some(DefenderAction::Wait(WaitReason::NoRoomForTowers));
+        };
+        return view.can_afford(building_type).then_some(DefenderAction::Build { building_type, node });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use bevy::prelude::{App, Transform, With};
+
+    use crate::{
+        tests::{build_test_tower_field, defender_harness, play_harness_rounds},
+        world::{attackers::AttackerType, defender_controller::ResourceStore, path_finding::a_star, towers::{Defender, Structure, SLOT_SIZE}},
+    };
+
+    use super::*;
+
+    const BUILDINGS: &str = r#"[
+        { "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } },
+        { "building_type": "Arrow", "config": { "cost": 30, "blocking": true, "type_config": { "Defender": {
+            "attack_timer": 1.0, "attack_range": 80.0,
+            "attack": { "Projectile": { "damage_type": "Piercing", "damage": 10.0, "projectile_speed": 200.0,
+                "sprite": { "Static": { "name": "towers", "index": 7, "size": [8.0, 3.0] } } } } } } } },
+        { "building_type": "Cannon", "config": { "cost": 50, "blocking": true, "type_config": { "Defender": {
+            "attack_timer": 2.0, "attack_range": 100.0,
+            "attack": { "Splash": { "damage_type": "Explosive", "damage": 30.0, "travel_time": 1.0, "splash_radius": 24.0,
+                "sprite": { "Static": { "name": "towers", "index": 7, "size": [8.0, 8.0] } } } } } } } }
+    ]"#;
+    const SPRITES: [(&str, usize); 3] = [("wall", 0), ("arrow", 1), ("cannon", 2)];
+
+    /* 60 gold of bounty every round */
+    fn script() -> Vec<Vec<AttackerType>> {
+        return vec![vec![AttackerType::OrcWarrior, AttackerType::OrcWarrior, AttackerType::OrcWarrior, AttackerType::Spider, AttackerType::Spider]];
+    }
+
+    fn tower_nodes(app: &mut App) -> Vec<Node> {
+        return app.world.query_filtered::<&Transform, With<Defender>>().iter(&app.world).map(|transform| {
+            let pos = transform.translation.truncate() / SLOT_SIZE as f32;
+            Node::new(pos.x as i32, pos.y as i32)
+        }).collect();
+    }
+
+    /* Hands out a fixed list of actions, one per turn */
+    struct ScriptedStrategy {
+        actions: VecDeque<DefenderAction>,
+    }
+
+    impl DefenderStrategy for ScriptedStrategy {
+        fn get_name(&self) -> &'static str {
+            return "Scripted";
+        }
+
+        fn decide(&mut self, _view: DefenderView, _next_tower: &mut Option<BuildingType>, _rng: &mut GameRng) -> Option<DefenderAction> {
+            return self.actions.pop_front();
+        }
+    }
+
+    #[test]
+    fn max_index_takes_the_first_of_equal_highest_scores() {
+        assert_eq!(max_index([1., 3., 3.]), 1);
+        assert_eq!(max_index([-5., -2.]), 1);
+        // Nothing beats the start value, the first action is the fallback
+        assert_eq!(max_index([f32::MIN, f32::MIN]), 0);
+        assert_eq!(max_index([f32::NAN, 2.]), 1);
+        assert_eq!(max_index::<0>([]), 0);
+    }
+
+    #[test]
+    fn strategies_disagree_on_the_opening_move() {
+        let config = DefenderConfiguration::default();
+        let walls_first = |strategy: &dyn DefenderStrategy| strategy.score_wall(&config, 1.) > strategy.score_defender(&config, 1.);
+        assert!(!walls_first(&DefaultDefenderStrategy));
+        assert!(!walls_first(&AggressiveStrategy));
+        assert!(walls_first(&TurtleStrategy));
+
+        // Once the opening walls stand the turtle weighs its options like the default, just with heavier walls
+        let mut config = DefenderConfiguration::default();
+        config.num_walls = TURTLE_OPENING_WALLS;
+        config.num_defenders = 1;
+        config.estimated_damage_potential = 500.;
+        assert_eq!(TurtleStrategy.score_wall(&config, 1.), DefaultDefenderStrategy.score_wall(&config, 1.) * TURTLE_WALL_FACTOR);
+    }
+
+    #[test]
+    fn the_tower_picks_follow_the_game_seed() {
+        let config = DefenderConfiguration::default();
+        let picks = |seed: u64| {
+            let mut rng = GameRng::seeded(seed);
+            return (0..20).map(|_| AggressiveStrategy.next_tower_type(&config, &|_| true, &mut rng)).collect::<Vec<BuildingType>>();
+        };
+        assert_eq!(picks(7), picks(7));
+    }
+
+    #[test]
+    fn nothing_is_sold_without_a_tower() {
+        let config = DefenderConfiguration::default();
+        for strategy in get_strategies() {
+            assert_eq!(strategy.score_sell(&config), f32::MIN);
+        }
+    }
+
+    #[test]
+    fn the_picked_tower_is_kept_while_saving_for_it() {
+        let field = build_test_tower_field();
+        let config = DefenderConfiguration::default();
+        let buildings = BuildingResource::from_json(BUILDINGS).unwrap();
+        let adjacency = HashMap::new();
+        let view = DefenderView { field: &field, config: &config, buildings: &buildings, gold: 20, adjacency: &adjacency, defender_nodes: &[], distance_factor: 1. };
+        let mut next_tower = Some(BuildingType::Cannon);
+        let action = DefaultDefenderStrategy.decide(view, &mut next_tower, &mut GameRng::seeded(1));
+        assert_eq!(action, Some(DefenderAction::Wait(WaitReason::Saving(SavingsTarget { building_type: BuildingType::Cannon, shortfall: 30, ticks: 1 }))));
+        assert_eq!(next_tower, Some(BuildingType::Cannon));
+    }
+
+    #[test]
+    fn the_default_strategy_builds_both_walls_and_towers() {
+        let mut app = defender_harness(Box::new(DefaultDefenderStrategy), BUILDINGS, &SPRITES, script());
+        // The harness never ends a round, so the damage needed stays at what a weak last round left behind.
+        // Walls only win once the towers deal twice that
+        app.world.resource_mut::<DefenderConfiguration>().estimated_damage_needed = 150.;
+        play_harness_rounds(&mut app, 10);
+        let config = app.world.resource::<DefenderConfiguration>();
+        assert!(config.num_walls > 0 && config.num_defenders > 0);
+        let field = app.world.resource::<TowerField>();
+        assert!(a_star(field, field.get_start(), field.get_end()).is_some());
+    }
+
+    #[test]
+    fn the_mazer_snakes_the_path_across_the_field() {
+        let plan = plan_serpentine(&build_test_tower_field());
+        let direct = {
+            let field = build_test_tower_field();
+            a_star(&field, field.get_start(), field.get_end()).unwrap().get_size()
+        };
+        let mut app = defender_harness(Box::new(MazerStrategy::default()), BUILDINGS, &SPRITES, script());
+        play_harness_rounds(&mut app, 30);
+        let towers = tower_nodes(&mut app);
+        let field = app.world.resource::<TowerField>();
+        let config = app.world.resource::<DefenderConfiguration>();
+
+        // The whole layout stands and the path has to walk the length of every row
+        assert!(plan.iter().all(|node| field.is_node_occupied(*node)));
+        assert!(a_star(field, field.get_start(), field.get_end()).unwrap().get_size() > direct * 3);
+        // Towers went next to the path, never onto a planned wall
+        assert!(!towers.is_empty());
+        assert!(towers.iter().all(|tower| !plan.contains(tower) && config.is_node_adjacent_to_or_on_path(*tower)));
+    }
+
+    #[test]
+    fn the_controller_builds_and_sells_what_it_is_told() {
+        let node = Node::new(6, 6);
+        let strategy = ScriptedStrategy {
+            actions: VecDeque::from([DefenderAction::Build { building_type: BuildingType::Arrow, node }, DefenderAction::Sell { node }]),
+        };
+        let mut app = defender_harness(Box::new(strategy), BUILDINGS, &SPRITES, Vec::new());
+        let gold = app.world.resource::<ResourceStore>().gold;
+        play_harness_rounds(&mut app, 1);
+
+        // Selling removes the tower again and refunds half of its cost
+        assert_eq!(app.world.query::<&Structure>().iter(&app.world).count(), 0);
+        assert!(!app.world.resource::<TowerField>().is_node_occupied(node));
+        assert_eq!(app.world.resource::<ResourceStore>().gold, gold - 30 + 15);
+        assert_eq!(app.world.resource::<DefenderConfiguration>().num_defenders, 0);
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use bevy::prelude::{Plugin, App, Resource, Res, ResMut, EventReader, Query, Local, With, IntoSystemConfig};
+
+use crate::{error::GameplaySet, util::storage};
+
+use super::{
+    attacker_controller::{AttackerResource, Coin, PersistentComboBonus},
+    attackers::{Attacker, AttackerStats, AttackerType},
+    defender_controller::{listen_goals, DefenderConfiguration, ResourceStore},
+    events::{EntityReachedEnd, RoundStartEvent},
+    game_config::GameConfig,
+    rounds::{RoundResource, ScheduledSpawns},
+    sudden_death::drain_lives,
+};
+
+/* Gold the defender gets at the start of every endless round, times the round number */
+const ENDLESS_GOLD_PER_ROUND: i32 = 25;
+/* Every endless round the defender acts this much faster, down to MIN_ENDLESS_COOLDOWN */
+const ENDLESS_COOLDOWN_FACTOR: f32 = 0.93;
+const MIN_ENDLESS_COOLDOWN: Duration = Duration::from_millis(300);
+
+/* Classic is won by draining lives, Endless is played for score until the attacker runs out of gold */
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Endless
+}
+
+impl GameMode {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            GameMode::Classic => "Classic",
+            GameMode::Endless => "Endless"
+        };
+    }
+}
+
+#[derive(Resource)]
+pub struct EndlessScore {
+    /* Leaks weighted by the round they happened in */
+    pub score: i32,
+    /* Highest score of any endless game on this machine, including the current one once it is over */
+    pub best: i32,
+    pub bankrupt: bool
+}
+
+impl Default for EndlessScore {
+    fn default() -> Self {
+        return Self { score: 0, best: 0, bankrupt: false };
+    }
+}
+
+pub fn endless_mode(mode: Res<GameMode>) -> bool {
+    return *mode == GameMode::Endless;
+}
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GameMode>()
+            .insert_resource(EndlessScore { best: load_best_score(), ..Default::default() })
+            .add_system(score_leaks.in_set(GameplaySet).run_if(endless_mode))
+            .add_system(keep_lives_full.in_set(GameplaySet).after(listen_goals).after(drain_lives).run_if(endless_mode))
+            .add_system(escalate_defender.in_set(GameplaySet).run_if(endless_mode))
+            .add_system(check_bankruptcy.in_set(GameplaySet).run_if(endless_mode))
+            .add_system(save_best_score.in_set(GameplaySet).after(check_bankruptcy).run_if(endless_mode));
+    }
+}
+
+pub(crate) fn score_leaks(
+    mut reached_end: EventReader<EntityReachedEnd>,
+    mut score: ResMut<EndlessScore>,
+    round: Res<RoundResource>
+) {
+    for _ in reached_end.iter() {
+        score.score += round.get_round_number().max(1) as i32;
+    }
+}
+
+/* Lives never run out in endless, leaks only count towards the score */
+fn keep_lives_full(mut resources: ResMut<ResourceStore>, config: Res<GameConfig>) {
+    if resources.lives != config.defender_starting_lives {
+        resources.lives = config.defender_starting_lives;
+    }
+}
+
+fn escalate_defender(
+    mut round_start: EventReader<RoundStartEvent>,
+    mut resources: ResMut<ResourceStore>,
+    mut defender_config: ResMut<DefenderConfiguration>,
+    round: Res<RoundResource>
+) {
+    for _ in round_start.iter() {
+        resources.gold += ENDLESS_GOLD_PER_ROUND * round.get_round_number() as i32;
+        let cooldown = defender_config.action_cooldown.duration().mul_f32(ENDLESS_COOLDOWN_FACTOR).max(MIN_ENDLESS_COOLDOWN);
+        defender_config.action_cooldown.set_duration(cooldown);
+    }
+}
+
+/* Nothing on the field, nothing queued and nothing left to buy with */
+pub fn is_bankrupt(gold: i32, cheapest_unit: i32, round_active: bool, units_left: bool, income_pending: bool) -> bool {
+    return !round_active && !units_left && !income_pending && gold < cheapest_unit;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn check_bankruptcy(
+    mut score: ResMut<EndlessScore>,
+    attacker_resource: Res<AttackerResource>,
+    combo_bonus: Res<PersistentComboBonus>,
+    stats: Res<AttackerStats>,
+    round: Res<RoundResource>,
+    scheduled: Res<ScheduledSpawns>,
+    attackers: Query<(), With<Attacker>>,
+    coins: Query<(), With<Coin>>
+) {
+    if score.bankrupt {
+        return;
+    }
+    let cheapest = AttackerType::ALL.iter().map(|attacker_type| stats.get_cost(*attacker_type)).min().unwrap_or(0);
+    let units_left = round.has_pending_units() || !scheduled.by_round.is_empty() || !attackers.is_empty();
+    // Coins still lying around and the combo bonus of the last round are gold that is yet to come
+    let income_pending = !coins.is_empty() || combo_bonus.0 > 0;
+    if is_bankrupt(attacker_resource.gold, cheapest, round.is_round_active(), units_left, income_pending) {
+        score.bankrupt = true;
+        score.best = score.best.max(score.score);
+    }
+}
+
+fn save_best_score(score: Res<EndlessScore>, mut saved: Local<bool>) {
+    if !score.bankrupt || *saved {
+        return;
+    }
+    *saved = true;
+    if score.score >= score.best {
+        store_best_score(score.best);
+    }
+}
+
+const BEST_SCORE_FILE: &str = "endless_best_score.txt";
+
+fn load_best_score() -> i32 {
+    return storage::load(BEST_SCORE_FILE).or_else(load_legacy_best_score).and_then(|contents| contents.trim().parse().ok()).unwrap_or(0);
+}
+
+/* Browsers used to keep the best score under a key of its own, it is moved over the first time it is read */
+#[cfg(target_arch = "wasm32")]
+const LEGACY_BEST_SCORE_KEY: &str = "gmtk23_endless_best_score";
+
+#[cfg(target_arch = "wasm32")]
+fn load_legacy_best_score() -> Option<String> {
+    let legacy = storage::load_key(LEGACY_BEST_SCORE_KEY)?;
+    if storage::save(BEST_SCORE_FILE, legacy.trim()).is_err() {
+        bevy::log::warn!("Failed to move the best endless score to its new key");
+    }
+    return Some(legacy);
+}
+
+/* Natively the file has always been the same */
+#[cfg(not(target_arch = "wasm32"))]
+fn load_legacy_best_score() -> Option<String> {
+    return None;
+}
+
+fn store_best_score(best: i32) {
+    if let Err(err) = storage::save(BEST_SCORE_FILE, &best.to_string()) {
+        bevy::log::warn!("Failed to save the best endless score: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, Events, Schedule, World};
+
+    use crate::world::{attackers::LeakEffect, events::{RequestRoundStart, RoundOverEvent}, rounds::{check_round_end, process_request_round_start, PrepTimer}};
+
+    use super::*;
+
+    #[test]
+    fn later_leaks_score_more() {
+        let mut world = World::new();
+        world.init_resource::<EndlessScore>();
+        world.init_resource::<RoundResource>();
+        world.init_resource::<ScheduledSpawns>();
+        world.init_resource::<PrepTimer>();
+        world.insert_resource(GameConfig { prep_duration: 0., ..Default::default() });
+        world.init_resource::<Events<EntityReachedEnd>>();
+        world.init_resource::<Events<RequestRoundStart>>();
+        world.init_resource::<Events<RoundStartEvent>>();
+        world.init_resource::<Events<RoundOverEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(process_request_round_start);
+        schedule.add_system(score_leaks.after(process_request_round_start));
+        // Nothing was queued, so every round is over right after it started
+        schedule.add_system(check_round_end.after(score_leaks));
+
+        world.send_event(RequestRoundStart);
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<EndlessScore>().score, 1);
+
+        world.send_event(RequestRoundStart);
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        world.send_event(EntityReachedEnd { entity: Entity::PLACEHOLDER, bounty: 0, leak_effect: LeakEffect::None });
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<EndlessScore>().score, 5);
+    }
+
+    #[test]
+    fn bankruptcy_waits_for_everything_still_in_play() {
+        assert!(is_bankrupt(10, 20, false, false, false));
+        assert!(!is_bankrupt(20, 20, false, false, false));
+        assert!(!is_bankrupt(0, 20, true, false, false));
+        assert!(!is_bankrupt(0, 20, false, true, false));
+        assert!(!is_bankrupt(0, 20, false, false, true));
+    }
+}
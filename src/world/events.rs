@@ -1,40 +1,112 @@
 use bevy::prelude::{Entity, Plugin, App, Vec2};
 
-use super::{path_finding::Node, building_configuration::BuildingType};
+use super::{path_finding::Node, building_configuration::BuildingType, towers::DamageType, attackers::LeakEffect};
 
 
 
 pub struct DamageEvent {
+    /* Everything the hit took, shield included */
     pub amount: f32,
-    pub target: Entity
+    /* Part of amount the shield soaked up */
+    pub absorbed: f32,
+    pub target: Entity,
+    pub damage_type: DamageType,
+    pub critical: bool
 }
 
 pub struct KillEvent {
     pub target: Entity,
     pub source: Entity,
+    pub tower: Entity,
     pub bounty: i32,
     pub original_cost: i32,
     pub group_size: i32,
-    pub death_position: Vec2
+    pub death_position: Vec2,
+    /* Size of the killed attacker, death effects scale with it */
+    pub size: Vec2
+}
+
+pub struct SlowEvent {
+    pub target: Entity,
+    pub factor: f32,
+    pub duration_secs: f32
+}
+
+/* Where a splash projectile went off, for effects that don't care about the individual targets */
+pub struct SplashImpactEvent {
+    pub position: Vec2,
+    pub radius: f32
 }
 
 pub struct EntityReachedEnd {
     pub entity: Entity,
-    pub bounty: i32
+    pub bounty: i32,
+    pub leak_effect: LeakEffect
+}
+
+/* An attacker jumped ahead on its path, projectiles that can't follow it have to land where it was */
+pub struct BlinkEvent {
+    pub entity: Entity,
+    pub from: Vec2,
+    pub to: Vec2
+}
+
+/* Gold a leaking attacker took from the defender, already capped to what the defender had */
+pub struct GoldStolenEvent {
+    pub entity: Entity,
+    pub amount: i32,
+    pub position: Vec2
 }
 
 pub struct RoundOverEvent;
 pub struct RoundStartEvent;
 pub struct RequestRoundStart;
-pub struct FieldModified;
+/* Slots that got or lost a structure */
+pub struct FieldModified {
+    pub nodes: Vec<Node>
+}
+
+pub struct ActivateAbility {
+    pub tower: Entity
+}
 
 pub struct RemoveStructureRequest {
-    pub node: Node
+    pub node: Node,
+    /* Torn down by attackers instead of sold, nothing is refunded */
+    pub destroyed: bool
 }
 
 pub struct RemovedStructureEvent {
     pub node: Node,
-    pub building_type: BuildingType
+    pub building_type: BuildingType,
+    pub destroyed: bool
+}
+
+/* A human defender wants `building_type` with its anchor on `node`, ignored if it can't be afforded or would seal the path */
+pub struct PlaceStructureRequest {
+    pub building_type: BuildingType,
+    pub node: Node
+}
+
+/* Removes every structure at once, refund_fraction of their cost goes back to the defender */
+pub struct ClearBoardRequest {
+    pub refund_fraction: f32
+}
+
+/* Sent instead of a RemovedStructureEvent per structure, so the usual sell refund isn't paid on top */
+pub struct BoardClearedEvent {
+    pub removed: Vec<(Node, BuildingType)>,
+    pub refund_fraction: f32
+}
+
+/* The match is decided, by the attacker reaching the objective or by going bankrupt in endless */
+pub struct GameOverEvent {
+    pub attacker_won: bool
+}
+
+/* The computer defender started or stopped rushing to block an attacker about to leak */
+pub struct DefenderPanicEvent {
+    pub panicking: bool
 }
 
 pub struct EventsPlugin;
@@ -44,12 +116,22 @@ impl Plugin for EventsPlugin {
         app
             .add_event::<DamageEvent>()
             .add_event::<KillEvent>()
+            .add_event::<SlowEvent>()
+            .add_event::<SplashImpactEvent>()
+            .add_event::<ActivateAbility>()
             .add_event::<RoundOverEvent>()
             .add_event::<RoundStartEvent>()
             .add_event::<RequestRoundStart>()
             .add_event::<FieldModified>()
             .add_event::<EntityReachedEnd>()
+            .add_event::<GoldStolenEvent>()
+            .add_event::<BlinkEvent>()
             .add_event::<RemoveStructureRequest>()
-            .add_event::<RemovedStructureEvent>();
+            .add_event::<PlaceStructureRequest>()
+            .add_event::<RemovedStructureEvent>()
+            .add_event::<ClearBoardRequest>()
+            .add_event::<BoardClearedEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<DefenderPanicEvent>();
     }
 }
\ No newline at end of file
@@ -0,0 +1,88 @@
+use std::{fs, io::ErrorKind};
+
+use bevy::prelude::Resource;
+use serde::Deserialize;
+
+const CONFIG_PATH: &str = "assets/game_config.json";
+
+/* What the attacker has to do to win, written as { "ReachEndNTimes": 5 } in the config */
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum VictoryCondition {
+    /* Every life of the defender is gone */
+    #[default]
+    DrainLives,
+    /* Attackers reached the end this many times over the whole game */
+    ReachEndNTimes(i32)
+}
+
+/* Starting conditions of a game, the setup screen can still change them before the first round */
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameConfig {
+    #[serde(default = "default_starting_gold")]
+    pub attacker_starting_gold: i32,
+    #[serde(default = "default_starting_gold")]
+    pub defender_starting_gold: i32,
+    #[serde(default = "default_starting_lives")]
+    pub defender_starting_lives: i32,
+    #[serde(default)]
+    pub victory_condition: VictoryCondition,
+    /* Starts on top of the main one, as [x, y] slots. Units are spread over them by the round's SpawnPolicy */
+    #[serde(default)]
+    pub extra_starts: Vec<[i32; 2]>,
+    /* Seconds between starting a round and its first unit, the defender keeps building meanwhile. 0 starts right away */
+    #[serde(default = "default_prep_duration")]
+    pub prep_duration: f32
+}
+
+fn default_starting_gold() -> i32 {
+    return 200;
+}
+
+fn default_starting_lives() -> i32 {
+    return 50;
+}
+
+fn default_prep_duration() -> f32 {
+    return 3.;
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        return Self {
+            attacker_starting_gold: default_starting_gold(),
+            defender_starting_gold: default_starting_gold(),
+            defender_starting_lives: default_starting_lives(),
+            victory_condition: VictoryCondition::DrainLives,
+            extra_starts: Vec::new(),
+            prep_duration: default_prep_duration()
+        };
+    }
+}
+
+impl GameConfig {
+    /* The file is optional, without it every value has its default */
+    pub fn load() -> Result<Self, String> {
+        return match fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => Self::from_json(&contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("Failed to read {}: {}", CONFIG_PATH, err))
+        };
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        return serde_json::from_str(contents).map_err(|err| format!("Failed to parse {}: {}", CONFIG_PATH, err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_values_use_defaults() {
+        let config = GameConfig::from_json(r#"{ "defender_starting_lives": 10 }"#).unwrap();
+        assert_eq!(config, GameConfig { defender_starting_lives: 10, ..Default::default() });
+        let config = GameConfig::from_json(r#"{ "victory_condition": { "ReachEndNTimes": 5 } }"#).unwrap();
+        assert_eq!(config.victory_condition, VictoryCondition::ReachEndNTimes(5));
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::{
+    prelude::{
+        Added, App, Commands, Component, CoreSchedule, CoreSet, Entity, IntoSystemAppConfig, IntoSystemConfig, IntoSystemSetConfig, Or, Plugin,
+        Query, Res, Resource, SystemSet, Transform, Vec3, Without,
+    },
+    time::fixed_timestep::FixedTime,
+    transform::TransformSystem,
+};
+
+use crate::error::GameplaySet;
+
+use super::{attackers::Attacker, towers::Projectile};
+
+/* Systems that move attackers and projectiles, they run in CoreSchedule::FixedUpdate and step by FixedTime::period */
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct FixedMovementSet;
+
+/* Where the last two fixed steps left an entity, the Transform shows a blend of both between steps */
+#[derive(Component)]
+pub struct FixedStepPosition {
+    previous: Vec3,
+    current: Vec3,
+    /* Last value written to the Transform, anything else means the entity was moved outside of the fixed step */
+    rendered: Vec3,
+}
+
+impl FixedStepPosition {
+    pub fn new(translation: Vec3) -> Self {
+        return Self { previous: translation, current: translation, rendered: translation };
+    }
+
+    /* Takes over a position that was set directly, e.g. an attacker sent back to the start */
+    fn snap_to(&mut self, translation: Vec3) {
+        *self = Self::new(translation);
+    }
+}
+
+#[derive(Resource)]
+pub struct InterpolationSettings {
+    /* Off shows the latest fixed step as is, movement then stutters when the frame rate differs from the fixed rate */
+    pub enabled: bool,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        return Self { enabled: true };
+    }
+}
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterpolationSettings>()
+            .edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+                schedule.configure_set(FixedMovementSet.in_set(GameplaySet));
+            })
+            .add_system(begin_fixed_step.before(FixedMovementSet).in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(end_fixed_step.after(FixedMovementSet).in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(track_moving_entities.in_set(GameplaySet))
+            .add_system(interpolate_transforms.in_base_set(CoreSet::PostUpdate).before(TransformSystem::TransformPropagate));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn track_moving_entities(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform), (Or<(Added<Attacker>, Added<Projectile>)>, Without<FixedStepPosition>)>,
+) {
+    for (entity, transform) in &query {
+        commands.entity(entity).insert(FixedStepPosition::new(transform.translation));
+    }
+}
+
+/* Movement continues from the last fixed step, not from the blended position that was rendered */
+pub(crate) fn begin_fixed_step(mut query: Query<(&mut FixedStepPosition, &mut Transform)>) {
+    for (mut position, mut transform) in query.iter_mut() {
+        if transform.translation != position.rendered {
+            position.snap_to(transform.translation);
+        }
+        transform.translation = position.current;
+    }
+}
+
+pub(crate) fn end_fixed_step(mut query: Query<(&mut FixedStepPosition, &Transform)>) {
+    for (mut position, transform) in query.iter_mut() {
+        position.previous = position.current;
+        position.current = transform.translation;
+        position.rendered = transform.translation;
+    }
+}
+
+pub(crate) fn interpolate_transforms(
+    mut query: Query<(&mut FixedStepPosition, &mut Transform)>,
+    fixed_time: Res<FixedTime>,
+    settings: Res<InterpolationSettings>,
+) {
+    // How far the clock already is into the next fixed step
+    let overstep = (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).clamp(0., 1.);
+    for (mut position, mut transform) in query.iter_mut() {
+        if transform.translation != position.rendered {
+            position.snap_to(transform.translation);
+            continue;
+        }
+        transform.translation = if settings.enabled {
+            position.previous.lerp(position.current, overstep)
+        } else {
+            position.current
+        };
+        position.rendered = transform.translation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::prelude::{Schedule, Vec2, World};
+
+    use crate::world::attackers::{update_positions, ORC_WARRIOR_STATS};
+
+    use super::*;
+
+    #[test]
+    fn rendered_position_lies_between_fixed_steps() {
+        let mut world = World::new();
+        world.insert_resource(FixedTime::new_from_secs(0.1));
+        world.init_resource::<InterpolationSettings>();
+        let mut stats = ORC_WARRIOR_STATS;
+        stats.velocity = Vec2::new(100., 0.);
+        let attacker = world.spawn((stats, Transform::default(), FixedStepPosition::new(Vec3::ZERO))).id();
+        let mut fixed_step = Schedule::new();
+        fixed_step.add_system(begin_fixed_step.before(update_positions));
+        fixed_step.add_system(update_positions);
+        fixed_step.add_system(end_fixed_step.after(update_positions));
+        let mut render = Schedule::new();
+        render.add_system(interpolate_transforms);
+
+        fixed_step.run(&mut world);
+        fixed_step.run(&mut world);
+        world.resource_mut::<FixedTime>().tick(Duration::from_millis(25));
+        render.run(&mut world);
+        let x = world.get::<Transform>(attacker).unwrap().translation.x;
+        assert!(x > 10. && x < 20.);
+        assert!((x - 12.5).abs() < 1e-3);
+
+        // The next step starts from the last fixed position, not from the blended one
+        fixed_step.run(&mut world);
+        assert!((world.get::<Transform>(attacker).unwrap().translation.x - 30.).abs() < 1e-3);
+
+        world.resource_mut::<InterpolationSettings>().enabled = false;
+        render.run(&mut world);
+        assert!((world.get::<Transform>(attacker).unwrap().translation.x - 30.).abs() < 1e-3);
+
+        // Positions set outside of the fixed step are kept
+        world.get_mut::<Transform>(attacker).unwrap().translation = Vec3::new(-50., 0., 0.);
+        render.run(&mut world);
+        assert_eq!(world.get::<Transform>(attacker).unwrap().translation.x, -50.);
+    }
+}
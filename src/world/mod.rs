@@ -1,37 +1,88 @@
-use bevy::{prelude::{Resource, Entity, Plugin, App, Query, Transform, Added, ResMut, Vec2, Commands, Res, Handle, default, Color}, sprite::{SpriteSheetBundle, TextureAtlasSprite, TextureAtlas}};
+use bevy::{log::warn, prelude::{Plugin, App, Transform, ResMut, Vec2, Commands, Res, Handle, default, Color, IntoSystemConfig, StartupSet}, sprite::{SpriteSheetBundle, TextureAtlasSprite}};
 
-use crate::textures::TextureResource;
+use crate::{textures::{self, TextureResource}, error::{no_fatal_error, FatalError}};
 
-use self::{towers::{Structure, TowerField, WallBundle, StructureBuilder, ArrowTower, TowersPlugin, SLOT_SIZE}, path_finding::{Node, a_star}, attackers::AttackersPlugin, building_configuration::BuildingResource, events::EventsPlugin, rounds::RoundPlugin};
+use self::{towers::{TowerField, WallBundle, StructureBuilder, DefenderBundle, BuildError, TowersPlugin, SLOT_SIZE}, path_finding::Node, attackers::AttackersPlugin, building_configuration::{BuildingResource, BuildingType}, damage_matrix::DamageMatrix, events::EventsPlugin, rounds::RoundPlugin, abilities::AbilitiesPlugin, interpolation::InterpolationPlugin, sudden_death::SuddenDeathPlugin, mutators::MutatorsPlugin, endless::EndlessPlugin, game_config::GameConfig};
 
 pub mod towers;
 pub mod path_finding;
 pub mod attacker_controller;
+pub mod attacker_ai;
 pub mod defender_controller;
 pub mod attackers;
 pub mod building_configuration;
+pub mod building_registry;
+pub mod damage_matrix;
+pub mod game_config;
 pub mod events;
 pub mod rounds;
+pub mod abilities;
+pub mod interpolation;
+pub mod sudden_death;
+pub mod mutators;
+pub mod endless;
+pub mod defender_strategy;
 
 
 pub struct TowerFieldPlugin;
 
 impl Plugin for TowerFieldPlugin {
     fn build(&self, app: &mut App) {
+        let mut field = TowerField::new(
+            16, 
+            16, 
+            Vec2::ZERO, 
+            Node::new(2, 0), 
+            Node::new(14, 15)
+        );
+        let extra_starts = app.world.get_resource::<GameConfig>().map(|config| config.extra_starts.clone()).unwrap_or_default();
+        for [x, y] in extra_starts {
+            if x < 0 || y < 0 || x as usize >= field.get_width() || y as usize >= field.get_height() {
+                warn!("Ignoring the start ({}, {}) from game_config.json, it is outside the field", x, y);
+                continue;
+            }
+            field.add_start(Node::new(x, y));
+        }
         app
-            .insert_resource(TowerField::new(
-                16, 
-                16, 
-                Vec2::ZERO, 
-                Node::new(2, 0), 
-                Node::new(14, 15)
-            ))
+            .insert_resource(field)
             .add_plugin(RoundPlugin)
             .add_plugin(EventsPlugin)
             .add_plugin(AttackersPlugin)
             .add_plugin(TowersPlugin)
+            .add_plugin(AbilitiesPlugin)
+            .add_plugin(InterpolationPlugin)
+            .add_plugin(SuddenDeathPlugin)
+            .add_plugin(MutatorsPlugin)
+            .add_plugin(EndlessPlugin)
             //.add_startup_system(setup)
-            .add_startup_system(setup_environment); 
+            .add_startup_system(validate_buildings.in_base_set(StartupSet::PreStartup).after(textures::setup))
+            .add_startup_system(validate_damage_matrix)
+            .add_startup_system(setup_environment.run_if(no_fatal_error));
+    }
+}
+
+fn validate_buildings(
+    buildings: Res<BuildingResource>,
+    textures: Res<TextureResource>,
+    mut fatal_error: ResMut<FatalError>
+) {
+    // Nothing loaded means loading itself failed and was already reported
+    if buildings.is_empty() {
+        return;
+    }
+    for error in buildings.validate(&textures) {
+        fatal_error.report(format!("tower_definitions.json: {}", error));
+    }
+}
+
+/* A gap in the matrix is not fatal, the combination just deals normal damage */
+fn validate_damage_matrix(matrix: Res<DamageMatrix>) {
+    // Nothing loaded means loading itself failed and was already reported
+    if matrix.is_empty() {
+        return;
+    }
+    for (damage_type, armor_class) in matrix.missing_cells() {
+        warn!("damage_matrix.json: no multiplier for {:?} against {:?}, using 1.0", damage_type, armor_class);
     }
 }
 
@@ -39,22 +90,27 @@ fn setup(
     mut commands: Commands,
     textures: Res<TextureResource>,
     buildings: Res<BuildingResource>,
-    tower_field: Res<TowerField>
+    tower_field: Res<TowerField>,
+    mut fatal_error: ResMut<FatalError>
 ) {
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 0, 0));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 0, 1));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 1, 1));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 2, 1));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 0, 2));
-
-    commands.spawn(ArrowTower::from_tower_field(&buildings, &tower_field, &textures, 12, 0));
-    commands.spawn(ArrowTower::from_tower_field(&buildings, &tower_field, &textures, 10, 3));
-    commands.spawn(ArrowTower::from_tower_field(&buildings, &tower_field, &textures, 12, 1));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 12, 2));
-    commands.spawn(ArrowTower::from_tower_field(&buildings, &tower_field, &textures, 12, 3));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 12, 4));
-    commands.spawn(ArrowTower::from_tower_field(&buildings, &tower_field, &textures, 13, 5));
-    commands.spawn(WallBundle::from_tower_field(&buildings, &tower_field, &textures, 14, 6));
+    let walls = [(0, 0), (0, 1), (1, 1), (2, 1), (0, 2), (12, 2), (12, 4), (14, 6)];
+    let arrows = [(12, 0), (10, 3), (12, 1), (12, 3), (13, 5)];
+    for (x, y) in walls {
+        match WallBundle::from_tower_field(&buildings, &tower_field, &textures, x, y) {
+            Ok(wall) => { commands.spawn(wall); },
+            Err(err) => fatal_error.report(err)
+        }
+    }
+    let (Some(config), Some(sprite_name)) = (buildings.get_building_config(&BuildingType::Arrow), buildings.get_sprite_name(&BuildingType::Arrow)) else {
+        fatal_error.report(BuildError::MissingConfig(BuildingType::Arrow));
+        return;
+    };
+    for (x, y) in arrows {
+        match DefenderBundle::from_config(BuildingType::Arrow, config, sprite_name, &tower_field, &textures, x, y) {
+            Ok(tower) => { tower.spawn(&mut commands); },
+            Err(err) => fatal_error.report(err)
+        }
+    }
 }
 
 fn setup_environment(
@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+use bevy::prelude::{Plugin, App, Resource, Res, ResMut, EventReader, Query, Added, IntoSystemConfig};
+use rand::Rng;
+
+use crate::{error::GameplaySet, util::GameRng};
+
+use super::{attacker_controller::{AttackerResource, calculate_round_end_bounty}, attackers::Attacker, events::{RoundStartEvent, RoundOverEvent}};
+
+/* Rules that only hold for a single round, picked by the attacker before it starts */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mutator {
+    /* The defender AI thinks slower */
+    Fog,
+    /* Attackers are faster but more fragile */
+    Adrenaline,
+    /* Kills and leaks pay half */
+    Scarcity
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 3] = [Mutator::Fog, Mutator::Adrenaline, Mutator::Scarcity];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            Mutator::Fog => "Fog",
+            Mutator::Adrenaline => "Adrenaline",
+            Mutator::Scarcity => "Scarcity"
+        };
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        return match self {
+            Mutator::Fog => "Defender actions take 50% longer",
+            Mutator::Adrenaline => "Attackers move 20% faster but have 10% less health",
+            Mutator::Scarcity => "Bounties of both sides are halved"
+        };
+    }
+
+    /* Attacker gold paid when picking the mutator, given back when it is dropped before the round starts */
+    pub fn get_cost(&self) -> i32 {
+        return match self {
+            Mutator::Fog => 40,
+            Mutator::Adrenaline => 60,
+            Mutator::Scarcity => 0
+        };
+    }
+}
+
+const FOG_COOLDOWN_FACTOR: f32 = 1.5;
+const ADRENALINE_SPEED_FACTOR: f32 = 1.2;
+const ADRENALINE_HEALTH_FACTOR: f32 = 0.9;
+const SCARCITY_BOUNTY_FACTOR: f32 = 0.5;
+
+/* Stats are never changed by a mutator, systems ask for the effective value instead. Ending the round can't leave anything behind that way */
+#[derive(Resource, Default)]
+pub struct RoundModifiers {
+    selected: Vec<Mutator>,
+    active: Vec<Mutator>,
+    /* Adds a random free mutator to every round */
+    pub chaos_mode: bool
+}
+
+impl RoundModifiers {
+    pub fn is_selected(&self, mutator: Mutator) -> bool {
+        return self.selected.contains(&mutator);
+    }
+
+    pub fn is_active(&self, mutator: Mutator) -> bool {
+        return self.active.contains(&mutator);
+    }
+
+    pub fn get_active(&self) -> &[Mutator] {
+        return &self.active;
+    }
+
+    /* Picks or drops a mutator for the next round, returns false when the attacker can't afford it */
+    pub fn toggle(&mut self, mutator: Mutator, attacker_resource: &mut AttackerResource) -> bool {
+        if self.is_selected(mutator) {
+            self.selected.retain(|selected| *selected != mutator);
+            attacker_resource.refund(mutator.get_cost());
+            return true;
+        }
+        if attacker_resource.gold < mutator.get_cost() {
+            return false;
+        }
+        attacker_resource.spend(mutator.get_cost());
+        self.selected.push(mutator);
+        return true;
+    }
+
+    pub fn get_action_cooldown_factor(&self) -> f32 {
+        return if self.is_active(Mutator::Fog) { FOG_COOLDOWN_FACTOR } else { 1. };
+    }
+
+    pub fn get_speed_factor(&self) -> f32 {
+        return if self.is_active(Mutator::Adrenaline) { ADRENALINE_SPEED_FACTOR } else { 1. };
+    }
+
+    pub fn get_health_factor(&self) -> f32 {
+        return if self.is_active(Mutator::Adrenaline) { ADRENALINE_HEALTH_FACTOR } else { 1. };
+    }
+
+    pub fn scale_bounty(&self, bounty: i32) -> i32 {
+        if self.is_active(Mutator::Scarcity) {
+            return (bounty as f32 * SCARCITY_BOUNTY_FACTOR) as i32;
+        }
+        return bounty;
+    }
+
+    /* How much of `delta` a timer that should run slower under Fog gets to tick */
+    pub fn scale_defender_delta(&self, delta: Duration) -> Duration {
+        return delta.div_f32(self.get_action_cooldown_factor());
+    }
+}
+
+pub struct MutatorsPlugin;
+
+impl Plugin for MutatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RoundModifiers>()
+            .add_system(activate_mutators.in_set(GameplaySet))
+            // The round end bounty is still paid under the mutators of the round that earned it
+            .add_system(deactivate_mutators.in_set(GameplaySet).after(calculate_round_end_bounty))
+            .add_system(apply_adrenaline_health.in_set(GameplaySet).after(activate_mutators));
+    }
+}
+
+pub(crate) fn activate_mutators(
+    mut round_start: EventReader<RoundStartEvent>,
+    mut modifiers: ResMut<RoundModifiers>,
+    mut rng: ResMut<GameRng>
+) {
+    for _ in round_start.iter() {
+        let mut active = std::mem::take(&mut modifiers.selected);
+        if modifiers.chaos_mode {
+            let mutator = Mutator::ALL[rng.0.gen_range(0..Mutator::ALL.len())];
+            if !active.contains(&mutator) {
+                active.push(mutator);
+            }
+        }
+        modifiers.active = active;
+    }
+}
+
+pub(crate) fn deactivate_mutators(
+    mut round_end: EventReader<RoundOverEvent>,
+    mut modifiers: ResMut<RoundModifiers>
+) {
+    for _ in round_end.iter() {
+        modifiers.active.clear();
+    }
+}
+
+/* Units only live for a round, so their health is the one thing changed directly */
+fn apply_adrenaline_health(
+    mut spawned: Query<&mut Attacker, Added<Attacker>>,
+    modifiers: Res<RoundModifiers>
+) {
+    let factor = modifiers.get_health_factor();
+    if factor == 1. {
+        return;
+    }
+    for mut attacker in spawned.iter_mut() {
+        attacker.health *= factor;
+        attacker.max_health *= factor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Events, Schedule, World};
+
+    use crate::world::attackers::{AttackerStats, AttackerType, UpgradeType};
+
+    use super::*;
+
+    #[test]
+    fn mutators_only_last_for_their_round() {
+        let mut world = World::new();
+        let mut attacker_resource = AttackerResource { gold: 50, current_bounty: 0, gold_spent_this_round: 0, gold_earned_this_round: 0 };
+        let mut modifiers = RoundModifiers::default();
+        assert!(!modifiers.toggle(Mutator::Adrenaline, &mut attacker_resource));
+        assert!(modifiers.toggle(Mutator::Fog, &mut attacker_resource));
+        assert!(modifiers.toggle(Mutator::Scarcity, &mut attacker_resource));
+        assert_eq!(attacker_resource.gold, 10);
+        world.insert_resource(modifiers);
+        world.insert_resource(GameRng::seeded(2127));
+        world.init_resource::<AttackerStats>();
+        world.init_resource::<Events<RoundStartEvent>>();
+        world.init_resource::<Events<RoundOverEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(activate_mutators);
+        schedule.add_system(deactivate_mutators.after(activate_mutators));
+
+        world.send_event(RoundStartEvent);
+        schedule.run(&mut world);
+        let modifiers = world.resource::<RoundModifiers>();
+        assert_eq!(modifiers.get_active(), &[Mutator::Fog, Mutator::Scarcity]);
+        assert!(!modifiers.is_selected(Mutator::Fog));
+        assert_eq!(modifiers.scale_bounty(15), 7);
+        assert_eq!(modifiers.scale_defender_delta(Duration::from_secs(3)), Duration::from_secs(2));
+
+        // Upgrades bought during the round stay exactly as bought
+        let speed = world.resource::<AttackerStats>().get_stats(AttackerType::Spider).movement_speed;
+        world.resource_mut::<AttackerStats>().apply_upgrade(AttackerType::Spider, UpgradeType::Speed);
+        let upgraded = world.resource::<AttackerStats>().get_stats(AttackerType::Spider).movement_speed;
+        assert!(upgraded > speed);
+
+        world.send_event(RoundOverEvent);
+        schedule.run(&mut world);
+        let modifiers = world.resource::<RoundModifiers>();
+        assert!(modifiers.get_active().is_empty());
+        assert_eq!(modifiers.scale_bounty(15), 15);
+        assert_eq!(modifiers.get_action_cooldown_factor(), 1.);
+        assert_eq!(world.resource::<AttackerStats>().get_stats(AttackerType::Spider).movement_speed, upgraded);
+    }
+
+    #[test]
+    fn adrenaline_weakens_new_attackers() {
+        let mut world = World::new();
+        let mut modifiers = RoundModifiers::default();
+        modifiers.active.push(Mutator::Adrenaline);
+        world.insert_resource(modifiers);
+        let stats = *AttackerStats::default().get_stats(AttackerType::OrcWarrior);
+        let attacker = world.spawn(stats).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(apply_adrenaline_health);
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        // Only applied once, when the attacker spawned
+        let health = world.get::<Attacker>(attacker).unwrap().health;
+        assert!((health - stats.health * ADRENALINE_HEALTH_FACTOR).abs() < 1e-3);
+        assert_eq!(world.resource::<RoundModifiers>().get_speed_factor(), ADRENALINE_SPEED_FACTOR);
+    }
+}
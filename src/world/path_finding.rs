@@ -1,9 +1,8 @@
-use std::{slice::Iter, option::IntoIter, fmt::Display};
+use std::{fmt::Display, collections::{HashMap, HashSet, VecDeque}};
 
-use bevy::prelude::{Vec2, Parent, Component};
-use serde::__private::de;
+use bevy::prelude::{Vec2, Component, Resource};
 
-use super::towers::{TowerField, SLOT_SIZE};
+use super::towers::{Footprint, TowerField, SLOT_SIZE};
 
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -67,7 +66,131 @@ impl HierarchicalNode {
     }
 }
 
-#[derive(Debug, Component)]
+/* Toggles line of sight shortcutting of attacker paths, only affects how attackers walk, not the AI's view of the path.
+   Off by default so attackers keep walking node to node unless it is turned on in the menu */
+#[derive(Resource, Default)]
+pub struct PathSmoothing {
+    pub enabled: bool
+}
+
+/* The way to the end from every slot at once, built backwards from the end so any number of attackers share one search */
+#[derive(Resource, Default)]
+pub struct FlowField {
+    /* Attackers entering the field while set follow the flow field instead of their own A* path */
+    pub enabled: bool,
+    width: usize,
+    /* Unit step towards the next slot on a shortest way, zero on the end, None where the end can't be reached */
+    directions: Vec<Option<Vec2>>,
+}
+
+impl FlowField {
+    pub fn rebuild(&mut self, field: &TowerField) {
+        self.width = field.get_width();
+        self.directions = vec![None; field.get_width() * field.get_height()];
+        let end = field.get_end();
+        if is_outside_field(end, field) || field.is_node_blocked(end) {
+            return;
+        }
+        let i = self.index(end);
+        self.directions[i] = Some(Vec2::ZERO);
+        let mut queue = VecDeque::from([end]);
+        while let Some(node) = queue.pop_front() {
+            for neighbor in get_successors(node) {
+                if is_outside_field(neighbor, field) || field.is_node_blocked(neighbor) {
+                    continue;
+                }
+                let i = self.index(neighbor);
+                if self.directions[i].is_some() {
+                    continue;
+                }
+                self.directions[i] = Some(Vec2::new((node.x - neighbor.x) as f32, (node.y - neighbor.y) as f32));
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    fn index(&self, node: Node) -> usize {
+        return node.y as usize * self.width + node.x as usize;
+    }
+
+    pub fn get_direction(&self, node: Node) -> Option<Vec2> {
+        if node.x < 0 || node.y < 0 || node.x as usize >= self.width {
+            return None;
+        }
+        return self.directions.get(self.index(node)).copied().flatten();
+    }
+
+    /* Slot reached by following the flow `cells` steps from `node`, stops early on the end or where the flow breaks off */
+    pub fn walk(&self, node: Node, cells: usize) -> Node {
+        let mut node = node;
+        for _ in 0..cells {
+            match self.get_direction(node) {
+                Some(direction) if direction != Vec2::ZERO => node = Node::new(node.x + direction.x as i32, node.y + direction.y as i32),
+                _ => break,
+            }
+        }
+        return node;
+    }
+
+    /* Slot whose center is closest to `position`, unlike TowerField::node_at this is meant for things moving between slots */
+    pub fn get_cell(position: Vec2) -> Node {
+        let cell = (position / SLOT_SIZE as f32).round();
+        return Node::new(cell.x as i32, cell.y as i32);
+    }
+}
+
+/* The way from every start to the end of the field as it is, searched once and shared by the attackers and the defender.
+   Searches with extra blocked slots never go through here. Invalidated on every FieldModified */
+#[derive(Resource, Default)]
+pub struct PathCache {
+    /* One per start, in the order of TowerField::get_starts(), None for starts that are cut off */
+    cached: Vec<Option<Path>>,
+    valid: bool,
+}
+
+impl PathCache {
+    pub fn is_valid(&self) -> bool {
+        return self.valid;
+    }
+
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /* Searches again only if the field changed since the last call */
+    pub fn get_paths(&mut self, field: &TowerField) -> &[Option<Path>] {
+        if !self.valid {
+            self.cached = field.get_starts().iter().map(|start| a_star(field, *start, field.get_end())).collect();
+            self.valid = true;
+        }
+        return &self.cached;
+    }
+
+    /* Slots that aren't a start are searched from every time */
+    pub fn get_path(&mut self, field: &TowerField, start: Node) -> Option<Path> {
+        return match field.get_starts().iter().position(|candidate| *candidate == start) {
+            Some(index) => self.get_paths(field)[index].clone(),
+            None => a_star(field, start, field.get_end()),
+        };
+    }
+
+    /* The cached version of a_star_from_all_starts */
+    pub fn get_all_paths(&mut self, field: &TowerField) -> Option<Vec<Path>> {
+        return self.get_paths(field).iter().cloned().collect();
+    }
+
+    /* The cached version of nodes_on_all_paths */
+    pub fn get_nodes_on_all_paths(&mut self, field: &TowerField) -> Vec<Node> {
+        let mut seen = HashSet::new();
+        return self.get_paths(field).iter()
+            .flatten()
+            .flat_map(|path| path.get_nodes())
+            .filter(|node| seen.insert(*node))
+            .collect();
+    }
+}
+
+#[derive(Debug, Component, Clone)]
 pub struct Path {
     route: Vec<Node>,
     current_index: usize
@@ -110,20 +233,254 @@ impl Path {
         return self.route.clone();
     }
 
+    pub fn contains(&self, node: Node) -> bool {
+        return self.route.contains(&node);
+    }
+
     pub fn get_current_index(&self) -> usize {
         return self.current_index;
     }
+
+    pub fn set_current_index(&mut self, index: usize) {
+        self.current_index = index.min(self.route.len().saturating_sub(1));
+    }
+
+    /* Sum of the straight line distances between consecutive nodes, in slots */
+    pub fn travel_distance(&self) -> f32 {
+        let mut distance = 0.;
+        for i in 1..self.route.len() {
+            let from = self.route[i - 1];
+            let to = self.route[i];
+            distance += Vec2::new(from.x as f32, from.y as f32).distance(Vec2::new(to.x as f32, to.y as f32));
+        }
+        return distance;
+    }
+
+    /* Copy of route[from_index..=to_index], starting again at its first node */
+    pub fn sub_path(&self, from_index: usize, to_index: usize) -> Path {
+        return Path {
+            route: self.route[from_index..=to_index].to_vec(),
+            current_index: 0
+        }
+    }
+
+    /* Appends other to this path, the joint node is only kept once. Progress of this path is kept */
+    pub fn concat(&self, other: &Path) -> Path {
+        let mut route = self.route.clone();
+        let skip = match (route.last(), other.route.first()) {
+            (Some(last), Some(first)) if last == first => 1,
+            _ => 0
+        };
+        route.extend(other.route.iter().skip(skip));
+        return Path { route, current_index: self.current_index };
+    }
+
+    /* First node at or after the current one that is blocked or can no longer be reached in a straight line from its predecessor */
+    pub fn first_obstructed_index(&self, field: &TowerField) -> Option<usize> {
+        for i in self.current_index..self.route.len() {
+            if field.is_node_blocked(self.route[i]) {
+                return Some(i);
+            }
+            if i > self.current_index && !has_line_of_sight(field, self.route[i - 1], self.route[i]) {
+                return Some(i);
+            }
+        }
+        return None;
+    }
+
+    /* String pulling: drop every node that can be skipped with an unobstructed straight line */
+    pub fn smoothed(&self, field: &TowerField) -> Path {
+        if self.route.len() <= 2 {
+            return Path { route: self.route.clone(), current_index: self.current_index };
+        }
+        let last = self.route.len() - 1;
+        let mut route: Vec<Node> = vec![self.route[0]];
+        let mut kept_indices: Vec<usize> = vec![0];
+        let mut anchor = 0;
+        for i in 1..last {
+            if !has_line_of_sight(field, self.route[anchor], self.route[i + 1]) {
+                route.push(self.route[i]);
+                kept_indices.push(i);
+                anchor = i;
+            }
+        }
+        route.push(self.route[last]);
+        kept_indices.push(last);
+
+        // Keep progress by targeting the first remaining node at or after the current one
+        let current_index = kept_indices.iter().position(|i| *i >= self.current_index).unwrap_or(route.len() - 1);
+        return Path { route, current_index };
+    }
+}
+
+/* Walks every slot the segment between the two node centers passes through, touching a corner counts both sides */
+pub fn has_line_of_sight(field: &TowerField, from: Node, to: Node) -> bool {
+    let is_free = |x: i32, y: i32| {
+        let node = Node::new(x, y);
+        return !is_outside_field(node, field) && !field.is_node_blocked(node);
+    };
+    if !is_free(from.x, from.y) || !is_free(to.x, to.y) {
+        return false;
+    }
+    let dx = (to.x - from.x) as f32;
+    let dy = (to.y - from.y) as f32;
+    let step_x = (to.x - from.x).signum();
+    let step_y = (to.y - from.y).signum();
+    let t_delta_x = if dx != 0. { 1. / dx.abs() } else { f32::INFINITY };
+    let t_delta_y = if dy != 0. { 1. / dy.abs() } else { f32::INFINITY };
+    let mut t_max_x = t_delta_x * 0.5;
+    let mut t_max_y = t_delta_y * 0.5;
+    let mut x = from.x;
+    let mut y = from.y;
+    let max_steps = (to.x - from.x).abs() + (to.y - from.y).abs();
+    for _ in 0..max_steps {
+        if x == to.x && y == to.y {
+            break;
+        }
+        if (t_max_x - t_max_y).abs() < 1e-4 {
+            if !is_free(x + step_x, y) || !is_free(x, y + step_y) {
+                return false;
+            }
+            x += step_x;
+            y += step_y;
+            t_max_x += t_delta_x;
+            t_max_y += t_delta_y;
+        } else if t_max_x < t_max_y {
+            x += step_x;
+            t_max_x += t_delta_x;
+        } else {
+            y += step_y;
+            t_max_y += t_delta_y;
+        }
+        if !is_free(x, y) {
+            return false;
+        }
+    }
+    return true;
+}
+
+/* Blocking a choke point makes the way to the end longer by more than this many nodes, or closes it */
+pub const CHOKE_POINT_MIN_DETOUR: i32 = 5;
+
+impl TowerField {
+    /* Nodes of `paths` without a short way around them, walling them off forces the longest detours.
+       There is one path per start, in the order of get_starts() */
+    pub fn compute_choke_points(&self, paths: &[Path]) -> Vec<Node> {
+        let mut choke_points = Vec::new();
+        for (start, path) in self.get_starts().iter().zip(paths) {
+            let original_length = path.get_size() as i32;
+            for node in path.get_nodes() {
+                if self.get_starts().contains(&node) || node == self.get_end() || choke_points.contains(&node) {
+                    continue;
+                }
+                let is_choke_point = match a_star_with_blocked_node(self, *start, self.get_end(), Some(node)) {
+                    Some(alternative) => alternative.get_size() as i32 - original_length > CHOKE_POINT_MIN_DETOUR,
+                    None => true
+                };
+                if is_choke_point {
+                    choke_points.push(node);
+                }
+            }
+        }
+        return choke_points;
+    }
 }
 
 pub fn a_star(field: &TowerField, start: Node, end: Node) -> Option<Path> {
     return a_star_with_blocked_node(field, start, end, None);
 }
 
-pub fn a_star_with_blocked_node(field: &TowerField, start: Node, end: Node, additional_blocked_node: Option<Node>) -> Option<Path> {
-    if let Some(blocked) = additional_blocked_node {
-        if start == blocked || end == blocked {
-            return None;
+/* The way from every start to the end, in the order of get_starts(). None once any start is cut off */
+pub fn a_star_from_all_starts(field: &TowerField) -> Option<Vec<Path>> {
+    return field.get_starts().iter().map(|start| a_star(field, *start, field.get_end())).collect();
+}
+
+/* Nodes on the way from any start to the end, each once. Starts that are cut off add none */
+pub fn nodes_on_all_paths(field: &TowerField) -> Vec<Node> {
+    let mut seen = HashSet::new();
+    return field.get_starts().iter()
+        .filter_map(|start| a_star(field, *start, field.get_end()))
+        .flat_map(|path| path.get_nodes())
+        .filter(|node| seen.insert(*node))
+        .collect();
+}
+
+/* Whether a structure on `node` still leaves a way from the start to the end */
+pub fn verify_placement_valid(field: &TowerField, node: Node, blocking: bool) -> bool {
+    return verify_footprint_placement_valid(field, node, Footprint::SINGLE, blocking);
+}
+
+/* verify_placement_valid for a structure covering all slots of the footprint at once */
+pub fn verify_footprint_placement_valid(field: &TowerField, anchor: Node, footprint: Footprint, blocking: bool) -> bool {
+    if !blocking {
+        // Attackers walk through passable structures
+        return true;
+    }
+    let blocked: Vec<Node> = footprint.nodes(anchor).collect();
+    // Every start has to keep its way to the end
+    return field.get_starts().iter().all(|start| a_star_with_blocked_nodes(field, *start, field.get_end(), &blocked).is_some());
+}
+
+/* Open slots every way from `start` to `end` leads through, including both ends, so the ones no blocking structure can go on.
+   One depth first search for cut vertices instead of a path search per slot */
+pub fn find_critical_nodes(field: &TowerField, start: Node, end: Node) -> HashSet<Node> {
+    let mut search = CutSearch { field, start, end, discovered: HashMap::new(), low: HashMap::new(), critical: HashSet::new() };
+    if is_outside_field(start, field) || is_outside_field(end, field) || field.is_node_blocked(start) || field.is_node_blocked(end) {
+        return search.critical;
+    }
+    if search.visit(start) {
+        search.critical.insert(start);
+        search.critical.insert(end);
+    }
+    return search.critical;
+}
+
+struct CutSearch<'a> {
+    field: &'a TowerField,
+    start: Node,
+    end: Node,
+    discovered: HashMap<Node, usize>,
+    /* Earliest discovered slot reachable from the subtree of a slot with a single step back */
+    low: HashMap<Node, usize>,
+    critical: HashSet<Node>,
+}
+
+impl CutSearch<'_> {
+    /* Whether the end is in the subtree of `node` */
+    fn visit(&mut self, node: Node) -> bool {
+        let time = self.discovered.len();
+        self.discovered.insert(node, time);
+        self.low.insert(node, time);
+        let mut reaches_end = node == self.end;
+        for neighbor in get_successors(node) {
+            if is_outside_field(neighbor, self.field) || self.field.is_node_blocked(neighbor) {
+                continue;
+            }
+            if let Some(discovered) = self.discovered.get(&neighbor).copied() {
+                self.low.insert(node, self.low[&node].min(discovered));
+                continue;
+            }
+            let child_reaches_end = self.visit(neighbor);
+            self.low.insert(node, self.low[&node].min(self.low[&neighbor]));
+            // Nothing in the child's subtree gets around `node`, so the end is cut off without it
+            if child_reaches_end {
+                reaches_end = true;
+                if node != self.start && self.low[&neighbor] >= time {
+                    self.critical.insert(node);
+                }
+            }
         }
+        return reaches_end;
+    }
+}
+
+pub fn a_star_with_blocked_node(field: &TowerField, start: Node, end: Node, additional_blocked_node: Option<Node>) -> Option<Path> {
+    return a_star_with_blocked_nodes(field, start, end, additional_blocked_node.as_slice());
+}
+
+pub fn a_star_with_blocked_nodes(field: &TowerField, start: Node, end: Node, additional_blocked_nodes: &[Node]) -> Option<Path> {
+    if additional_blocked_nodes.contains(&start) || additional_blocked_nodes.contains(&end) {
+        return None;
     }
     if is_outside_field(start, &field) {
         return None;
@@ -155,10 +512,8 @@ pub fn a_star_with_blocked_node(field: &TowerField, start: Node, end: Node, addi
                     if successor.node == end {
                         return Some(get_path(successor));
                     }
-                    if let Some(blocked) = additional_blocked_node {
-                        if blocked == successor.node {
-                            continue;
-                        }
+                    if additional_blocked_nodes.contains(&successor.node) {
+                        continue;
                     }
                     if is_outside_field(successor.to_node(), &field) {
                         continue;
@@ -277,4 +632,216 @@ fn heuristic(node: Node, end: Node) -> f32 {
 
 fn distance(from_node: Node, to_node: Node) -> f32 {
     return f32::abs((from_node.x - to_node.x) as f32) + f32::abs((from_node.y - to_node.y) as f32);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Vec2, Entity};
+
+    use super::*;
+
+    #[test]
+    fn a_star_finds_the_shortest_way_around_walls() {
+        let mut field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(7, 0));
+        // Paths hold the start as well, so there is one node more than steps
+        assert_eq!(a_star(&field, field.get_start(), field.get_end()).unwrap().get_size(), 8);
+        assert_eq!(a_star(&field, Node::new(0, 0), Node::new(7, 7)).unwrap().get_size(), 15);
+
+        // A wall across all but the top row, over it and back down is 14 steps more
+        for y in 0..7 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(3., y as f32) * SLOT_SIZE as f32, Footprint::SINGLE);
+        }
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert_eq!(path.get_size(), 22);
+        assert!(path.get_nodes().contains(&Node::new(3, 7)));
+        assert!(a_star_with_blocked_node(&field, field.get_start(), field.get_end(), Some(Node::new(3, 7))).is_none());
+
+        // Blocked, outside or identical ends have no path at all
+        assert!(a_star(&field, Node::new(3, 0), field.get_end()).is_none());
+        assert!(a_star(&field, Node::new(-1, 0), field.get_end()).is_none());
+        assert!(a_star(&field, field.get_end(), Node::new(8, 0)).is_none());
+        assert!(a_star(&field, field.get_end(), field.get_end()).is_none());
+    }
+
+    #[test]
+    fn path_progress_stays_on_the_route() {
+        let field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(3, 0));
+        let mut path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert_eq!(path.get_current_index(), 0);
+        assert_eq!(path.get_target_position(), Vec2::ZERO);
+        path.increment_index();
+        assert_eq!(path.get_target_position(), Vec2::new(SLOT_SIZE as f32, 0.));
+        for _ in 0..10 {
+            path.increment_index();
+        }
+        assert_eq!(path.get_current_index(), 3);
+        assert_eq!(path.get_node(path.get_current_index()), field.get_end());
+        path.set_current_index(1);
+        assert_eq!(path.get_current_index(), 1);
+        path.set_current_index(99);
+        assert_eq!(path.get_current_index(), 3);
+        assert_eq!(path.travel_distance(), 3.);
+    }
+
+    #[test]
+    fn smoothed_path_is_shorter_in_open_field() {
+        let field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(10, 7));
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        let smoothed = path.smoothed(&field);
+        assert!(smoothed.get_size() < path.get_size());
+        assert!(smoothed.travel_distance() < path.travel_distance());
+        assert_eq!(smoothed.get_node(0), path.get_node(0));
+        assert_eq!(smoothed.get_node(smoothed.get_size() - 1), field.get_end());
+    }
+
+    #[test]
+    fn smoothed_path_does_not_cut_through_blocked_slots() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(4, 4));
+        for y in 0..4 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(2. * SLOT_SIZE as f32, (y * SLOT_SIZE) as f32), Footprint::SINGLE);
+        }
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap().smoothed(&field);
+        let nodes = path.get_nodes();
+        for i in 1..nodes.len() {
+            assert!(has_line_of_sight(&field, nodes[i - 1], nodes[i]));
+        }
+    }
+
+    #[test]
+    fn sub_path_and_concat_rebuild_the_route() {
+        let field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(6, 3));
+        let mut path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        path.increment_index();
+        path.increment_index();
+        let prefix = path.sub_path(0, 4);
+        assert_eq!(prefix.get_size(), 5);
+        assert_eq!(prefix.get_current_index(), 0);
+        let suffix = path.sub_path(4, path.get_size() - 1);
+        let mut prefix_with_progress = prefix;
+        prefix_with_progress.increment_index();
+        let joined = prefix_with_progress.concat(&suffix);
+        assert_eq!(joined.get_nodes(), path.get_nodes());
+        assert_eq!(joined.get_current_index(), 1);
+    }
+
+    #[test]
+    fn obstruction_is_only_reported_ahead() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(0, 0), Node::new(6, 0));
+        let mut path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert_eq!(path.first_obstructed_index(&field), None);
+        let blocked = path.get_node(3);
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(blocked.x as f32 * SLOT_SIZE as f32, blocked.y as f32 * SLOT_SIZE as f32), Footprint::SINGLE);
+        assert_eq!(path.first_obstructed_index(&field), Some(3));
+        for _ in 0..4 {
+            path.increment_index();
+        }
+        assert_eq!(path.first_obstructed_index(&field), None);
+    }
+
+    #[test]
+    fn placement_that_closes_the_last_gap_is_invalid() {
+        let mut field = TowerField::new(4, 4, Vec2::ZERO, Node::new(0, 0), Node::new(0, 3));
+        for x in 0..3 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new((x * SLOT_SIZE) as f32, SLOT_SIZE as f32), Footprint::SINGLE);
+        }
+        let gap = Node::new(3, 1);
+        assert!(!verify_placement_valid(&field, gap, true));
+        assert!(verify_placement_valid(&field, gap, false));
+        assert!(verify_placement_valid(&field, Node::new(2, 2), true));
+        assert!(!verify_placement_valid(&field, field.get_end(), true));
+        // A single slot there leaves room, all four of a large building don't
+        assert!(!verify_footprint_placement_valid(&field, Node::new(2, 2), Footprint { width: 2, height: 2 }, true));
+    }
+
+    #[test]
+    fn only_the_gap_in_a_wall_is_a_choke_point() {
+        let mut field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(0, 7));
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert!(field.compute_choke_points(&[path]).is_empty());
+
+        for x in 0..7 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new((x * SLOT_SIZE) as f32, (3 * SLOT_SIZE) as f32), Footprint::SINGLE);
+        }
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        let choke_points = field.compute_choke_points(&[path]);
+        assert!(choke_points.contains(&Node::new(7, 3)));
+        assert!(!choke_points.contains(&field.get_start()));
+        assert!(choke_points.iter().all(|node| node.x >= 6));
+    }
+
+    #[test]
+    fn choke_points_of_every_start_are_found() {
+        let mut field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(0, 7));
+        field.add_start(Node::new(7, 7));
+        // The wall only closes off the way of the main start, the second one spawns past it
+        for x in 0..7 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new((x * SLOT_SIZE) as f32, (3 * SLOT_SIZE) as f32), Footprint::SINGLE);
+        }
+        let paths = a_star_from_all_starts(&field).unwrap();
+        assert_eq!(paths.len(), 2);
+        let choke_points = field.compute_choke_points(&paths);
+        assert!(choke_points.contains(&Node::new(7, 3)));
+        assert!(!choke_points.contains(&Node::new(7, 7)));
+    }
+
+    #[test]
+    fn flow_field_leads_around_a_wall_as_short_as_a_star() {
+        let mut field = TowerField::new(8, 8, Vec2::ZERO, Node::new(0, 0), Node::new(0, 7));
+        for x in 0..7 {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new((x * SLOT_SIZE) as f32, (3 * SLOT_SIZE) as f32), Footprint::SINGLE);
+        }
+        let mut flow = FlowField::default();
+        flow.rebuild(&field);
+        assert_eq!(flow.get_direction(Node::new(2, 3)), None);
+        assert_eq!(flow.get_direction(field.get_end()), Some(Vec2::ZERO));
+
+        let mut node = field.get_start();
+        let mut steps = 0;
+        while node != field.get_end() {
+            let direction = flow.get_direction(node).unwrap();
+            assert_eq!(direction.length(), 1.);
+            node = FlowField::get_cell((Vec2::new(node.x as f32, node.y as f32) + direction) * SLOT_SIZE as f32);
+            assert!(!field.is_node_blocked(node));
+            steps += 1;
+        }
+        let path = a_star(&field, field.get_start(), field.get_end()).unwrap();
+        assert_eq!(steps, path.get_size() - 1);
+    }
+
+    #[test]
+    fn critical_nodes_are_exactly_the_invalid_placements() {
+        let mut field = TowerField::new(6, 6, Vec2::ZERO, Node::new(0, 0), Node::new(5, 5));
+        for (x, y) in [(0, 2), (1, 2), (2, 2), (3, 2), (5, 2), (2, 4), (3, 4), (4, 4)] {
+            field.add_structure(Entity::PLACEHOLDER, true, Vec2::new((x * SLOT_SIZE) as f32, (y * SLOT_SIZE) as f32), Footprint::SINGLE);
+        }
+        let critical = find_critical_nodes(&field, field.get_start(), field.get_end());
+        assert!(critical.contains(&Node::new(4, 2)));
+        for x in 0..6 {
+            for y in 0..6 {
+                let node = Node::new(x, y);
+                if !field.is_node_blocked(node) {
+                    assert_eq!(critical.contains(&node), !verify_placement_valid(&field, node, true), "{}", node);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn path_cache_searches_again_only_after_invalidation() {
+        let mut field = TowerField::new(12, 12, Vec2::ZERO, Node::new(0, 6), Node::new(11, 6));
+        field.add_start(Node::new(0, 0));
+        let mut cache = PathCache::default();
+        let paths = cache.get_all_paths(&field).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1].get_size(), a_star(&field, Node::new(0, 0), field.get_end()).unwrap().get_size());
+
+        let wall = Node::new(5, 6);
+        assert!(paths[0].contains(wall));
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::new(wall.x as f32, wall.y as f32) * SLOT_SIZE as f32, Footprint::SINGLE);
+        // Until told otherwise the field is taken to be unchanged
+        assert!(cache.get_path(&field, field.get_start()).unwrap().contains(wall));
+        cache.invalidate();
+        assert!(!cache.get_path(&field, field.get_start()).unwrap().contains(wall));
+        assert!(cache.is_valid());
+    }
+}
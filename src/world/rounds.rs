@@ -1,23 +1,190 @@
-use std::collections::VecDeque;
+use std::{collections::{HashMap, VecDeque}, time::Duration};
 
-use bevy::{prelude::{Plugin, App, Resource, ResMut, Commands, Res, Local, EventReader, Query, Entity, EventWriter}, time::Time};
+use bevy::{prelude::{Plugin, App, Resource, ResMut, Commands, Res, Local, EventReader, Query, Entity, EventWriter, IntoSystemConfig, Transform, Vec2}, time::Time};
+use rand::Rng;
 
-use crate::{textures::TextureResource, util::RepeatingLocalTimer};
+use crate::{textures::TextureResource, util::{GameRng, RepeatingLocalTimer}, error::GameplaySet};
 
-use super::{attackers::{AttackerType, spawn_attacker, Attacker, AttackerStats}, towers::TowerField, events::{RequestRoundStart, RoundStartEvent, RoundOverEvent}};
+use super::{attackers::{AttackerType, spawn_attacker, invalidate_path_cache, Attacker, AttackerStats}, building_configuration::BuildingResource, game_config::GameConfig, towers::{Defender, Structure, TowerField, SLOT_SIZE}, events::{RequestRoundStart, RoundStartEvent, RoundOverEvent}, path_finding::{Path, PathCache}};
 
 
-#[derive(Resource)]
+/* How the queued units leave the start, grouped units are easy prey for splash towers but overwhelm single targets */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SpawnCadence {
+    #[default]
+    Trickle,
+    Pairs,
+    Burst
+}
+
+impl SpawnCadence {
+    pub const ALL: [SpawnCadence; 3] = [SpawnCadence::Trickle, SpawnCadence::Pairs, SpawnCadence::Burst];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            SpawnCadence::Trickle => "Trickle",
+            SpawnCadence::Pairs => "Pairs",
+            SpawnCadence::Burst => "Burst"
+        };
+    }
+
+    pub fn get_group_size(&self) -> usize {
+        return match self {
+            SpawnCadence::Trickle => 1,
+            SpawnCadence::Pairs => 2,
+            SpawnCadence::Burst => 4
+        };
+    }
+
+    /* Time between two groups, a unit per second on average whatever the cadence */
+    pub fn get_interval(&self) -> Duration {
+        return Duration::from_secs(self.get_group_size() as u64);
+    }
+}
+
+/* Which start a unit enters from, only matters on fields with more than one */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SpawnPolicy {
+    #[default]
+    RoundRobin,
+    Random,
+    /* The start whose path runs past the least tower damage */
+    LeastDefended
+}
+
+impl SpawnPolicy {
+    pub const ALL: [SpawnPolicy; 3] = [SpawnPolicy::RoundRobin, SpawnPolicy::Random, SpawnPolicy::LeastDefended];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            SpawnPolicy::RoundRobin => "Round robin",
+            SpawnPolicy::Random => "Random",
+            SpawnPolicy::LeastDefended => "Least defended"
+        };
+    }
+}
+
+#[derive(Resource, Default)]
 pub struct RoundResource {
     pending_spawn_queue: VecDeque<AttackerType>,
     active_spawn_queue: VecDeque<AttackerType>,
-    round_active: bool
+    round_active: bool,
+    /* Number of the last round that was started, 0 before the first one */
+    round_number: u32,
+    /* Picked for the next round, it is locked in when the round starts */
+    cadence: SpawnCadence,
+    active_cadence: SpawnCadence,
+    spawn_policy: SpawnPolicy,
+    /* Start the next unit takes under SpawnPolicy::RoundRobin */
+    next_start: usize
 }
 
 impl RoundResource {
+    pub fn get_cadence(&self) -> SpawnCadence {
+        return self.cadence;
+    }
+
+    pub fn set_cadence(&mut self, cadence: SpawnCadence) {
+        self.cadence = cadence;
+    }
+
+    /* Cadence of the current round, or of the last one between rounds */
+    pub fn get_active_cadence(&self) -> SpawnCadence {
+        return self.active_cadence;
+    }
+
+    pub fn get_spawn_policy(&self) -> SpawnPolicy {
+        return self.spawn_policy;
+    }
+
+    pub fn set_spawn_policy(&mut self, spawn_policy: SpawnPolicy) {
+        self.spawn_policy = spawn_policy;
+    }
+
+    /* Start of the next unit out of `starts`, `defense` estimates the damage waiting on the way from a start */
+    pub fn pick_start(&mut self, starts: usize, rng: &mut GameRng, defense: impl Fn(usize) -> f32) -> usize {
+        if starts <= 1 {
+            return 0;
+        }
+        return match self.spawn_policy {
+            SpawnPolicy::RoundRobin => {
+                let start = self.next_start % starts;
+                self.next_start = start + 1;
+                start
+            }
+            SpawnPolicy::Random => rng.0.gen_range(0..starts),
+            SpawnPolicy::LeastDefended => (0..starts).map(|start| (start, defense(start))).min_by(|a, b| a.1.total_cmp(&b.1)).map_or(0, |(start, _)| start)
+        };
+    }
+
+    /* The next group to spawn, fewer than a full group once the queue runs dry */
+    fn pop_spawn_group(&mut self) -> Vec<AttackerType> {
+        let size = self.active_cadence.get_group_size().min(self.active_spawn_queue.len());
+        return self.active_spawn_queue.drain(..size).collect();
+    }
+
     pub fn queue(&mut self, attacker_type: &AttackerType) {
         self.pending_spawn_queue.push_back(attacker_type.clone());
     }
+
+    /* Units that go out with the next round */
+    pub fn get_pending_units(&self) -> &VecDeque<AttackerType> {
+        return &self.pending_spawn_queue;
+    }
+
+    pub fn get_round_number(&self) -> u32 {
+        return self.round_number;
+    }
+
+    pub fn has_pending_units(&self) -> bool {
+        return !self.pending_spawn_queue.is_empty();
+    }
+
+    pub fn is_round_active(&self) -> bool {
+        return self.round_active;
+    }
+}
+
+/* Units bought ahead of time, joining the spawn queue when their round starts */
+#[derive(Resource, Default)]
+pub struct ScheduledSpawns {
+    pub by_round: HashMap<u32, Vec<AttackerType>>
+}
+
+impl ScheduledSpawns {
+    pub fn schedule(&mut self, round: u32, attacker_type: AttackerType) {
+        self.by_round.entry(round).or_default().push(attacker_type);
+    }
+
+    /* Scheduled rounds in the order they will be played */
+    pub fn get_rounds(&self) -> Vec<u32> {
+        let mut rounds: Vec<u32> = self.by_round.keys().copied().collect();
+        rounds.sort();
+        return rounds;
+    }
+}
+
+/* Countdown between RequestRoundStart and RoundStartEvent, only running while original is above 0 */
+#[derive(Resource, Default)]
+pub struct PrepTimer {
+    pub remaining: f32,
+    pub original: f32,
+}
+
+impl PrepTimer {
+    pub fn is_running(&self) -> bool {
+        return self.original > 0.;
+    }
+
+    /* Share of the countdown that is over */
+    pub fn get_progress(&self) -> f32 {
+        return if self.is_running() { 1. - self.remaining / self.original } else { 0. };
+    }
+
+    /* Starts the round with the next tick */
+    pub fn skip(&mut self) {
+        self.remaining = 0.;
+    }
 }
 
 pub struct RoundPlugin;
@@ -25,17 +192,18 @@ pub struct RoundPlugin;
 impl Plugin for RoundPlugin {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(RoundResource {
-                active_spawn_queue: VecDeque::new(),
-                pending_spawn_queue: VecDeque::new(),
-                round_active: false
-            })
-            .add_system(process_spawn_queue)
-            .add_system(process_request_round_start)
-            .add_system(check_round_end);
+            .init_resource::<RoundResource>()
+            .init_resource::<ScheduledSpawns>()
+            .init_resource::<PathCache>()
+            .init_resource::<PrepTimer>()
+            .add_system(process_spawn_queue.in_set(GameplaySet).after(invalidate_path_cache))
+            .add_system(process_request_round_start.in_set(GameplaySet))
+            .add_system(tick_prep_timer.in_set(GameplaySet).after(process_request_round_start))
+            .add_system(check_round_end.in_set(GameplaySet));
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_spawn_queue(
     mut commands: Commands,
     mut round: ResMut<RoundResource>,
@@ -43,34 +211,87 @@ fn process_spawn_queue(
     textures: Res<TextureResource>,
     mut timer: Local<RepeatingLocalTimer<1000>>,
     attackers: Res<AttackerStats>,
-    time: Res<Time>
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    buildings: Res<BuildingResource>,
+    towers: Query<(&Structure, &Defender, &Transform)>,
+    mut cache: ResMut<PathCache>
 ) {
+    timer.timer.set_duration(round.active_cadence.get_interval());
     timer.timer.tick(time.delta());
-    let active = round.round_active;
-    let queue = &mut round.active_spawn_queue;
-    if timer.timer.just_finished() && !queue.is_empty() && active {
-        if let Some(next) = queue.pop_front() {
-            spawn_attacker(commands, &field, &textures, next, &attackers);
+    if timer.timer.just_finished() && round.round_active {
+        let paths = cache.get_paths(&field);
+        let defense = |start: usize| estimate_path_damage(paths[start].as_ref(), &buildings, &towers);
+        for (lane, next) in round.pop_spawn_group().into_iter().enumerate() {
+            let start = round.pick_start(field.get_starts().len(), &mut rng, defense);
+            spawn_attacker(&mut commands, &field, &textures, next, &attackers, start, lane);
         }
     }
 }
 
-fn process_request_round_start(
+/* Like the defender's own estimate: the damage per second of every tower times the path nodes it reaches */
+fn estimate_path_damage(path: Option<&Path>, buildings: &BuildingResource, towers: &Query<(&Structure, &Defender, &Transform)>) -> f32 {
+    let Some(path) = path else {
+        return f32::MAX;
+    };
+    let nodes: Vec<Vec2> = path.get_nodes().iter().map(|node| Vec2::new(node.x as f32, node.y as f32) * SLOT_SIZE as f32).collect();
+    return towers.iter().map(|(structure, defender, transform)| {
+        let position = transform.translation.truncate();
+        let covered = nodes.iter().filter(|node| node.distance(position) <= defender.attack_range).count();
+        buildings.get_dps(&structure.building_type) * covered as f32
+    }).sum();
+}
+
+pub(crate) fn process_request_round_start(
     mut event: EventReader<RequestRoundStart>,
     mut round: ResMut<RoundResource>,
+    mut scheduled: ResMut<ScheduledSpawns>,
+    mut prep: ResMut<PrepTimer>,
+    config: Res<GameConfig>,
     mut round_start: EventWriter<RoundStartEvent>
 ) {
     for ev in event.iter() {
-        if !round.round_active && round.active_spawn_queue.is_empty() {
-            round.round_active = true;
-            round.active_spawn_queue = round.pending_spawn_queue.clone();
-            round.pending_spawn_queue = VecDeque::new();
-            round_start.send(RoundStartEvent);
+        if round.round_active || !round.active_spawn_queue.is_empty() || prep.is_running() {
+            continue;
         }
+        if config.prep_duration > 0. {
+            *prep = PrepTimer { remaining: config.prep_duration, original: config.prep_duration };
+        } else {
+            start_round(&mut round, &mut scheduled, &mut round_start);
+        }
+    }
+}
+
+pub(crate) fn tick_prep_timer(
+    mut prep: ResMut<PrepTimer>,
+    mut round: ResMut<RoundResource>,
+    mut scheduled: ResMut<ScheduledSpawns>,
+    mut round_start: EventWriter<RoundStartEvent>,
+    time: Res<Time>
+) {
+    if !prep.is_running() {
+        return;
+    }
+    prep.remaining -= time.delta_seconds();
+    if prep.remaining <= 0. {
+        *prep = PrepTimer::default();
+        start_round(&mut round, &mut scheduled, &mut round_start);
     }
 }
 
-fn check_round_end(
+/* Units queued during the countdown still make it into the round */
+fn start_round(round: &mut RoundResource, scheduled: &mut ScheduledSpawns, round_start: &mut EventWriter<RoundStartEvent>) {
+    round.round_active = true;
+    round.round_number += 1;
+    round.active_cadence = round.cadence;
+    round.active_spawn_queue = std::mem::take(&mut round.pending_spawn_queue);
+    if let Some(units) = scheduled.by_round.remove(&round.round_number) {
+        round.active_spawn_queue.extend(units);
+    }
+    round_start.send(RoundStartEvent);
+}
+
+pub(crate) fn check_round_end(
     mut round: ResMut<RoundResource>,
     query: Query<(Entity, &Attacker)>,
     mut round_end: EventWriter<RoundOverEvent>
@@ -79,4 +300,116 @@ fn check_round_end(
         round.round_active = false;
         round_end.send(RoundOverEvent);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::prelude::{Events, Schedule, World};
+
+    use crate::world::path_finding::Node;
+
+    use super::*;
+
+    #[test]
+    fn scheduled_units_join_their_round_only() {
+        let mut world = World::new();
+        world.insert_resource(RoundResource {
+            pending_spawn_queue: VecDeque::from([AttackerType::OrcWarrior]),
+            active_spawn_queue: VecDeque::new(),
+            round_active: false,
+            round_number: 0,
+            cadence: SpawnCadence::Trickle,
+            active_cadence: SpawnCadence::Trickle,
+            spawn_policy: SpawnPolicy::RoundRobin,
+            next_start: 0
+        });
+        let mut scheduled = ScheduledSpawns::default();
+        scheduled.schedule(1, AttackerType::Golem);
+        scheduled.schedule(2, AttackerType::Spider);
+        world.insert_resource(scheduled);
+        world.insert_resource(GameConfig { prep_duration: 0., ..Default::default() });
+        world.init_resource::<PrepTimer>();
+        world.init_resource::<Events<RequestRoundStart>>();
+        world.init_resource::<Events<RoundStartEvent>>();
+        world.send_event(RequestRoundStart);
+        let mut schedule = Schedule::new();
+        schedule.add_system(process_request_round_start);
+        schedule.run(&mut world);
+
+        let round = world.resource::<RoundResource>();
+        assert_eq!(round.get_round_number(), 1);
+        assert_eq!(round.active_spawn_queue, VecDeque::from([AttackerType::OrcWarrior, AttackerType::Golem]));
+        assert_eq!(world.resource::<ScheduledSpawns>().get_rounds(), vec![2]);
+    }
+
+    #[test]
+    fn rounds_start_once_the_prep_countdown_is_over() {
+        let start = Instant::now();
+        let mut world = World::new();
+        world.init_resource::<RoundResource>();
+        world.init_resource::<ScheduledSpawns>();
+        world.init_resource::<PrepTimer>();
+        world.insert_resource(GameConfig { prep_duration: 2., ..Default::default() });
+        world.init_resource::<Time>();
+        world.resource_mut::<Time>().update_with_instant(start);
+        world.init_resource::<Events<RequestRoundStart>>();
+        world.init_resource::<Events<RoundStartEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(process_request_round_start);
+        schedule.add_system(tick_prep_timer.after(process_request_round_start));
+
+        world.send_event(RequestRoundStart);
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_millis(500));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PrepTimer>().remaining, 1.5);
+        // Asking again during the countdown doesn't restart it
+        world.send_event(RequestRoundStart);
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_millis(1000));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<PrepTimer>().get_progress(), 0.5);
+        assert!(!world.resource::<RoundResource>().is_round_active());
+
+        world.resource_mut::<PrepTimer>().skip();
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_millis(1016));
+        schedule.run(&mut world);
+        assert!(world.resource::<RoundResource>().is_round_active());
+        assert!(!world.resource::<PrepTimer>().is_running());
+        assert_eq!(world.resource::<Events<RoundStartEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn bursts_leave_in_groups_of_four() {
+        let mut round = RoundResource::default();
+        for _ in 0..6 {
+            round.queue(&AttackerType::Spider);
+        }
+        round.set_cadence(SpawnCadence::Burst);
+        round.active_spawn_queue = round.pending_spawn_queue.drain(..).collect();
+        // Only a started round uses the new cadence
+        assert_eq!(round.pop_spawn_group().len(), 1);
+        round.active_cadence = round.get_cadence();
+        assert_eq!(round.pop_spawn_group().len(), 4);
+        assert_eq!(round.pop_spawn_group().len(), 1);
+        assert!(round.pop_spawn_group().is_empty());
+    }
+
+    #[test]
+    fn round_robin_alternates_between_starts() {
+        let mut field = TowerField::new(16, 16, Vec2::ZERO, Node::new(2, 0), Node::new(14, 15));
+        field.add_start(Node::new(13, 0));
+        let mut rng = GameRng::seeded(2131);
+        let mut round = RoundResource::default();
+        let spawns: Vec<Transform> = (0..4).map(|_| field.get_start_transform_of(round.pick_start(field.get_starts().len(), &mut rng, |_| 0.))).collect();
+        assert_eq!(spawns[0], field.get_start_transform());
+        assert_eq!(spawns[1], field.get_start_transform_of(1));
+        assert_ne!(spawns[0], spawns[1]);
+        assert_eq!((spawns[2], spawns[3]), (spawns[0], spawns[1]));
+
+        round.set_spawn_policy(SpawnPolicy::LeastDefended);
+        assert_eq!(round.pick_start(2, &mut rng, |start| if start == 0 { 300. } else { 40. }), 1);
+        // A single start leaves nothing to pick
+        assert_eq!(round.pick_start(1, &mut rng, |_| 0.), 0);
+    }
+}
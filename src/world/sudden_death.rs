@@ -0,0 +1,131 @@
+use bevy::{prelude::{Plugin, App, Resource, Res, ResMut, EventReader, Local, IntoSystemConfig}, time::{Time, Timer, TimerMode}};
+
+use crate::error::GameplaySet;
+
+use super::{attacker_controller::{AttackerResource, calculate_round_end_bounty}, defender_controller::{ResourceStore, RoundStats}, events::RoundOverEvent};
+
+/* Rounds in a row without a kill or a leak before the game goes into overtime */
+pub const STALEMATE_ROUNDS: u32 = 3;
+pub const SUDDEN_DEATH_DRAIN_SECONDS: f32 = 10.;
+
+/* Overtime for games where neither side gets anywhere, the defender bleeds lives until it ends */
+#[derive(Resource)]
+pub struct SuddenDeathState {
+    pub active: bool,
+    pub passive_drain_timer: Timer
+}
+
+impl Default for SuddenDeathState {
+    fn default() -> Self {
+        return Self {
+            active: false,
+            passive_drain_timer: Timer::from_seconds(SUDDEN_DEATH_DRAIN_SECONDS, TimerMode::Repeating)
+        };
+    }
+}
+
+impl SuddenDeathState {
+    /* Multiplier for the round end bounty of kills */
+    pub fn get_kill_bounty_factor(&self) -> i32 {
+        return if self.active { 2 } else { 1 };
+    }
+}
+
+pub struct SuddenDeathPlugin;
+
+impl Plugin for SuddenDeathPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SuddenDeathState>()
+            // The bounty is paid out and reset at the end of the round
+            .add_system(check_stalemate.in_set(GameplaySet).before(calculate_round_end_bounty))
+            .add_system(drain_lives.in_set(GameplaySet));
+    }
+}
+
+pub(crate) fn check_stalemate(
+    mut round_end: EventReader<RoundOverEvent>,
+    mut state: ResMut<SuddenDeathState>,
+    attacker_resource: Res<AttackerResource>,
+    stats: Res<RoundStats>,
+    mut rounds_without_progress: Local<u32>
+) {
+    for _ in round_end.iter() {
+        if attacker_resource.current_bounty == 0 && stats.num_reached_end == 0 {
+            *rounds_without_progress += 1;
+        } else {
+            *rounds_without_progress = 0;
+        }
+        if *rounds_without_progress >= STALEMATE_ROUNDS && !state.active {
+            state.active = true;
+            state.passive_drain_timer.reset();
+        }
+    }
+}
+
+pub(crate) fn drain_lives(
+    mut state: ResMut<SuddenDeathState>,
+    mut resources: ResMut<ResourceStore>,
+    time: Res<Time>
+) {
+    if !state.active || resources.lives <= 0 {
+        return;
+    }
+    state.passive_drain_timer.tick(time.delta());
+    resources.lives -= state.passive_drain_timer.times_finished_this_tick() as i32;
+    resources.lives = resources.lives.max(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::prelude::{Events, Schedule, World};
+
+    use super::*;
+
+    fn stalemate_world(current_bounty: i32) -> (World, Schedule) {
+        let mut world = World::new();
+        world.init_resource::<SuddenDeathState>();
+        world.insert_resource(AttackerResource { gold: 0, current_bounty, gold_spent_this_round: 0, gold_earned_this_round: 0 });
+        world.init_resource::<RoundStats>();
+        world.insert_resource(ResourceStore { gold: 0, lives: 5 });
+        world.init_resource::<Time>();
+        world.init_resource::<Events<RoundOverEvent>>();
+        let mut schedule = Schedule::new();
+        schedule.add_system(check_stalemate);
+        schedule.add_system(drain_lives.after(check_stalemate));
+        return (world, schedule);
+    }
+
+    fn play_rounds(world: &mut World, schedule: &mut Schedule, rounds: u32) {
+        for _ in 0..rounds {
+            world.send_event(RoundOverEvent);
+            schedule.run(world);
+        }
+    }
+
+    #[test]
+    fn three_rounds_without_progress_start_sudden_death() {
+        let (mut world, mut schedule) = stalemate_world(0);
+        play_rounds(&mut world, &mut schedule, STALEMATE_ROUNDS - 1);
+        assert!(!world.resource::<SuddenDeathState>().active);
+        play_rounds(&mut world, &mut schedule, 1);
+        assert!(world.resource::<SuddenDeathState>().active);
+        assert_eq!(world.resource::<SuddenDeathState>().get_kill_bounty_factor(), 2);
+
+        let start = Instant::now();
+        world.resource_mut::<Time>().update_with_instant(start);
+        world.resource_mut::<Time>().update_with_instant(start + Duration::from_secs_f32(SUDDEN_DEATH_DRAIN_SECONDS * 2.5));
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<ResourceStore>().lives, 3);
+    }
+
+    #[test]
+    fn kills_keep_the_game_out_of_sudden_death() {
+        let (mut world, mut schedule) = stalemate_world(4);
+        play_rounds(&mut world, &mut schedule, STALEMATE_ROUNDS * 2);
+        assert!(!world.resource::<SuddenDeathState>().active);
+        assert_eq!(world.resource::<ResourceStore>().lives, 5);
+    }
+}
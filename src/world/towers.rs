@@ -1,27 +1,61 @@
-use std::{f32::consts::PI, time::Duration};
+use std::{collections::{HashMap, HashSet}, f32::consts::PI, fmt::Display, time::Duration};
 
 use bevy::{
+    ecs::event::ManualEventReader,
     prelude::{
-        default, Added, App, Bundle, Commands, Component, Entity, EventReader, EventWriter, Handle,
-        Plugin, Quat, Query, Rect, Res, ResMut, Resource, Transform, Vec2, Visibility, Without,
+        default, Added, App, BuildChildren, Bundle, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader, EventWriter, Events, Handle,
+        Local, Plugin, Quat, Query, Rect, Res, ResMut, Resource, SystemSet, Transform, Vec2, Vec3, Visibility, With, Without, IntoSystemConfig, IntoSystemAppConfig, IntoSystemSetConfig, CoreSchedule,
     },
     sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite},
-    time::{Time, Timer},
+    time::{fixed_timestep::FixedTime, Time, Timer},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use crate::{textures::TextureResource, particle::{spawn_large_explosion, spawn_blood_splatter, spawn_coin}};
+use crate::{textures::TextureResource, error::GameplaySet, particle::{spawn_large_explosion, spawn_blood_splatter, spawn_death_effects, spawn_dust, spawn_floating_text, spawn_lightning, ParticleSettings}, util::GameRng};
 
 use super::{
-    attackers::{AnimationIndices, Attacker, Grounded},
+    attackers::{advance_animation, kill_attacker, AnimationIndices, AnimationPlayback, AnimationStep, AnimationTimer, Attacker, Grounded, Interceptor},
     building_configuration::{BuildingConfig, BuildingResource, BuildingType, BuildingTypeConfig},
+    building_registry::BuildingRegistry,
+    damage_matrix::DamageMatrix,
     events::{
-        DamageEvent, FieldModified, KillEvent, RemoveStructureRequest, RemovedStructureEvent,
+        BlinkEvent, BoardClearedEvent, ClearBoardRequest, DamageEvent, FieldModified, KillEvent, RemoveStructureRequest, RemovedStructureEvent,
+        SlowEvent, SplashImpactEvent,
     },
-    path_finding::{a_star, Node},
+    path_finding::{nodes_on_all_paths, Node},
+    abilities::SpecialAbility,
+    attacker_controller::GoldPickup,
+    interpolation::FixedMovementSet,
 };
 
 pub const SLOT_SIZE: usize = 64;
+/* How far a piercing projectile keeps flying after passing through an attacker */
+pub const PIERCE_TRAVEL_DISTANCE: f32 = 640.;
+/* How far a dodged projectile keeps flying past the attacker before it hits the ground */
+const WHIFF_TRAVEL_DISTANCE: f32 = 32.;
+pub const FORK_RANGE: f32 = 200.;
+pub const FORK_DAMAGE_FACTOR: f32 = 0.6;
+pub const KILLS_PER_LEVEL: usize = 5;
+const DEFAULT_FORK_SPEED: f32 = 300.;
+/* Secondary explosions deal a share of the tower's own damage */
+pub const KILL_EXPLOSION_DAMAGE_FACTOR: f32 = 0.5;
+const CANNON_KILL_EXPLOSION_RADIUS: f32 = 48.;
+/* How fast turrets turn towards their target in radians per second, heavier weapons are slower */
+const ARROW_TURN_RATE: f32 = 2. * PI;
+const BALLISTA_TURN_RATE: f32 = PI / 2.;
+const CANNON_TURN_RATE: f32 = PI;
+/* A turret fires once it points at its target at least this closely, in radians */
+const TURRET_AIM_TOLERANCE: f32 = 0.1;
+/* New towers start this share of their attack period in at most, so towers placed together don't fire in lockstep */
+const MAX_ATTACK_PHASE_OFFSET: f32 = 0.3;
+const LARGE_CANNON_KILL_EXPLOSION_RADIUS: f32 = 96.;
+/* Banners stack, but a tower never gets more than this out of all of them combined */
+pub const MAX_BANNER_BONUS: f32 = 0.5;
+/* Damage zones hurt in pulses this far apart instead of every frame */
+pub const DAMAGE_ZONE_TICK_SECONDS: f32 = 0.5;
+/* Frames of the "towers" atlas going from an empty to a full sweep */
+const COOLDOWN_ANIMATION: &str = "cooldown";
 
 #[derive(Resource)]
 pub struct TowerField {
@@ -29,8 +63,11 @@ pub struct TowerField {
     pub field_transform: Vec2,
     width: usize,
     height: usize,
-    start: Node,
+    /* Every start attackers can enter from, the first one is the main start the defender plans around */
+    starts: Vec<Node>,
     end: Node,
+    /* Goes up with every structure added or cleared, so observers can tell the field changed since they last looked */
+    field_version: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -61,25 +98,50 @@ impl TowerField {
             width,
             height,
             field_transform: field_offset,
-            start,
+            starts: vec![start],
             end,
+            field_version: 0,
         };
     }
 
-    pub fn add_structure(&mut self, entity: Entity, blocking: bool, pos: Vec2) {
-        let y = pos.y as usize / SLOT_SIZE;
-        let x = pos.x as usize / SLOT_SIZE;
-        let i = y * self.width + x;
-        if i < self.slots.len() {
-            self.slots[i] = FieldSlot {
-                entity,
-                blocked: blocking,
-                occupied: true,
-            };
+    pub fn get_field_version(&self) -> u64 {
+        return self.field_version;
+    }
+
+    /* Node of the slot a structure at `pos` is registered in */
+    pub fn node_at(&self, pos: Vec2) -> Node {
+        return Node::new(pos.x as i32 / SLOT_SIZE as i32, pos.y as i32 / SLOT_SIZE as i32);
+    }
+
+    /* `pos` is in the anchor slot, every slot of the footprint gets the structure */
+    pub fn add_structure(&mut self, entity: Entity, blocking: bool, pos: Vec2, footprint: Footprint) {
+        for node in footprint.nodes(self.node_at(pos)) {
+            if node.x < 0 || node.y < 0 || node.x as usize >= self.width {
+                continue;
+            }
+            let i = node.y as usize * self.width + node.x as usize;
+            if i < self.slots.len() {
+                self.slots[i] = FieldSlot {
+                    entity,
+                    blocked: blocking,
+                    occupied: true,
+                };
+            }
         }
+        self.field_version += 1;
+    }
+
+    /* Every slot of the footprint is inside the field and empty */
+    pub fn is_footprint_free(&self, anchor: Node, footprint: Footprint) -> bool {
+        // Slots past the right edge would wrap around into the next row
+        return footprint.nodes(anchor).all(|node| (node.x as usize) < self.width && !self.is_node_occupied(node));
     }
 
+    /* Slots outside the field count as occupied and blocked */
     pub fn is_occupied(&self, x: usize, y: usize) -> bool {
+        if x >= self.width {
+            return true;
+        }
         let i = y * self.width + x;
         if i < self.slots.len() {
             return self.slots[i].occupied;
@@ -89,6 +151,9 @@ impl TowerField {
     }
 
     pub fn is_blocked(&self, x: usize, y: usize) -> bool {
+        if x >= self.width {
+            return true;
+        }
         let i = y * self.width + x;
         if i < self.slots.len() {
             return self.slots[i].blocked;
@@ -120,7 +185,15 @@ impl TowerField {
     }
 
     pub fn get_start(&self) -> Node {
-        return self.start;
+        return self.starts[0];
+    }
+
+    pub fn get_starts(&self) -> &[Node] {
+        return &self.starts;
+    }
+
+    pub fn add_start(&mut self, start: Node) {
+        self.starts.push(start);
     }
 
     pub fn get_end(&self) -> Node {
@@ -128,18 +201,29 @@ impl TowerField {
     }
 
     pub fn get_start_transform(&self) -> Transform {
+        return self.get_start_transform_of(0);
+    }
+
+    /* `start` indexes get_starts(), unknown ones fall back to the main start */
+    pub fn get_start_transform_of(&self, start: usize) -> Transform {
+        return self.get_start_transform_with_offset(start, Vec2::ZERO);
+    }
+
+    pub fn get_start_transform_with_offset(&self, start: usize, offset: Vec2) -> Transform {
+        let start = self.starts.get(start).copied().unwrap_or(self.starts[0]);
         return Transform::from_xyz(
-            (self.start.x as usize * SLOT_SIZE) as f32,
-            (self.start.y as usize * SLOT_SIZE) as f32,
+            (start.x as usize * SLOT_SIZE) as f32 + offset.x,
+            (start.y as usize * SLOT_SIZE) as f32 + offset.y,
             1.,
         );
     }
 
-    pub fn get_start_transform_with_offset(&self, offset: Vec2) -> Transform {
-        return Transform::from_xyz(
-            (self.start.x as usize * SLOT_SIZE) as f32 + offset.x,
-            (self.start.y as usize * SLOT_SIZE) as f32 + offset.y,
-            1.,
+    /* Middle of the slot grid in world space */
+    pub fn get_center(&self) -> Vec2 {
+        let sizef = SLOT_SIZE as f32;
+        return self.field_transform + Vec2::new(
+            (self.width as f32 - 1.) * sizef / 2.,
+            (self.height as f32 - 1.) * sizef / 2.,
         );
     }
 
@@ -152,6 +236,9 @@ impl TowerField {
     }
 
     pub fn get_slot(&self, node: Node) -> Option<FieldSlot> {
+        if node.x < 0 || node.y < 0 || node.x as usize >= self.width {
+            return None;
+        }
         let i = node.y as usize * self.width + node.x as usize;
         if i < self.slots.len() {
             return Some(self.slots[i]);
@@ -160,18 +247,51 @@ impl TowerField {
         }
     }
 
-    pub fn clear_slot(&mut self, node: Node) {
-        let i = node.y as usize * self.width + node.x as usize;
-        if i < self.slots.len() {
-            self.slots[i].occupied = false;
-            self.slots[i].entity = Entity::PLACEHOLDER;
-            self.slots[i].blocked = false;
+    pub fn clear_slot(&mut self, anchor: Node, footprint: Footprint) {
+        for node in footprint.nodes(anchor) {
+            if node.x < 0 || node.y < 0 || node.x as usize >= self.width {
+                continue;
+            }
+            let i = node.y as usize * self.width + node.x as usize;
+            if i < self.slots.len() {
+                self.slots[i].occupied = false;
+                self.slots[i].entity = Entity::PLACEHOLDER;
+                self.slots[i].blocked = false;
+            }
         }
+        self.field_version += 1;
     }
 
     pub fn distance_to_start(&self, node: Node) -> f32 {
         return Vec2::new(node.x as f32, node.y as f32)
-            .distance(Vec2::new(self.start.x as f32, self.end.y as f32));
+            .distance(Vec2::new(self.starts[0].x as f32, self.end.y as f32));
+    }
+}
+
+/* Slots a structure covers, going right and up from the slot it is placed on. Structures without one cover a single slot */
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Footprint {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Footprint {
+    fn default() -> Self {
+        return Footprint::SINGLE;
+    }
+}
+
+impl Footprint {
+    pub const SINGLE: Footprint = Footprint { width: 1, height: 1 };
+
+    pub fn nodes(&self, anchor: Node) -> impl Iterator<Item = Node> {
+        let width = self.width as i32;
+        return (0..(self.width * self.height) as i32).map(move |i| Node::new(anchor.x + i % width, anchor.y + i / width));
+    }
+
+    /* From the center of the anchor slot to the center of the whole footprint */
+    pub fn center_offset(&self) -> Vec2 {
+        return Vec2::new(self.width as f32 - 1., self.height as f32 - 1.) * SLOT_SIZE as f32 / 2.;
     }
 }
 
@@ -181,15 +301,45 @@ pub struct Structure {
     pub blocking: bool,
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
 pub enum DamageType {
     Magic,
     Piercing,
     Crushing,
     Explosive,
+    /* Elemental damage comes with built-in multipliers, see DamageMatrix */
+    Fire,
+    Ice,
+    Lightning,
+    Earth,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 8] = [
+        DamageType::Magic, DamageType::Piercing, DamageType::Crushing, DamageType::Explosive,
+        DamageType::Fire, DamageType::Ice, DamageType::Lightning, DamageType::Earth
+    ];
+    pub const ELEMENTS: [DamageType; 4] = [DamageType::Fire, DamageType::Ice, DamageType::Lightning, DamageType::Earth];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            DamageType::Magic => "Magic",
+            DamageType::Piercing => "Piercing",
+            DamageType::Crushing => "Crushing",
+            DamageType::Explosive => "Explosive",
+            DamageType::Fire => "Fire",
+            DamageType::Ice => "Ice",
+            DamageType::Lightning => "Lightning",
+            DamageType::Earth => "Earth"
+        };
+    }
+
+    pub fn is_elemental(&self) -> bool {
+        return Self::ELEMENTS.contains(self);
+    }
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub enum ProjectileSprite {
     Static {
         name: String,
@@ -200,11 +350,66 @@ pub enum ProjectileSprite {
         name: String,
         animation_name: String,
         animation: AnimationIndices,
+        #[serde(with = "timer_seconds")]
         timer: Timer,
         size: Vec2,
     },
 }
 
+/* Timer has no PartialEq, two sprites are the same if their timers tick the same way */
+impl PartialEq for ProjectileSprite {
+    fn eq(&self, other: &Self) -> bool {
+        return match (self, other) {
+            (
+                ProjectileSprite::Static { name, index, size },
+                ProjectileSprite::Static { name: other_name, index: other_index, size: other_size },
+            ) => name == other_name && index == other_index && size == other_size,
+            (
+                ProjectileSprite::Animated { name, animation_name, animation, timer, size },
+                ProjectileSprite::Animated {
+                    name: other_name,
+                    animation_name: other_animation_name,
+                    animation: other_animation,
+                    timer: other_timer,
+                    size: other_size,
+                },
+            ) => {
+                name == other_name
+                    && animation_name == other_animation_name
+                    && animation == other_animation
+                    && timer.duration() == other_timer.duration()
+                    && timer.mode() == other_timer.mode()
+                    && size == other_size
+            }
+            _ => false,
+        };
+    }
+}
+
+/* Frame timers are written as seconds, the full Timer state is still accepted for older definitions */
+mod timer_seconds {
+    use bevy::time::{Timer, TimerMode};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TimerRepr {
+        Seconds(f32),
+        Full(Timer),
+    }
+
+    pub fn serialize<S: Serializer>(timer: &Timer, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_f32(timer.duration().as_secs_f32());
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timer, D::Error> {
+        return match TimerRepr::deserialize(deserializer)? {
+            TimerRepr::Seconds(seconds) => Ok(Timer::from_seconds(seconds, TimerMode::Repeating)),
+            TimerRepr::Full(timer) => Ok(timer),
+        };
+    }
+}
+
 pub enum Target {
     Entity(Entity),
     Ground(Vec2),
@@ -214,6 +419,19 @@ pub enum ProjectileMotion {
     Fixed(Duration, Vec2),
     Velocity(f32),
     FixedArc(Duration, f32, Vec2),
+    /* Homing with a speed and a maximum turn rate in radians per second */
+    Steered(f32, f32),
+    /* Flies at a fixed point and hits whatever it passes through on the way */
+    Straight(f32),
+}
+
+impl ProjectileMotion {
+    pub fn get_speed(&self) -> Option<f32> {
+        return match self {
+            ProjectileMotion::Velocity(speed) | ProjectileMotion::Steered(speed, _) | ProjectileMotion::Straight(speed) => Some(*speed),
+            ProjectileMotion::Fixed(..) | ProjectileMotion::FixedArc(..) => None,
+        };
+    }
 }
 
 #[derive(Component)]
@@ -228,6 +446,145 @@ pub struct Projectile {
     pub size: Vec2,
     pub dead: bool,
     pub age: Duration,
+    pub piercing: u8,
+    pub hit_entities: Vec<Entity>,
+    pub forks_on_hit: u8,
+    pub mark: Option<MarkEffect>,
+}
+
+/* Damage a projectile is expected to deal to the attacker it was fired at */
+#[derive(Component)]
+pub struct ReservedDamage {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+/* Damage on its way to each attacker, so towers don't waste shots on targets that are already as good as dead.
+   Rebuilt from the projectiles in flight every frame, a projectile that hit or despawned no longer counts */
+#[derive(Default)]
+pub struct IncomingDamage {
+    reserved: HashMap<Entity, f32>,
+}
+
+impl IncomingDamage {
+    pub fn from_projectiles<'a>(projectiles: impl IntoIterator<Item = (&'a ReservedDamage, &'a Projectile)>) -> Self {
+        let mut incoming = Self::default();
+        for (reservation, projectile) in projectiles {
+            if !projectile.dead {
+                incoming.reserve(reservation.target, reservation.amount);
+            }
+        }
+        return incoming;
+    }
+
+    pub fn reserve(&mut self, target: Entity, amount: f32) {
+        *self.reserved.entry(target).or_insert(0.) += amount;
+    }
+
+    pub fn get(&self, target: Entity) -> f32 {
+        return self.reserved.get(&target).copied().unwrap_or(0.);
+    }
+
+    pub fn is_doomed(&self, target: Entity, attacker: &Attacker) -> bool {
+        return self.get(target) >= attacker.get_effective_health();
+    }
+}
+
+/* Hits of the current frame, summed per attacker so a splash round sends one DamageEvent per target instead of one per hit.
+   Hits of another damage type or criticality stay separate, stats and damage texts tell them apart */
+#[derive(Resource, Default)]
+pub struct DamageBatchAccumulator {
+    pub pending: HashMap<(Entity, DamageType, bool), DamageEvent>,
+}
+
+impl DamageBatchAccumulator {
+    pub fn add(&mut self, ev: DamageEvent) {
+        match self.pending.get_mut(&(ev.target, ev.damage_type, ev.critical)) {
+            Some(batch) => {
+                batch.amount += ev.amount;
+                batch.absorbed += ev.absorbed;
+            }
+            None => {
+                self.pending.insert((ev.target, ev.damage_type, ev.critical), ev);
+            }
+        }
+    }
+}
+
+/* Systems adding hits to the DamageBatchAccumulator, they run before the batches of the frame are flushed */
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct DamageProducerSet;
+
+/* Systems reading DamageEvents, they run after the batches of the frame are flushed */
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct DamageConsumerSet;
+
+pub struct DamageBatchPlugin;
+
+impl Plugin for DamageBatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DamageBatchAccumulator>()
+            .configure_set(DamageProducerSet.before(flush_damage_batches))
+            .configure_set(DamageConsumerSet.after(flush_damage_batches))
+            .add_system(flush_damage_batches.in_set(GameplaySet));
+    }
+}
+
+pub fn flush_damage_batches(mut accumulator: ResMut<DamageBatchAccumulator>, mut damage_events: EventWriter<DamageEvent>) {
+    let mut batches: Vec<DamageEvent> = accumulator.pending.drain().map(|(_, batch)| batch).collect();
+    // Map order changes between runs, sorting keeps seeded replays sending the same events
+    batches.sort_by_key(|batch| (batch.target, batch.damage_type as u8, batch.critical));
+    damage_events.send_batch(batches);
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct MarkEffect {
+    pub bonus_mult: f32,
+    pub duration: f32,
+}
+
+/* Slows every attacker within radius for as long as they stay in it */
+#[derive(Component)]
+pub struct SlowAura {
+    pub radius: f32,
+    pub factor: f32,
+}
+
+/* Hurts every attacker within radius for as long as they stay in it */
+#[derive(Component)]
+pub struct DamageZone {
+    pub damage_per_second: f32,
+    pub radius: f32,
+    pub tick: Timer,
+}
+
+impl DamageZone {
+    pub fn new(damage_per_second: f32, radius: f32) -> Self {
+        return Self { damage_per_second, radius, tick: Timer::from_seconds(DAMAGE_ZONE_TICK_SECONDS, bevy::time::TimerMode::Repeating) };
+    }
+}
+
+/* Speeds up and strengthens every defender within radius */
+#[derive(Component)]
+pub struct BannerAura {
+    pub radius: f32,
+    pub attack_speed_bonus: f32,
+    pub damage_bonus: f32,
+}
+
+/* Combined bonus of the banners a defender stands next to, kept up to date when the field changes */
+#[derive(Component, Clone, PartialEq, Debug)]
+pub struct Buffed {
+    pub attack_speed_bonus: f32,
+    pub damage_bonus: f32,
+    pub banners: Vec<Entity>,
+}
+
+/* Debuff on an attacker that multiplies the damage it takes from other towers */
+#[derive(Component)]
+pub struct Marked {
+    pub bonus_mult: f32,
+    pub timer: Timer,
 }
 
 trait SpriteProvider {
@@ -254,6 +611,24 @@ impl ProjectileSprite {
             }
         };
     }
+    pub fn find_missing(&self, textures: &TextureResource) -> Option<String> {
+        return match self {
+            ProjectileSprite::Static { name, .. } if !textures.has_atlas(name) => Some(format!("atlas '{}'", name)),
+            ProjectileSprite::Animated { name, animation_name, .. } if !textures.has_animation(name, animation_name) => {
+                Some(format!("animation '{}' in atlas '{}'", animation_name, name))
+            },
+            _ => None
+        };
+    }
+    /* Frames and frame timer for animated projectiles, None for static ones */
+    fn get_animation(&self, textures: &TextureResource) -> Option<(AnimationIndices, Timer)> {
+        return match self {
+            ProjectileSprite::Static { .. } => None,
+            ProjectileSprite::Animated { name, animation_name, timer, .. } => {
+                Some((*textures.get_animation(name, animation_name).1, timer.clone()))
+            }
+        };
+    }
     fn get_size(&self) -> Vec2 {
         return match self {
             ProjectileSprite::Static { name, index, size } => *size,
@@ -268,13 +643,25 @@ impl ProjectileSprite {
     }
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
 pub enum DefenderAttack {
     Projectile {
         damage_type: DamageType,
         damage: f32,
         projectile_speed: f32,
         sprite: ProjectileSprite,
+        #[serde(default)]
+        piercing: u8,
+        #[serde(default)]
+        forks_on_hit: u8,
+        #[serde(default)]
+        mark: Option<MarkEffect>,
+        /* Non homing projectiles are aimed at where the target will be and fly straight */
+        #[serde(default = "default_homing")]
+        homing: bool,
+        /* Degrees per second a homing projectile can turn, unlimited if not set */
+        #[serde(default)]
+        turn_rate: Option<f32>,
     },
     Splash {
         damage_type: DamageType,
@@ -283,6 +670,44 @@ pub enum DefenderAttack {
         splash_radius: f32,
         sprite: ProjectileSprite,
     },
+    /* Hits instantly and jumps to the closest attacker not hit yet, each hop deals falloff times the previous one */
+    Chain {
+        damage_type: DamageType,
+        damage: f32,
+        falloff: f32,
+        max_targets: u8,
+        arc_range: f32,
+    },
+}
+
+fn default_homing() -> bool {
+    return true;
+}
+
+impl DefenderAttack {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            DefenderAttack::Projectile { .. } => "Projectile",
+            DefenderAttack::Splash { .. } => "Splash",
+            DefenderAttack::Chain { .. } => "Chain",
+        };
+    }
+
+    pub fn get_damage(&self) -> f32 {
+        return match self {
+            DefenderAttack::Projectile { damage, .. } => *damage,
+            DefenderAttack::Splash { damage, .. } => *damage,
+            DefenderAttack::Chain { damage, .. } => *damage,
+        };
+    }
+
+    pub fn get_damage_type(&self) -> DamageType {
+        return match self {
+            DefenderAttack::Projectile { damage_type, .. } => *damage_type,
+            DefenderAttack::Splash { damage_type, .. } => *damage_type,
+            DefenderAttack::Chain { damage_type, .. } => *damage_type,
+        };
+    }
 }
 
 pub enum TargetingStrategy {
@@ -291,39 +716,115 @@ pub enum TargetingStrategy {
     Random,
 }
 
+/* Turns the tower towards its last target, it only fires once it is aimed. Angles are in radians */
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TurretRotation {
+    pub angular_velocity_rad_s: f32,
+    pub current_angle_rad: f32,
+    pub target_angle_rad: f32,
+}
+
+impl TurretRotation {
+    pub fn new(angular_velocity_rad_s: f32) -> Self {
+        return Self { angular_velocity_rad_s, current_angle_rad: 0., target_angle_rad: 0. };
+    }
+
+    /* Shortest signed turn from the current angle to the target angle, in [-PI, PI) */
+    pub fn remaining_turn(&self) -> f32 {
+        return (self.target_angle_rad - self.current_angle_rad + PI).rem_euclid(2. * PI) - PI;
+    }
+
+    pub fn is_aimed(&self) -> bool {
+        return self.remaining_turn().abs() < TURRET_AIM_TOLERANCE;
+    }
+
+    pub fn turn(&mut self, delta_seconds: f32) {
+        let max_step = self.angular_velocity_rad_s * delta_seconds;
+        self.current_angle_rad += self.remaining_turn().clamp(-max_step, max_step);
+    }
+}
+
 #[derive(Component)]
 pub struct Defender {
     pub attack_timer: Timer,
     pub attack: DefenderAttack,
     pub attack_range: f32,
+    /* Dead zone around the tower, attackers hugging it can't be targeted */
+    pub min_range: f32,
+    /* Facing angle and half width of the firing arc in radians, None covers all directions */
+    pub arc: Option<(f32, f32)>,
     pub kill_count: usize,
+    pub kill_efficiency: f32,
     pub pending_attack: bool,
+    pub on_kill_effect: OnKillEffect,
+}
+
+/* Extra effect at the position of every attacker a tower kills */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OnKillEffect {
+    SpawnExplosion { radius: f32 },
+    SlowNearby { factor: f32, radius: f32, duration: f32 },
+    None,
+}
+
+/* Points at the child sprite that sweeps over the tower while its attack recharges */
+#[derive(Component)]
+pub struct CooldownIndicator {
+    pub sweep_entity: Entity,
+}
+
+impl Defender {
+    /* Towers level up from kills, there is no other experience source */
+    pub fn get_level(&self) -> u32 {
+        return 1 + (self.kill_count / KILLS_PER_LEVEL) as u32;
+    }
 }
 
 pub struct TowersPlugin;
 
 impl Plugin for TowersPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(register_structures)
-            .add_system(find_targets)
-            .add_system(update_projectiles)
-            .add_system(process_removal_requests)
-            .add_system(update_projectile_motion)
-            .add_system(spawn_coin_particle_on_death)
-            .add_system(lost_targets);
+        app.add_plugin(DamageBatchPlugin)
+            .init_resource::<BuildingRegistry>()
+            .add_system(register_structures.in_set(GameplaySet))
+            .add_system(update_banner_buffs.in_set(GameplaySet).after(register_structures).after(process_removal_requests).before(find_targets))
+            .add_system(face_nearest_path.before(find_targets).in_set(GameplaySet))
+            .add_system(offset_attack_phases.in_set(GameplaySet).before(find_targets))
+            .add_system(find_targets.in_set(GameplaySet).in_set(DamageProducerSet))
+            .add_system(update_turret_rotation.in_set(GameplaySet).after(find_targets))
+            .add_system(update_projectiles.in_set(GameplaySet).in_set(DamageProducerSet))
+            .add_system(intercept_projectiles.in_set(GameplaySet).before(update_projectiles))
+            .add_system(process_removal_requests.in_set(GameplaySet))
+            .add_system(process_clear_board.in_set(GameplaySet).after(process_removal_requests).before(update_banner_buffs))
+            .add_system(update_projectile_motion.in_set(FixedMovementSet).in_schedule(CoreSchedule::FixedUpdate))
+            .add_system(spawn_death_effects_on_kill.in_set(GameplaySet))
+            .add_system(count_kills.in_set(GameplaySet))
+            .add_system(handle_kill_effects.in_set(GameplaySet).in_set(DamageProducerSet))
+            .add_system(tick_marks.in_set(GameplaySet))
+            .add_system(update_projectile_animations.in_set(GameplaySet))
+            .add_system(apply_slow_auras.in_set(GameplaySet))
+            .add_system(tick_damage_zones.in_set(GameplaySet).in_set(DamageProducerSet))
+            .add_system(spawn_cooldown_indicators.in_set(GameplaySet).after(find_targets))
+            .add_system(update_cooldown_indicators.in_set(GameplaySet).after(spawn_cooldown_indicators))
+            .add_system(lost_targets.in_set(GameplaySet))
+            .add_system(retarget_blinked_projectiles.in_set(GameplaySet));
     }
 }
 
 fn register_structures(
-    query: Query<(Entity, &Structure, &Transform), Added<Structure>>,
+    query: Query<(Entity, &Structure, &Transform, Option<&Footprint>), Added<Structure>>,
     mut field: ResMut<TowerField>,
     mut modified_field: EventWriter<FieldModified>,
 ) {
-    for (e, structure, transform) in &query {
-        field.add_structure(e, structure.blocking, transform.translation.truncate())
+    let mut nodes = Vec::new();
+    for (e, structure, transform, footprint) in &query {
+        let position = transform.translation.truncate();
+        let footprint = footprint.copied().unwrap_or_default();
+        field.add_structure(e, structure.blocking, position, footprint);
+        nodes.extend(footprint.nodes(field.node_at(position)));
     }
-    if !query.is_empty() {
-        modified_field.send(FieldModified);
+    if !nodes.is_empty() {
+        modified_field.send(FieldModified { nodes });
     }
 }
 
@@ -333,155 +834,589 @@ fn process_removal_requests(
     mut modified_field: EventWriter<FieldModified>,
     mut removed: EventWriter<RemovedStructureEvent>,
     mut requests: EventReader<RemoveStructureRequest>,
-    query: Query<(Entity, &Structure)>,
+    query: Query<(Entity, &Structure, &Transform, Option<&Footprint>)>,
 ) {
     for ev in requests.iter() {
         if let Some(slot) = field.get_slot(ev.node) {
-            field.clear_slot(ev.node);
-            if let Ok(entity) = query.get(slot.entity) {
-                removed.send(RemovedStructureEvent {
-                    node: ev.node,
-                    building_type: entity.1.building_type,
-                });
-                commands.entity(entity.0).despawn();
+            let Ok((entity, structure, transform, footprint)) = query.get(slot.entity) else {
+                field.clear_slot(ev.node, Footprint::SINGLE);
+                modified_field.send(FieldModified { nodes: vec![ev.node] });
+                continue;
+            };
+            // Any slot of a large structure removes all of it
+            let anchor = field.node_at(transform.translation.truncate());
+            let footprint = footprint.copied().unwrap_or_default();
+            field.clear_slot(anchor, footprint);
+            removed.send(RemovedStructureEvent {
+                node: anchor,
+                building_type: structure.building_type,
+                destroyed: ev.destroyed
+            });
+            commands.entity(entity).despawn_recursive();
+            modified_field.send(FieldModified { nodes: footprint.nodes(anchor).collect() });
+        }
+    }
+}
+
+/* Empties the whole field in one go, pathing only has to react to a single FieldModified */
+pub(crate) fn process_clear_board(
+    mut commands: Commands,
+    mut field: ResMut<TowerField>,
+    mut modified_field: EventWriter<FieldModified>,
+    mut cleared: EventWriter<BoardClearedEvent>,
+    mut requests: EventReader<ClearBoardRequest>,
+    query: Query<(Entity, &Structure)>,
+) {
+    // Clicking twice in one frame must not refund anything twice
+    let Some(request) = requests.iter().last() else {
+        return;
+    };
+    let mut removed = Vec::new();
+    let mut cleared_nodes = Vec::new();
+    let mut seen = HashSet::new();
+    for y in 0..field.get_height() {
+        for x in 0..field.get_width() {
+            if !field.is_occupied(x, y) {
+                continue;
+            }
+            let node = Node::new(x as i32, y as i32);
+            if let Some(slot) = field.get_slot(node) {
+                if let Ok((_, structure)) = query.get(slot.entity) {
+                    // Large structures are met first at their anchor and are only refunded once
+                    if seen.insert(slot.entity) {
+                        removed.push((node, structure.building_type));
+                    }
+                }
+            }
+            field.clear_slot(node, Footprint::SINGLE);
+            cleared_nodes.push(node);
+        }
+    }
+    // Structures that never made it onto the field go as well
+    for (entity, _) in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if removed.is_empty() {
+        return;
+    }
+    modified_field.send(FieldModified { nodes: cleared_nodes });
+    cleared.send(BoardClearedEvent { removed, refund_fraction: request.refund_fraction.clamp(0., 1.) });
+}
+
+fn update_banner_buffs(
+    mut commands: Commands,
+    mut field_modified: EventReader<FieldModified>,
+    banners: Query<(Entity, &BannerAura, &Transform)>,
+    towers: Query<(Entity, &Transform, Option<&Buffed>), With<Defender>>,
+    field: Res<TowerField>,
+) {
+    if field_modified.is_empty() {
+        return;
+    }
+    field_modified.clear();
+    // A sold banner is off the field right away, but its entity is only despawned at the end of the frame
+    let banners: Vec<(Entity, &BannerAura, Vec2)> = banners
+        .iter()
+        .map(|(entity, aura, transform)| (entity, aura, transform.translation.truncate()))
+        .filter(|(entity, _, position)| field.get_slot(field.node_at(*position)).map(|slot| slot.entity) == Some(*entity))
+        .collect();
+    for (tower, transform, current) in towers.iter() {
+        let buff = combine_banner_buffs(banners.iter().copied(), transform.translation.truncate());
+        match (buff, current) {
+            (Some(buff), Some(current)) if buff == *current => {}
+            (Some(buff), _) => {
+                commands.entity(tower).insert(buff);
+            }
+            (None, Some(_)) => {
+                commands.entity(tower).remove::<Buffed>();
             }
-            modified_field.send(FieldModified);
+            (None, None) => {}
+        }
+    }
+}
+
+/* Sum of the bonuses of every banner reaching `position`, capped at MAX_BANNER_BONUS */
+pub fn combine_banner_buffs<'a>(banners: impl IntoIterator<Item = (Entity, &'a BannerAura, Vec2)>, position: Vec2) -> Option<Buffed> {
+    let mut buff = Buffed { attack_speed_bonus: 0., damage_bonus: 0., banners: Vec::new() };
+    for (entity, aura, banner_position) in banners {
+        if banner_position.distance(position) <= aura.radius {
+            buff.attack_speed_bonus += aura.attack_speed_bonus;
+            buff.damage_bonus += aura.damage_bonus;
+            buff.banners.push(entity);
+        }
+    }
+    if buff.banners.is_empty() {
+        return None;
+    }
+    buff.attack_speed_bonus = buff.attack_speed_bonus.min(MAX_BANNER_BONUS);
+    buff.damage_bonus = buff.damage_bonus.min(MAX_BANNER_BONUS);
+    return Some(buff);
+}
+
+/* New towers with a firing arc face the closest node of the current path */
+fn face_nearest_path(
+    mut towers: Query<(&mut Defender, &Transform), Added<Defender>>,
+    field: Res<TowerField>,
+) {
+    if towers.iter().all(|(defender, _)| defender.arc.is_none()) {
+        return;
+    }
+    let size = SLOT_SIZE as f32;
+    let path_positions: Vec<Vec2> = nodes_on_all_paths(&field).iter()
+        .map(|node| field.field_transform + Vec2::new(node.x as f32, node.y as f32) * size)
+        .collect();
+    for (mut defender, transform) in towers.iter_mut() {
+        let Some((_, half_width)) = defender.arc else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let nearest = path_positions.iter().min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+        if let Some(nearest) = nearest {
+            let offset = *nearest - position;
+            defender.arc = Some((offset.y.atan2(offset.x), half_width));
+        }
+    }
+}
+
+pub(crate) fn update_turret_rotation(mut turrets: Query<(&mut TurretRotation, &mut Transform)>, time: Res<Time>) {
+    for (mut turret, mut transform) in turrets.iter_mut() {
+        if turret.remaining_turn() == 0. {
+            continue;
         }
+        turret.turn(time.delta_seconds());
+        transform.rotation = Quat::from_rotation_z(turret.current_angle_rad);
     }
 }
 
+/* Only the phase is moved, the period stays the same, so towers of one type spread their shots instead of all hitting one target */
+pub(crate) fn offset_attack_phases(mut towers: Query<&mut Defender, Added<Defender>>, mut rng: ResMut<GameRng>) {
+    for mut defender in towers.iter_mut() {
+        let offset = defender.attack_timer.duration().mul_f32(rng.0.gen_range(0. ..MAX_ATTACK_PHASE_OFFSET));
+        defender.attack_timer.set_elapsed(offset);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn find_targets(
     mut commands: Commands,
-    mut towers: Query<(Entity, &mut Defender, &Transform)>,
-    enemies: Query<(Entity, &Attacker, &Transform)>,
+    mut towers: Query<(Entity, &mut Defender, &Transform, Option<&Buffed>, Option<&mut TurretRotation>)>,
+    mut enemies: Query<(Entity, &mut Attacker, &Transform)>,
+    hit_modifiers: Query<(Option<&Marked>, Option<&Grounded>)>,
+    reserved: Query<(&ReservedDamage, &Projectile)>,
+    mut damage_batches: ResMut<DamageBatchAccumulator>,
+    mut kill_events: EventWriter<KillEvent>,
     textures: Res<TextureResource>,
+    particle_settings: Res<ParticleSettings>,
+    matrix: Res<DamageMatrix>,
+    mut rng: ResMut<GameRng>,
     time: Res<Time>,
 ) {
-    for (entity, mut defender, transform) in towers.iter_mut() {
-        defender.attack_timer.tick(time.delta());
+    let mut incoming = IncomingDamage::from_projectiles(reserved.iter());
+    for (entity, mut defender, transform, buff, turret) in towers.iter_mut() {
+        // A faster attack speed is the same as time passing faster for the attack timer
+        let (speed_factor, damage_factor) = buff.map(|buff| (1. + buff.attack_speed_bonus, 1. + buff.damage_bonus)).unwrap_or((1., 1.));
+        defender.attack_timer.tick(time.delta().mul_f32(speed_factor));
         if defender.attack_timer.just_finished() {
             defender.pending_attack = true;
         }
 
         if defender.pending_attack {
-            // TODO: Implement Target strategy
-            let maybe_target = enemies
-                .iter()
-                .filter(|e| {
-                    e.2.translation
-                        .truncate()
-                        .distance(transform.translation.truncate())
-                        <= defender.attack_range
-                })
-                .min_by(|a, b| a.1.health.total_cmp(&b.1.health))
-                .take();
-            if let Some(target) = maybe_target {
-                defender.pending_attack = false;
-                match &defender.attack {
-                    DefenderAttack::Projectile {
-                        damage_type,
-                        damage,
-                        projectile_speed,
-                        sprite,
-                    } => {
-                        let sprite_details = sprite.get_sprite(&textures);
-                        commands.spawn(ProjectileBundle {
-                            projectile: Projectile {
-                                damage: *damage,
-                                target: Target::Entity(target.0),
-                                source: entity,
-                                projectile_motion: ProjectileMotion::Velocity(*projectile_speed),
-                                damage_type: *damage_type,
-                                splash_radius: 0.,
-                                velocity: Vec2::ZERO,
-                                size: sprite.get_size(),
-                                dead: false,
-                                age: Duration::ZERO,
-                            },
-                            sprite: SpriteSheetBundle {
-                                sprite: sprite_details.1,
-                                texture_atlas: sprite_details.0.clone_weak(),
-                                transform: Transform::from_translation(transform.translation),
-                                ..Default::default()
-                            },
-                        });
-                    }
-                    DefenderAttack::Splash {
-                        damage_type,
-                        damage,
-                        travel_time,
-                        splash_radius,
-                        sprite,
-                    } => {
-                        let sprite_details = sprite.get_sprite(&textures);
-                        commands.spawn(ProjectileBundle {
-                            projectile: Projectile {
-                                damage: *damage,
-                                target: Target::Ground(target.2.translation.truncate()),
-                                source: entity,
-                                projectile_motion: ProjectileMotion::FixedArc(
-                                    Duration::from_secs_f32(*travel_time),
-                                    34.,
-                                    transform.translation.truncate()
-                                ),
-                                damage_type: *damage_type,
-                                splash_radius: *splash_radius,
-                                velocity: Vec2::ZERO,
-                                size: sprite.get_size(),
-                                dead: false,
-                                age: Duration::ZERO,
-                            },
-                            sprite: SpriteSheetBundle {
-                                sprite: sprite_details.1,
-                                texture_atlas: sprite_details.0.clone_weak(),
-                                transform: Transform::from_translation(transform.translation),
-                                ..Default::default()
-                            },
-                        });
+            if let Some(target) = select_defender_target(&defender, enemies.iter(), transform.translation.truncate(), &incoming) {
+                if let Some(mut turret) = turret {
+                    let offset = target.2.translation.truncate() - transform.translation.truncate();
+                    turret.target_angle_rad = offset.y.atan2(offset.x);
+                    // The attack stays pending until the turret has turned far enough
+                    if !turret.is_aimed() {
+                        continue;
                     }
                 }
+                defender.pending_attack = false;
+                let expected_damage = if target.1.is_immune_to(defender.attack.get_damage_type()) {
+                    0.
+                } else {
+                    defender.attack.get_damage() * damage_factor * matrix.get_multiplier(defender.attack.get_damage_type(), target.1.armor_class)
+                };
+                let (target, target_transform, target_velocity) = (target.0, *target.2, target.1.velocity);
+                if let DefenderAttack::Chain { .. } = defender.attack {
+                    resolve_chain(&mut commands, entity, &defender.attack, damage_factor, transform.translation.truncate(), target, &mut enemies, &hit_modifiers, &mut rng, &mut damage_batches, &mut kill_events, &textures, &particle_settings, &matrix);
+                } else if let Some(projectile) = spawn_attack(&mut commands, entity, &defender.attack, damage_factor, transform, target, &target_transform, target_velocity, &textures) {
+                    // Towers later in this frame can't see the new projectile yet, so it is reserved right away too
+                    commands.entity(projectile).insert(ReservedDamage { target, amount: expected_damage });
+                    incoming.reserve(target, expected_damage);
+                }
             }
         }
     }
 }
 
-fn update_projectile_motion(
-    mut commands: Commands,
-    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform), Without<Attacker>>,
-    mut enemies: Query<(Entity, &mut Attacker, &Transform), Without<Projectile>>,
-    time: Res<Time>,
+/* Chains have no projectile to carry them, every hop is damaged on the spot */
+#[allow(clippy::too_many_arguments)]
+fn resolve_chain(
+    commands: &mut Commands,
+    tower: Entity,
+    attack: &DefenderAttack,
+    damage_factor: f32,
+    origin: Vec2,
+    first: Entity,
+    enemies: &mut Query<(Entity, &mut Attacker, &Transform)>,
+    hit_modifiers: &Query<(Option<&Marked>, Option<&Grounded>)>,
+    rng: &mut GameRng,
+    damage_batches: &mut DamageBatchAccumulator,
+    kill_events: &mut EventWriter<KillEvent>,
+    textures: &TextureResource,
+    particle_settings: &ParticleSettings,
+    matrix: &DamageMatrix,
 ) {
-    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
-        projectile.age += time.delta();
-        if projectile.age.as_secs_f32() < 20. {
-            let maybe_target_pos: Option<Vec2> = match projectile.target {
-                Target::Entity(entity) => enemies
-                    .get_component::<Transform>(entity)
-                    .ok()
-                    .map(|transform| transform.translation.truncate()),
-                Target::Ground(pos) => Some(pos),
-            };
-            if let Some(target_pos) = maybe_target_pos {
-                match &projectile.projectile_motion {
-                    ProjectileMotion::Velocity(speed) => {
-                        let projectile_pos = transform.translation.truncate();
-                        let direction = (target_pos - projectile_pos).normalize_or_zero();
-                        projectile.velocity = direction * *speed;
-                        transform.translation +=
-                            projectile.velocity.extend(0.) * time.delta_seconds();
-                        let angle = f32::atan2(
-                            target_pos.y - projectile_pos.y,
-                            target_pos.x - projectile_pos.x,
-                        );
-                        transform.rotation = Quat::from_rotation_z(angle - PI / 4.);
-                    }
-                    ProjectileMotion::Fixed(duration, start_pos) => {
-                        let projectile_pos = transform.translation.truncate();
-                        let factor =
-                            (projectile.age.as_secs_f32() / duration.as_secs_f32()).clamp(0., 1.);
-                        transform.translation = start_pos.lerp(target_pos, factor).extend(transform.translation.z);
-                        let angle = f32::atan2(
-                            target_pos.y - projectile_pos.y,
+    let DefenderAttack::Chain { damage, falloff, max_targets, arc_range, .. } = attack else {
+        return;
+    };
+    let candidates: Vec<(Entity, Vec2)> = enemies
+        .iter()
+        .filter(|e| e.1.health > 0.)
+        .map(|e| (e.0, e.2.translation.truncate()))
+        .collect();
+    let mut from = origin;
+    let mut hop_damage = *damage * damage_factor;
+    for (target, position) in chain_targets(first, &candidates, *arc_range, *max_targets) {
+        spawn_lightning(commands, from, position, particle_settings);
+        if let Ok((_, mut attacker, target_transform)) = enemies.get_mut(target) {
+            if attacker.is_immune_to(attack.get_damage_type()) {
+                // The arc still jumps on from an immune attacker
+                spawn_floating_text(commands, target_transform, "IMMUNE", Color::GRAY);
+            } else {
+                let (marked, grounded) = hit_modifiers.get(target).unwrap_or((None, None));
+                let (damage, critical) = roll_hit_damage(hop_damage, attack.get_damage_type(), false, &attacker, marked, grounded.is_some(), matrix, rng);
+                let absorbed = attacker.take_damage(damage);
+                damage_batches.add(DamageEvent {
+                    amount: damage,
+                    absorbed,
+                    target,
+                    damage_type: attack.get_damage_type(),
+                    critical,
+                });
+                // A killing hit leaves the larger death splatter instead
+                if attacker.health > 0. {
+                    spawn_blood_splatter(commands, target_transform, textures, particle_settings);
+                } else {
+                    kill_events.send(KillEvent {
+                        target,
+                        source: tower,
+                        tower,
+                        bounty: attacker.bounty,
+                        original_cost: attacker.original_cost,
+                        group_size: attacker.num_summoned,
+                        death_position: position,
+                        size: attacker.size,
+                    });
+                    kill_attacker(commands, target);
+                }
+            }
+        }
+        from = position;
+        hop_damage *= falloff;
+    }
+}
+
+/* Starting at `first`, keeps jumping to the closest candidate within arc_range that wasn't hit yet */
+pub fn chain_targets(first: Entity, candidates: &[(Entity, Vec2)], arc_range: f32, max_targets: u8) -> Vec<(Entity, Vec2)> {
+    let Some(mut current) = candidates.iter().find(|candidate| candidate.0 == first).copied() else {
+        return Vec::new();
+    };
+    let mut chain = vec![current];
+    while chain.len() < max_targets as usize {
+        let next = candidates
+            .iter()
+            .filter(|candidate| !chain.iter().any(|hit| hit.0 == candidate.0))
+            .map(|candidate| (*candidate, candidate.1.distance(current.1)))
+            .filter(|candidate| candidate.1 <= arc_range)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((next, _)) = next else {
+            break;
+        };
+        chain.push(next);
+        current = next;
+    }
+    return chain;
+}
+
+/* The weakest attacker between min_range and range, kept apart from the system so it can be measured without a World */
+pub fn select_target<'a>(
+    enemies: impl IntoIterator<Item = (Entity, &'a Attacker, &'a Transform)>,
+    position: Vec2,
+    min_range: f32,
+    range: f32,
+) -> Option<(Entity, &'a Attacker, &'a Transform)> {
+    // TODO: Implement Target strategy
+    return enemies
+        .into_iter()
+        .filter(|e| {
+            let distance = e.2.translation.truncate().distance(position);
+            distance >= min_range && distance <= range
+        })
+        .min_by(|a, b| a.1.get_effective_health().total_cmp(&b.1.get_effective_health()));
+}
+
+/* Brute force pass over plain slices: the target of every tower given as (position, min_range, range), before any shot is reserved */
+pub fn select_targets(towers: &[(Vec2, f32, f32)], enemies: &[(Entity, Attacker, Transform)]) -> Vec<Option<Entity>> {
+    return towers
+        .iter()
+        .map(|(position, min_range, range)| {
+            let candidates = enemies.iter().map(|(entity, attacker, transform)| (*entity, attacker, transform));
+            select_target(candidates, *position, *min_range, *range).map(|target| target.0)
+        })
+        .collect();
+}
+
+/* Whether `target` lies within the firing arc of a tower at `position` */
+pub fn is_in_arc(arc: Option<(f32, f32)>, position: Vec2, target: Vec2) -> bool {
+    let Some((facing, half_width)) = arc else {
+        return true;
+    };
+    let offset = target - position;
+    if offset == Vec2::ZERO {
+        return true;
+    }
+    return Vec2::from_angle(facing).angle_between(offset).abs() <= half_width;
+}
+
+/* Target for a defender standing at `position`, respecting its ranges and firing arc.
+   Attackers the damage in flight will kill anyway are left alone */
+pub fn select_defender_target<'a>(
+    defender: &Defender,
+    enemies: impl IntoIterator<Item = (Entity, &'a Attacker, &'a Transform)>,
+    position: Vec2,
+    incoming: &IncomingDamage,
+) -> Option<(Entity, &'a Attacker, &'a Transform)> {
+    let in_arc = enemies
+        .into_iter()
+        .filter(|e| !incoming.is_doomed(e.0, e.1) && is_in_arc(defender.arc, position, e.2.translation.truncate()));
+    return select_target(in_arc, position, defender.min_range, defender.attack_range);
+}
+
+/* Nodes of `path` at least one of the towers can fire at, with dead zones and firing arcs taken into account */
+pub fn covered_nodes<'a>(
+    path: &[Node],
+    field: &TowerField,
+    towers: impl IntoIterator<Item = (&'a Defender, &'a Transform)>,
+) -> Vec<Node> {
+    let towers: Vec<(&Defender, Vec2)> = towers.into_iter().map(|(defender, transform)| (defender, transform.translation.truncate())).collect();
+    let size = SLOT_SIZE as f32;
+    return path
+        .iter()
+        .copied()
+        .filter(|node| {
+            let position = field.field_transform + Vec2::new(node.x as f32, node.y as f32) * size;
+            towers.iter().any(|(defender, tower)| {
+                let distance = position.distance(*tower);
+                distance >= defender.min_range && distance <= defender.attack_range && is_in_arc(defender.arc, *tower, position)
+            })
+        })
+        .collect();
+}
+
+/* Where a projectile fired now at `speed` meets a target keeping its current velocity, None if it can never catch up */
+pub fn intercept_point(shooter: Vec2, target: Vec2, target_velocity: Vec2, speed: f32) -> Option<Vec2> {
+    // |offset + velocity * t| = speed * t, solved for the earliest positive t
+    let offset = target - shooter;
+    let a = target_velocity.length_squared() - speed * speed;
+    let b = 2. * offset.dot(target_velocity);
+    let c = offset.length_squared();
+    let time = if a.abs() < 1e-6 {
+        // Target as fast as the projectile, only catchable when it is coming closer
+        if b >= 0. {
+            return None;
+        }
+        -c / b
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let first = (-b - root) / (2. * a);
+        let second = (-b + root) / (2. * a);
+        match (first > 0., second > 0.) {
+            (true, true) => first.min(second),
+            (true, false) => first,
+            (false, true) => second,
+            (false, false) => return None,
+        }
+    };
+    return Some(target + target_velocity * time);
+}
+
+/* Rotates `current` towards `desired` by at most `max_angle` radians, both are expected to be normalized */
+fn turn_towards(current: Vec2, desired: Vec2, max_angle: f32) -> Vec2 {
+    if current == Vec2::ZERO {
+        return desired;
+    }
+    let angle = current.angle_between(desired);
+    if angle.abs() <= max_angle {
+        return desired;
+    }
+    return Vec2::from_angle(max_angle * angle.signum()).rotate(current);
+}
+
+/* Fires a defender's attack at a target, damage_factor scales the damage of this one shot.
+   Returns the projectile that was fired, chains have none */
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_attack(
+    commands: &mut Commands,
+    source: Entity,
+    attack: &DefenderAttack,
+    damage_factor: f32,
+    transform: &Transform,
+    target: Entity,
+    target_transform: &Transform,
+    target_velocity: Vec2,
+    textures: &TextureResource,
+) -> Option<Entity> {
+    match attack {
+        DefenderAttack::Projectile {
+            damage_type,
+            damage,
+            projectile_speed,
+            sprite,
+            piercing,
+            forks_on_hit,
+            mark,
+            homing,
+            turn_rate,
+        } => {
+            let (projectile_target, projectile_motion) = match (homing, turn_rate) {
+                (true, None) => (Target::Entity(target), ProjectileMotion::Velocity(*projectile_speed)),
+                (true, Some(turn_rate)) => (Target::Entity(target), ProjectileMotion::Steered(*projectile_speed, turn_rate.to_radians())),
+                (false, _) => {
+                    let target_position = target_transform.translation.truncate();
+                    let aim = intercept_point(transform.translation.truncate(), target_position, target_velocity, *projectile_speed)
+                        .unwrap_or(target_position);
+                    (Target::Ground(aim), ProjectileMotion::Straight(*projectile_speed))
+                }
+            };
+            let sprite_details = sprite.get_sprite(textures);
+            let mut projectile = commands.spawn(ProjectileBundle {
+                projectile: Projectile {
+                    damage: *damage * damage_factor,
+                    target: projectile_target,
+                    source,
+                    projectile_motion,
+                    damage_type: *damage_type,
+                    splash_radius: 0.,
+                    velocity: Vec2::ZERO,
+                    size: sprite.get_size(),
+                    dead: false,
+                    age: Duration::ZERO,
+                    piercing: *piercing,
+                    hit_entities: Vec::new(),
+                    forks_on_hit: *forks_on_hit,
+                    mark: *mark,
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: sprite_details.1,
+                    texture_atlas: sprite_details.0.clone_weak(),
+                    transform: Transform::from_translation(transform.translation),
+                    ..Default::default()
+                },
+            });
+            if let Some((animation, timer)) = sprite.get_animation(textures) {
+                projectile.insert((animation, AnimationTimer(timer), AnimationPlayback::default()));
+            }
+            return Some(projectile.id());
+        }
+        DefenderAttack::Splash {
+            damage_type,
+            damage,
+            travel_time,
+            splash_radius,
+            sprite,
+        } => {
+            let sprite_details = sprite.get_sprite(textures);
+            let mut projectile = commands.spawn(ProjectileBundle {
+                projectile: Projectile {
+                    damage: *damage * damage_factor,
+                    target: Target::Ground(target_transform.translation.truncate()),
+                    source,
+                    projectile_motion: ProjectileMotion::FixedArc(
+                        Duration::from_secs_f32(*travel_time),
+                        34.,
+                        transform.translation.truncate()
+                    ),
+                    damage_type: *damage_type,
+                    splash_radius: *splash_radius,
+                    velocity: Vec2::ZERO,
+                    size: sprite.get_size(),
+                    dead: false,
+                    age: Duration::ZERO,
+                    piercing: 0,
+                    hit_entities: Vec::new(),
+                    forks_on_hit: 0,
+                    mark: None,
+                },
+                sprite: SpriteSheetBundle {
+                    sprite: sprite_details.1,
+                    texture_atlas: sprite_details.0.clone_weak(),
+                    transform: Transform::from_translation(transform.translation),
+                    ..Default::default()
+                },
+            });
+            if let Some((animation, timer)) = sprite.get_animation(textures) {
+                projectile.insert((animation, AnimationTimer(timer), AnimationPlayback::default()));
+            }
+            return Some(projectile.id());
+        }
+        // Nothing travels, find_targets resolves chains on the spot
+        DefenderAttack::Chain { .. } => None
+    }
+}
+
+/* Runs in the fixed step, see FixedMovementSet */
+fn update_projectile_motion(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform), Without<Attacker>>,
+    mut enemies: Query<(Entity, &mut Attacker, &Transform), Without<Projectile>>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        projectile.age += fixed_time.period;
+        if projectile.age.as_secs_f32() < 20. {
+            let maybe_target_pos: Option<Vec2> = match projectile.target {
+                Target::Entity(entity) => enemies
+                    .get_component::<Transform>(entity)
+                    .ok()
+                    .map(|transform| transform.translation.truncate()),
+                Target::Ground(pos) => Some(pos),
+            };
+            if let Some(target_pos) = maybe_target_pos {
+                match &projectile.projectile_motion {
+                    ProjectileMotion::Velocity(speed) | ProjectileMotion::Straight(speed) => {
+                        let projectile_pos = transform.translation.truncate();
+                        let direction = (target_pos - projectile_pos).normalize_or_zero();
+                        projectile.velocity = direction * *speed;
+                        transform.translation +=
+                            projectile.velocity.extend(0.) * delta_seconds;
+                        let angle = f32::atan2(
+                            target_pos.y - projectile_pos.y,
+                            target_pos.x - projectile_pos.x,
+                        );
+                        transform.rotation = Quat::from_rotation_z(angle - PI / 4.);
+                    }
+                    ProjectileMotion::Steered(speed, turn_rate) => {
+                        let projectile_pos = transform.translation.truncate();
+                        let desired = (target_pos - projectile_pos).normalize_or_zero();
+                        let direction = turn_towards(projectile.velocity.normalize_or_zero(), desired, *turn_rate * delta_seconds);
+                        projectile.velocity = direction * *speed;
+                        transform.translation +=
+                            projectile.velocity.extend(0.) * delta_seconds;
+                        transform.rotation = Quat::from_rotation_z(direction.y.atan2(direction.x) - PI / 4.);
+                    }
+                    ProjectileMotion::Fixed(duration, start_pos) => {
+                        let projectile_pos = transform.translation.truncate();
+                        let factor =
+                            (projectile.age.as_secs_f32() / duration.as_secs_f32()).clamp(0., 1.);
+                        transform.translation = start_pos.lerp(target_pos, factor).extend(transform.translation.z);
+                        let angle = f32::atan2(
+                            target_pos.y - projectile_pos.y,
                             target_pos.x - projectile_pos.x,
                         );
                         transform.rotation = Quat::from_rotation_z(angle - PI / 4.);
@@ -507,6 +1442,18 @@ fn update_projectile_motion(
     }
 }
 
+/* Homing projectiles simply follow a blinking attacker, the ones flying a fixed course land where it was */
+fn retarget_blinked_projectiles(mut blinks: EventReader<BlinkEvent>, mut projectiles: Query<&mut Projectile>) {
+    for ev in blinks.iter() {
+        for mut projectile in projectiles.iter_mut() {
+            let follows = matches!(projectile.projectile_motion, ProjectileMotion::Velocity(_) | ProjectileMotion::Steered(..));
+            if !follows && matches!(projectile.target, Target::Entity(target) if target == ev.entity) {
+                projectile.target = Target::Ground(ev.from);
+            }
+        }
+    }
+}
+
 fn lost_targets(
     mut commands: Commands,
     mut projectiles: Query<(Entity, &mut Projectile), Without<Attacker>>,
@@ -526,93 +1473,182 @@ fn lost_targets(
     }
 }
 
+/* A shot down projectile is gone before update_projectiles gets to resolve its hit */
+pub(crate) fn intercept_projectiles(
+    mut commands: Commands,
+    mut interceptors: Query<(&mut Interceptor, &Transform), With<Attacker>>,
+    mut projectiles: Query<(Entity, &mut Projectile, &Transform)>,
+    textures: Res<TextureResource>,
+    particle_settings: Res<ParticleSettings>,
+    time: Res<Time>,
+) {
+    for (mut interceptor, transform) in interceptors.iter_mut() {
+        interceptor.cooldown.tick(time.delta());
+        if !interceptor.cooldown.finished() {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let nearest = projectiles
+            .iter_mut()
+            .filter(|(_, projectile, projectile_transform)| {
+                !projectile.dead && projectile_transform.translation.truncate().distance(position) <= interceptor.range
+            })
+            .min_by(|a, b| {
+                a.2.translation.truncate().distance(position).total_cmp(&b.2.translation.truncate().distance(position))
+            });
+        // Nothing in range keeps the interceptor ready
+        let Some((entity, mut projectile, projectile_transform)) = nearest else {
+            continue;
+        };
+        projectile.dead = true;
+        spawn_dust(&mut commands, projectile_transform, &textures, &particle_settings);
+        commands.entity(entity).despawn();
+        interceptor.cooldown.reset();
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn update_projectiles(
     mut commands: Commands,
-    mut enemies: Query<(Entity, &mut Attacker, &Transform), Without<Projectile>>,
-    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform), Without<Attacker>>,
-    mut damage_events: EventWriter<DamageEvent>,
+    mut enemies: Query<(Entity, &mut Attacker, &Transform, Option<&Marked>, Option<&Grounded>), Without<Projectile>>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform, &Handle<TextureAtlas>, &TextureAtlasSprite), Without<Attacker>>,
+    mut damage_batches: ResMut<DamageBatchAccumulator>,
     mut kill_events: EventWriter<KillEvent>,
+    mut splash_impacts: EventWriter<SplashImpactEvent>,
+    mut rng: ResMut<GameRng>,
     textures: Res<TextureResource>,
+    particle_settings: Res<ParticleSettings>,
+    matrix: Res<DamageMatrix>,
     time: Res<Time>,
 ) {
-    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+    for (entity, mut projectile, transform, atlas, sprite) in projectiles.iter_mut() {
         if projectile.dead {
             continue;
         }
         match projectile.target {
-            Target::Entity(target_entity) => match enemies.get_mut(target_entity) {
-                Ok(mut target) => {
-                    let target_rect = Rect::new(
-                        target.2.translation.x,
-                        target.2.translation.y,
-                        target.2.translation.x + target.1.size.x,
-                        target.2.translation.y + target.1.size.y,
-                    );
-                    let projectile_rect = Rect::new(
-                        transform.translation.x,
-                        transform.translation.y,
-                        transform.translation.x + projectile.size.x,
-                        transform.translation.y + projectile.size.y,
-                    );
-                    if !target_rect.intersect(projectile_rect).is_empty() {
-                        let damage = calculate_damage(&projectile, &target.1);
-                        target.1.health -= damage;
-                        damage_events.send(DamageEvent {
-                            amount: damage,
-                            target: target.0,
-                        });
-                        spawn_blood_splatter(&mut commands, &target.2.clone(), &textures);
-                        if target.1.health <= 0. {
-                            kill_events.send(KillEvent {
-                                target: target.0,
-                                source: entity,
-                                bounty: target.1.bounty,
-                                original_cost: target.1.original_cost,
-                                group_size: target.1.num_summoned,
-                                death_position: target.2.translation.truncate(),
-                            });
-                            commands.entity(target.0).despawn();
+            Target::Entity(target_entity) => {
+                let mut forked_from: Option<Entity> = None;
+                match enemies.get_mut(target_entity) {
+                    Ok(mut target) => {
+                        let target_rect = Rect::new(
+                            target.2.translation.x,
+                            target.2.translation.y,
+                            target.2.translation.x + target.1.size.x,
+                            target.2.translation.y + target.1.size.y,
+                        );
+                        let projectile_rect = Rect::new(
+                            transform.translation.x,
+                            transform.translation.y,
+                            transform.translation.x + projectile.size.x,
+                            transform.translation.y + projectile.size.y,
+                        );
+                        if !target_rect.intersect(projectile_rect).is_empty() && !projectile.hit_entities.contains(&target.0) {
+                            if roll_dodge(&target.1, &mut rng) {
+                                spawn_floating_text(&mut commands, target.2, "MISS", Color::GRAY);
+                                whiff(&mut projectile, target.0, transform.translation.truncate());
+                                continue;
+                            }
+                            hit_attacker(&mut commands, &projectile, entity, target.0, &mut target.1, target.2, target.3, target.4.is_some(), &mut rng, &mut damage_batches, &mut kill_events, &textures, &particle_settings, &matrix);
+                            if projectile.forks_on_hit > 0 {
+                                forked_from = Some(target.0);
+                            }
+                            if projectile.piercing > 0 {
+                                pierce(&mut projectile, target.0, transform.translation.truncate());
+                            } else {
+                                projectile.dead = true;
+                                commands.entity(entity).despawn();
+                            }
                         }
-                        projectile.dead = true;
-                        commands.entity(entity).despawn();
                     }
+                    Err(_) => {}
                 }
-                Err(_) => {}
-            },
+                if let Some(hit) = forked_from {
+                    spawn_forks(&mut commands, &projectile, transform.translation, hit, &enemies, atlas, sprite);
+                }
+            }
             Target::Ground(pos) => {
                 let projectile_pos = transform.translation.truncate();
+                let straight = matches!(projectile.projectile_motion, ProjectileMotion::Straight(_));
+                if straight || projectile.piercing > 0 || !projectile.hit_entities.is_empty() {
+                    // A piercing or straight flying projectile keeps hitting whatever it passes through on its way
+                    let projectile_rect = Rect::new(
+                        projectile_pos.x,
+                        projectile_pos.y,
+                        projectile_pos.x + projectile.size.x,
+                        projectile_pos.y + projectile.size.y,
+                    );
+                    for (enemy_entity, mut attacker, enemy_transform, marked, grounded) in enemies.iter_mut() {
+                        if attacker.health <= 0. || projectile.hit_entities.contains(&enemy_entity) {
+                            continue;
+                        }
+                        let enemy_rect = Rect::new(
+                            enemy_transform.translation.x,
+                            enemy_transform.translation.y,
+                            enemy_transform.translation.x + attacker.size.x,
+                            enemy_transform.translation.y + attacker.size.y,
+                        );
+                        if !enemy_rect.intersect(projectile_rect).is_empty() {
+                            if roll_dodge(&attacker, &mut rng) {
+                                // Already flying to the ground, it just keeps going
+                                spawn_floating_text(&mut commands, enemy_transform, "MISS", Color::GRAY);
+                                projectile.hit_entities.push(enemy_entity);
+                                continue;
+                            }
+                            hit_attacker(&mut commands, &projectile, entity, enemy_entity, &mut attacker, enemy_transform, marked, grounded.is_some(), &mut rng, &mut damage_batches, &mut kill_events, &textures, &particle_settings, &matrix);
+                            if projectile.piercing > 0 {
+                                pierce(&mut projectile, enemy_entity, projectile_pos);
+                            } else {
+                                projectile.dead = true;
+                                commands.entity(entity).despawn();
+                                break;
+                            }
+                        }
+                    }
+                    if projectile.dead {
+                        continue;
+                    }
+                }
                 if projectile_pos.distance(pos) < 4. {
                     if projectile.splash_radius > 0. {
+                        splash_impacts.send(SplashImpactEvent { position: pos, radius: projectile.splash_radius });
                         let enemies_to_damage: Vec<(
                             Entity,
                             bevy::prelude::Mut<'_, Attacker>,
                             &Transform,
+                            Option<&Marked>,
+                            Option<&Grounded>,
                         )> = enemies
                             .iter_mut()
                             .filter(|e| {
-                                e.2.translation.truncate().distance(pos) <= projectile.splash_radius
+                                e.2.translation.truncate().distance(pos) <= projectile.splash_radius && !e.1.is_immune_to(projectile.damage_type)
                             })
                             .collect();
                         for mut target in enemies_to_damage {
-                            let damage = calculate_damage(&projectile, &target.1);
-                            target.1.health -= damage;
-                            damage_events.send(DamageEvent {
+                            let damage = calculate_damage(&projectile, &target.1, target.3, &matrix);
+                            let (damage, critical) = roll_critical(damage, &target.1, target.4.is_some(), &mut rng);
+                            let absorbed = target.1.take_damage(damage);
+                            damage_batches.add(DamageEvent {
                                 amount: damage,
+                                absorbed,
                                 target: target.0,
+                                damage_type: projectile.damage_type,
+                                critical,
                             });
                             if target.1.health <= 0. {
                                 kill_events.send(KillEvent {
                                     target: target.0,
                                     source: entity,
+                                    tower: projectile.source,
                                     bounty: target.1.bounty,
                                     original_cost: target.1.original_cost,
                                     group_size: target.1.num_summoned,
                                     death_position: target.2.translation.truncate(),
+                                    size: target.1.size,
                                 });
-                                commands.entity(target.0).despawn();
+                                kill_attacker(&mut commands, target.0);
                             }
                         }
-                        spawn_large_explosion(&mut commands, &Transform::from_translation(pos.extend(transform.translation.z)), &textures);
+                        spawn_large_explosion(&mut commands, &Transform::from_translation(pos.extend(transform.translation.z)), &textures, &particle_settings);
                     }
                     projectile.dead = true;
                     commands.entity(entity).despawn();
@@ -622,17 +1658,388 @@ fn update_projectiles(
     }
 }
 
-fn calculate_damage(projectile: &Projectile, attacker: &Attacker) -> f32 {
-    return projectile.damage;
+#[allow(clippy::too_many_arguments)]
+fn hit_attacker(
+    commands: &mut Commands,
+    projectile: &Projectile,
+    source: Entity,
+    target: Entity,
+    attacker: &mut Attacker,
+    target_transform: &Transform,
+    marked: Option<&Marked>,
+    grounded: bool,
+    rng: &mut GameRng,
+    damage_batches: &mut DamageBatchAccumulator,
+    kill_events: &mut EventWriter<KillEvent>,
+    textures: &TextureResource,
+    particle_settings: &ParticleSettings,
+    matrix: &DamageMatrix,
+) {
+    // The projectile is still used up, it just does nothing
+    if attacker.is_immune_to(projectile.damage_type) {
+        spawn_floating_text(commands, target_transform, "IMMUNE", Color::GRAY);
+        return;
+    }
+    let (damage, critical) = roll_hit_damage(projectile.damage, projectile.damage_type, projectile.mark.is_some(), attacker, marked, grounded, matrix, rng);
+    let absorbed = attacker.take_damage(damage);
+    damage_batches.add(DamageEvent {
+        amount: damage,
+        absorbed,
+        target,
+        damage_type: projectile.damage_type,
+        critical,
+    });
+    // A killing hit leaves the larger death splatter instead
+    if attacker.health > 0. {
+        spawn_blood_splatter(commands, target_transform, textures, particle_settings);
+        if let Some(mark) = projectile.mark {
+            // Inserting again replaces the old mark, so repeated hits refresh instead of stacking
+            commands.entity(target).insert(Marked {
+                bonus_mult: mark.bonus_mult,
+                timer: Timer::from_seconds(mark.duration, bevy::time::TimerMode::Once),
+            });
+        }
+    } else {
+        kill_events.send(KillEvent {
+            target,
+            source,
+            tower: projectile.source,
+            bounty: attacker.bounty,
+            original_cost: attacker.original_cost,
+            group_size: attacker.num_summoned,
+            death_position: target_transform.translation.truncate(),
+            size: attacker.size,
+        });
+        kill_attacker(commands, target);
+    }
+}
+
+/* Splits a projectile into children aimed at the attackers closest to where it hit */
+#[allow(clippy::type_complexity)]
+fn spawn_forks(
+    commands: &mut Commands,
+    parent: &Projectile,
+    position: Vec3,
+    hit: Entity,
+    enemies: &Query<(Entity, &mut Attacker, &Transform, Option<&Marked>, Option<&Grounded>), Without<Projectile>>,
+    atlas: &Handle<TextureAtlas>,
+    sprite: &TextureAtlasSprite,
+) {
+    let origin = position.truncate();
+    let mut candidates: Vec<(Entity, f32)> = enemies
+        .iter()
+        .filter(|e| e.0 != hit && e.1.health > 0.)
+        .map(|e| (e.0, e.2.translation.truncate().distance(origin)))
+        .filter(|e| e.1 <= FORK_RANGE)
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let speed = parent.projectile_motion.get_speed().unwrap_or(DEFAULT_FORK_SPEED);
+    for (target, _) in candidates.iter().take(parent.forks_on_hit as usize) {
+        commands.spawn(ProjectileBundle {
+            projectile: Projectile {
+                damage: parent.damage * FORK_DAMAGE_FACTOR,
+                target: Target::Entity(*target),
+                source: parent.source,
+                projectile_motion: ProjectileMotion::Velocity(speed),
+                damage_type: parent.damage_type,
+                splash_radius: parent.splash_radius,
+                velocity: Vec2::ZERO,
+                size: parent.size,
+                dead: false,
+                age: Duration::ZERO,
+                piercing: 0,
+                hit_entities: Vec::new(),
+                forks_on_hit: 0,
+                mark: None,
+            },
+            sprite: SpriteSheetBundle {
+                sprite: sprite.clone(),
+                texture_atlas: atlas.clone_weak(),
+                transform: Transform::from_translation(position),
+                ..Default::default()
+            },
+        });
+    }
+}
+
+/* A dodged projectile flies on a bit past the attacker and lands there, it can't come back for the same one */
+fn whiff(projectile: &mut Projectile, dodged_by: Entity, position: Vec2) {
+    projectile.hit_entities.push(dodged_by);
+    let direction = projectile.velocity.normalize_or_zero();
+    projectile.target = Target::Ground(position + direction * WHIFF_TRAVEL_DISTANCE);
+}
+
+/* Uses up one pierce and keeps the projectile flying in a straight line past the attacker it hit */
+fn pierce(projectile: &mut Projectile, hit: Entity, position: Vec2) {
+    projectile.piercing -= 1;
+    projectile.hit_entities.push(hit);
+    let direction = projectile.velocity.normalize_or_zero();
+    projectile.target = Target::Ground(position + direction * PIERCE_TRAVEL_DISTANCE);
+}
+
+pub(crate) fn calculate_damage(projectile: &Projectile, attacker: &Attacker, marked: Option<&Marked>, matrix: &DamageMatrix) -> f32 {
+    return modified_damage(projectile.damage, projectile.damage_type, projectile.mark.is_some(), attacker, marked, matrix);
+}
+
+/* Armor, immunity and mark applied to a hit of `damage`, `marks` is set for the marker's own hits */
+fn modified_damage(damage: f32, damage_type: DamageType, marks: bool, attacker: &Attacker, marked: Option<&Marked>, matrix: &DamageMatrix) -> f32 {
+    if attacker.is_immune_to(damage_type) {
+        return 0.;
+    }
+    let damage = damage * matrix.get_multiplier(damage_type, attacker.armor_class);
+    // The marker's own shots don't benefit from the mark
+    if let (Some(marked), false) = (marked, marks) {
+        return damage * marked.bonus_mult;
+    }
+    return damage;
+}
+
+/* Projectile hits and chain hops both go through here, so marks and critical hits apply to either */
+#[allow(clippy::too_many_arguments)]
+fn roll_hit_damage(damage: f32, damage_type: DamageType, marks: bool, attacker: &Attacker, marked: Option<&Marked>, grounded: bool, matrix: &DamageMatrix, rng: &mut GameRng) -> (f32, bool) {
+    let damage = modified_damage(damage, damage_type, marks, attacker, marked, matrix);
+    return roll_critical(damage, attacker, grounded, rng);
+}
+
+/* Direct hits only, splash damage can't be dodged */
+fn roll_dodge(attacker: &Attacker, rng: &mut GameRng) -> bool {
+    return attacker.dodge_chance > 0. && rng.0.gen::<f32>() < attacker.dodge_chance;
+}
+
+/* The damage after the roll and whether it was a critical hit */
+fn roll_critical(damage: f32, attacker: &Attacker, grounded: bool, rng: &mut GameRng) -> (f32, bool) {
+    if grounded && rng.0.gen::<f32>() < attacker.crit_chance {
+        return (damage * attacker.crit_multiplier, true);
+    }
+    return (damage, false);
+}
+
+pub(crate) fn apply_slow_auras(
+    auras: Query<(&SlowAura, &Transform)>,
+    attackers: Query<(Entity, &Transform), With<Attacker>>,
+    mut slow_events: EventWriter<SlowEvent>,
+) {
+    for (aura, aura_transform) in auras.iter() {
+        let aura_pos = aura_transform.translation.truncate();
+        for (entity, transform) in attackers.iter() {
+            if transform.translation.truncate().distance(aura_pos) <= aura.radius {
+                // Short lived and resent every frame, so the slow wears off shortly after leaving the aura
+                slow_events.send(SlowEvent {
+                    target: entity,
+                    factor: aura.factor,
+                    duration_secs: 0.5,
+                });
+            }
+        }
+    }
+}
+
+pub(crate) fn tick_damage_zones(
+    mut commands: Commands,
+    mut zones: Query<(Entity, &mut DamageZone, &Transform)>,
+    mut attackers: Query<(Entity, &mut Attacker, &Transform)>,
+    mut damage_batches: ResMut<DamageBatchAccumulator>,
+    mut kill_events: EventWriter<KillEvent>,
+    matrix: Res<DamageMatrix>,
+    time: Res<Time>,
+) {
+    for (zone_entity, mut zone, zone_transform) in zones.iter_mut() {
+        zone.tick.tick(time.delta());
+        // A long frame can span several pulses, they land as one hit
+        let pulses = zone.tick.times_finished_this_tick();
+        if pulses == 0 {
+            continue;
+        }
+        let pulse_damage = zone.damage_per_second * zone.tick.duration().as_secs_f32() * pulses as f32;
+        let zone_pos = zone_transform.translation.truncate();
+        for (entity, mut attacker, transform) in attackers.iter_mut() {
+            if attacker.health <= 0. || attacker.is_immune_to(DamageType::Magic) || transform.translation.truncate().distance(zone_pos) > zone.radius {
+                continue;
+            }
+            let damage = pulse_damage * matrix.get_multiplier(DamageType::Magic, attacker.armor_class);
+            let absorbed = attacker.take_damage(damage);
+            damage_batches.add(DamageEvent { amount: damage, absorbed, target: entity, damage_type: DamageType::Magic, critical: false });
+            if attacker.health <= 0. {
+                // Nothing is fired, so the zone is both the source and the tower of the kill
+                kill_events.send(KillEvent {
+                    target: entity,
+                    source: zone_entity,
+                    tower: zone_entity,
+                    bounty: attacker.bounty,
+                    original_cost: attacker.original_cost,
+                    group_size: attacker.num_summoned,
+                    death_position: transform.translation.truncate(),
+                    size: attacker.size,
+                });
+                kill_attacker(&mut commands, entity);
+            }
+        }
+    }
+}
+
+fn update_projectile_animations(
+    mut commands: Commands,
+    mut projectiles: Query<(Entity, &AnimationIndices, &mut AnimationTimer, &mut AnimationPlayback, &mut TextureAtlasSprite), With<Projectile>>,
+    time: Res<Time>,
+) {
+    for (entity, animation, mut timer, mut playback, mut sprite) in projectiles.iter_mut() {
+        timer.tick(time.delta());
+        if timer.just_finished() {
+            match advance_animation(animation, sprite.index, &mut playback) {
+                AnimationStep::Frame(index) => sprite.index = index,
+                AnimationStep::Despawn => commands.entity(entity).despawn(),
+            }
+        }
+    }
+}
+
+/* The sweep is only added once a tower fired for the first time */
+fn spawn_cooldown_indicators(
+    mut commands: Commands,
+    towers: Query<(Entity, &Defender), Without<CooldownIndicator>>,
+    mut was_pending: Local<HashMap<Entity, bool>>,
+    textures: Res<TextureResource>,
+) {
+    if !textures.has_animation("towers", COOLDOWN_ANIMATION) {
+        return;
+    }
+    for (entity, defender) in &towers {
+        let previously_pending = was_pending.insert(entity, defender.pending_attack).unwrap_or(false);
+        if previously_pending && !defender.pending_attack {
+            let (atlas, indices) = textures.get_animation("towers", COOLDOWN_ANIMATION);
+            let sweep_entity = commands
+                .spawn(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite::new(indices.start),
+                    texture_atlas: atlas.clone_weak(),
+                    transform: Transform::from_xyz(0., 0., 0.5),
+                    ..default()
+                })
+                .id();
+            commands.entity(entity).add_child(sweep_entity).insert(CooldownIndicator { sweep_entity });
+        }
+    }
+    was_pending.retain(|entity, _| towers.contains(*entity));
+}
+
+fn update_cooldown_indicators(
+    towers: Query<(&Defender, &CooldownIndicator)>,
+    mut sweeps: Query<(&mut TextureAtlasSprite, &mut Visibility)>,
+    textures: Res<TextureResource>,
+) {
+    if !textures.has_animation("towers", COOLDOWN_ANIMATION) {
+        return;
+    }
+    let (_, indices) = textures.get_animation("towers", COOLDOWN_ANIMATION);
+    let frames = indices.end - indices.start + 1;
+    for (defender, indicator) in &towers {
+        if let Ok((mut sprite, mut visibility)) = sweeps.get_mut(indicator.sweep_entity) {
+            let fraction = defender.attack_timer.elapsed_secs() / defender.attack_timer.duration().as_secs_f32();
+            // A tower waiting for a target is ready as well, its timer already wrapped around
+            if fraction >= 1. || defender.pending_attack {
+                *visibility = Visibility::Hidden;
+                continue;
+            }
+            *visibility = Visibility::Inherited;
+            sprite.index = indices.start + ((fraction * frames as f32) as usize).min(frames - 1);
+        }
+    }
+}
+
+pub(crate) fn tick_marks(
+    mut commands: Commands,
+    mut marked: Query<(Entity, &mut Marked)>,
+    time: Res<Time>,
+) {
+    for (entity, mut mark) in marked.iter_mut() {
+        mark.timer.tick(time.delta());
+        if mark.timer.finished() {
+            commands.entity(entity).remove::<Marked>();
+        }
+    }
+}
+
+fn count_kills(
+    mut kill_events: EventReader<KillEvent>,
+    mut towers: Query<(&mut Defender, &Structure)>,
+    buildings: Res<BuildingResource>,
+) {
+    for ev in kill_events.iter() {
+        if let Ok((mut defender, structure)) = towers.get_mut(ev.tower) {
+            defender.kill_count += 1;
+            let cost = buildings.get_cost(&structure.building_type).max(1);
+            defender.kill_efficiency = defender.kill_count as f32 / cost as f32;
+        }
+    }
 }
 
-fn spawn_coin_particle_on_death(
+#[allow(clippy::too_many_arguments)]
+fn handle_kill_effects(
+    mut commands: Commands,
+    mut kill_events: ResMut<Events<KillEvent>>,
+    mut kill_reader: Local<ManualEventReader<KillEvent>>,
+    mut damage_batches: ResMut<DamageBatchAccumulator>,
+    mut slow_events: EventWriter<SlowEvent>,
+    mut enemies: Query<(Entity, &mut Attacker, &Transform)>,
+    towers: Query<&Defender>,
+    textures: Res<TextureResource>,
+    particle_settings: Res<ParticleSettings>,
+    matrix: Res<DamageMatrix>,
+) {
+    // Kills caused here are sent as new events and read again next frame, so chains stay bounded per frame
+    let kills: Vec<(Entity, Entity, Entity, Vec2)> = kill_reader.iter(&kill_events).map(|ev| (ev.target, ev.source, ev.tower, ev.death_position)).collect();
+    for (target, source, tower, death_position) in kills {
+        let Ok(defender) = towers.get(tower) else {
+            continue;
+        };
+        match defender.on_kill_effect {
+            OnKillEffect::SpawnExplosion { radius } => {
+                spawn_large_explosion(&mut commands, &Transform::from_translation(death_position.extend(20.)), &textures, &particle_settings);
+                let damage = defender.attack.get_damage() * KILL_EXPLOSION_DAMAGE_FACTOR;
+                for (entity, mut attacker, transform) in enemies.iter_mut() {
+                    if entity == target || attacker.health <= 0. || attacker.is_immune_to(DamageType::Explosive) || transform.translation.truncate().distance(death_position) > radius {
+                        continue;
+                    }
+                    let damage = damage * matrix.get_multiplier(DamageType::Explosive, attacker.armor_class);
+                    let absorbed = attacker.take_damage(damage);
+                    damage_batches.add(DamageEvent { amount: damage, absorbed, target: entity, damage_type: DamageType::Explosive, critical: false });
+                    if attacker.health <= 0. {
+                        kill_events.send(KillEvent {
+                            target: entity,
+                            source,
+                            tower,
+                            bounty: attacker.bounty,
+                            original_cost: attacker.original_cost,
+                            group_size: attacker.num_summoned,
+                            death_position: transform.translation.truncate(),
+                            size: attacker.size,
+                        });
+                        kill_attacker(&mut commands, entity);
+                    }
+                }
+            }
+            OnKillEffect::SlowNearby { factor, radius, duration } => {
+                for (entity, attacker, transform) in enemies.iter() {
+                    if entity != target && attacker.health > 0. && transform.translation.truncate().distance(death_position) <= radius {
+                        slow_events.send(SlowEvent { target: entity, factor, duration_secs: duration });
+                    }
+                }
+            }
+            OnKillEffect::None => {}
+        }
+    }
+}
+
+fn spawn_death_effects_on_kill(
     mut commands: Commands,
     mut kill_events: EventReader<KillEvent>,
     textures: Res<TextureResource>,
+    particle_settings: Res<ParticleSettings>,
+    pickup: Res<GoldPickup>,
 ) {
     for ev in kill_events.iter() {
-        spawn_coin(&mut commands, &Transform::from_translation(ev.death_position.extend(20.)), &textures);
+        let transform = Transform::from_translation(ev.death_position.extend(20.));
+        // Coins that have to be collected are shown by the coin itself
+        spawn_death_effects(&mut commands, &transform, ev.bounty, ev.size, pickup.instant, &textures, &particle_settings);
     }
 }
 
@@ -650,7 +2057,9 @@ pub trait StructureBuilder {
         named_textures: &TextureResource,
         x: usize,
         y: usize,
-    ) -> Self;
+    ) -> Result<Self, String>
+    where
+        Self: Sized;
 }
 
 #[derive(Bundle)]
@@ -667,169 +2076,801 @@ impl StructureBuilder for WallBundle {
         named_textures: &TextureResource,
         x: usize,
         y: usize,
-    ) -> Self {
-        let sprite = named_textures.get_sprite("towers", 0);
-        return Self {
+    ) -> Result<Self, String> {
+        let sprite = get_tower_sprite(defenders, named_textures, BuildingType::Wall)?;
+        return Ok(Self {
             structure: Structure {
                 blocking: true,
                 building_type: BuildingType::Wall,
             },
+            sprite: structure_sprite(sprite, tower_field, x, y),
+        });
+    }
+}
+
+/* Why a building couldn't be built from its entry in tower_definitions.json */
+#[derive(Debug, PartialEq)]
+pub enum BuildError {
+    MissingConfig(BuildingType),
+    WrongConfig { building_type: BuildingType, expected: &'static str, found: &'static str },
+    MissingSprite { building_type: BuildingType, sprite: String },
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            BuildError::MissingConfig(building_type) => write!(f, "No configuration for {:?} in tower_definitions.json", building_type),
+            BuildError::WrongConfig { building_type, expected, found } => write!(f, "{:?} has a {} configuration in tower_definitions.json, expected {}", building_type, found, expected),
+            BuildError::MissingSprite { building_type, sprite } => write!(f, "{:?}: no sprite named '{}' in the towers atlas", building_type, sprite),
+        };
+    }
+}
+
+/* Reported as is through the fatal error overlay */
+impl From<BuildError> for String {
+    fn from(err: BuildError) -> Self {
+        return err.to_string();
+    }
+}
+
+/* The building's sprite in the "towers" atlas, a building without a definition has no sprite name either */
+fn get_tower_sprite<'a>(defenders: &BuildingResource, named_textures: &'a TextureResource, building_type: BuildingType) -> Result<(&'a Handle<TextureAtlas>, TextureAtlasSprite), BuildError> {
+    let sprite_name = defenders.get_sprite_name(&building_type).ok_or(BuildError::MissingConfig(building_type))?;
+    return named_textures.get_named_sprite("towers", sprite_name)
+        .ok_or(BuildError::MissingSprite { building_type, sprite: sprite_name.to_string() });
+}
+
+/* Single slot building sprite at x, y, rows further down are drawn on top */
+fn structure_sprite((atlas, sprite): (&Handle<TextureAtlas>, TextureAtlasSprite), tower_field: &TowerField, x: usize, y: usize) -> SpriteSheetBundle {
+    return SpriteSheetBundle {
+        sprite,
+        texture_atlas: atlas.clone_weak(),
+        transform: Transform::from_xyz(
+            (x * SLOT_SIZE) as f32 + tower_field.field_transform.x,
+            (y * SLOT_SIZE) as f32 + tower_field.field_transform.y,
+            10. + (tower_field.height - y) as f32 / tower_field.height as f32,
+        ),
+        ..default()
+    };
+}
+
+/* Every tower with an attack, whichever kind of attack its configuration has */
+#[derive(Bundle)]
+pub struct DefenderBundle {
+    structure: Structure,
+    footprint: Footprint,
+    defender: Defender,
+    grounded: Grounded,
+    #[bundle]
+    sprite: SpriteSheetBundle,
+}
+
+impl DefenderBundle {
+    pub fn from_config(
+        building_type: BuildingType,
+        config: &BuildingConfig,
+        sprite_name: &str,
+        tower_field: &TowerField,
+        named_textures: &TextureResource,
+        x: usize,
+        y: usize,
+    ) -> Result<Self, BuildError> {
+        let BuildingTypeConfig::Defender { attack_timer, attack, attack_range, min_range, arc_half_width } = &config.type_config else {
+            return Err(BuildError::WrongConfig { building_type, expected: "Defender", found: config.type_config.get_name() });
+        };
+        let Some((atlas, mut tower_sprite)) = named_textures.get_named_sprite("towers", sprite_name) else {
+            return Err(BuildError::MissingSprite { building_type, sprite: sprite_name.to_string() });
+        };
+        let footprint = building_type.get_footprint();
+        if footprint != Footprint::SINGLE {
+            tower_sprite.custom_size = Some(Vec2::new(footprint.width as f32, footprint.height as f32) * SLOT_SIZE as f32);
+        }
+        // Centered on the footprint, the anchor slot still resolves from the transform
+        let center = footprint.center_offset();
+        return Ok(Self {
+            structure: Structure {
+                blocking: config.blocking,
+                building_type,
+            },
+            footprint,
             sprite: SpriteSheetBundle {
-                sprite: sprite.1,
-                texture_atlas: sprite.0.clone_weak(),
+                sprite: tower_sprite,
+                texture_atlas: atlas.clone_weak(),
                 transform: Transform::from_xyz(
-                    (x * SLOT_SIZE) as f32 + tower_field.field_transform.x,
-                    (y * SLOT_SIZE) as f32 + tower_field.field_transform.y,
+                    (x * SLOT_SIZE) as f32 + tower_field.field_transform.x + center.x,
+                    (y * SLOT_SIZE) as f32 + tower_field.field_transform.y + center.y,
                     10. + (tower_field.height - y) as f32 / tower_field.height as f32,
                 ),
                 ..default()
             },
-        };
+            defender: Defender {
+                attack_timer: Timer::from_seconds(*attack_timer, bevy::time::TimerMode::Repeating),
+                attack: attack.clone(),
+                kill_count: 0,
+                kill_efficiency: 0.,
+                on_kill_effect: get_on_kill_effect(building_type),
+                attack_range: *attack_range,
+                min_range: *min_range,
+                arc: arc_half_width.map(|half_width| (0., half_width.to_radians())),
+                pending_attack: false,
+            },
+            grounded: Grounded,
+        });
+    }
+
+    /* Turrets and special abilities only come with some towers, they are added next to the bundle */
+    pub fn spawn(self, commands: &mut Commands) -> Entity {
+        let building_type = self.structure.building_type;
+        let mut entity = commands.spawn(self);
+        if let Some(turn_rate) = get_turn_rate(building_type) {
+            entity.insert(TurretRotation::new(turn_rate));
+        }
+        if let Some(ability) = get_special_ability(building_type) {
+            entity.insert(ability);
+        }
+        return entity.id();
     }
 }
 
+fn get_on_kill_effect(building_type: BuildingType) -> OnKillEffect {
+    return match building_type {
+        BuildingType::Cannon => OnKillEffect::SpawnExplosion { radius: CANNON_KILL_EXPLOSION_RADIUS },
+        BuildingType::LargeCannon => OnKillEffect::SpawnExplosion { radius: LARGE_CANNON_KILL_EXPLOSION_RADIUS },
+        _ => OnKillEffect::None
+    };
+}
+
+fn get_turn_rate(building_type: BuildingType) -> Option<f32> {
+    return match building_type {
+        // The elemental arrows share the arrow turret
+        BuildingType::Arrow | BuildingType::FireArrow | BuildingType::IceArrow => Some(ARROW_TURN_RATE),
+        BuildingType::Ballista => Some(BALLISTA_TURN_RATE),
+        BuildingType::Cannon => Some(CANNON_TURN_RATE),
+        _ => None
+    };
+}
+
+fn get_special_ability(building_type: BuildingType) -> Option<SpecialAbility> {
+    return match building_type {
+        BuildingType::Arrow | BuildingType::FireArrow | BuildingType::IceArrow => Some(SpecialAbility::rapid_fire()),
+        BuildingType::Ballista => Some(SpecialAbility::mega_shot()),
+        _ => None
+    };
+}
+
 #[derive(Bundle)]
-pub struct ArrowTower {
+pub struct FreezeTower {
     structure: Structure,
-    defender: Defender,
-    grounded: Grounded,
+    aura: SlowAura,
     #[bundle]
     sprite: SpriteSheetBundle,
 }
 
-impl StructureBuilder for ArrowTower {
+impl StructureBuilder for FreezeTower {
     fn from_tower_field(
         defenders: &BuildingResource,
         tower_field: &TowerField,
         named_textures: &TextureResource,
         x: usize,
         y: usize,
-    ) -> Self {
-        let tower_sprite = named_textures.get_sprite("towers", 4);
-        let config = defenders.get_building_config(&BuildingType::Arrow).unwrap();
+    ) -> Result<Self, String> {
+        let tower_sprite = get_tower_sprite(defenders, named_textures, BuildingType::Freeze)?;
+        let config = defenders.get_building_config(&BuildingType::Freeze)
+            .ok_or(BuildError::MissingConfig(BuildingType::Freeze))?;
         match &config.type_config {
-            BuildingTypeConfig::Defender {
-                attack_timer,
-                attack,
-                attack_range,
-            } => match attack {
-                DefenderAttack::Projectile {
-                    damage_type,
-                    damage,
-                    projectile_speed,
-                    sprite,
-                } => {
-                    return Self {
-                        structure: Structure {
-                            blocking: config.blocking,
-                            building_type: BuildingType::Arrow,
-                        },
-                        sprite: SpriteSheetBundle {
-                            sprite: tower_sprite.1,
-                            texture_atlas: tower_sprite.0.clone_weak(),
-                            transform: Transform::from_xyz(
-                                (x * SLOT_SIZE) as f32 + tower_field.field_transform.x,
-                                (y * SLOT_SIZE) as f32 + tower_field.field_transform.y,
-                                10. + (tower_field.height - y) as f32 / tower_field.height as f32,
-                            ),
-                            ..default()
-                        },
-                        defender: Defender {
-                            attack_timer: Timer::from_seconds(
-                                *attack_timer,
-                                bevy::time::TimerMode::Repeating,
-                            ),
-                            attack: DefenderAttack::Projectile {
-                                damage_type: *damage_type,
-                                damage: *damage,
-                                projectile_speed: *projectile_speed,
-                                sprite: sprite.clone(),
-                            },
-                            kill_count: 0,
-                            attack_range: *attack_range,
-                            pending_attack: false,
-                        },
-                        grounded: Grounded,
-                    }
-                }
-                _ => panic!(),
-            },
-            BuildingTypeConfig::Wall => panic!(),
+            BuildingTypeConfig::Aura { radius, factor, .. } => {
+                return Ok(Self {
+                    structure: Structure {
+                        blocking: config.blocking,
+                        building_type: BuildingType::Freeze,
+                    },
+                    aura: SlowAura {
+                        radius: *radius,
+                        factor: *factor,
+                    },
+                    sprite: structure_sprite(tower_sprite, tower_field, x, y),
+                });
+            }
+            other => Err(BuildError::WrongConfig { building_type: BuildingType::Freeze, expected: "Aura", found: other.get_name() }.into()),
         }
     }
 }
 
 #[derive(Bundle)]
-pub struct CannonTower {
+pub struct ZoneDamageTower {
     structure: Structure,
-    defender: Defender,
-    grounded: Grounded,
+    zone: DamageZone,
     #[bundle]
     sprite: SpriteSheetBundle,
 }
 
-impl StructureBuilder for CannonTower {
+impl StructureBuilder for ZoneDamageTower {
     fn from_tower_field(
         defenders: &BuildingResource,
         tower_field: &TowerField,
         named_textures: &TextureResource,
         x: usize,
         y: usize,
-    ) -> Self {
-        let tower_sprite = named_textures.get_sprite("towers", 1);
-        let config = defenders
-            .get_building_config(&BuildingType::Cannon)
-            .unwrap();
+    ) -> Result<Self, String> {
+        let tower_sprite = get_tower_sprite(defenders, named_textures, BuildingType::ZoneDamage)?;
+        let config = defenders.get_building_config(&BuildingType::ZoneDamage)
+            .ok_or(BuildError::MissingConfig(BuildingType::ZoneDamage))?;
         match &config.type_config {
-            BuildingTypeConfig::Defender {
-                attack_timer,
-                attack,
-                attack_range,
-            } => match attack {
-                DefenderAttack::Splash {
-                    damage_type,
-                    damage,
-                    travel_time,
-                    sprite,
-                    splash_radius,
-                } => {
-                    return Self {
-                        structure: Structure {
-                            blocking: config.blocking,
-                            building_type: BuildingType::Cannon,
-                        },
-                        sprite: SpriteSheetBundle {
-                            sprite: tower_sprite.1,
-                            texture_atlas: tower_sprite.0.clone_weak(),
-                            transform: Transform::from_xyz(
-                                (x * SLOT_SIZE) as f32 + tower_field.field_transform.x,
-                                (y * SLOT_SIZE) as f32 + tower_field.field_transform.y,
-                                10. + (tower_field.height - y) as f32 / tower_field.height as f32,
-                            ),
-                            ..default()
-                        },
-                        defender: Defender {
-                            attack_timer: Timer::from_seconds(
-                                *attack_timer,
-                                bevy::time::TimerMode::Repeating,
-                            ),
-                            attack: DefenderAttack::Splash {
-                                damage_type: *damage_type,
-                                damage: *damage,
-                                splash_radius: *splash_radius,
-                                travel_time: *travel_time,
-                                sprite: sprite.clone(),
-                            },
-                            kill_count: 0,
-                            attack_range: *attack_range,
-                            pending_attack: false,
-                        },
-                        grounded: Grounded,
-                    }
-                }
-                _ => panic!(),
+            BuildingTypeConfig::Zone { damage_per_second, radius } => {
+                return Ok(Self {
+                    structure: Structure {
+                        blocking: config.blocking,
+                        building_type: BuildingType::ZoneDamage,
+                    },
+                    zone: DamageZone::new(*damage_per_second, *radius),
+                    sprite: structure_sprite(tower_sprite, tower_field, x, y),
+                });
+            }
+            other => Err(BuildError::WrongConfig { building_type: BuildingType::ZoneDamage, expected: "Zone", found: other.get_name() }.into()),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct BannerBundle {
+    structure: Structure,
+    aura: BannerAura,
+    #[bundle]
+    sprite: SpriteSheetBundle,
+}
+
+impl StructureBuilder for BannerBundle {
+    fn from_tower_field(
+        defenders: &BuildingResource,
+        tower_field: &TowerField,
+        named_textures: &TextureResource,
+        x: usize,
+        y: usize,
+    ) -> Result<Self, String> {
+        let tower_sprite = get_tower_sprite(defenders, named_textures, BuildingType::Banner)?;
+        let config = defenders.get_building_config(&BuildingType::Banner)
+            .ok_or(BuildError::MissingConfig(BuildingType::Banner))?;
+        match &config.type_config {
+            BuildingTypeConfig::Aura { radius, attack_speed_bonus, damage_bonus, .. } => {
+                return Ok(Self {
+                    structure: Structure {
+                        blocking: config.blocking,
+                        building_type: BuildingType::Banner,
+                    },
+                    aura: BannerAura {
+                        radius: *radius,
+                        attack_speed_bonus: *attack_speed_bonus,
+                        damage_bonus: *damage_bonus,
+                    },
+                    sprite: structure_sprite(tower_sprite, tower_field, x, y),
+                });
+            }
+            other => Err(BuildError::WrongConfig { building_type: BuildingType::Banner, expected: "Aura", found: other.get_name() }.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::prelude::{Entity, Vec2};
+
+    use super::*;
+    use crate::world::{attackers::{ArmorClass, GOLEM_STATS, ORC_WARRIOR_STATS}, path_finding::PathCache};
+
+    /* Fires every second at anything within 150 pixels, in all directions */
+    fn test_defender(attack: DefenderAttack) -> Defender {
+        return Defender {
+            attack_timer: Timer::from_seconds(1., bevy::time::TimerMode::Repeating),
+            attack,
+            attack_range: 150.,
+            min_range: 0.,
+            arc: None,
+            kill_count: 0,
+            kill_efficiency: 0.,
+            pending_attack: false,
+            on_kill_effect: OnKillEffect::None,
+        };
+    }
+
+    #[test]
+    fn elemental_arrows_turn_and_fire_like_arrows() {
+        for building_type in [BuildingType::FireArrow, BuildingType::IceArrow] {
+            assert_eq!(get_turn_rate(building_type), get_turn_rate(BuildingType::Arrow));
+            assert!(get_special_ability(building_type).is_some());
+        }
+    }
+
+    #[test]
+    fn a_wall_configuration_is_rejected_for_a_defender() {
+        let field = TowerField::new(4, 3, Vec2::ZERO, Node::new(0, 0), Node::new(3, 2));
+        let wall = BuildingConfig { cost: 5, blocking: true, type_config: BuildingTypeConfig::Wall, sprite: None, icon: None };
+        let result = DefenderBundle::from_config(BuildingType::Arrow, &wall, "arrow", &field, &TextureResource::default(), 1, 1);
+        let err = result.err().unwrap();
+        assert_eq!(err, BuildError::WrongConfig { building_type: BuildingType::Arrow, expected: "Defender", found: "Wall" });
+        assert_eq!(String::from(err), "Arrow has a Wall configuration in tower_definitions.json, expected Defender");
+    }
+
+    #[test]
+    fn a_defender_without_its_sprite_is_an_error() {
+        let field = TowerField::new(4, 3, Vec2::ZERO, Node::new(0, 0), Node::new(3, 2));
+        let tesla = BuildingConfig {
+            cost: 50,
+            blocking: true,
+            type_config: BuildingTypeConfig::Defender {
+                attack_timer: 1.,
+                attack: DefenderAttack::Chain { damage_type: DamageType::Magic, damage: 10., falloff: 0.5, max_targets: 3, arc_range: 64. },
+                attack_range: 160.,
+                min_range: 0.,
+                arc_half_width: None,
             },
-            BuildingTypeConfig::Wall => panic!(),
+            sprite: Some("coil".to_string()),
+            icon: None,
+        };
+        let result = DefenderBundle::from_config(BuildingType::Tesla, &tesla, "coil", &field, &TextureResource::default(), 1, 1);
+        assert_eq!(result.err(), Some(BuildError::MissingSprite { building_type: BuildingType::Tesla, sprite: "coil".to_string() }));
+    }
+
+    #[test]
+    fn adding_and_clearing_slots_bump_the_field_version() {
+        let mut field = TowerField::new(4, 3, Vec2::ZERO, Node::new(0, 0), Node::new(3, 2));
+        assert_eq!(field.get_field_version(), 0);
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::ZERO, Footprint::SINGLE);
+        assert_eq!(field.get_field_version(), 1);
+        field.clear_slot(Node::new(0, 0), Footprint::SINGLE);
+        assert_eq!(field.get_field_version(), 2);
+    }
+
+    #[test]
+    fn slots_round_trip_up_to_the_edges() {
+        let mut field = TowerField::new(4, 3, Vec2::ZERO, Node::new(0, 0), Node::new(3, 2));
+        let corner = Vec2::new(3., 2.) * SLOT_SIZE as f32;
+        field.add_structure(Entity::PLACEHOLDER, true, Vec2::ZERO, Footprint::SINGLE);
+        field.add_structure(Entity::PLACEHOLDER, false, corner, Footprint::SINGLE);
+        assert!(field.is_blocked(0, 0) && field.is_occupied(0, 0));
+        // Non blocking structures take the slot but let attackers through
+        assert!(!field.is_blocked(3, 2) && field.is_occupied(3, 2));
+        assert!(!field.is_occupied(1, 0));
+
+        // Outside the field is blocked, the right edge doesn't wrap around into the next row
+        for outside in [Node::new(-1, 0), Node::new(0, -1), Node::new(4, 0), Node::new(0, 3)] {
+            assert!(field.is_node_blocked(outside) && field.is_node_occupied(outside), "{:?}", outside);
+        }
+        assert!(field.get_slot(Node::new(4, 0)).is_none());
+
+        field.clear_slot(Node::new(0, 0), Footprint::SINGLE);
+        field.clear_slot(field.node_at(corner), Footprint::SINGLE);
+        assert!(!field.is_node_occupied(Node::new(0, 0)) && !field.is_node_blocked(Node::new(0, 0)));
+        assert!(!field.is_node_occupied(Node::new(3, 2)));
+        // Structures hanging over the edge only take the slots inside
+        field.add_structure(Entity::PLACEHOLDER, true, corner, Footprint { width: 2, height: 2 });
+        assert!(field.is_node_blocked(Node::new(3, 2)));
+        assert!(!field.is_node_blocked(Node::new(0, 2)));
+    }
+
+    fn arrow(mark: Option<MarkEffect>) -> Projectile {
+        return Projectile {
+            target: Target::Ground(Vec2::ZERO),
+            source: Entity::PLACEHOLDER,
+            projectile_motion: ProjectileMotion::Velocity(100.),
+            damage: 10.,
+            damage_type: DamageType::Piercing,
+            splash_radius: 0.,
+            velocity: Vec2::ZERO,
+            size: Vec2::ONE,
+            dead: false,
+            age: Duration::ZERO,
+            piercing: 0,
+            hit_entities: Vec::new(),
+            forks_on_hit: 0,
+            mark,
+        };
+    }
+
+    #[test]
+    fn marked_attacker_takes_more_damage() {
+        let marked = Marked { bonus_mult: 1.5, timer: Timer::from_seconds(2., bevy::time::TimerMode::Once) };
+        let unmarked_damage = calculate_damage(&arrow(None), &ORC_WARRIOR_STATS, None, &DamageMatrix::default());
+        let marked_damage = calculate_damage(&arrow(None), &ORC_WARRIOR_STATS, Some(&marked), &DamageMatrix::default());
+        assert!(marked_damage > unmarked_damage);
+        assert_eq!(marked_damage, 15.);
+    }
+
+    #[test]
+    fn dodge_rolls_miss_at_the_dodge_rate() {
+        let mut rng = GameRng::seeded(2114);
+        let mut attacker = ORC_WARRIOR_STATS;
+        attacker.dodge_chance = 0.8;
+        let rolls = 10000;
+        let misses = (0..rolls).filter(|_| roll_dodge(&attacker, &mut rng)).count();
+        let fraction = misses as f32 / rolls as f32;
+        assert!((fraction - 0.8).abs() < 0.02, "missed {} of rolls", fraction);
+    }
+
+    #[test]
+    fn armor_class_scales_projectile_damage() {
+        let matrix = DamageMatrix::from_json(r#"{ "Piercing": { "Heavy": 0.5 } }"#).unwrap();
+        let projectile = arrow(None);
+        assert_eq!(calculate_damage(&projectile, &GOLEM_STATS, None, &matrix), 5.);
+        assert_eq!(calculate_damage(&projectile, &ORC_WARRIOR_STATS, None, &matrix), 10.);
+    }
+
+    #[test]
+    fn elemental_projectiles_use_the_built_in_multipliers() {
+        let matrix = DamageMatrix::from_json(r#"{ "Piercing": { "Heavy": 0.5 } }"#).unwrap();
+        let mut fire_arrow = arrow(None);
+        fire_arrow.damage_type = DamageType::Fire;
+        assert_eq!(calculate_damage(&fire_arrow, &ORC_WARRIOR_STATS, None, &matrix), 15.);
+        assert_eq!(calculate_damage(&fire_arrow, &GOLEM_STATS, None, &matrix), 10.);
+        let mut construct = GOLEM_STATS;
+        construct.armor_class = ArmorClass::Mechanical;
+        assert_eq!(calculate_damage(&fire_arrow, &construct, None, &matrix), 5.);
+        let mut ice_arrow = arrow(None);
+        ice_arrow.damage_type = DamageType::Ice;
+        assert_eq!(calculate_damage(&ice_arrow, &GOLEM_STATS, None, &DamageMatrix::default()), 8.);
+        assert_eq!(calculate_damage(&ice_arrow, &construct, None, &DamageMatrix::default()), 10.);
+    }
+
+    #[test]
+    fn immune_attackers_take_no_damage() {
+        let marked = Marked { bonus_mult: 1.5, timer: Timer::from_seconds(2., bevy::time::TimerMode::Once) };
+        let mut attacker = ORC_WARRIOR_STATS;
+        attacker.immunities = &[DamageType::Piercing];
+        assert_eq!(calculate_damage(&arrow(None), &attacker, Some(&marked), &DamageMatrix::default()), 0.);
+        let mut cannonball = arrow(None);
+        cannonball.damage_type = DamageType::Crushing;
+        assert_eq!(calculate_damage(&cannonball, &attacker, None, &DamageMatrix::default()), 10.);
+    }
+
+    #[test]
+    fn dodged_projectiles_land_past_the_attacker() {
+        let dodger = Entity::from_raw(7);
+        let mut projectile = arrow(None);
+        projectile.target = Target::Entity(dodger);
+        projectile.velocity = Vec2::new(0., 100.);
+        whiff(&mut projectile, dodger, Vec2::new(50., 50.));
+        assert!(matches!(projectile.target, Target::Ground(pos) if pos == Vec2::new(50., 50. + WHIFF_TRAVEL_DISTANCE)));
+        assert_eq!(projectile.hit_entities, vec![dodger]);
+        assert!(!projectile.dead);
+    }
+
+    #[test]
+    fn marker_shots_do_not_benefit_from_the_mark() {
+        let marked = Marked { bonus_mult: 1.5, timer: Timer::from_seconds(2., bevy::time::TimerMode::Once) };
+        let marker = arrow(Some(MarkEffect { bonus_mult: 1.5, duration: 2. }));
+        assert_eq!(calculate_damage(&marker, &ORC_WARRIOR_STATS, Some(&marked), &DamageMatrix::default()), 10.);
+    }
+
+    #[test]
+    fn intercept_point_is_reached_by_both_at_the_same_time() {
+        let shooter = Vec2::new(0., 0.);
+        let target = Vec2::new(100., 0.);
+        let velocity = Vec2::new(0., 40.);
+        let speed = 200.;
+        let aim = intercept_point(shooter, target, velocity, speed).unwrap();
+        let projectile_time = aim.distance(shooter) / speed;
+        let target_time = aim.distance(target) / velocity.length();
+        assert!((projectile_time - target_time).abs() < 1e-3);
+        assert!(aim.y > 0.);
+    }
+
+    #[test]
+    fn intercept_point_of_a_standing_target_is_its_position() {
+        let aim = intercept_point(Vec2::ZERO, Vec2::new(30., 40.), Vec2::ZERO, 100.).unwrap();
+        assert!(aim.distance(Vec2::new(30., 40.)) < 1e-4);
+    }
+
+    #[test]
+    fn faster_target_running_away_cannot_be_intercepted() {
+        assert_eq!(intercept_point(Vec2::ZERO, Vec2::new(100., 0.), Vec2::new(300., 0.), 200.), None);
+        // Just as fast but coming closer is still caught halfway
+        let aim = intercept_point(Vec2::ZERO, Vec2::new(100., 0.), Vec2::new(-200., 0.), 200.).unwrap();
+        assert!(aim.distance(Vec2::new(50., 0.)) < 1e-3);
+    }
+
+    #[test]
+    fn turning_is_limited_to_the_turn_rate() {
+        let turned = turn_towards(Vec2::X, Vec2::Y, 0.1);
+        assert!((Vec2::X.angle_between(turned) - 0.1).abs() < 1e-5);
+        let turned = turn_towards(Vec2::X, -Vec2::Y, 0.1);
+        assert!((Vec2::X.angle_between(turned) + 0.1).abs() < 1e-5);
+        assert_eq!(turn_towards(Vec2::X, Vec2::new(1., 0.01).normalize(), 0.1), Vec2::new(1., 0.01).normalize());
+    }
+
+    #[test]
+    fn targets_inside_the_dead_zone_are_ignored() {
+        let close = ORC_WARRIOR_STATS;
+        let mut far = ORC_WARRIOR_STATS;
+        far.health = close.health + 10.;
+        let close_transform = Transform::from_xyz(20., 0., 0.);
+        let far_transform = Transform::from_xyz(100., 0., 0.);
+        let enemies = [
+            (Entity::from_raw(1), &close, &close_transform),
+            (Entity::from_raw(2), &far, &far_transform),
+        ];
+        assert_eq!(select_target(enemies, Vec2::ZERO, 0., 150.).unwrap().0, Entity::from_raw(1));
+        assert_eq!(select_target(enemies, Vec2::ZERO, 48., 150.).unwrap().0, Entity::from_raw(2));
+    }
+
+    #[test]
+    fn every_tower_gets_its_own_target() {
+        let enemies = [
+            (Entity::from_raw(1), ORC_WARRIOR_STATS, Transform::from_xyz(20., 0., 0.)),
+            (Entity::from_raw(2), ORC_WARRIOR_STATS, Transform::from_xyz(500., 0., 0.)),
+        ];
+        let towers = [(Vec2::ZERO, 0., 150.), (Vec2::new(480., 0.), 0., 150.), (Vec2::new(250., 0.), 0., 100.)];
+        assert_eq!(select_targets(&towers, &enemies), vec![Some(Entity::from_raw(1)), Some(Entity::from_raw(2)), None]);
+    }
+
+    #[test]
+    fn targets_behind_an_arc_are_not_fired_upon() {
+        let mut defender = test_defender(DefenderAttack::Splash {
+            damage_type: DamageType::Explosive,
+            damage: 10.,
+            travel_time: 1.,
+            splash_radius: 16.,
+            sprite: ProjectileSprite::Static { name: "towers".to_string(), index: 7, size: Vec2::ONE },
+        });
+        defender.arc = Some((0., 45f32.to_radians()));
+        let behind = ORC_WARRIOR_STATS;
+        let behind_transform = Transform::from_xyz(-50., 0., 0.);
+        let mut in_front = ORC_WARRIOR_STATS;
+        in_front.health = behind.health + 10.;
+        let in_front_transform = Transform::from_xyz(80., 30., 0.);
+
+        assert!(select_defender_target(&defender, [(Entity::from_raw(1), &behind, &behind_transform)], Vec2::ZERO, &IncomingDamage::default()).is_none());
+        let enemies = [
+            (Entity::from_raw(1), &behind, &behind_transform),
+            (Entity::from_raw(2), &in_front, &in_front_transform),
+        ];
+        assert_eq!(select_defender_target(&defender, enemies, Vec2::ZERO, &IncomingDamage::default()).unwrap().0, Entity::from_raw(2));
+        // Turned around it is the other way round
+        defender.arc = Some((PI, 45f32.to_radians()));
+        assert_eq!(select_defender_target(&defender, enemies, Vec2::ZERO, &IncomingDamage::default()).unwrap().0, Entity::from_raw(1));
+    }
+
+    #[test]
+    fn towers_pick_another_target_once_enough_damage_is_in_flight() {
+        let defender = test_defender(DefenderAttack::Splash {
+            damage_type: DamageType::Explosive,
+            damage: 10.,
+            travel_time: 1.,
+            splash_radius: 16.,
+            sprite: ProjectileSprite::Static { name: "towers".to_string(), index: 7, size: Vec2::ONE },
+        });
+        let transform = Transform::from_xyz(50., 0., 0.);
+        let mut weak = ORC_WARRIOR_STATS;
+        weak.health = 30.;
+        let mut strong = ORC_WARRIOR_STATS;
+        strong.health = 50.;
+        let enemies = [(Entity::from_raw(1), &weak, &transform), (Entity::from_raw(2), &strong, &transform)];
+
+        // One arrow is on its way to the weak attacker, another one already hit and is just fading out
+        let mut spent = arrow(None);
+        spent.dead = true;
+        let in_flight = [
+            (ReservedDamage { target: Entity::from_raw(1), amount: 20. }, arrow(None)),
+            (ReservedDamage { target: Entity::from_raw(1), amount: 20. }, spent),
+        ];
+        let mut incoming = IncomingDamage::from_projectiles(in_flight.iter().map(|(reservation, projectile)| (reservation, projectile)));
+        assert_eq!(select_defender_target(&defender, enemies, Vec2::ZERO, &incoming).unwrap().0, Entity::from_raw(1));
+
+        // The next tower firing this frame sees the shot of the one before it
+        incoming.reserve(Entity::from_raw(1), 20.);
+        assert_eq!(select_defender_target(&defender, enemies, Vec2::ZERO, &incoming).unwrap().0, Entity::from_raw(2));
+        incoming.reserve(Entity::from_raw(2), 50.);
+        assert!(select_defender_target(&defender, enemies, Vec2::ZERO, &incoming).is_none());
+    }
+
+    #[test]
+    fn turrets_turn_the_short_way_at_their_own_speed() {
+        let mut turret = TurretRotation::new(PI);
+        turret.target_angle_rad = PI / 2.;
+        turret.turn(0.25);
+        assert!((turret.current_angle_rad - PI / 4.).abs() < 1e-5);
+        assert!(!turret.is_aimed());
+        turret.turn(1.);
+        assert!(turret.is_aimed());
+
+        // From just below PI to just above -PI is a small turn across the wrap, not almost a full circle back
+        turret.current_angle_rad = PI - 0.2;
+        turret.target_angle_rad = -PI + 0.2;
+        assert!((turret.remaining_turn() - 0.4).abs() < 1e-5);
+        turret.turn(0.1);
+        assert!(turret.is_aimed());
+    }
+
+    #[test]
+    fn towers_placed_together_fire_out_of_step() {
+        use bevy::prelude::{Schedule, World};
+
+        let tower = || test_defender(DefenderAttack::Chain { damage_type: DamageType::Magic, damage: 10., falloff: 0.5, max_targets: 3, arc_range: 64. });
+        let mut world = World::new();
+        world.insert_resource(GameRng::seeded(7));
+        let first = world.spawn(tower()).id();
+        let second = world.spawn(tower()).id();
+        let mut schedule = Schedule::new();
+        schedule.add_system(offset_attack_phases);
+        schedule.run(&mut world);
+
+        let mut elapsed = Vec::new();
+        for entity in [first, second] {
+            let mut defender = world.get_mut::<Defender>(entity).unwrap();
+            defender.attack_timer.tick(Duration::from_millis(100));
+            assert_eq!(defender.attack_timer.duration(), Duration::from_secs(1));
+            elapsed.push(defender.attack_timer.elapsed());
+        }
+        assert_ne!(elapsed[0], elapsed[1]);
+        assert!(elapsed.iter().all(|elapsed| *elapsed < Duration::from_millis(100) + Duration::from_secs(1).mul_f32(MAX_ATTACK_PHASE_OFFSET)));
+    }
+
+    #[test]
+    fn chains_jump_to_the_closest_attacker_not_hit_yet() {
+        let candidates = [
+            (Entity::from_raw(0), Vec2::new(0., 0.)),
+            (Entity::from_raw(1), Vec2::new(50., 0.)),
+            (Entity::from_raw(2), Vec2::new(20., 0.)),
+            (Entity::from_raw(3), Vec2::new(400., 0.)),
+        ];
+        let chain: Vec<Entity> = chain_targets(Entity::from_raw(0), &candidates, 64., 4).iter().map(|hop| hop.0).collect();
+        // The far away attacker is out of arc range, so the chain stops early instead of jumping back
+        assert_eq!(chain, vec![Entity::from_raw(0), Entity::from_raw(2), Entity::from_raw(1)]);
+        let capped = chain_targets(Entity::from_raw(0), &candidates, 64., 2);
+        assert_eq!(capped.len(), 2);
+    }
+
+    #[test]
+    fn covered_nodes_respect_range_and_dead_zone() {
+        let field = TowerField::new(5, 1, Vec2::ZERO, Node::new(0, 0), Node::new(4, 0));
+        let mut defender = test_defender(DefenderAttack::Chain { damage_type: DamageType::Magic, damage: 10., falloff: 0.5, max_targets: 3, arc_range: 64. });
+        defender.min_range = 32.;
+        let path: Vec<Node> = (0..5).map(|x| Node::new(x, 0)).collect();
+        let covered = covered_nodes(&path, &field, [(&defender, &Transform::IDENTITY)]);
+        assert_eq!(covered, vec![Node::new(1, 0), Node::new(2, 0)]);
+    }
+
+    #[test]
+    fn banner_bonuses_stack_up_to_the_cap() {
+        let banner = BannerAura { radius: 100., attack_speed_bonus: 0.3, damage_bonus: 0.1 };
+        let banners = [
+            (Entity::from_raw(1), &banner, Vec2::new(64., 0.)),
+            (Entity::from_raw(2), &banner, Vec2::new(0., 64.)),
+            (Entity::from_raw(3), &banner, Vec2::new(400., 0.)),
+        ];
+        let buff = combine_banner_buffs(banners, Vec2::ZERO).unwrap();
+        assert_eq!(buff.banners, vec![Entity::from_raw(1), Entity::from_raw(2)]);
+        assert_eq!(buff.attack_speed_bonus, MAX_BANNER_BONUS);
+        assert!((buff.damage_bonus - 0.2).abs() < 1e-6);
+        assert!(combine_banner_buffs(banners, Vec2::new(-300., 0.)).is_none());
+    }
+
+    #[test]
+    fn clearing_the_board_removes_every_structure_once() {
+        use bevy::prelude::{Events, Schedule, World};
+
+        use crate::{
+            tests::build_test_tower_field,
+            world::defender_controller::{listen_board_cleared, DefenderConfiguration, ResourceStore},
+        };
+
+        let mut world = World::new();
+        let mut field = build_test_tower_field();
+        for x in 0..4 {
+            let wall = world.spawn(Structure { building_type: BuildingType::Wall, blocking: true }).id();
+            field.add_structure(wall, true, Vec2::new(x as f32, 5.) * SLOT_SIZE as f32, Footprint::SINGLE);
         }
+        world.insert_resource(field);
+        world.insert_resource(ResourceStore { gold: 0, lives: 10 });
+        let mut config = DefenderConfiguration::default();
+        config.num_walls = 4;
+        config.free_wall_slots = 0;
+        world.insert_resource(config);
+        world.insert_resource(BuildingResource::from_json(r#"[{ "building_type": "Wall", "config": { "cost": 10, "blocking": true, "type_config": "Wall" } }]"#).unwrap());
+        world.init_resource::<Events<ClearBoardRequest>>();
+        world.init_resource::<Events<BoardClearedEvent>>();
+        world.init_resource::<Events<FieldModified>>();
+        world.init_resource::<PathCache>();
+        world.send_event(ClearBoardRequest { refund_fraction: 0.5 });
+        world.send_event(ClearBoardRequest { refund_fraction: 0.5 });
+        let mut schedule = Schedule::new();
+        schedule.add_system(process_clear_board);
+        schedule.add_system(listen_board_cleared.after(process_clear_board));
+        schedule.run(&mut world);
+
+        let field = world.resource::<TowerField>();
+        for y in 0..field.get_height() {
+            for x in 0..field.get_width() {
+                assert!(!field.is_occupied(x, y));
+            }
+        }
+        assert_eq!(world.query::<&Structure>().iter(&world).count(), 0);
+        let modified = world.resource::<Events<FieldModified>>();
+        let modified: Vec<usize> = modified.get_reader().iter(modified).map(|ev| ev.nodes.len()).collect();
+        assert_eq!(modified, vec![4]);
+        assert_eq!(world.resource::<ResourceStore>().gold, 20);
+        let config = world.resource::<DefenderConfiguration>();
+        assert_eq!((config.num_walls, config.num_defenders), (0, 0));
+        assert!(config.free_wall_slots > 0 && config.free_tower_slots == 16 * 16 - 2);
+    }
+
+    #[test]
+    fn large_structures_take_and_free_all_their_slots() {
+        use bevy::prelude::{Events, Schedule, World};
+
+        use crate::tests::build_test_tower_field;
+
+        let footprint = BuildingType::LargeCannon.get_footprint();
+        let mut world = World::new();
+        world.insert_resource(build_test_tower_field());
+        world.init_resource::<Events<FieldModified>>();
+        world.init_resource::<Events<RemoveStructureRequest>>();
+        world.init_resource::<Events<RemovedStructureEvent>>();
+        let anchor = Node::new(4, 4);
+        let position = Vec2::new(anchor.x as f32, anchor.y as f32) * SLOT_SIZE as f32 + footprint.center_offset();
+        world.spawn((Structure { building_type: BuildingType::LargeCannon, blocking: true }, footprint, Transform::from_translation(position.extend(0.))));
+        let mut schedule = Schedule::new();
+        schedule.add_system(register_structures);
+        schedule.add_system(process_removal_requests.after(register_structures));
+        schedule.run(&mut world);
+
+        let field = world.resource::<TowerField>();
+        assert_eq!(footprint.nodes(anchor).filter(|node| field.is_node_blocked(*node)).count(), 4);
+        assert!(!field.is_footprint_free(Node::new(3, 3), footprint));
+        assert!(field.is_footprint_free(Node::new(6, 4), footprint));
+        // Hanging over the right edge
+        assert!(!field.is_footprint_free(Node::new(field.get_width() as i32 - 1, 0), footprint));
+
+        // Selling from the top right slot removes the whole cannon
+        world.send_event(RemoveStructureRequest { node: Node::new(5, 5), destroyed: false });
+        schedule.run(&mut world);
+        let field = world.resource::<TowerField>();
+        assert!(field.is_footprint_free(anchor, footprint));
+        let removed = world.resource::<Events<RemovedStructureEvent>>();
+        let removed: Vec<Node> = removed.get_reader().iter(removed).map(|ev| ev.node).collect();
+        assert_eq!(removed, vec![anchor]);
+        assert_eq!(world.query::<&Structure>().iter(&world).count(), 0);
+    }
+
+    #[test]
+    fn interceptors_shoot_down_projectiles_before_they_hit() {
+        use bevy::prelude::{Events, Schedule, World};
+
+        use crate::world::attackers::Interceptor;
+
+        let mut world = World::new();
+        world.init_resource::<TextureResource>();
+        // No sprites are loaded, so no particles either
+        world.insert_resource(ParticleSettings { density: 0., ..Default::default() });
+        world.init_resource::<DamageMatrix>();
+        world.insert_resource(GameRng::seeded(2129));
+        world.init_resource::<Time>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<DamageBatchAccumulator>();
+        world.init_resource::<Events<KillEvent>>();
+        world.init_resource::<Events<SplashImpactEvent>>();
+        world.spawn((GOLEM_STATS, Interceptor::new(64., 4.), Transform::from_xyz(-40., 0., 0.)));
+        let mut stats = ORC_WARRIOR_STATS;
+        stats.dodge_chance = 0.;
+        let target = world.spawn((stats, Transform::default())).id();
+        // Already overlapping the target, without the interceptor both hit this frame
+        let shots: Vec<Entity> = (0..2)
+            .map(|_| {
+                let mut shot = arrow(None);
+                shot.target = Target::Entity(target);
+                world.spawn((shot, Transform::default(), Handle::<TextureAtlas>::default(), TextureAtlasSprite::default())).id()
+            })
+            .collect();
+        let mut schedule = Schedule::new();
+        schedule.add_system(intercept_projectiles.before(update_projectiles));
+        schedule.add_system(update_projectiles);
+        schedule.add_system(flush_damage_batches.after(update_projectiles));
+        schedule.run(&mut world);
+
+        // One interception per cooldown, the second shot still lands
+        let remaining = shots.iter().filter(|shot| world.get_entity(**shot).is_some()).count();
+        assert_eq!(remaining, 0);
+        let damage = world.resource::<Events<DamageEvent>>();
+        assert_eq!(damage.get_reader().iter(damage).count(), 1);
     }
 }